@@ -6,6 +6,7 @@ fn main() {
             "src/schema/webrtc.proto",
             "src/protocol/libp2p/schema/identify.proto",
             "src/protocol/libp2p/schema/kademlia.proto",
+            "src/protocol/libp2p/schema/kademlia_store.proto",
             "src/protocol/libp2p/schema/bitswap.proto",
         ],
         &["src"],
@@ -20,11 +20,142 @@
 
 //! Bandwidth sinks for metering inbound/outbound bytes.
 
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use crate::{
+    metrics::{Direction, Metrics},
+    transport::manager::SupportedTransport,
+    PeerId, ProtocolName,
 };
 
+use parking_lot::Mutex;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of one-second slots kept for calculating bandwidth rates.
+const RATE_SLOTS: usize = 60usize;
+
+/// Maximum window over which bandwidth rates can be calculated.
+pub const MAX_RATE_WINDOW: Duration = Duration::from_secs(RATE_SLOTS as u64);
+
+/// Number of bytes transferred.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthSnapshot {
+    /// Number of inbound bytes.
+    pub inbound: usize,
+
+    /// Number of outbound bytes.
+    pub outbound: usize,
+}
+
+/// Bandwidth usage in bytes per second.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BandwidthRate {
+    /// Inbound bytes per second.
+    pub inbound: f64,
+
+    /// Outbound bytes per second.
+    pub outbound: f64,
+}
+
+/// Byte meter of one direction.
+///
+/// In addition to the total number of bytes, the meter keeps the number of bytes transferred
+/// during each of the last [`RATE_SLOTS`] seconds.
+#[derive(Debug)]
+struct Meter {
+    /// Total number of bytes.
+    total: AtomicUsize,
+
+    /// Number of bytes transferred during the second stored in `epochs`.
+    slots: [AtomicU64; RATE_SLOTS],
+
+    /// Second, counted from the creation of [`BandwidthSink`], each slot belongs to.
+    epochs: [AtomicU64; RATE_SLOTS],
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self {
+            total: AtomicUsize::new(0usize),
+            slots: std::array::from_fn(|_| AtomicU64::new(0u64)),
+            epochs: std::array::from_fn(|_| AtomicU64::new(0u64)),
+        }
+    }
+}
+
+impl Meter {
+    /// Record `bytes` transferred during `second`.
+    fn record(&self, second: u64, bytes: usize) {
+        let _ = self.total.fetch_add(bytes, Ordering::Relaxed);
+
+        let index = (second % RATE_SLOTS as u64) as usize;
+        if self.epochs[index].swap(second, Ordering::Relaxed) != second {
+            self.slots[index].store(0u64, Ordering::Relaxed);
+        }
+        let _ = self.slots[index].fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Get total number of bytes.
+    fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Calculate bytes per second over the last `window` seconds, including `second`.
+    fn rate(&self, second: u64, window: u64) -> f64 {
+        let bytes = (0..RATE_SLOTS)
+            .filter(|index| {
+                let epoch = self.epochs[*index].load(Ordering::Relaxed);
+                epoch <= second && second - epoch < window
+            })
+            .map(|index| self.slots[index].load(Ordering::Relaxed))
+            .sum::<u64>();
+
+        bytes as f64 / window as f64
+    }
+}
+
+/// Inbound and outbound byte meters.
+#[derive(Debug, Default)]
+struct Traffic {
+    /// Inbound bytes.
+    inbound: Meter,
+
+    /// Outbound bytes.
+    outbound: Meter,
+}
+
+impl Traffic {
+    fn snapshot(&self) -> BandwidthSnapshot {
+        BandwidthSnapshot {
+            inbound: self.inbound.total(),
+            outbound: self.outbound.total(),
+        }
+    }
+
+    fn rate(&self, second: u64, window: u64) -> BandwidthRate {
+        BandwidthRate {
+            inbound: self.inbound.rate(second, window),
+            outbound: self.outbound.rate(second, window),
+        }
+    }
+}
+
+/// Traffic of a peer and the number of its open substreams.
+#[derive(Debug, Default)]
+struct PeerTraffic {
+    /// Traffic of the peer.
+    traffic: Arc<Traffic>,
+
+    /// Number of open substreams.
+    substreams: usize,
+}
+
 /// Inner bandwidth sink
 #[derive(Debug)]
 struct InnerBandwidthSink {
@@ -33,10 +164,32 @@ struct InnerBandwidthSink {
 
     /// Number of outbound bytes.
     outbound: AtomicUsize,
+
+    /// Time when the sink was created, used as the epoch of the rate meters.
+    started: Instant,
+
+    /// Traffic of each protocol.
+    protocols: Mutex<HashMap<ProtocolName, Arc<Traffic>>>,
+
+    /// Traffic of each peer with open substreams.
+    peers: Mutex<HashMap<PeerId, PeerTraffic>>,
+
+    /// Metrics.
+    metrics: Metrics,
+}
+
+impl InnerBandwidthSink {
+    /// Get number of seconds since the sink was created.
+    fn second(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
 }
 
 /// Bandwidth sink which provides metering for inbound/outbound byte usage.
 ///
+/// The bytes are also attributed to the protocol and the peer of the substream they were
+/// transferred over. Peer statistics are kept only while the peer has open substreams.
+///
 /// The reported values are not necessarily up to date with the latest information
 /// and should not be used for metrics that require high precision but they do provide
 /// an overall view of the data usage of `litep2p`.
@@ -45,13 +198,52 @@ pub struct BandwidthSink(Arc<InnerBandwidthSink>);
 
 impl BandwidthSink {
     /// Create new [`BandwidthSink`].
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
+        Self::with_metrics(Metrics::default())
+    }
+
+    /// Create new [`BandwidthSink`] which also records the metered bytes to `metrics`.
+    pub(crate) fn with_metrics(metrics: Metrics) -> Self {
         Self(Arc::new(InnerBandwidthSink {
             inbound: AtomicUsize::new(0usize),
             outbound: AtomicUsize::new(0usize),
+            started: Instant::now(),
+            protocols: Mutex::new(HashMap::new()),
+            peers: Mutex::new(HashMap::new()),
+            metrics,
         }))
     }
 
+    /// Create bandwidth sink for a substream of `protocol` opened to `peer` over `transport`.
+    pub(crate) fn substream(
+        &self,
+        transport: SupportedTransport,
+        peer: PeerId,
+        protocol: ProtocolName,
+    ) -> SubstreamBandwidthSink {
+        self.0.metrics.on_substream_opened(&protocol);
+
+        let protocol_traffic =
+            Arc::clone(self.0.protocols.lock().entry(protocol.clone()).or_default());
+        let peer_traffic = {
+            let mut peers = self.0.peers.lock();
+            let entry = peers.entry(peer).or_default();
+            entry.substreams += 1;
+
+            Arc::clone(&entry.traffic)
+        };
+
+        SubstreamBandwidthSink {
+            sink: self.clone(),
+            transport,
+            peer,
+            protocol,
+            protocol_traffic,
+            peer_traffic,
+        }
+    }
+
     /// Increase the amount of inbound bytes.
     pub(crate) fn increase_inbound(&self, bytes: usize) {
         let _ = self.0.inbound.fetch_add(bytes, Ordering::Relaxed);
@@ -71,6 +263,130 @@ impl BandwidthSink {
     pub fn outbound(&self) -> usize {
         self.0.outbound.load(Ordering::Relaxed)
     }
+
+    /// Get the number of bytes transferred over each protocol.
+    pub fn protocols(&self) -> HashMap<ProtocolName, BandwidthSnapshot> {
+        self.0
+            .protocols
+            .lock()
+            .iter()
+            .map(|(protocol, traffic)| (protocol.clone(), traffic.snapshot()))
+            .collect()
+    }
+
+    /// Get the number of bytes transferred to and from each peer with open substreams.
+    pub fn peers(&self) -> HashMap<PeerId, BandwidthSnapshot> {
+        self.0
+            .peers
+            .lock()
+            .iter()
+            .map(|(peer, entry)| (*peer, entry.traffic.snapshot()))
+            .collect()
+    }
+
+    /// Get the bandwidth usage of each protocol averaged over the last `window`.
+    ///
+    /// `window` is rounded up to full seconds and capped at [`MAX_RATE_WINDOW`].
+    pub fn protocol_rates(&self, window: Duration) -> HashMap<ProtocolName, BandwidthRate> {
+        let (second, window) = (self.0.second(), Self::window_secs(window));
+
+        self.0
+            .protocols
+            .lock()
+            .iter()
+            .map(|(protocol, traffic)| (protocol.clone(), traffic.rate(second, window)))
+            .collect()
+    }
+
+    /// Get the bandwidth usage of each peer with open substreams averaged over the last
+    /// `window`.
+    ///
+    /// `window` is rounded up to full seconds and capped at [`MAX_RATE_WINDOW`].
+    pub fn peer_rates(&self, window: Duration) -> HashMap<PeerId, BandwidthRate> {
+        let (second, window) = (self.0.second(), Self::window_secs(window));
+
+        self.0
+            .peers
+            .lock()
+            .iter()
+            .map(|(peer, entry)| (*peer, entry.traffic.rate(second, window)))
+            .collect()
+    }
+
+    /// Convert `window` to the number of one-second slots it spans.
+    fn window_secs(window: Duration) -> u64 {
+        let secs = window.as_secs() + (window.subsec_nanos() > 0) as u64;
+        secs.clamp(1u64, RATE_SLOTS as u64)
+    }
+}
+
+/// Bandwidth sink of a single substream.
+///
+/// Meters the bytes of the substream to the parent [`BandwidthSink`] and attributes them to
+/// the transport, peer and protocol of the substream.
+#[derive(Debug)]
+pub(crate) struct SubstreamBandwidthSink {
+    /// Parent bandwidth sink.
+    sink: BandwidthSink,
+
+    /// Transport of the substream.
+    transport: SupportedTransport,
+
+    /// Remote peer ID.
+    peer: PeerId,
+
+    /// Protocol of the substream.
+    protocol: ProtocolName,
+
+    /// Traffic of the protocol.
+    protocol_traffic: Arc<Traffic>,
+
+    /// Traffic of the peer.
+    peer_traffic: Arc<Traffic>,
+}
+
+impl SubstreamBandwidthSink {
+    /// Increase the amount of inbound bytes.
+    pub(crate) fn increase_inbound(&self, bytes: usize) {
+        let second = self.sink.0.second();
+
+        self.sink.increase_inbound(bytes);
+        self.protocol_traffic.inbound.record(second, bytes);
+        self.peer_traffic.inbound.record(second, bytes);
+        self.sink
+            .0
+            .metrics
+            .on_bytes(self.transport, &self.protocol, Direction::Inbound, bytes);
+    }
+
+    /// Increase the amount of outbound bytes.
+    pub(crate) fn increase_outbound(&self, bytes: usize) {
+        let second = self.sink.0.second();
+
+        self.sink.increase_outbound(bytes);
+        self.protocol_traffic.outbound.record(second, bytes);
+        self.peer_traffic.outbound.record(second, bytes);
+        self.sink
+            .0
+            .metrics
+            .on_bytes(self.transport, &self.protocol, Direction::Outbound, bytes);
+    }
+}
+
+impl Drop for SubstreamBandwidthSink {
+    fn drop(&mut self) {
+        self.sink.0.metrics.on_substream_closed(&self.protocol);
+
+        // remove the peer's traffic once its last substream is closed
+        let mut peers = self.sink.0.peers.lock();
+        if let Some(entry) = peers.get_mut(&self.peer) {
+            entry.substreams -= 1;
+
+            if entry.substreams == 0 {
+                peers.remove(&self.peer);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -87,4 +403,145 @@ mod tests {
         assert_eq!(sink.inbound(), 1337usize);
         assert_eq!(sink.outbound(), 1338usize);
     }
+
+    #[test]
+    fn substream_bandwidth_is_recorded() {
+        let registry = crate::metrics::tests::TestRegistry::default();
+        let sink = BandwidthSink::with_metrics(Metrics::new(&registry).unwrap());
+
+        let substream = sink.substream(
+            SupportedTransport::Tcp,
+            PeerId::random(),
+            ProtocolName::from("/notif/1"),
+        );
+        substream.increase_inbound(1337usize);
+        substream.increase_outbound(1338usize);
+
+        assert_eq!(sink.inbound(), 1337usize);
+        assert_eq!(sink.outbound(), 1338usize);
+        assert_eq!(
+            registry.values.get("litep2p_protocol_bytes_total", &["/notif/1", "inbound"]),
+            Some(1337f64)
+        );
+        assert_eq!(
+            registry.values.get("litep2p_substreams", &["/notif/1"]),
+            Some(1f64)
+        );
+
+        drop(substream);
+        assert_eq!(
+            registry.values.get("litep2p_substreams", &["/notif/1"]),
+            Some(0f64)
+        );
+    }
+
+    #[test]
+    fn bandwidth_is_attributed_to_protocol_and_peer() {
+        let sink = BandwidthSink::new();
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+        let (notif, sync) = (
+            ProtocolName::from("/notif/1"),
+            ProtocolName::from("/sync/1"),
+        );
+
+        let substream1 = sink.substream(SupportedTransport::Tcp, peer1, notif.clone());
+        let substream2 = sink.substream(SupportedTransport::Tcp, peer1, sync.clone());
+        let substream3 = sink.substream(SupportedTransport::Quic, peer2, sync.clone());
+
+        substream1.increase_inbound(100usize);
+        substream2.increase_outbound(200usize);
+        substream3.increase_inbound(300usize);
+        substream3.increase_outbound(400usize);
+
+        let protocols = sink.protocols();
+        assert_eq!(
+            protocols.get(&notif),
+            Some(&BandwidthSnapshot {
+                inbound: 100usize,
+                outbound: 0usize
+            })
+        );
+        assert_eq!(
+            protocols.get(&sync),
+            Some(&BandwidthSnapshot {
+                inbound: 300usize,
+                outbound: 600usize
+            })
+        );
+
+        let peers = sink.peers();
+        assert_eq!(
+            peers.get(&peer1),
+            Some(&BandwidthSnapshot {
+                inbound: 100usize,
+                outbound: 200usize
+            })
+        );
+        assert_eq!(
+            peers.get(&peer2),
+            Some(&BandwidthSnapshot {
+                inbound: 300usize,
+                outbound: 400usize
+            })
+        );
+
+        let rates = sink.protocol_rates(Duration::from_secs(10));
+        assert_eq!(rates.get(&sync).unwrap().outbound, 60f64);
+
+        let rates = sink.peer_rates(Duration::from_millis(500));
+        assert_eq!(rates.get(&peer2).unwrap().inbound, 300f64);
+
+        // peer statistics are removed once the peer has no open substreams
+        drop(substream1);
+        assert!(sink.peers().contains_key(&peer1));
+        drop(substream2);
+        assert!(!sink.peers().contains_key(&peer1));
+
+        // protocol statistics are kept
+        drop(substream3);
+        assert_eq!(sink.protocols().len(), 2);
+        assert_eq!(sink.inbound(), 400usize);
+        assert_eq!(sink.outbound(), 600usize);
+    }
+
+    #[test]
+    fn peer_is_removed_after_concurrent_drops() {
+        let sink = BandwidthSink::new();
+        let peer = PeerId::random();
+        let protocol = ProtocolName::from("/notif/1");
+
+        for _ in 0..100 {
+            let substream1 = sink.substream(SupportedTransport::Tcp, peer, protocol.clone());
+            let substream2 = sink.substream(SupportedTransport::Tcp, peer, protocol.clone());
+
+            let handle = std::thread::spawn(move || drop(substream1));
+            drop(substream2);
+            handle.join().unwrap();
+
+            assert!(sink.peers().is_empty());
+        }
+
+        // a new substream starts from an empty entry
+        let substream = sink.substream(SupportedTransport::Tcp, peer, protocol.clone());
+        substream.increase_inbound(10usize);
+        assert_eq!(sink.peers().get(&peer).unwrap().inbound, 10usize);
+    }
+
+    #[test]
+    fn rate_window() {
+        let meter = Meter::default();
+
+        meter.record(0u64, 100usize);
+        meter.record(5u64, 200usize);
+        meter.record(5u64, 200usize);
+
+        assert_eq!(meter.rate(5u64, 1u64), 400f64);
+        assert_eq!(meter.rate(5u64, 10u64), 50f64);
+        assert_eq!(meter.rate(15u64, 10u64), 0f64);
+
+        // slot is reused once the window has wrapped around
+        meter.record(RATE_SLOTS as u64, 10usize);
+        assert_eq!(meter.rate(RATE_SLOTS as u64, 1u64), 10f64);
+        assert_eq!(meter.total(), 510usize);
+    }
 }
@@ -23,15 +23,18 @@
 use crate::{
     crypto::ed25519::Keypair,
     executor::{DefaultExecutor, Executor},
+    metrics::MetricsRegistry,
     protocol::{
         libp2p::{bitswap, identify, kademlia, ping},
         mdns::Config as MdnsConfig,
         notification, request_response, UserProtocol,
     },
+    rate_limit::RateLimitConfig,
     transport::{
-        quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
-        webrtc::config::Config as WebRtcConfig, websocket::config::Config as WebSocketConfig,
-        MAX_PARALLEL_DIALS,
+        memory::config::Config as MemoryConfig, quic::config::Config as QuicConfig,
+        tcp::config::Config as TcpConfig, webrtc::config::Config as WebRtcConfig,
+        websocket::config::Config as WebSocketConfig, ConnectionGater, ConnectionLimitsConfig,
+        IpNetwork, MAX_PARALLEL_DIALS,
     },
     types::protocol::ProtocolName,
     PeerId,
@@ -74,6 +77,9 @@ pub struct ConfigBuilder {
     /// WebSocket transport config.
     websocket: Option<WebSocketConfig>,
 
+    /// Memory transport config.
+    memory: Option<MemoryConfig>,
+
     /// Keypair.
     keypair: Option<Keypair>,
 
@@ -109,6 +115,24 @@ pub struct ConfigBuilder {
 
     /// Maximum number of parallel dial attempts.
     max_parallel_dials: usize,
+
+    /// Connection limits.
+    connection_limits: ConnectionLimitsConfig,
+
+    /// Connection gater.
+    connection_gater: Option<Arc<dyn ConnectionGater>>,
+
+    /// Allowed IP networks.
+    allowed_networks: Vec<IpNetwork>,
+
+    /// Denied IP networks.
+    denied_networks: Vec<IpNetwork>,
+
+    /// Metrics registry.
+    metrics_registry: Option<Arc<dyn MetricsRegistry>>,
+
+    /// Rate limits for substream traffic.
+    rate_limits: RateLimitConfig,
 }
 
 impl Default for ConfigBuilder {
@@ -125,6 +149,7 @@ impl ConfigBuilder {
             quic: None,
             webrtc: None,
             websocket: None,
+            memory: None,
             keypair: None,
             ping: None,
             identify: None,
@@ -133,6 +158,12 @@ impl ConfigBuilder {
             mdns: None,
             executor: None,
             max_parallel_dials: MAX_PARALLEL_DIALS,
+            connection_limits: ConnectionLimitsConfig::default(),
+            connection_gater: None,
+            allowed_networks: Vec::new(),
+            denied_networks: Vec::new(),
+            metrics_registry: None,
+            rate_limits: RateLimitConfig::default(),
             user_protocols: HashMap::new(),
             notification_protocols: HashMap::new(),
             request_response_protocols: HashMap::new(),
@@ -164,6 +195,12 @@ impl ConfigBuilder {
         self
     }
 
+    /// Add memory transport configuration, enabling the transport.
+    pub fn with_memory(mut self, config: MemoryConfig) -> Self {
+        self.memory = Some(config);
+        self
+    }
+
     /// Add keypair.
     ///
     /// If no keypair is specified, litep2p creates a new keypair.
@@ -243,6 +280,52 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set connection limits.
+    ///
+    /// By default no limits are enforced.
+    pub fn with_connection_limits(mut self, config: ConnectionLimitsConfig) -> Self {
+        self.connection_limits = config;
+        self
+    }
+
+    /// Install connection gater which can veto dial attempts and negotiated connections.
+    pub fn with_connection_gater(mut self, gater: Arc<dyn ConnectionGater>) -> Self {
+        self.connection_gater = Some(gater);
+        self
+    }
+
+    /// Only accept connections to and from addresses in `networks`.
+    ///
+    /// By default all networks are allowed.
+    pub fn with_allowed_networks(mut self, networks: Vec<IpNetwork>) -> Self {
+        self.allowed_networks = networks;
+        self
+    }
+
+    /// Reject connections to and from addresses in `networks`.
+    ///
+    /// Denied networks take precedence over allowed networks.
+    pub fn with_denied_networks(mut self, networks: Vec<IpNetwork>) -> Self {
+        self.denied_networks = networks;
+        self
+    }
+
+    /// Register metrics to `registry`.
+    ///
+    /// By default no metrics are collected.
+    pub fn with_metrics_registry(mut self, registry: Arc<dyn MetricsRegistry>) -> Self {
+        self.metrics_registry = Some(registry);
+        self
+    }
+
+    /// Throttle substream traffic using `rate_limits`.
+    ///
+    /// By default substream traffic is not throttled.
+    pub fn with_rate_limits(mut self, rate_limits: RateLimitConfig) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
     /// Build [`Litep2pConfig`].
     pub fn build(mut self) -> Litep2pConfig {
         let keypair = match self.keypair {
@@ -257,11 +340,18 @@ impl ConfigBuilder {
             quic: self.quic.take(),
             webrtc: self.webrtc.take(),
             websocket: self.websocket.take(),
+            memory: self.memory.take(),
             ping: self.ping.take(),
             identify: self.identify.take(),
             kademlia: self.kademlia.take(),
             bitswap: self.bitswap.take(),
             max_parallel_dials: self.max_parallel_dials,
+            connection_limits: self.connection_limits,
+            connection_gater: self.connection_gater,
+            allowed_networks: self.allowed_networks,
+            denied_networks: self.denied_networks,
+            metrics_registry: self.metrics_registry,
+            rate_limits: self.rate_limits,
             executor: self.executor.map_or(Arc::new(DefaultExecutor {}), |executor| executor),
             user_protocols: self.user_protocols,
             notification_protocols: self.notification_protocols,
@@ -285,6 +375,9 @@ pub struct Litep2pConfig {
     /// WebSocket transport config.
     pub(crate) websocket: Option<WebSocketConfig>,
 
+    /// Memory transport config.
+    pub(crate) memory: Option<MemoryConfig>,
+
     /// Keypair.
     pub(crate) keypair: Keypair,
 
@@ -320,4 +413,22 @@ pub struct Litep2pConfig {
 
     /// Known addresses.
     pub(crate) known_addresses: Vec<(PeerId, Vec<Multiaddr>)>,
+
+    /// Connection limits.
+    pub(crate) connection_limits: ConnectionLimitsConfig,
+
+    /// Connection gater.
+    pub(crate) connection_gater: Option<Arc<dyn ConnectionGater>>,
+
+    /// Allowed IP networks.
+    pub(crate) allowed_networks: Vec<IpNetwork>,
+
+    /// Denied IP networks.
+    pub(crate) denied_networks: Vec<IpNetwork>,
+
+    /// Metrics registry.
+    pub(crate) metrics_registry: Option<Arc<dyn MetricsRegistry>>,
+
+    /// Rate limits for substream traffic.
+    pub(crate) rate_limits: RateLimitConfig,
 }
@@ -143,20 +143,44 @@ impl NoiseContext {
     }
 
     /// Create new [`NoiseContext`] with prologue.
-    pub fn with_prologue(id_keys: &Keypair, prologue: Vec<u8>) -> crate::Result<Self> {
+    pub fn with_prologue(id_keys: &Keypair, prologue: Vec<u8>, role: Role) -> crate::Result<Self> {
         let noise: Builder<'_> = Builder::with_resolver(
             NOISE_PARAMETERS.parse().expect("qed; Valid noise pattern"),
             Box::new(protocol::Resolver),
         );
 
         let keypair = noise.generate_keypair()?;
+        let noise = noise.local_private_key(&keypair.private).prologue(&prologue);
 
-        let noise = noise
-            .local_private_key(&keypair.private)
-            .prologue(&prologue)
-            .build_initiator()?;
+        let noise = match role {
+            Role::Dialer => noise.build_initiator()?,
+            Role::Listener => noise.build_responder()?,
+        };
+
+        Self::assemble(noise, keypair, id_keys, role)
+    }
+
+    /// Read the first handshake message sent by the dialer.
+    ///
+    /// The message doesn't contain a payload.
+    pub fn read_first_message(&mut self, message: &[u8]) -> crate::Result<()> {
+        if message.len() < 2 {
+            return Err(error::Error::InvalidData);
+        }
+        let (_, message) = message.split_at(2);
+
+        let NoiseState::Handshake(ref mut noise) = self.noise else {
+            tracing::error!(target: LOG_TARGET, "invalid state to read the first handshake message");
+            debug_assert!(false);
+            return Err(error::Error::Other(
+                "Noise state missmatch: expected handshake".into(),
+            ));
+        };
+
+        let mut buffer = vec![0u8; message.len()];
+        noise.read_message(message, &mut buffer)?;
 
-        Self::assemble(noise, keypair, id_keys, Role::Dialer)
+        Ok(())
     }
 
     /// Get remote public key from the received Noise payload.
@@ -118,6 +118,10 @@ pub enum Error {
     ChannelClogged,
     #[error("Connection doesn't exist: `{0:?}`")]
     ConnectionDoesntExist(ConnectionId),
+    #[error("Connection rejected: `{0}`")]
+    ConnectionRejected(crate::transport::ConnectionRejectReason),
+    #[error("Metric error: `{0}`")]
+    MetricError(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -30,32 +30,48 @@
 
 use crate::{
     config::Litep2pConfig,
+    executor::Executor,
+    metrics::Metrics,
     protocol::{
-        libp2p::{bitswap::Bitswap, identify::Identify, kademlia::Kademlia, ping::Ping},
+        libp2p::{
+            bitswap::Bitswap,
+            identify::{Identify, IdentifyCommand},
+            kademlia::{Kademlia, KademliaCommand, Mode as KademliaMode},
+            ping::Ping,
+        },
         mdns::Mdns,
-        notification::NotificationProtocol,
-        request_response::RequestResponseProtocol,
+        notification::{Config as NotificationConfig, NotificationProtocol},
+        request_response::{Config as RequestResponseConfig, RequestResponseProtocol},
+        UserProtocol,
     },
+    shutdown::Shutdown,
     transport::{
         manager::{SupportedTransport, TransportManager},
+        memory::MemoryTransport,
         quic::QuicTransport,
         tcp::TcpTransport,
         webrtc::WebRtcTransport,
         websocket::WebSocketTransport,
-        TransportBuilder, TransportEvent,
+        ConnectionCloseReason, ConnectionRejectReason, TransportBuilder, TransportEvent,
     },
 };
 
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
-use transport::Endpoint;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use transport::{Endpoint, Muxer, Security};
 use types::ConnectionId;
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
-pub use bandwidth::BandwidthSink;
+pub use bandwidth::{BandwidthRate, BandwidthSink, BandwidthSnapshot, MAX_RATE_WINDOW};
 pub use error::Error;
 pub use peer_id::PeerId;
+pub use rate_limit::{RateLimit, RateLimitConfig};
 pub use types::protocol::ProtocolName;
 
 pub(crate) mod peer_id;
@@ -65,6 +81,7 @@ pub mod config;
 pub mod crypto;
 pub mod error;
 pub mod executor;
+pub mod metrics;
 pub mod protocol;
 pub mod substream;
 pub mod transport;
@@ -74,6 +91,8 @@ pub mod yamux;
 mod bandwidth;
 mod mock;
 mod multistream_select;
+mod rate_limit;
+mod shutdown;
 
 /// Public result type used by the crate.
 pub type Result<T> = std::result::Result<T, error::Error>;
@@ -94,6 +113,20 @@ pub enum Litep2pEvent {
 
         /// Endpoint.
         endpoint: Endpoint,
+
+        /// Security protocol of the connection.
+        ///
+        /// Fixed by the transport of the connection rather than negotiated with the remote peer:
+        /// Noise for TCP, WebSocket and memory connections, TLS for QUIC and DTLS with Noise for
+        /// WebRTC.
+        security: Security,
+
+        /// Stream multiplexer of the connection.
+        ///
+        /// Fixed by the transport of the connection rather than negotiated with the remote peer:
+        /// Yamux for TCP, WebSocket and memory connections and the native streams of QUIC and
+        /// WebRTC.
+        muxer: Muxer,
     },
 
     /// Connection closed to remote peer.
@@ -103,6 +136,9 @@ pub enum Litep2pEvent {
 
         /// Connection ID.
         connection_id: ConnectionId,
+
+        /// Reason why the connection was closed.
+        reason: ConnectionCloseReason,
     },
 
     /// Failed to dial peer.
@@ -113,6 +149,109 @@ pub enum Litep2pEvent {
         /// Dial error.
         error: Error,
     },
+
+    /// Failed to open a connection to a peer dialed with [`Litep2p::dial()`] over any of its
+    /// addresses.
+    OpenFailure {
+        /// Addresses that were tried and the errors they failed with.
+        errors: Vec<(Multiaddr, Error)>,
+    },
+
+    /// Negotiated connection was rejected by the connection gater or the connection limits.
+    ConnectionRejected {
+        /// Remote peer ID.
+        peer: PeerId,
+
+        /// Endpoint.
+        endpoint: Endpoint,
+
+        /// Reason for the rejection.
+        reason: ConnectionRejectReason,
+    },
+
+    /// Local node started listening on a new address.
+    NewListenAddr {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Local node stopped listening on an address.
+    ExpiredListenAddr {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Listener failed to accept an inbound connection.
+    ///
+    /// The listener keeps accepting connections. TCP and WebSocket listeners pause accepting after
+    /// an error, for longer if errors persist, so errors such as running out of file descriptors
+    /// are not reported for every connection attempt.
+    ListenerError {
+        /// Listen address.
+        address: Multiaddr,
+
+        /// Error.
+        error: Error,
+    },
+
+    /// Listener was closed by the transport.
+    ///
+    /// Preceded by [`Litep2pEvent::ExpiredListenAddr`] for the address of the listener.
+    ///
+    /// Emitted by QUIC when the endpoint of the listener is closed and by WebRTC when the UDP
+    /// socket of the listener fails. TCP and WebSocket listeners are closed only when they are
+    /// removed, which is reported with [`Litep2pEvent::ExpiredListenAddr`] alone. Failed accepts
+    /// on them are reported with [`Litep2pEvent::ListenerError`].
+    ListenerClosed {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Inbound connection was accepted by a listener and is being negotiated.
+    ///
+    /// Followed by either [`Litep2pEvent::ConnectionEstablished`] or
+    /// [`Litep2pEvent::IncomingConnectionError`] with the same connection ID, unless the
+    /// negotiated connection is rejected.
+    IncomingConnection {
+        /// Connection ID.
+        connection_id: ConnectionId,
+
+        /// Local address the connection was accepted on.
+        local_address: Multiaddr,
+
+        /// Remote address.
+        remote_address: Multiaddr,
+    },
+
+    /// Failed to negotiate an inbound connection.
+    IncomingConnectionError {
+        /// Connection ID.
+        connection_id: ConnectionId,
+
+        /// Error.
+        error: Error,
+    },
+
+    /// Connection opened to one of the addresses of a dialed peer and is being negotiated.
+    ConnectionOpened {
+        /// Connection ID.
+        connection_id: ConnectionId,
+
+        /// Address of the peer.
+        address: Multiaddr,
+    },
+
+    /// Remote peer observed the local node at `address`.
+    ///
+    /// Reported by Identify, if enabled. The address may be reachable by other peers and can be
+    /// used as an external address of the local node once confirmed.
+    ExternalAddressCandidate {
+        /// Peer who observed the address.
+        peer: PeerId,
+
+        /// Observed address.
+        address: Multiaddr,
+    },
 }
 
 /// [`Litep2p`] object.
@@ -128,14 +267,50 @@ pub struct Litep2p {
 
     /// Bandwidth sink.
     bandwidth_sink: BandwidthSink,
+
+    /// Shutdown coordinator of the protocol event loops.
+    shutdown: Shutdown,
+
+    /// Executor used to run protocols registered at runtime.
+    executor: Arc<dyn Executor>,
+
+    /// TX channel for sending commands to `Identify`, if enabled.
+    identify_tx: Option<Sender<IdentifyCommand>>,
+
+    /// RX channel for receiving the addresses remote peers observed, if `Identify` is enabled.
+    observed_rx: Option<Receiver<(PeerId, Multiaddr)>>,
+
+    /// TX channel for sending commands to `Kademlia`, if enabled.
+    kademlia_tx: Option<Sender<KademliaCommand>>,
+
+    /// Kademlia mode.
+    kademlia_mode: KademliaMode,
+
+    /// Whether Kademlia runs in server mode, accepting inbound substreams and advertised over
+    /// Identify.
+    kademlia_server: bool,
+
+    /// Main Kademlia protocol name, if Kademlia is enabled.
+    kademlia_protocol: Option<ProtocolName>,
+
+    /// Addresses the local node has been confirmed to be reachable at.
+    external_addresses: Vec<Multiaddr>,
+
+    /// Pending events.
+    pending_events: VecDeque<Litep2pEvent>,
 }
 
 impl Litep2p {
     /// Create new [`Litep2p`].
     pub fn new(mut litep2p_config: Litep2pConfig) -> crate::Result<Litep2p> {
         let local_peer_id = PeerId::from_public_key(&litep2p_config.keypair.public().into());
-        let bandwidth_sink = BandwidthSink::new();
+        let metrics = match litep2p_config.metrics_registry.take() {
+            Some(registry) => Metrics::new(&*registry)?,
+            None => Metrics::default(),
+        };
+        let bandwidth_sink = BandwidthSink::with_metrics(metrics.clone());
         let mut listen_addresses = vec![];
+        let shutdown = Shutdown::new();
 
         let supported_transports = Self::supported_transports(&litep2p_config);
         let (mut transport_manager, transport_handle) = TransportManager::new(
@@ -144,6 +319,17 @@ impl Litep2p {
             bandwidth_sink.clone(),
             litep2p_config.max_parallel_dials,
         );
+        transport_manager.set_connection_limits(litep2p_config.connection_limits);
+        transport_manager.set_metrics(metrics);
+        transport_manager.set_rate_limits(std::mem::take(&mut litep2p_config.rate_limits));
+
+        if let Some(gater) = litep2p_config.connection_gater.take() {
+            transport_manager.set_connection_gater(gater);
+        }
+        transport_manager.set_ip_networks(
+            std::mem::take(&mut litep2p_config.allowed_networks),
+            std::mem::take(&mut litep2p_config.denied_networks),
+        );
 
         // add known addresses to `TransportManager`, if any exist
         if !litep2p_config.known_addresses.is_empty() {
@@ -153,51 +339,33 @@ impl Litep2p {
         }
 
         // start notification protocol event loops
-        for (protocol, config) in litep2p_config.notification_protocols.into_iter() {
-            tracing::debug!(
-                target: LOG_TARGET,
-                ?protocol,
-                "enable notification protocol",
-            );
-
-            let service = transport_manager.register_protocol(
-                protocol,
-                config.fallback_names.clone(),
-                config.codec,
+        for (_, config) in litep2p_config.notification_protocols.into_iter() {
+            Self::start_notification_protocol(
+                &mut transport_manager,
+                &litep2p_config.executor,
+                &shutdown,
+                config,
             );
-            let executor = Arc::clone(&litep2p_config.executor);
-            litep2p_config.executor.run(Box::pin(async move {
-                NotificationProtocol::new(service, config, executor).run().await
-            }));
         }
 
         // start request-response protocol event loops
-        for (protocol, config) in litep2p_config.request_response_protocols.into_iter() {
-            tracing::debug!(
-                target: LOG_TARGET,
-                ?protocol,
-                "enable request-response protocol",
-            );
-
-            let service = transport_manager.register_protocol(
-                protocol,
-                config.fallback_names.clone(),
-                config.codec,
+        for (_, config) in litep2p_config.request_response_protocols.into_iter() {
+            Self::start_request_response_protocol(
+                &mut transport_manager,
+                &litep2p_config.executor,
+                &shutdown,
+                config,
             );
-            litep2p_config.executor.run(Box::pin(async move {
-                RequestResponseProtocol::new(service, config).run().await
-            }));
         }
 
         // start user protocol event loops
-        for (protocol_name, protocol) in litep2p_config.user_protocols.into_iter() {
-            tracing::debug!(target: LOG_TARGET, protocol = ?protocol_name, "enable user protocol");
-
-            let service =
-                transport_manager.register_protocol(protocol_name, Vec::new(), protocol.codec());
-            litep2p_config.executor.run(Box::pin(async move {
-                let _ = protocol.run(service).await;
-            }));
+        for (_, protocol) in litep2p_config.user_protocols.into_iter() {
+            Self::start_user_protocol(
+                &mut transport_manager,
+                &litep2p_config.executor,
+                &shutdown,
+                protocol,
+            );
         }
 
         // start ping protocol event loop if enabled
@@ -213,12 +381,16 @@ impl Litep2p {
                 Vec::new(),
                 ping_config.codec,
             );
-            litep2p_config.executor.run(Box::pin(async move {
-                Ping::new(service, ping_config).run().await
-            }));
+            litep2p_config
+                .executor
+                .run(shutdown.track(async move { Ping::new(service, ping_config).run().await }));
         }
 
-        // start kademlia protocol event loop if enabled
+        // register kademlia protocol if enabled, the event loop is started once the listen
+        // addresses are known
+        let mut kademlia_info = None;
+        let mut kademlia_mode = KademliaMode::Server;
+        let mut kademlia_protocol = None;
         if let Some(kademlia_config) = litep2p_config.kademlia.take() {
             tracing::debug!(
                 target: LOG_TARGET,
@@ -235,9 +407,16 @@ impl Litep2p {
                 fallback_names,
                 kademlia_config.codec,
             );
-            litep2p_config.executor.run(Box::pin(async move {
-                let _ = Kademlia::new(service, kademlia_config).run().await;
-            }));
+            // `Kademlia` in client or auto mode doesn't accept inbound substreams until it's
+            // switched to server mode
+            if kademlia_config.mode != KademliaMode::Server {
+                transport_manager
+                    .set_protocol_inbound(main_protocol, false)
+                    .expect("protocol to be registered");
+            }
+            kademlia_mode = kademlia_config.mode;
+            kademlia_protocol = Some(main_protocol.clone());
+            kademlia_info = Some((service, kademlia_config));
         }
 
         // start identify protocol event loop if enabled
@@ -255,9 +434,14 @@ impl Litep2p {
                     Vec::new(),
                     identify_config.codec,
                 );
+                let push_service = transport_manager.register_protocol(
+                    identify_config.push_protocol.clone(),
+                    Vec::new(),
+                    identify_config.codec,
+                );
                 identify_config.public = Some(litep2p_config.keypair.public().into());
 
-                Some((service, identify_config))
+                Some((service, push_service, identify_config))
             }
         };
 
@@ -274,9 +458,9 @@ impl Litep2p {
                 Vec::new(),
                 bitswap_config.codec,
             );
-            litep2p_config.executor.run(Box::pin(async move {
-                Bitswap::new(service, bitswap_config).run().await
-            }));
+            litep2p_config.executor.run(
+                shutdown.track(async move { Bitswap::new(service, bitswap_config).run().await }),
+            );
         }
 
         // enable tcp transport if the config exists
@@ -344,25 +528,73 @@ impl Litep2p {
                 .register_transport(SupportedTransport::WebSocket, Box::new(transport));
         }
 
+        // enable memory transport if the config exists
+        if let Some(config) = litep2p_config.memory.take() {
+            let handle = transport_manager.transport_handle(Arc::clone(&litep2p_config.executor));
+            let (transport, transport_listen_addresses) =
+                <MemoryTransport as TransportBuilder>::new(handle, config)?;
+
+            for address in transport_listen_addresses {
+                transport_manager.register_listen_address(address.clone());
+                listen_addresses.push(address.with(Protocol::P2p(
+                    Multihash::from_bytes(&local_peer_id.to_bytes()).unwrap(),
+                )));
+            }
+
+            transport_manager.register_transport(SupportedTransport::Memory, Box::new(transport));
+        }
+
         // enable mdns if the config exists
         if let Some(config) = litep2p_config.mdns.take() {
             let mdns = Mdns::new(transport_handle, config, listen_addresses.clone())?;
 
-            litep2p_config.executor.run(Box::pin(async move {
+            litep2p_config.executor.run(shutdown.track(async move {
                 let _ = mdns.start().await;
             }));
         }
 
+        // `Kademlia` in auto mode starts as a client as there are no external addresses yet
+        let kademlia_server = kademlia_mode == KademliaMode::Server;
+
         // if identify was enabled, give it the enabled protocols and listen addresses and start it
-        if let Some((service, mut identify_config)) = identify_info.take() {
+        let mut identify_tx = None;
+        let mut observed_rx = None;
+        if let Some((service, push_service, mut identify_config)) = identify_info.take() {
             identify_config.protocols = transport_manager.protocols().cloned().collect();
-            let identify = Identify::new(service, identify_config, listen_addresses.clone());
+            let (tx, rx) = channel(DEFAULT_CHANNEL_SIZE);
+            let (observed_tx, rx_observed) = channel(DEFAULT_CHANNEL_SIZE);
+            let identify = Identify::new(
+                service,
+                push_service,
+                identify_config,
+                listen_addresses.clone(),
+                rx,
+                observed_tx,
+            );
+            identify_tx = Some(tx);
+            observed_rx = Some(rx_observed);
 
-            litep2p_config.executor.run(Box::pin(async move {
+            litep2p_config.executor.run(shutdown.track(async move {
                 let _ = identify.run().await;
             }));
         }
 
+        // if kademlia was enabled, give it the listen addresses and start it
+        let mut kademlia_tx = None;
+        if let Some((service, kademlia_config)) = kademlia_info.take() {
+            kademlia_tx = Some(kademlia_config.cmd_tx.clone());
+            let kademlia = Kademlia::new(
+                service,
+                kademlia_config,
+                listen_addresses.clone(),
+                shutdown.draining_signal(),
+            );
+
+            litep2p_config.executor.run(shutdown.track(async move {
+                let _ = kademlia.run().await;
+            }));
+        }
+
         if transport_manager.installed_transports().count() == 0 {
             return Err(Error::Other("No transport specified".to_string()));
         }
@@ -380,9 +612,85 @@ impl Litep2p {
             bandwidth_sink,
             listen_addresses,
             transport_manager,
+            shutdown,
+            identify_tx,
+            observed_rx,
+            kademlia_tx,
+            kademlia_mode,
+            kademlia_server,
+            kademlia_protocol,
+            external_addresses: Vec::new(),
+            executor: litep2p_config.executor,
+            pending_events: VecDeque::new(),
         })
     }
 
+    /// Register notification protocol and start its event loop.
+    fn start_notification_protocol(
+        transport_manager: &mut TransportManager,
+        executor: &Arc<dyn Executor>,
+        shutdown: &Shutdown,
+        config: NotificationConfig,
+    ) {
+        tracing::debug!(
+            target: LOG_TARGET,
+            protocol = ?config.protocol_name,
+            "enable notification protocol",
+        );
+
+        let service = transport_manager.register_protocol(
+            config.protocol_name.clone(),
+            config.fallback_names.clone(),
+            config.codec,
+        );
+        let protocol_executor = Arc::clone(executor);
+        executor.run(shutdown.track(async move {
+            NotificationProtocol::new(service, config, protocol_executor).run().await
+        }));
+    }
+
+    /// Register request-response protocol and start its event loop.
+    fn start_request_response_protocol(
+        transport_manager: &mut TransportManager,
+        executor: &Arc<dyn Executor>,
+        shutdown: &Shutdown,
+        config: RequestResponseConfig,
+    ) {
+        tracing::debug!(
+            target: LOG_TARGET,
+            protocol = ?config.protocol_name,
+            "enable request-response protocol",
+        );
+
+        let service = transport_manager.register_protocol(
+            config.protocol_name.clone(),
+            config.fallback_names.clone(),
+            config.codec,
+        );
+        let signal = shutdown.draining_signal();
+        executor.run(shutdown.track(async move {
+            RequestResponseProtocol::new(service, config, signal).run().await
+        }));
+    }
+
+    /// Register user protocol and start its event loop.
+    fn start_user_protocol(
+        transport_manager: &mut TransportManager,
+        executor: &Arc<dyn Executor>,
+        shutdown: &Shutdown,
+        protocol: Box<dyn UserProtocol>,
+    ) {
+        let protocol_name = protocol.protocol();
+
+        tracing::debug!(target: LOG_TARGET, protocol = ?protocol_name, "enable user protocol");
+
+        let service =
+            transport_manager.register_protocol(protocol_name, Vec::new(), protocol.codec());
+        executor.run(shutdown.track(async move {
+            let _ = protocol.run(service).await;
+        }));
+    }
+
     /// Collect supported transports before initializing the transports themselves.
     ///
     /// Information of the supported transports is needed to initialize protocols but
@@ -407,6 +715,10 @@ impl Litep2p {
             .webrtc
             .is_some()
             .then(|| supported_transports.insert(SupportedTransport::WebRtc));
+        config
+            .memory
+            .is_some()
+            .then(|| supported_transports.insert(SupportedTransport::Memory));
 
         supported_transports
     }
@@ -421,6 +733,72 @@ impl Litep2p {
         self.listen_addresses.iter()
     }
 
+    /// Get the addresses the local node has been confirmed to be reachable at.
+    pub fn external_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.external_addresses.iter()
+    }
+
+    /// Confirm that the local node is reachable by other peers at `address`.
+    ///
+    /// The address can be, for example, a [`Litep2pEvent::ExternalAddressCandidate`] that has been
+    /// verified to be reachable. It's advertised over Identify and, if Kademlia runs in
+    /// [`Mode::Auto`](protocol::libp2p::kademlia::Mode::Auto), Kademlia switches to server mode.
+    ///
+    /// Returns `false` if the address had already been confirmed.
+    pub async fn add_external_address(&mut self, address: Multiaddr) -> bool {
+        let address = self.with_local_peer_id(address);
+
+        if self.external_addresses.contains(&address) {
+            return false;
+        }
+
+        tracing::debug!(target: LOG_TARGET, ?address, "new external address");
+
+        self.external_addresses.push(address.clone());
+
+        if !self.listen_addresses.contains(&address) {
+            self.on_listen_address_added(address).await;
+        }
+        self.update_kademlia_mode().await;
+
+        true
+    }
+
+    /// Remove confirmed external address.
+    ///
+    /// If Kademlia runs in [`Mode::Auto`](protocol::libp2p::kademlia::Mode::Auto) and the last
+    /// external address is removed, Kademlia switches to client mode.
+    ///
+    /// Returns `false` if the address hadn't been confirmed.
+    pub async fn remove_external_address(&mut self, address: &Multiaddr) -> bool {
+        let address = self.with_local_peer_id(address.clone());
+
+        if !self.external_addresses.contains(&address) {
+            return false;
+        }
+
+        tracing::debug!(target: LOG_TARGET, ?address, "expired external address");
+
+        self.external_addresses.retain(|external_address| external_address != &address);
+
+        if !self.listen_addresses.contains(&address) {
+            self.on_listen_address_removed(address).await;
+        }
+        self.update_kademlia_mode().await;
+
+        true
+    }
+
+    /// Append the peer ID of the local node to `address` if it doesn't contain a peer ID.
+    fn with_local_peer_id(&self, address: Multiaddr) -> Multiaddr {
+        match address.iter().last() {
+            Some(Protocol::P2p(_)) => address,
+            _ => address.with(Protocol::P2p(
+                Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+            )),
+        }
+    }
+
     /// Get handle to bandwidth sink.
     pub fn bandwidth_sink(&self) -> BandwidthSink {
         self.bandwidth_sink.clone()
@@ -436,6 +814,216 @@ impl Litep2p {
         self.transport_manager.dial_address(address).await
     }
 
+    /// Start listening on `address`.
+    ///
+    /// The address is routed to the transport it belongs to, which must be enabled. Returns the
+    /// addresses the new listener is reachable at, each of which is also reported in a
+    /// [`Litep2pEvent::NewListenAddr`] event and advertised over Identify.
+    pub async fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        let listen_addresses = self
+            .transport_manager
+            .listen_on(address)?
+            .into_iter()
+            .map(|address| {
+                address.with(Protocol::P2p(
+                    Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        for address in &listen_addresses {
+            tracing::debug!(target: LOG_TARGET, ?address, "new listen address");
+
+            self.listen_addresses.push(address.clone());
+            self.pending_events.push_back(Litep2pEvent::NewListenAddr {
+                address: address.clone(),
+            });
+
+            self.on_listen_address_added(address.clone()).await;
+        }
+
+        Ok(listen_addresses)
+    }
+
+    /// Stop the listener which is either bound to or reachable at `address`.
+    ///
+    /// Connections accepted by the listener are not closed. Each address the listener was
+    /// reachable at is reported in a [`Litep2pEvent::ExpiredListenAddr`] event and is no longer
+    /// advertised over Identify.
+    pub async fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<()> {
+        let expired_addresses = self.transport_manager.remove_listener(address)?;
+
+        for address in expired_addresses {
+            let address = address.with(Protocol::P2p(
+                Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+            ));
+
+            tracing::debug!(target: LOG_TARGET, ?address, "expired listen address");
+
+            self.listen_addresses.retain(|listen_address| listen_address != &address);
+            self.pending_events.push_back(Litep2pEvent::ExpiredListenAddr {
+                address: address.clone(),
+            });
+
+            self.on_listen_address_removed(address).await;
+        }
+
+        Ok(())
+    }
+
+    /// Register notification protocol while litep2p is running.
+    ///
+    /// Open connections start accepting substreams for the protocol and the protocol is informed
+    /// of them. Connected peers are sent the updated protocols over Identify push.
+    pub async fn register_notification_protocol(
+        &mut self,
+        config: NotificationConfig,
+    ) -> crate::Result<()> {
+        self.transport_manager
+            .can_register_protocol(&config.protocol_name, &config.fallback_names)?;
+
+        Self::start_notification_protocol(
+            &mut self.transport_manager,
+            &self.executor,
+            &self.shutdown,
+            config,
+        );
+        self.on_protocols_changed().await;
+
+        Ok(())
+    }
+
+    /// Register request-response protocol while litep2p is running.
+    ///
+    /// See [`Litep2p::register_notification_protocol()`] for more details.
+    pub async fn register_request_response_protocol(
+        &mut self,
+        config: RequestResponseConfig,
+    ) -> crate::Result<()> {
+        self.transport_manager
+            .can_register_protocol(&config.protocol_name, &config.fallback_names)?;
+
+        Self::start_request_response_protocol(
+            &mut self.transport_manager,
+            &self.executor,
+            &self.shutdown,
+            config,
+        );
+        self.on_protocols_changed().await;
+
+        Ok(())
+    }
+
+    /// Register user protocol while litep2p is running.
+    ///
+    /// See [`Litep2p::register_notification_protocol()`] for more details.
+    pub async fn register_user_protocol(
+        &mut self,
+        protocol: Box<dyn UserProtocol>,
+    ) -> crate::Result<()> {
+        self.transport_manager.can_register_protocol(&protocol.protocol(), &[])?;
+
+        Self::start_user_protocol(
+            &mut self.transport_manager,
+            &self.executor,
+            &self.shutdown,
+            protocol,
+        );
+        self.on_protocols_changed().await;
+
+        Ok(())
+    }
+
+    /// Unregister `protocol`.
+    ///
+    /// Open connections stop accepting substreams for the protocol and its event loop exits once
+    /// all connections have observed the change. Substreams that are already open are not
+    /// closed. Connected peers are sent the updated protocols over Identify push.
+    pub async fn unregister_protocol(&mut self, protocol: &ProtocolName) -> crate::Result<()> {
+        self.transport_manager.unregister_protocol(protocol)?;
+        self.on_protocols_changed().await;
+
+        Ok(())
+    }
+
+    /// Inform `Identify` and `Kademlia` that the local node is reachable at a new address.
+    async fn on_listen_address_added(&mut self, address: Multiaddr) {
+        if let Some(tx) = &self.identify_tx {
+            let _ = tx
+                .send(IdentifyCommand::AddListenAddress {
+                    address: address.clone(),
+                })
+                .await;
+        }
+
+        if let Some(tx) = &self.kademlia_tx {
+            let _ = tx.send(KademliaCommand::AddListenAddress { address }).await;
+        }
+    }
+
+    /// Inform `Identify` and `Kademlia` that the local node is no longer reachable at the address.
+    async fn on_listen_address_removed(&mut self, address: Multiaddr) {
+        if let Some(tx) = &self.identify_tx {
+            let _ = tx
+                .send(IdentifyCommand::RemoveListenAddress {
+                    address: address.clone(),
+                })
+                .await;
+        }
+
+        if let Some(tx) = &self.kademlia_tx {
+            let _ = tx.send(KademliaCommand::RemoveListenAddress { address }).await;
+        }
+    }
+
+    /// Inform `Identify` that the advertised protocols have changed.
+    async fn on_protocols_changed(&self) {
+        if let Some(tx) = &self.identify_tx {
+            let _ = tx
+                .send(IdentifyCommand::SetProtocols {
+                    protocols: self.transport_manager.protocols().cloned().collect(),
+                })
+                .await;
+        }
+    }
+
+    /// Switch Kademlia running in auto mode to server mode if the local node has confirmed
+    /// external addresses and to client mode otherwise.
+    async fn update_kademlia_mode(&mut self) {
+        let server = !self.external_addresses.is_empty();
+
+        if self.kademlia_mode != KademliaMode::Auto || self.kademlia_server == server {
+            return;
+        }
+
+        tracing::debug!(target: LOG_TARGET, ?server, "switch kademlia mode");
+
+        self.kademlia_server = server;
+
+        if let Some(protocol) = &self.kademlia_protocol {
+            let _ = self.transport_manager.set_protocol_inbound(protocol, server);
+        }
+        if let Some(tx) = &self.kademlia_tx {
+            let _ = tx.send(KademliaCommand::SetServerMode { enabled: server }).await;
+        }
+        self.on_protocols_changed().await;
+    }
+
+    /// Ban `peer` for `duration`.
+    ///
+    /// Open connections to the peer are closed and new connections to and from the peer are
+    /// rejected until the ban expires or [`Litep2p::unban_peer()`] is called.
+    pub fn ban_peer(&mut self, peer: PeerId, duration: Duration) {
+        self.transport_manager.ban_peer(peer, duration)
+    }
+
+    /// Unban `peer`.
+    ///
+    /// Returns `true` if the peer was banned.
+    pub fn unban_peer(&mut self, peer: &PeerId) -> bool {
+        self.transport_manager.unban_peer(peer)
+    }
+
     /// Add one ore more known addresses for peer.
     ///
     /// Return value denotes how many addresses were added for the peer.
@@ -448,26 +1036,134 @@ impl Litep2p {
         self.transport_manager.add_known_address(peer, address)
     }
 
+    /// Shut down litep2p gracefully.
+    ///
+    /// Listeners are stopped first so no new connections are accepted, after which
+    /// request-response protocols are given until `timeout` to finish their in-flight exchanges.
+    /// All open connections are then closed, sending yamux `GoAway` or QUIC `CONNECTION_CLOSE`
+    /// to the remote peers, and the call resolves once every protocol event loop has exited.
+    pub async fn shutdown(mut self, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        tracing::info!(target: LOG_TARGET, ?timeout, "shutting down litep2p");
+
+        self.transport_manager.stop_listeners();
+
+        tokio::select! {
+            _ = tokio::time::timeout_at(deadline, self.shutdown.drain()) => {}
+            _ = self.transport_manager.process_events() => {}
+        }
+
+        if tokio::time::timeout_at(deadline, self.transport_manager.close_connections())
+            .await
+            .is_err()
+        {
+            tracing::debug!(target: LOG_TARGET, "timed out while closing connections");
+        }
+
+        self.shutdown.stop().await;
+    }
+
     /// Poll next event.
     ///
     /// This function must be called in order for litep2p to make progress.
     pub async fn next_event(&mut self) -> Option<Litep2pEvent> {
-        loop {
-            match self.transport_manager.next().await? {
-                TransportEvent::ConnectionEstablished { peer, endpoint, .. } =>
-                    return Some(Litep2pEvent::ConnectionEstablished { peer, endpoint }),
-                TransportEvent::ConnectionClosed {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Some(event);
+        }
+
+        let observed_rx = &mut self.observed_rx;
+        let event = tokio::select! {
+            event = self.transport_manager.next() => event?,
+            Some((peer, address)) = async move {
+                match observed_rx {
+                    Some(rx) => rx.recv().await,
+                    None => futures::future::pending().await,
+                }
+            } => return Some(Litep2pEvent::ExternalAddressCandidate { peer, address }),
+        };
+
+        match event {
+            TransportEvent::ConnectionEstablished { peer, endpoint } => {
+                let transport = self
+                    .transport_manager
+                    .connection_transport(&endpoint.connection_id())
+                    .expect("transport of an accepted connection to exist");
+
+                Some(Litep2pEvent::ConnectionEstablished {
                     peer,
-                    connection_id,
-                } =>
-                    return Some(Litep2pEvent::ConnectionClosed {
-                        peer,
-                        connection_id,
-                    }),
-                TransportEvent::DialFailure { address, error, .. } =>
-                    return Some(Litep2pEvent::DialFailure { address, error }),
-                _ => {}
+                    endpoint,
+                    security: transport.security(),
+                    muxer: transport.muxer(),
+                })
+            }
+            TransportEvent::ConnectionClosed {
+                peer,
+                connection_id,
+                reason,
+            } => Some(Litep2pEvent::ConnectionClosed {
+                peer,
+                connection_id,
+                reason,
+            }),
+            TransportEvent::DialFailure { address, error, .. } =>
+                Some(Litep2pEvent::DialFailure { address, error }),
+            TransportEvent::ConnectionRejected {
+                peer,
+                endpoint,
+                reason,
+            } => Some(Litep2pEvent::ConnectionRejected {
+                peer,
+                endpoint,
+                reason,
+            }),
+            TransportEvent::ConnectionOpened {
+                connection_id,
+                address,
+            } => Some(Litep2pEvent::ConnectionOpened {
+                connection_id,
+                address,
+            }),
+            TransportEvent::IncomingConnection {
+                connection_id,
+                local_address,
+                remote_address,
+            } => Some(Litep2pEvent::IncomingConnection {
+                connection_id,
+                local_address,
+                remote_address,
+            }),
+            TransportEvent::IncomingConnectionError {
+                connection_id,
+                error,
+            } => Some(Litep2pEvent::IncomingConnectionError {
+                connection_id,
+                error,
+            }),
+            TransportEvent::ListenerError { address, error } => Some(Litep2pEvent::ListenerError {
+                address: address.with(Protocol::P2p(
+                    Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+                )),
+                error,
+            }),
+            TransportEvent::ListenerClosed { address } => {
+                let address = address.with(Protocol::P2p(
+                    Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+                ));
+
+                tracing::debug!(target: LOG_TARGET, ?address, "listener closed");
+
+                self.listen_addresses.retain(|listen_address| listen_address != &address);
+                self.pending_events.push_back(Litep2pEvent::ListenerClosed {
+                    address: address.clone(),
+                });
+
+                self.on_listen_address_removed(address.clone()).await;
+
+                Some(Litep2pEvent::ExpiredListenAddr { address })
             }
+            TransportEvent::OpenFailure { errors, .. } =>
+                Some(Litep2pEvent::OpenFailure { errors }),
         }
     }
 }
@@ -477,8 +1173,9 @@ mod tests {
     use crate::{
         config::ConfigBuilder,
         protocol::{libp2p::ping, notification::Config as NotificationConfig},
+        transport::{ConnectionCloseReason, Endpoint, Muxer, Security},
         types::protocol::ProtocolName,
-        Litep2p, Litep2pEvent, PeerId,
+        Error, Litep2p, Litep2pEvent, PeerId,
     };
     use multiaddr::{Multiaddr, Protocol};
     use multihash::Multihash;
@@ -560,6 +1257,52 @@ mod tests {
         assert!(Litep2p::new(config).is_err());
     }
 
+    #[tokio::test]
+    async fn open_failure_reports_errors() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (ping_config, _ping_event_stream) = ping::Config::default();
+        let config = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config)
+            .build();
+
+        // reserve two ports and close the listeners so the dials are refused
+        let peer = PeerId::random();
+        let addresses = (0..2)
+            .map(|_| {
+                let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+                let port = listener.local_addr().unwrap().port();
+
+                Multiaddr::empty()
+                    .with(Protocol::Ip4(Ipv4Addr::new(127, 0, 0, 1)))
+                    .with(Protocol::Tcp(port))
+                    .with(Protocol::P2p(
+                        Multihash::from_bytes(&peer.to_bytes()).unwrap(),
+                    ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut litep2p = Litep2p::new(config).unwrap();
+        litep2p.add_known_address(peer, addresses.clone().into_iter());
+        litep2p.dial(&peer).await.unwrap();
+
+        match tokio::time::timeout(std::time::Duration::from_secs(10), litep2p.next_event()).await {
+            Ok(Some(Litep2pEvent::OpenFailure { errors })) => {
+                let mut failed = errors.into_iter().map(|(address, _)| address).collect::<Vec<_>>();
+                failed.sort();
+
+                let mut expected = addresses;
+                expected.sort();
+
+                assert_eq!(failed, expected);
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn dial_same_address_twice() {
         let _ = tracing_subscriber::fmt()
@@ -619,4 +1362,344 @@ mod tests {
             _ => panic!("invalid event received"),
         }
     }
+
+    #[tokio::test]
+    async fn add_and_remove_listener() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let config = ConfigBuilder::new()
+            .with_tcp(crate::transport::tcp::config::Config {
+                listen_addresses: Vec::new(),
+                ..Default::default()
+            })
+            .build();
+
+        let mut litep2p = Litep2p::new(config).unwrap();
+        assert_eq!(litep2p.listen_addresses().count(), 0);
+
+        // quic is not enabled
+        assert!(litep2p
+            .listen_on("/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap())
+            .await
+            .is_err());
+
+        let listen_addresses =
+            litep2p.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).await.unwrap();
+        assert_eq!(listen_addresses.len(), 1);
+        assert_eq!(
+            litep2p.listen_addresses().cloned().collect::<Vec<_>>(),
+            listen_addresses
+        );
+
+        match litep2p.next_event().await {
+            Some(Litep2pEvent::NewListenAddr { address }) =>
+                assert_eq!(address, listen_addresses[0]),
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        litep2p.remove_listener(&listen_addresses[0]).await.unwrap();
+        assert_eq!(litep2p.listen_addresses().count(), 0);
+
+        match litep2p.next_event().await {
+            Some(Litep2pEvent::ExpiredListenAddr { address }) =>
+                assert_eq!(address, listen_addresses[0]),
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        assert!(litep2p.remove_listener(&listen_addresses[0]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_connections_tcp() {
+        shutdown_closes_connections(
+            ConfigBuilder::new().with_tcp(Default::default()),
+            ConfigBuilder::new().with_tcp(Default::default()),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_connections_quic() {
+        shutdown_closes_connections(
+            ConfigBuilder::new().with_quic(Default::default()),
+            ConfigBuilder::new().with_quic(Default::default()),
+        )
+        .await;
+    }
+
+    async fn shutdown_closes_connections(config1: ConfigBuilder, config2: ConfigBuilder) {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (ping_config1, _ping_event_stream1) = ping::Config::default();
+        let config1 = config1.with_libp2p_ping(ping_config1).build();
+
+        let (ping_config2, _ping_event_stream2) = ping::Config::default();
+        let config2 = config2.with_libp2p_ping(ping_config2).build();
+
+        let mut litep2p1 = Litep2p::new(config1).unwrap();
+        let mut litep2p2 = Litep2p::new(config2).unwrap();
+        let address = litep2p2.listen_addresses().next().unwrap().clone();
+
+        litep2p1.dial_address(address).await.unwrap();
+
+        let mut litep2p1_connected = false;
+        let mut litep2p2_connected = false;
+
+        while !litep2p1_connected || !litep2p2_connected {
+            tokio::select! {
+                event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p1_connected = true;
+                },
+                event = litep2p2.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p2_connected = true;
+                },
+            }
+        }
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            litep2p1.shutdown(std::time::Duration::from_secs(5)),
+        )
+        .await
+        .expect("shutdown to complete");
+
+        loop {
+            match tokio::time::timeout(std::time::Duration::from_secs(10), litep2p2.next_event())
+                .await
+                .expect("connection to be closed")
+            {
+                Some(Litep2pEvent::ConnectionClosed { .. }) => break,
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn register_protocol_at_runtime() {
+        use crate::protocol::request_response::{
+            ConfigBuilder as RequestResponseConfigBuilder, DialOptions, RequestResponseEvent,
+        };
+        use futures::StreamExt;
+
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        // ping keeps the connection open until the protocols are registered
+        let (ping_config1, _ping_event_stream1) = ping::Config::default();
+        let config1 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config1)
+            .build();
+
+        let (ping_config2, _ping_event_stream2) = ping::Config::default();
+        let config2 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config2)
+            .build();
+
+        let mut litep2p1 = Litep2p::new(config1).unwrap();
+        let mut litep2p2 = Litep2p::new(config2).unwrap();
+        let peer2 = *litep2p2.local_peer_id();
+        let address = litep2p2.listen_addresses().next().unwrap().clone();
+
+        litep2p1.dial_address(address).await.unwrap();
+
+        let mut litep2p1_connected = false;
+        let mut litep2p2_connected = false;
+
+        while !litep2p1_connected || !litep2p2_connected {
+            tokio::select! {
+                event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p1_connected = true;
+                },
+                event = litep2p2.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p2_connected = true;
+                },
+            }
+        }
+
+        // register the protocol on both nodes after the connection has been established
+        let (rr_config1, mut handle1) =
+            RequestResponseConfigBuilder::new(ProtocolName::from("/request/1"))
+                .with_max_size(1024)
+                .build();
+        let (rr_config2, mut handle2) =
+            RequestResponseConfigBuilder::new(ProtocolName::from("/request/1"))
+                .with_max_size(1024)
+                .build();
+
+        litep2p1.register_request_response_protocol(rr_config1).await.unwrap();
+        litep2p2.register_request_response_protocol(rr_config2).await.unwrap();
+
+        let (rr_config, _handle) =
+            RequestResponseConfigBuilder::new(ProtocolName::from("/request/1"))
+                .with_max_size(1024)
+                .build();
+        assert!(std::matches!(
+            litep2p1.register_request_response_protocol(rr_config).await,
+            Err(Error::ProtocolAlreadyExists(_))
+        ));
+
+        // give the protocols time to learn about the open connection
+        let sleep = tokio::time::sleep(std::time::Duration::from_secs(1));
+        tokio::pin!(sleep);
+
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {}
+                _ = litep2p2.next_event() => {}
+                _ = &mut sleep => break,
+            }
+        }
+
+        handle1.send_request(peer2, vec![1, 2, 3], DialOptions::Reject).await.unwrap();
+
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {}
+                _ = litep2p2.next_event() => {}
+                event = handle2.next() => match event.unwrap() {
+                    RequestResponseEvent::RequestReceived { request_id, request, .. } => {
+                        assert_eq!(request, vec![1, 2, 3]);
+                        handle2.send_response(request_id, vec![4, 5, 6]);
+                    }
+                    event => panic!("unexpected event: {event:?}"),
+                },
+                event = handle1.next() => match event.unwrap() {
+                    RequestResponseEvent::ResponseReceived { response, .. } => {
+                        assert_eq!(response, vec![4, 5, 6]);
+                        break;
+                    }
+                    event => panic!("unexpected event: {event:?}"),
+                },
+            }
+        }
+
+        litep2p1.unregister_protocol(&ProtocolName::from("/request/1")).await.unwrap();
+        assert!(std::matches!(
+            litep2p1.unregister_protocol(&ProtocolName::from("/request/1")).await,
+            Err(Error::ProtocolNotSupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn incoming_connection_reported_before_established() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (ping_config1, _ping_event_stream1) = ping::Config::default();
+        let config1 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config1)
+            .build();
+
+        let (ping_config2, _ping_event_stream2) = ping::Config::default();
+        let config2 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config2)
+            .build();
+
+        let mut litep2p1 = Litep2p::new(config1).unwrap();
+        let mut litep2p2 = Litep2p::new(config2).unwrap();
+        let address = litep2p2.listen_addresses().next().unwrap().clone();
+
+        litep2p1.dial_address(address).await.unwrap();
+
+        let mut incoming_reported = false;
+        let mut litep2p1_connected = false;
+        let mut litep2p2_connected = false;
+
+        while !litep2p1_connected || !litep2p2_connected {
+            tokio::select! {
+                event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { security, muxer, .. }) = event {
+                    assert_eq!(security, Security::Noise);
+                    assert_eq!(muxer, Muxer::Yamux);
+                    litep2p1_connected = true;
+                },
+                event = litep2p2.next_event() => match event {
+                    Some(Litep2pEvent::IncomingConnection { .. }) => {
+                        assert!(!litep2p2_connected);
+                        incoming_reported = true;
+                    }
+                    Some(Litep2pEvent::ConnectionEstablished { endpoint, security, muxer, .. }) => {
+                        assert!(incoming_reported);
+                        assert!(std::matches!(endpoint, Endpoint::Listener { .. }));
+                        assert_eq!(security, Security::Noise);
+                        assert_eq!(muxer, Muxer::Yamux);
+                        litep2p2_connected = true;
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_close_reason_reported() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (ping_config1, _ping_event_stream1) = ping::Config::default();
+        let config1 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config1)
+            .build();
+
+        let (ping_config2, _ping_event_stream2) = ping::Config::default();
+        let config2 = ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_libp2p_ping(ping_config2)
+            .build();
+
+        let mut litep2p1 = Litep2p::new(config1).unwrap();
+        let mut litep2p2 = Litep2p::new(config2).unwrap();
+        let peer2 = *litep2p2.local_peer_id();
+        let address = litep2p2.listen_addresses().next().unwrap().clone();
+
+        litep2p1.dial_address(address).await.unwrap();
+
+        let mut litep2p1_connected = false;
+        let mut litep2p2_connected = false;
+
+        while !litep2p1_connected || !litep2p2_connected {
+            tokio::select! {
+                event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p1_connected = true;
+                },
+                event = litep2p2.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { .. }) = event {
+                    litep2p2_connected = true;
+                },
+            }
+        }
+
+        litep2p1.ban_peer(peer2, std::time::Duration::from_secs(60));
+
+        let mut litep2p1_reason = None;
+        let mut litep2p2_reason = None;
+
+        while litep2p1_reason.is_none() || litep2p2_reason.is_none() {
+            tokio::select! {
+                event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionClosed { reason, .. }) = event {
+                    litep2p1_reason = Some(reason);
+                },
+                event = litep2p2.next_event() => if let Some(Litep2pEvent::ConnectionClosed { reason, .. }) = event {
+                    litep2p2_reason = Some(reason);
+                },
+            }
+        }
+
+        assert_eq!(litep2p1_reason, Some(ConnectionCloseReason::Banned));
+        // the remote may fail to write to the socket before it reads the `GoAway` frame
+        assert!(std::matches!(
+            litep2p2_reason,
+            Some(ConnectionCloseReason::RemoteGoAway | ConnectionCloseReason::IoError(_))
+        ));
+    }
 }
@@ -0,0 +1,528 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Metrics.
+//!
+//! `litep2p` doesn't depend on any particular metrics library. Instead, the user provides an
+//! implementation of [`MetricsRegistry`] which `litep2p` uses to register its metrics at startup.
+//! The metric types map directly to Prometheus counters, gauges and histograms with labels so the
+//! registry can be backed by, e.g., a `prometheus` registry or a text exposition encoder.
+//!
+//! The following metrics are registered:
+//!
+//! | Name | Type | Labels |
+//! |------|------|--------|
+//! | `litep2p_transport_bytes_total` | counter | `transport`, `direction` |
+//! | `litep2p_protocol_bytes_total` | counter | `protocol`, `direction` |
+//! | `litep2p_connections` | gauge | `transport` |
+//! | `litep2p_substreams` | gauge | `protocol` |
+//! | `litep2p_dials_total` | counter | `transport`, `result` |
+//! | `litep2p_handshake_duration_seconds` | histogram | `security` |
+//! | `litep2p_notification_queue_depth` | gauge | `protocol` |
+//! | `litep2p_request_duration_seconds` | histogram | `protocol` |
+
+use crate::{error::Error, transport::manager::SupportedTransport, ProtocolName};
+
+use std::{fmt, sync::Arc, time::Duration};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::metrics";
+
+/// Buckets used for the handshake duration histogram, in seconds.
+const HANDSHAKE_DURATION_BUCKETS: &[f64] =
+    &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Buckets used for the request duration histogram, in seconds.
+const REQUEST_DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0];
+
+/// Counter with labels.
+pub trait Counter: Send + Sync {
+    /// Increase the counter identified by `label_values` by `value`.
+    fn inc_by(&self, label_values: &[&str], value: u64);
+}
+
+/// Gauge with labels.
+pub trait Gauge: Send + Sync {
+    /// Add `value` to the gauge identified by `label_values`.
+    ///
+    /// `value` may be negative.
+    fn add(&self, label_values: &[&str], value: i64);
+
+    /// Set the gauge identified by `label_values` to `value`.
+    fn set(&self, label_values: &[&str], value: i64);
+}
+
+/// Histogram with labels.
+pub trait Histogram: Send + Sync {
+    /// Observe `value` for the histogram identified by `label_values`.
+    fn observe(&self, label_values: &[&str], value: f64);
+}
+
+/// Registered counter.
+pub type MetricCounter = Arc<dyn Counter>;
+
+/// Registered gauge.
+pub type MetricGauge = Arc<dyn Gauge>;
+
+/// Registered histogram.
+pub type MetricHistogram = Arc<dyn Histogram>;
+
+/// Metrics registry.
+///
+/// Label values are given to the metric in the same order as the label names were given to the
+/// registry.
+pub trait MetricsRegistry: Send + Sync {
+    /// Register counter.
+    fn register_counter(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> crate::Result<MetricCounter>;
+
+    /// Register gauge.
+    fn register_gauge(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> crate::Result<MetricGauge>;
+
+    /// Register histogram with `buckets`.
+    fn register_histogram(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> crate::Result<MetricHistogram>;
+}
+
+/// Traffic direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    /// Inbound traffic.
+    Inbound,
+
+    /// Outbound traffic.
+    Outbound,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+/// Get label for `transport`.
+fn transport_label(transport: SupportedTransport) -> &'static str {
+    match transport {
+        SupportedTransport::Tcp => "tcp",
+        SupportedTransport::Quic => "quic",
+        SupportedTransport::WebRtc => "webrtc",
+        SupportedTransport::WebSocket => "websocket",
+        SupportedTransport::Memory => "memory",
+    }
+}
+
+/// Get label for a dial error.
+fn dial_error_label(error: &Error) -> &'static str {
+    match error {
+        Error::Timeout => "timeout",
+        Error::IoError(_) => "io",
+        Error::NegotiationError(_) => "negotiation",
+        Error::AddressError(_) | Error::DnsAddressResolutionFailed => "address",
+        Error::PeerIdMismatch(_, _) => "peer-id-mismatch",
+        Error::TransportNotSupported(_) => "transport-not-supported",
+        Error::YamuxError(_, _) => "yamux",
+        Error::WebSocket(_) => "websocket",
+        Error::Quinn(_) => "quic",
+        Error::ConnectionRejected(_) => "rejected",
+        _ => "other",
+    }
+}
+
+/// Registered metrics.
+struct InnerMetrics {
+    transport_bytes: MetricCounter,
+    protocol_bytes: MetricCounter,
+    connections: MetricGauge,
+    substreams: MetricGauge,
+    dials: MetricCounter,
+    handshake_duration: MetricHistogram,
+    notification_queue_depth: MetricGauge,
+    request_duration: MetricHistogram,
+}
+
+/// Metrics recorder used internally by `litep2p`.
+///
+/// If no [`MetricsRegistry`] was configured, all recording functions are no-ops.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics(Option<Arc<InnerMetrics>>);
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics").field("enabled", &self.0.is_some()).finish()
+    }
+}
+
+impl Metrics {
+    /// Register `litep2p` metrics to `registry`.
+    pub(crate) fn new(registry: &dyn MetricsRegistry) -> crate::Result<Self> {
+        tracing::debug!(target: LOG_TARGET, "register metrics");
+
+        Ok(Self(Some(Arc::new(InnerMetrics {
+            transport_bytes: registry.register_counter(
+                "litep2p_transport_bytes_total",
+                "Number of bytes sent and received over substreams, per transport",
+                &["transport", "direction"],
+            )?,
+            protocol_bytes: registry.register_counter(
+                "litep2p_protocol_bytes_total",
+                "Number of bytes sent and received over substreams, per protocol",
+                &["protocol", "direction"],
+            )?,
+            connections: registry.register_gauge(
+                "litep2p_connections",
+                "Number of open connections",
+                &["transport"],
+            )?,
+            substreams: registry.register_gauge(
+                "litep2p_substreams",
+                "Number of open substreams",
+                &["protocol"],
+            )?,
+            dials: registry.register_counter(
+                "litep2p_dials_total",
+                "Number of concluded dial attempts by result",
+                &["transport", "result"],
+            )?,
+            handshake_duration: registry.register_histogram(
+                "litep2p_handshake_duration_seconds",
+                "Time it took to upgrade a connection, including the security handshake",
+                &["security"],
+                HANDSHAKE_DURATION_BUCKETS,
+            )?,
+            notification_queue_depth: registry.register_gauge(
+                "litep2p_notification_queue_depth",
+                "Number of outbound notifications waiting to be sent",
+                &["protocol"],
+            )?,
+            request_duration: registry.register_histogram(
+                "litep2p_request_duration_seconds",
+                "Time it took to receive a response to an outbound request",
+                &["protocol"],
+                REQUEST_DURATION_BUCKETS,
+            )?,
+        }))))
+    }
+
+    /// Record `bytes` sent or received over a substream of `protocol` over `transport`.
+    pub(crate) fn on_bytes(
+        &self,
+        transport: SupportedTransport,
+        protocol: &ProtocolName,
+        direction: Direction,
+        bytes: usize,
+    ) {
+        if let Some(inner) = &self.0 {
+            inner.transport_bytes.inc_by(
+                &[transport_label(transport), direction.as_str()],
+                bytes as u64,
+            );
+            inner.protocol_bytes.inc_by(&[&protocol[..], direction.as_str()], bytes as u64);
+        }
+    }
+
+    /// Record opened connection.
+    pub(crate) fn on_connection_opened(&self, transport: SupportedTransport) {
+        if let Some(inner) = &self.0 {
+            inner.connections.add(&[transport_label(transport)], 1i64);
+        }
+    }
+
+    /// Record closed connection.
+    pub(crate) fn on_connection_closed(&self, transport: SupportedTransport) {
+        if let Some(inner) = &self.0 {
+            inner.connections.add(&[transport_label(transport)], -1i64);
+        }
+    }
+
+    /// Record opened substream.
+    pub(crate) fn on_substream_opened(&self, protocol: &ProtocolName) {
+        if let Some(inner) = &self.0 {
+            inner.substreams.add(&[&protocol[..]], 1i64);
+        }
+    }
+
+    /// Record closed substream.
+    pub(crate) fn on_substream_closed(&self, protocol: &ProtocolName) {
+        if let Some(inner) = &self.0 {
+            inner.substreams.add(&[&protocol[..]], -1i64);
+        }
+    }
+
+    /// Record successful dial.
+    pub(crate) fn on_dial_success(&self, transport: SupportedTransport) {
+        if let Some(inner) = &self.0 {
+            inner.dials.inc_by(&[transport_label(transport), "success"], 1u64);
+        }
+    }
+
+    /// Record failed dial.
+    pub(crate) fn on_dial_failure(&self, transport: SupportedTransport, error: &Error) {
+        if let Some(inner) = &self.0 {
+            inner.dials.inc_by(&[transport_label(transport), dial_error_label(error)], 1u64);
+        }
+    }
+
+    /// Record duration of a connection upgrade secured with `security`.
+    pub(crate) fn on_handshake(&self, security: &str, duration: Duration) {
+        if let Some(inner) = &self.0 {
+            inner.handshake_duration.observe(&[security], duration.as_secs_f64());
+        }
+    }
+
+    /// Record duration of an outbound request.
+    pub(crate) fn on_request_completed(&self, protocol: &ProtocolName, duration: Duration) {
+        if let Some(inner) = &self.0 {
+            inner.request_duration.observe(&[&protocol[..]], duration.as_secs_f64());
+        }
+    }
+
+    /// Get handle for tracking the outbound notification queue depth of `protocol`.
+    pub(crate) fn notification_queue(&self, protocol: &ProtocolName) -> QueueDepth {
+        QueueDepth {
+            gauge: self.0.as_ref().map(|inner| Arc::clone(&inner.notification_queue_depth)),
+            protocol: protocol.clone(),
+        }
+    }
+}
+
+/// Handle for tracking the number of queued items of a protocol.
+#[derive(Clone)]
+pub(crate) struct QueueDepth {
+    /// Gauge, if metrics are enabled.
+    gauge: Option<MetricGauge>,
+
+    /// Protocol.
+    protocol: ProtocolName,
+}
+
+impl fmt::Debug for QueueDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueDepth").field("protocol", &self.protocol).finish()
+    }
+}
+
+impl QueueDepth {
+    /// Record `num_items` queued items.
+    pub(crate) fn enqueued(&self, num_items: usize) {
+        if let Some(gauge) = &self.gauge {
+            gauge.add(&[&self.protocol[..]], num_items as i64);
+        }
+    }
+
+    /// Record `num_items` dequeued items.
+    pub(crate) fn dequeued(&self, num_items: usize) {
+        if let Some(gauge) = &self.gauge {
+            gauge.add(&[&self.protocol[..]], -(num_items as i64));
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    /// Metric values, keyed by name and label values.
+    #[derive(Default)]
+    pub(crate) struct Values(Mutex<HashMap<(&'static str, Vec<String>), f64>>);
+
+    impl Values {
+        pub(crate) fn get(&self, name: &'static str, label_values: &[&str]) -> Option<f64> {
+            self.0
+                .lock()
+                .get(&(
+                    name,
+                    label_values.iter().map(|value| value.to_string()).collect(),
+                ))
+                .copied()
+        }
+
+        fn update(&self, name: &'static str, label_values: &[&str], f: impl FnOnce(&mut f64)) {
+            f(self
+                .0
+                .lock()
+                .entry((
+                    name,
+                    label_values.iter().map(|value| value.to_string()).collect(),
+                ))
+                .or_default())
+        }
+    }
+
+    struct Metric {
+        name: &'static str,
+        values: Arc<Values>,
+    }
+
+    impl Counter for Metric {
+        fn inc_by(&self, label_values: &[&str], value: u64) {
+            self.values.update(self.name, label_values, |current| *current += value as f64);
+        }
+    }
+
+    impl Gauge for Metric {
+        fn add(&self, label_values: &[&str], value: i64) {
+            self.values.update(self.name, label_values, |current| *current += value as f64);
+        }
+
+        fn set(&self, label_values: &[&str], value: i64) {
+            self.values.update(self.name, label_values, |current| *current = value as f64);
+        }
+    }
+
+    impl Histogram for Metric {
+        fn observe(&self, label_values: &[&str], _value: f64) {
+            // only count observations
+            self.values.update(self.name, label_values, |current| *current += 1f64);
+        }
+    }
+
+    /// Registry which stores the latest metric values in memory.
+    #[derive(Default)]
+    pub(crate) struct TestRegistry {
+        pub(crate) values: Arc<Values>,
+    }
+
+    impl TestRegistry {
+        fn metric(&self, name: &'static str) -> Arc<Metric> {
+            Arc::new(Metric {
+                name,
+                values: Arc::clone(&self.values),
+            })
+        }
+    }
+
+    impl MetricsRegistry for TestRegistry {
+        fn register_counter(
+            &self,
+            name: &'static str,
+            _help: &'static str,
+            _labels: &'static [&'static str],
+        ) -> crate::Result<MetricCounter> {
+            Ok(self.metric(name))
+        }
+
+        fn register_gauge(
+            &self,
+            name: &'static str,
+            _help: &'static str,
+            _labels: &'static [&'static str],
+        ) -> crate::Result<MetricGauge> {
+            Ok(self.metric(name))
+        }
+
+        fn register_histogram(
+            &self,
+            name: &'static str,
+            _help: &'static str,
+            _labels: &'static [&'static str],
+            _buckets: &'static [f64],
+        ) -> crate::Result<MetricHistogram> {
+            Ok(self.metric(name))
+        }
+    }
+
+    #[test]
+    fn metrics_disabled_by_default() {
+        let metrics = Metrics::default();
+        let protocol = ProtocolName::from("/notif/1");
+
+        metrics.on_bytes(
+            SupportedTransport::Tcp,
+            &protocol,
+            Direction::Inbound,
+            1337usize,
+        );
+        metrics.notification_queue(&protocol).enqueued(1usize);
+    }
+
+    #[test]
+    fn metrics_are_recorded() {
+        let registry = TestRegistry::default();
+        let metrics = Metrics::new(&registry).unwrap();
+        let protocol = ProtocolName::from("/notif/1");
+
+        metrics.on_bytes(
+            SupportedTransport::Tcp,
+            &protocol,
+            Direction::Inbound,
+            1337usize,
+        );
+        metrics.on_bytes(
+            SupportedTransport::Quic,
+            &protocol,
+            Direction::Outbound,
+            1338usize,
+        );
+        assert_eq!(
+            registry.values.get("litep2p_transport_bytes_total", &["tcp", "inbound"]),
+            Some(1337f64)
+        );
+        assert_eq!(
+            registry.values.get("litep2p_protocol_bytes_total", &["/notif/1", "outbound"]),
+            Some(1338f64)
+        );
+
+        metrics.on_connection_opened(SupportedTransport::Tcp);
+        metrics.on_connection_opened(SupportedTransport::Tcp);
+        metrics.on_connection_closed(SupportedTransport::Tcp);
+        assert_eq!(
+            registry.values.get("litep2p_connections", &["tcp"]),
+            Some(1f64)
+        );
+
+        metrics.on_dial_success(SupportedTransport::Tcp);
+        metrics.on_dial_failure(SupportedTransport::Tcp, &Error::Timeout);
+        assert_eq!(
+            registry.values.get("litep2p_dials_total", &["tcp", "success"]),
+            Some(1f64)
+        );
+        assert_eq!(
+            registry.values.get("litep2p_dials_total", &["tcp", "timeout"]),
+            Some(1f64)
+        );
+
+        let queue = metrics.notification_queue(&protocol);
+        queue.enqueued(3usize);
+        queue.dequeued(2usize);
+        assert_eq!(
+            registry.values.get("litep2p_notification_queue_depth", &["/notif/1"]),
+            Some(1f64)
+        );
+    }
+}
@@ -23,6 +23,7 @@
 use crate::{
     error::Error,
     protocol::protocol_set::ProtocolCommand,
+    transport::ConnectionCloseReason,
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
 };
 
@@ -82,6 +83,15 @@ impl ConnectionHandle {
         }
     }
 
+    /// Get an active handle to the connection, if the connection is still open.
+    pub(crate) fn upgrade(&self) -> Option<Self> {
+        match &self.connection {
+            ConnectionType::Active(active) => Some(Self::new(self.connection_id, active.clone())),
+            ConnectionType::Inactive(inactive) =>
+                Some(Self::new(self.connection_id, inactive.upgrade()?)),
+        }
+    }
+
     /// Get reference to connection ID.
     pub fn connection_id(&self) -> &ConnectionId {
         &self.connection_id
@@ -128,14 +138,14 @@ impl ConnectionHandle {
         })
     }
 
-    /// Force close connection.
-    pub fn force_close(&mut self) -> crate::Result<()> {
+    /// Force close connection for `reason`.
+    pub fn force_close(&mut self, reason: ConnectionCloseReason) -> crate::Result<()> {
         match &self.connection {
             ConnectionType::Active(active) => active.clone(),
             ConnectionType::Inactive(inactive) =>
                 inactive.upgrade().ok_or(Error::ConnectionClosed)?,
         }
-        .try_send(ProtocolCommand::ForceClose)
+        .try_send(ProtocolCommand::ForceClose { reason })
         .map_err(|error| match error {
             TrySendError::Full(_) => Error::ChannelClogged,
             TrySendError::Closed(_) => Error::ConnectionClosed,
@@ -34,7 +34,7 @@ use crate::{
 use futures::{future::BoxFuture, stream::FuturesUnordered, Stream, StreamExt};
 use multiaddr::Multiaddr;
 use prost::Message;
-use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio_stream::wrappers::ReceiverStream;
 
 use std::{
@@ -49,7 +49,7 @@ const LOG_TARGET: &str = "litep2p::ipfs::identify";
 const PROTOCOL_NAME: &str = "/ipfs/id/1.0.0";
 
 /// IPFS Identify push protocol name.
-const _PUSH_PROTOCOL_NAME: &str = "/ipfs/id/push/1.0.0";
+const PUSH_PROTOCOL_NAME: &str = "/ipfs/id/push/1.0.0";
 
 /// Default agent version.
 const DEFAULT_AGENT: &str = "litep2p/1.0.0";
@@ -67,6 +67,9 @@ pub struct Config {
     /// Protocol name.
     pub(crate) protocol: ProtocolName,
 
+    /// Push protocol name.
+    pub(crate) push_protocol: ProtocolName,
+
     /// Codec used by the protocol.
     pub(crate) codec: ProtocolCodec,
 
@@ -111,6 +114,7 @@ impl Config {
                 codec: ProtocolCodec::UnsignedVarint(Some(IDENTIFY_PAYLOAD_SIZE)),
                 protocols: Vec::new(),
                 protocol: ProtocolName::from(PROTOCOL_NAME),
+                push_protocol: ProtocolName::from(PUSH_PROTOCOL_NAME),
             },
             Box::new(ReceiverStream::new(rx_event)),
         )
@@ -142,6 +146,30 @@ pub enum IdentifyEvent {
     },
 }
 
+/// Commands sent to [`Identify`] by [`Litep2p`](crate::Litep2p).
+#[derive(Debug)]
+pub(crate) enum IdentifyCommand {
+    /// Local node started listening on `address`.
+    AddListenAddress {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Local node stopped listening on `address`.
+    RemoveListenAddress {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Protocols supported by the local node changed.
+    ///
+    /// The new protocols are pushed to all connected peers.
+    SetProtocols {
+        /// Supported protocols.
+        protocols: Vec<ProtocolName>,
+    },
+}
+
 /// Identify response received from remote.
 struct IdentifyResponse {
     /// Remote peer ID.
@@ -167,9 +195,18 @@ pub(crate) struct Identify {
     // Connection service.
     service: TransportService,
 
+    // Connection service of the push protocol.
+    push_service: TransportService,
+
     /// TX channel for sending events to the user protocol.
     tx: Sender<IdentifyEvent>,
 
+    /// RX channel for receiving commands from `Litep2p`.
+    cmd_rx: Receiver<IdentifyCommand>,
+
+    /// TX channel for reporting the addresses remote peers observed for the local node.
+    observed_tx: Sender<(PeerId, Multiaddr)>,
+
     /// Connected peers and their observed addresses.
     peers: HashMap<PeerId, Endpoint>,
 
@@ -202,11 +239,17 @@ impl Identify {
     /// Create new [`Identify`] protocol.
     pub(crate) fn new(
         service: TransportService,
+        push_service: TransportService,
         config: Config,
         listen_addresses: Vec<Multiaddr>,
+        cmd_rx: Receiver<IdentifyCommand>,
+        observed_tx: Sender<(PeerId, Multiaddr)>,
     ) -> Self {
         Self {
             service,
+            push_service,
+            cmd_rx,
+            observed_tx,
             tx: config.tx_event,
             peers: HashMap::new(),
             listen_addresses: config.public_addresses.into_iter().chain(listen_addresses).collect(),
@@ -238,33 +281,36 @@ impl Identify {
         self.peers.remove(&peer);
     }
 
-    /// Inbound substream opened.
-    fn on_inbound_substream(
-        &mut self,
-        peer: PeerId,
-        protocol: ProtocolName,
-        mut substream: Substream,
-    ) {
-        tracing::trace!(
-            target: LOG_TARGET,
-            ?peer,
-            ?protocol,
-            "inbound substream opened"
-        );
+    /// Handle command received from `Litep2p`.
+    fn on_command(&mut self, command: IdentifyCommand) {
+        tracing::trace!(target: LOG_TARGET, ?command, "handle command");
 
-        let observed_addr = match self.peers.get(&peer) {
-            Some(endpoint) => Some(endpoint.address().to_vec()),
-            None => {
-                tracing::warn!(
-                    target: LOG_TARGET,
-                    ?peer,
-                    %protocol,
-                    "inbound identify substream opened for peer who doesn't exist",
-                );
-                None
+        match command {
+            IdentifyCommand::AddListenAddress { address } => {
+                self.listen_addresses.insert(address);
             }
-        };
+            IdentifyCommand::RemoveListenAddress { address } => {
+                self.listen_addresses.remove(&address);
+            }
+            IdentifyCommand::SetProtocols { protocols } => {
+                self.protocols = protocols.iter().map(|protocol| protocol.to_string()).collect();
+
+                for peer in self.peers.keys() {
+                    if let Err(error) = self.push_service.open_substream(*peer) {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            ?error,
+                            "failed to open identify push substream",
+                        );
+                    }
+                }
+            }
+        }
+    }
 
+    /// Create encoded identify message of the local node.
+    fn identify_message(&self, observed_addr: Option<Vec<u8>>) -> Vec<u8> {
         let identify = identify_schema::Identify {
             protocol_version: Some(self.protocol_version.clone()),
             agent_version: Some(self.user_agent.clone()),
@@ -278,17 +324,21 @@ impl Identify {
             protocols: self.protocols.clone(),
         };
 
-        tracing::trace!(
-            target: LOG_TARGET,
-            ?peer,
-            ?identify,
-            "sending identify response",
-        );
+        tracing::trace!(target: LOG_TARGET, ?identify, "create identify message");
 
         let mut msg = Vec::with_capacity(identify.encoded_len());
         identify.encode(&mut msg).expect("`msg` to have enough capacity");
 
-        self.pending_inbound.push(Box::pin(async move {
+        msg
+    }
+
+    /// Send identify message `msg` to `peer` over `substream`.
+    fn send_identify(
+        peer: PeerId,
+        mut substream: Substream,
+        msg: Vec<u8>,
+    ) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
             match tokio::time::timeout(Duration::from_secs(10), substream.send_framed(msg.into()))
                 .await
             {
@@ -297,7 +347,7 @@ impl Identify {
                         target: LOG_TARGET,
                         ?peer,
                         ?error,
-                        "timed out while sending ipfs identify response",
+                        "timed out while sending ipfs identify message",
                     );
                 }
                 Ok(Err(error)) => {
@@ -305,38 +355,28 @@ impl Identify {
                         target: LOG_TARGET,
                         ?peer,
                         ?error,
-                        "failed to send ipfs identify response",
+                        "failed to send ipfs identify message",
                     );
                 }
                 Ok(_) => {}
             }
-        }))
+        })
     }
 
-    /// Outbound substream opened.
-    fn on_outbound_substream(
-        &mut self,
+    /// Read identify message of `peer` from `substream`.
+    fn read_identify(
         peer: PeerId,
-        protocol: ProtocolName,
-        substream_id: SubstreamId,
+        substream_id: Option<SubstreamId>,
         mut substream: Substream,
-    ) {
-        tracing::trace!(
-            target: LOG_TARGET,
-            ?peer,
-            ?protocol,
-            ?substream_id,
-            "outbound substream opened"
-        );
-
-        self.pending_outbound.push(Box::pin(async move {
+    ) -> BoxFuture<'static, crate::Result<IdentifyResponse>> {
+        Box::pin(async move {
             let payload =
                 match tokio::time::timeout(Duration::from_secs(10), substream.next()).await {
                     Err(_) => return Err(Error::Timeout),
                     Ok(None) =>
-                        return Err(Error::SubstreamError(SubstreamError::ReadFailure(Some(
+                        return Err(Error::SubstreamError(SubstreamError::ReadFailure(
                             substream_id,
-                        )))),
+                        ))),
                     Ok(Some(Err(error))) => return Err(error),
                     Ok(Some(Ok(payload))) => payload,
                 };
@@ -363,7 +403,67 @@ impl Identify {
                 observed_address,
                 listen_addresses,
             })
-        }));
+        })
+    }
+
+    /// Identify push substream opened.
+    fn on_push_substream(&mut self, peer: PeerId, direction: Direction, substream: Substream) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?direction, "identify push substream opened");
+
+        match direction {
+            Direction::Inbound =>
+                self.pending_outbound.push(Self::read_identify(peer, None, substream)),
+            Direction::Outbound(_) => {
+                let msg = self.identify_message(None);
+                self.pending_inbound.push(Self::send_identify(peer, substream, msg));
+            }
+        }
+    }
+
+    /// Inbound substream opened.
+    fn on_inbound_substream(&mut self, peer: PeerId, protocol: ProtocolName, substream: Substream) {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            ?protocol,
+            "inbound substream opened"
+        );
+
+        let observed_addr = match self.peers.get(&peer) {
+            Some(endpoint) => Some(endpoint.address().to_vec()),
+            None => {
+                tracing::warn!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    %protocol,
+                    "inbound identify substream opened for peer who doesn't exist",
+                );
+                None
+            }
+        };
+
+        let msg = self.identify_message(observed_addr);
+        self.pending_inbound.push(Self::send_identify(peer, substream, msg));
+    }
+
+    /// Outbound substream opened.
+    fn on_outbound_substream(
+        &mut self,
+        peer: PeerId,
+        protocol: ProtocolName,
+        substream_id: SubstreamId,
+        substream: Substream,
+    ) {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            ?protocol,
+            ?substream_id,
+            "outbound substream opened"
+        );
+
+        self.pending_outbound
+            .push(Self::read_identify(peer, Some(substream_id), substream));
     }
 
     /// Start [`Identify`] event loop.
@@ -377,7 +477,7 @@ impl Identify {
                     Some(TransportEvent::ConnectionEstablished { peer, endpoint }) => {
                         let _ = self.on_connection_established(peer, endpoint);
                     }
-                    Some(TransportEvent::ConnectionClosed { peer }) => {
+                    Some(TransportEvent::ConnectionClosed { peer, .. }) => {
                         self.on_connection_closed(peer);
                     }
                     Some(TransportEvent::SubstreamOpened {
@@ -392,9 +492,20 @@ impl Identify {
                     },
                     _ => {}
                 },
+                event = self.push_service.next() => match event {
+                    None => return,
+                    Some(TransportEvent::SubstreamOpened { peer, direction, substream, .. }) =>
+                        self.on_push_substream(peer, direction, substream),
+                    _ => {}
+                },
+                Some(command) = self.cmd_rx.recv() => self.on_command(command),
                 _ = self.pending_inbound.next(), if !self.pending_inbound.is_empty() => {}
                 event = self.pending_outbound.next(), if !self.pending_outbound.is_empty() => match event {
                     Some(Ok(response)) => {
+                        if let Some(address) = &response.observed_address {
+                            let _ = self.observed_tx.try_send((response.peer, address.clone()));
+                        }
+
                         let _ = self.tx
                             .send(IdentifyEvent::PeerIdentified {
                                 peer: response.peer,
@@ -22,81 +22,161 @@
 //! Kademlia k-bucket implementation.
 
 use crate::{
-    protocol::libp2p::kademlia::types::{ConnectionType, KademliaPeer, Key},
+    protocol::libp2p::kademlia::types::{KademliaPeer, Key},
     PeerId,
 };
 
+use std::time::{Duration, Instant};
+
+/// Time after which a pending entry can be replaced if the probe of the least recently seen node
+/// hasn't finished.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// K-bucket entry.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug)]
 pub enum KBucketEntry<'a> {
     /// Entry points to local node.
     LocalNode,
 
-    /// Occupied entry to a connected node.
+    /// Entry to a node in the k-bucket.
     Occupied(&'a mut KademliaPeer),
 
-    /// Vacant entry.
-    Vacant(&'a mut KademliaPeer),
+    /// Entry not found and the k-bucket has room for it.
+    Vacant(VacantEntry<'a>),
+
+    /// Entry not found and the k-bucket is full.
+    Full(FullEntry<'a>),
+}
+
+/// Vacant entry into a k-bucket.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    bucket: &'a mut KBucket,
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Insert `node` into the k-bucket as the most recently seen node.
+    pub fn insert(self, node: KademliaPeer) {
+        self.bucket.nodes.push(node);
+    }
+}
 
-    /// Entry not found and any present entry cannot be replaced.
-    NoSlot,
+/// Entry into a full k-bucket.
+#[derive(Debug)]
+pub struct FullEntry<'a> {
+    bucket: &'a mut KBucket,
 }
 
-impl<'a> KBucketEntry<'a> {
-    /// Insert new entry into the entry if possible.
-    pub fn insert(&'a mut self, new: KademliaPeer) {
-        if let KBucketEntry::Vacant(old) = self {
-            old.peer = new.peer;
-            old.key = Key::from(new.peer);
-            old.addresses = new.addresses;
-            old.connection = new.connection;
+impl<'a> FullEntry<'a> {
+    /// Make `node` the pending entry of the k-bucket.
+    ///
+    /// The pending entry replaces the least recently seen node if that node fails to respond to a
+    /// probe. Returns the least recently seen node which must be probed, or `None` if the k-bucket
+    /// already has a pending entry, in which case `node` is discarded.
+    pub fn insert_pending(self, node: KademliaPeer, now: Instant) -> Option<PeerId> {
+        if let Some(pending) = &self.bucket.pending {
+            if pending.deadline > now {
+                return None;
+            }
         }
+
+        self.bucket.pending = Some(PendingEntry {
+            node,
+            deadline: now + PENDING_TIMEOUT,
+        });
+        self.bucket.nodes.first().map(|node| node.peer)
     }
 }
 
+/// Node waiting to be inserted into a full k-bucket.
+#[derive(Debug)]
+struct PendingEntry {
+    /// Pending node.
+    node: KademliaPeer,
+
+    /// Time after which the entry can be replaced by another pending entry.
+    deadline: Instant,
+}
+
 /// Kademlia k-bucket.
+#[derive(Debug)]
 pub struct KBucket {
-    // TODO: store peers in a btreemap with increasing distance from local key?
+    /// Nodes, ordered from the least recently seen to the most recently seen.
     nodes: Vec<KademliaPeer>,
+
+    /// Node waiting for the result of the least recently seen node's probe, if any.
+    pending: Option<PendingEntry>,
+
+    /// Maximum number of nodes in the k-bucket.
+    max_size: usize,
 }
 
 impl KBucket {
     /// Create new [`KBucket`].
-    pub fn new() -> Self {
+    pub fn new(max_size: usize) -> Self {
         Self {
-            nodes: Vec::with_capacity(20),
+            nodes: Vec::with_capacity(max_size),
+            pending: None,
+            max_size,
         }
     }
 
     /// Get entry into the bucket.
-    // TODO: this is horrible code
     pub fn entry<K: Clone>(&mut self, key: Key<K>) -> KBucketEntry<'_> {
-        for i in 0..self.nodes.len() {
-            if self.nodes[i].key == key {
-                return KBucketEntry::Occupied(&mut self.nodes[i]);
-            }
+        if let Some(index) = self.nodes.iter().position(|node| node.key == key) {
+            return KBucketEntry::Occupied(&mut self.nodes[index]);
         }
 
-        if self.nodes.len() < 20 {
-            self.nodes.push(KademliaPeer::new(
-                PeerId::random(),
-                vec![],
-                ConnectionType::NotConnected,
-            ));
-            let len = self.nodes.len() - 1;
-            return KBucketEntry::Vacant(&mut self.nodes[len]);
+        match self.nodes.len() < self.max_size {
+            true => KBucketEntry::Vacant(VacantEntry { bucket: self }),
+            false => KBucketEntry::Full(FullEntry { bucket: self }),
         }
+    }
 
-        for i in 0..self.nodes.len() {
-            match self.nodes[i].connection {
-                ConnectionType::NotConnected | ConnectionType::CannotConnect => {
-                    return KBucketEntry::Vacant(&mut self.nodes[i]);
-                }
-                _ => continue,
-            }
+    /// Mark `key` as the most recently seen node of the k-bucket.
+    pub fn on_node_seen<K: Clone>(&mut self, key: &Key<K>) {
+        if let Some(index) = self.nodes.iter().position(|node| &node.key == key) {
+            let node = self.nodes.remove(index);
+            self.nodes.push(node);
         }
+    }
 
-        KBucketEntry::NoSlot
+    /// Handle the result of the probe sent to `key`.
+    ///
+    /// If the node responded, it's marked as the most recently seen node and the pending entry is
+    /// discarded. Otherwise the node is removed and replaced by the pending entry, if any.
+    pub fn on_probe_result<K: Clone>(&mut self, key: &Key<K>, alive: bool) {
+        if alive {
+            self.on_node_seen(key);
+            self.pending = None;
+            return;
+        }
+
+        self.remove(key);
+    }
+
+    /// Remove `key` from the k-bucket.
+    ///
+    /// The pending entry, if any, takes the place of the removed node. If `key` is the pending
+    /// entry, only the pending entry is removed.
+    pub fn remove<K: Clone>(&mut self, key: &Key<K>) -> Option<KademliaPeer> {
+        if self.pending.as_ref().is_some_and(|pending| &pending.node.key == key) {
+            return self.pending.take().map(|pending| pending.node);
+        }
+
+        let index = self.nodes.iter().position(|node| &node.key == key)?;
+        let node = self.nodes.remove(index);
+
+        if let Some(pending) = self.pending.take() {
+            self.nodes.push(pending.node);
+        }
+
+        Some(node)
+    }
+
+    /// Get nodes of the k-bucket, ordered from the least recently seen to the most recently seen.
+    pub fn nodes(&self) -> &[KademliaPeer] {
+        &self.nodes
     }
 
     /// Get iterator over the k-bucket, sorting the k-bucket entries in increasing order
@@ -111,10 +191,131 @@ impl KBucket {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::libp2p::kademlia::types::ConnectionType;
+
+    fn node() -> KademliaPeer {
+        KademliaPeer::new(
+            PeerId::random(),
+            vec!["/ip6/::1/tcp/8888".parse().unwrap()],
+            ConnectionType::NotConnected,
+        )
+    }
+
+    fn peers(bucket: &KBucket) -> Vec<PeerId> {
+        bucket.nodes.iter().map(|node| node.peer).collect()
+    }
+
+    #[test]
+    fn seen_node_becomes_most_recently_seen() {
+        let mut bucket = KBucket::new(3);
+        let nodes = (0..3).map(|_| node()).collect::<Vec<_>>();
+
+        for node in &nodes {
+            match bucket.entry(node.key.clone()) {
+                KBucketEntry::Vacant(entry) => entry.insert(node.clone()),
+                _ => panic!("invalid entry"),
+            }
+        }
+        assert_eq!(
+            peers(&bucket),
+            vec![nodes[0].peer, nodes[1].peer, nodes[2].peer]
+        );
+
+        bucket.on_node_seen(&nodes[0].key);
+        assert_eq!(
+            peers(&bucket),
+            vec![nodes[1].peer, nodes[2].peer, nodes[0].peer]
+        );
+
+        assert!(std::matches!(
+            bucket.entry(nodes[1].key.clone()),
+            KBucketEntry::Occupied(_)
+        ));
+        assert!(std::matches!(
+            bucket.entry(Key::from(PeerId::random())),
+            KBucketEntry::Full(_)
+        ));
+    }
+
+    #[test]
+    fn pending_node_replaces_unresponsive_node() {
+        let mut bucket = KBucket::new(2);
+        let nodes = (0..2).map(|_| node()).collect::<Vec<_>>();
+
+        for node in &nodes {
+            match bucket.entry(node.key.clone()) {
+                KBucketEntry::Vacant(entry) => entry.insert(node.clone()),
+                _ => panic!("invalid entry"),
+            }
+        }
+
+        // the least recently seen node must be probed before it's replaced
+        let pending = node();
+        let now = Instant::now();
+        match bucket.entry(pending.key.clone()) {
+            KBucketEntry::Full(entry) => {
+                assert_eq!(
+                    entry.insert_pending(pending.clone(), now),
+                    Some(nodes[0].peer)
+                );
+            }
+            _ => panic!("invalid entry"),
+        }
+
+        // only one node can be pending at a time
+        match bucket.entry(Key::from(PeerId::random())) {
+            KBucketEntry::Full(entry) => assert_eq!(entry.insert_pending(node(), now), None),
+            _ => panic!("invalid entry"),
+        }
+
+        bucket.on_probe_result(&nodes[0].key, false);
+        assert_eq!(peers(&bucket), vec![nodes[1].peer, pending.peer]);
+        assert!(bucket.pending.is_none());
+    }
+
+    #[test]
+    fn responsive_node_is_kept() {
+        let mut bucket = KBucket::new(2);
+        let nodes = (0..2).map(|_| node()).collect::<Vec<_>>();
+
+        for node in &nodes {
+            match bucket.entry(node.key.clone()) {
+                KBucketEntry::Vacant(entry) => entry.insert(node.clone()),
+                _ => panic!("invalid entry"),
+            }
+        }
+
+        let now = Instant::now();
+        match bucket.entry(Key::from(PeerId::random())) {
+            KBucketEntry::Full(entry) => {
+                assert_eq!(entry.insert_pending(node(), now), Some(nodes[0].peer));
+            }
+            _ => panic!("invalid entry"),
+        }
+
+        bucket.on_probe_result(&nodes[0].key, true);
+        assert_eq!(peers(&bucket), vec![nodes[1].peer, nodes[0].peer]);
+        assert!(bucket.pending.is_none());
+
+        // expired pending entry is replaced by a new one
+        match bucket.entry(Key::from(PeerId::random())) {
+            KBucketEntry::Full(entry) => {
+                assert_eq!(entry.insert_pending(node(), now), Some(nodes[1].peer));
+            }
+            _ => panic!("invalid entry"),
+        }
+        match bucket.entry(Key::from(PeerId::random())) {
+            KBucketEntry::Full(entry) => assert_eq!(
+                entry.insert_pending(node(), now + PENDING_TIMEOUT),
+                Some(nodes[1].peer)
+            ),
+            _ => panic!("invalid entry"),
+        }
+    }
 
     #[test]
     fn closest_iter() {
-        let mut bucket = KBucket::new();
+        let mut bucket = KBucket::new(20);
 
         // add some random nodes to the bucket
         let _ = (0..10)
@@ -141,7 +342,7 @@ mod tests {
 
     #[test]
     fn ignore_peers_with_no_addresses() {
-        let mut bucket = KBucket::new();
+        let mut bucket = KBucket::new(20);
 
         // add peers with no addresses to the bucket
         let _ = (0..10)
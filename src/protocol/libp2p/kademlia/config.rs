@@ -20,9 +20,12 @@
 
 use crate::{
     codec::ProtocolCodec,
-    protocol::libp2p::kademlia::handle::{
-        IncomingRecordValidationMode, KademliaCommand, KademliaEvent, KademliaHandle,
-        RoutingTableUpdateMode,
+    protocol::libp2p::kademlia::{
+        handle::{
+            IncomingRecordValidationMode, KademliaCommand, KademliaEvent, KademliaHandle, Mode,
+            RoutingTableUpdateMode,
+        },
+        store::{MemoryStore, RecordStore},
     },
     types::protocol::ProtocolName,
     PeerId, DEFAULT_CHANNEL_SIZE,
@@ -31,17 +34,45 @@ use crate::{
 use multiaddr::Multiaddr;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 
 /// Default TTL for the records.
 const DEFAULT_TTL: u64 = 36 * 60 * 60;
 
+/// Default TTL for the provider records.
+const DEFAULT_PROVIDER_TTL: u64 = 48 * 60 * 60;
+
+/// Default interval for refreshing the routing table.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Default interval for republishing the records published by the local node.
+const DEFAULT_REPUBLISH_INTERVAL: Duration = Duration::from_secs(22 * 60 * 60);
+
+/// Default interval for republishing the provider records of the local node.
+const DEFAULT_PROVIDER_REPUBLISH_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Default interval for replicating the records stored by the local node.
+const DEFAULT_REPLICATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 /// Protocol name.
 const PROTOCOL_NAME: &str = "/ipfs/kad/1.0.0";
 
 /// Kademlia replication factor.
 const REPLICATION_FACTOR: usize = 20usize;
 
+/// Default k-bucket size.
+const KBUCKET_SIZE: usize = 20usize;
+
+/// Default number of disjoint paths used by the lookups.
+const DISJOINT_PATHS: usize = 1usize;
+
+/// Default parallelism factor, `α`.
+const PARALLELISM_FACTOR: usize = 3usize;
+
 /// Kademlia configuration.
 #[derive(Debug)]
 pub struct Config {
@@ -56,6 +87,18 @@ pub struct Config {
     /// Replication factor.
     pub(super) replication_factor: usize,
 
+    /// Maximum number of nodes in a k-bucket.
+    pub(super) kbucket_size: usize,
+
+    /// Number of disjoint paths used by the lookups.
+    pub(super) disjoint_paths: usize,
+
+    /// Parallelism factor.
+    pub(super) parallelism_factor: usize,
+
+    /// Query timeout, if any.
+    pub(super) query_timeout: Option<Duration>,
+
     /// Known peers.
     pub(super) known_peers: HashMap<PeerId, Vec<Multiaddr>>,
 
@@ -65,9 +108,39 @@ pub struct Config {
     /// Incoming records validation mode.
     pub(super) validation_mode: IncomingRecordValidationMode,
 
+    /// Kademlia mode.
+    pub(crate) mode: Mode,
+
+    /// Default TTL for the records.
+    pub(super) record_ttl: Duration,
+
+    /// TTL for the provider records.
+    pub(super) provider_ttl: Duration,
+
+    /// Interval for refreshing the routing table, if enabled.
+    pub(super) refresh_interval: Option<Duration>,
+
+    /// Interval for republishing the records published by the local node, if enabled.
+    pub(super) republish_interval: Option<Duration>,
+
+    /// Interval for replicating the records stored by the local node, if enabled.
+    pub(super) replication_interval: Option<Duration>,
+
+    /// Interval for republishing the provider records of the local node, if enabled.
+    pub(super) provider_republish_interval: Option<Duration>,
+
+    /// Record store.
+    pub(super) record_store: Box<dyn RecordStore>,
+
+    /// Next query ID, shared with `KademliaHandle`.
+    pub(super) next_query_id: Arc<AtomicUsize>,
+
     /// TX channel for sending events to `KademliaHandle`.
     pub(super) event_tx: Sender<KademliaEvent>,
 
+    /// TX channel for informing `Kademlia` about changes in the listen addresses.
+    pub(crate) cmd_tx: Sender<KademliaCommand>,
+
     /// RX channel for receiving commands from `KademliaHandle`.
     pub(super) cmd_rx: Receiver<KademliaCommand>,
 }
@@ -75,14 +148,26 @@ pub struct Config {
 impl Config {
     fn new(
         replication_factor: usize,
+        kbucket_size: usize,
+        disjoint_paths: usize,
+        parallelism_factor: usize,
+        query_timeout: Option<Duration>,
         known_peers: HashMap<PeerId, Vec<Multiaddr>>,
         mut protocol_names: Vec<ProtocolName>,
         update_mode: RoutingTableUpdateMode,
         validation_mode: IncomingRecordValidationMode,
+        mode: Mode,
         record_ttl: Duration,
+        provider_ttl: Duration,
+        refresh_interval: Option<Duration>,
+        republish_interval: Option<Duration>,
+        replication_interval: Option<Duration>,
+        provider_republish_interval: Option<Duration>,
+        record_store: Box<dyn RecordStore>,
     ) -> (Self, KademliaHandle) {
         let (cmd_tx, cmd_rx) = channel(DEFAULT_CHANNEL_SIZE);
         let (event_tx, event_rx) = channel(DEFAULT_CHANNEL_SIZE);
+        let next_query_id = Arc::new(AtomicUsize::new(0usize));
 
         // if no protocol names were provided, use the default protocol
         if protocol_names.is_empty() {
@@ -94,13 +179,27 @@ impl Config {
                 protocol_names,
                 update_mode,
                 validation_mode,
+                mode,
+                record_ttl,
+                provider_ttl,
+                refresh_interval,
+                republish_interval,
+                replication_interval,
+                provider_republish_interval,
+                record_store,
+                next_query_id: next_query_id.clone(),
                 codec: ProtocolCodec::UnsignedVarint(None),
                 replication_factor,
+                kbucket_size,
+                disjoint_paths,
+                parallelism_factor,
+                query_timeout,
                 known_peers,
                 cmd_rx,
                 event_tx,
+                cmd_tx: cmd_tx.clone(),
             },
-            KademliaHandle::new(cmd_tx, event_rx, record_ttl),
+            KademliaHandle::new(cmd_tx, event_rx, record_ttl, next_query_id),
         )
     }
 
@@ -108,11 +207,22 @@ impl Config {
     pub fn default() -> (Self, KademliaHandle) {
         Self::new(
             REPLICATION_FACTOR,
+            KBUCKET_SIZE,
+            DISJOINT_PATHS,
+            PARALLELISM_FACTOR,
+            None,
             HashMap::new(),
             Vec::new(),
             RoutingTableUpdateMode::Automatic,
             IncomingRecordValidationMode::Automatic,
+            Mode::Server,
             Duration::from_secs(DEFAULT_TTL),
+            Duration::from_secs(DEFAULT_PROVIDER_TTL),
+            Some(DEFAULT_REFRESH_INTERVAL),
+            Some(DEFAULT_REPUBLISH_INTERVAL),
+            Some(DEFAULT_REPLICATION_INTERVAL),
+            Some(DEFAULT_PROVIDER_REPUBLISH_INTERVAL),
+            Box::new(MemoryStore::new()),
         )
     }
 }
@@ -123,12 +233,27 @@ pub struct ConfigBuilder {
     /// Replication factor.
     pub(super) replication_factor: usize,
 
+    /// Maximum number of nodes in a k-bucket.
+    pub(super) kbucket_size: usize,
+
+    /// Number of disjoint paths used by the lookups.
+    pub(super) disjoint_paths: usize,
+
+    /// Parallelism factor.
+    pub(super) parallelism_factor: usize,
+
+    /// Query timeout, if any.
+    pub(super) query_timeout: Option<Duration>,
+
     /// Routing table update mode.
     pub(super) update_mode: RoutingTableUpdateMode,
 
     /// Incoming records validation mode.
     pub(super) validation_mode: IncomingRecordValidationMode,
 
+    /// Kademlia mode.
+    pub(super) mode: Mode,
+
     /// Known peers.
     pub(super) known_peers: HashMap<PeerId, Vec<Multiaddr>>,
 
@@ -137,6 +262,24 @@ pub struct ConfigBuilder {
 
     /// Default TTL for the records.
     pub(super) record_ttl: Duration,
+
+    /// TTL for the provider records.
+    pub(super) provider_ttl: Duration,
+
+    /// Interval for refreshing the routing table, if enabled.
+    pub(super) refresh_interval: Option<Duration>,
+
+    /// Interval for republishing the records published by the local node, if enabled.
+    pub(super) republish_interval: Option<Duration>,
+
+    /// Interval for replicating the records stored by the local node, if enabled.
+    pub(super) replication_interval: Option<Duration>,
+
+    /// Interval for republishing the provider records of the local node, if enabled.
+    pub(super) provider_republish_interval: Option<Duration>,
+
+    /// Record store.
+    pub(super) record_store: Box<dyn RecordStore>,
 }
 
 impl Default for ConfigBuilder {
@@ -150,20 +293,79 @@ impl ConfigBuilder {
     pub fn new() -> Self {
         Self {
             replication_factor: REPLICATION_FACTOR,
+            kbucket_size: KBUCKET_SIZE,
+            disjoint_paths: DISJOINT_PATHS,
+            parallelism_factor: PARALLELISM_FACTOR,
+            query_timeout: None,
             known_peers: HashMap::new(),
             protocol_names: Vec::new(),
             update_mode: RoutingTableUpdateMode::Automatic,
             validation_mode: IncomingRecordValidationMode::Automatic,
+            mode: Mode::Server,
             record_ttl: Duration::from_secs(DEFAULT_TTL),
+            provider_ttl: Duration::from_secs(DEFAULT_PROVIDER_TTL),
+            refresh_interval: Some(DEFAULT_REFRESH_INTERVAL),
+            republish_interval: Some(DEFAULT_REPUBLISH_INTERVAL),
+            replication_interval: Some(DEFAULT_REPLICATION_INTERVAL),
+            provider_republish_interval: Some(DEFAULT_PROVIDER_REPUBLISH_INTERVAL),
+            record_store: Box::new(MemoryStore::new()),
         }
     }
 
     /// Set replication factor.
+    ///
+    /// The replication factor is also the default number of results of a query, see
+    /// [`QueryConfig::num_results`](super::QueryConfig::num_results).
     pub fn with_replication_factor(mut self, replication_factor: usize) -> Self {
         self.replication_factor = replication_factor;
         self
     }
 
+    /// Set the default number of requests a query has in flight at the same time, `α`.
+    ///
+    /// If unspecified, the default parallelism factor is 3.
+    pub fn with_parallelism_factor(mut self, parallelism_factor: usize) -> Self {
+        self.parallelism_factor = parallelism_factor;
+        self
+    }
+
+    /// Set the default query timeout.
+    ///
+    /// Once the timeout expires, the query is finished with the results found so far. The timeout
+    /// can be overridden per query with [`KademliaHandle::find_node_with()`] and
+    /// [`KademliaHandle::get_record_with()`].
+    ///
+    /// If unspecified, the queries don't time out.
+    pub fn with_query_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.query_timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of nodes in a k-bucket.
+    ///
+    /// When a k-bucket is full, a new node is only added if the least-recently-seen node of the
+    /// k-bucket fails to respond to a probe.
+    ///
+    /// If unspecified, the default k-bucket size is 20. Zero is treated as one.
+    pub fn with_kbucket_size(mut self, kbucket_size: usize) -> Self {
+        self.kbucket_size = kbucket_size;
+        self
+    }
+
+    /// Set the number of disjoint paths used by the lookups.
+    ///
+    /// With more than one path, the lookups of `FIND_NODE` and `GET_VALUE` queries are split into
+    /// `disjoint_paths` independent paths which never query the same peer and whose results are
+    /// combined, making it harder for a few malicious peers to steer the results. The value can be
+    /// overridden per query with [`KademliaHandle::find_node_with()`] and
+    /// [`KademliaHandle::get_record_with()`].
+    ///
+    /// If unspecified, the lookups use a single path.
+    pub fn with_disjoint_paths(mut self, disjoint_paths: usize) -> Self {
+        self.disjoint_paths = disjoint_paths;
+        self
+    }
+
     /// Seed Kademlia with one or more known peers.
     pub fn with_known_peers(mut self, peers: HashMap<PeerId, Vec<Multiaddr>>) -> Self {
         self.known_peers = peers;
@@ -185,6 +387,18 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set Kademlia mode.
+    ///
+    /// In client mode the local node queries the DHT but doesn't accept inbound requests and
+    /// doesn't advertise the Kademlia protocol over Identify, which is suitable for nodes that
+    /// aren't reachable by other peers.
+    ///
+    /// If unspecified, Kademlia runs in server mode.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
     /// Set Kademlia protocol names, overriding the default protocol name.
     ///
     /// The order of the protocol names signifies preference so if, for example, there are two
@@ -207,15 +421,94 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set TTL for the provider records received from remote peers.
+    ///
+    /// If unspecified, the default TTL is 48 hours.
+    pub fn with_provider_ttl(mut self, provider_ttl: Duration) -> Self {
+        self.provider_ttl = provider_ttl;
+        self
+    }
+
+    /// Set the interval for refreshing the routing table.
+    ///
+    /// On each refresh the routing table is bootstrapped as with [`KademliaHandle::bootstrap()`].
+    /// Passing `None` disables the periodic refresh.
+    ///
+    /// If unspecified, the routing table is refreshed every 5 minutes.
+    pub fn with_routing_table_refresh_interval(mut self, interval: Option<Duration>) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Set the interval for republishing the records published by the local node.
+    ///
+    /// On each republish the expiration time of the records stored with
+    /// [`KademliaHandle::put_record()`] is reset to the record TTL and the records are stored
+    /// again to the peers closest to their keys. Passing `None` disables republishing, in which
+    /// case the records expire once their TTL has elapsed.
+    ///
+    /// The interval should be shorter than the record TTL. If unspecified, the records are
+    /// republished every 22 hours.
+    pub fn with_record_republish_interval(mut self, interval: Option<Duration>) -> Self {
+        self.republish_interval = interval;
+        self
+    }
+
+    /// Set the interval for replicating the records stored by the local node.
+    ///
+    /// On each replication the records received from remote peers are stored to the peers
+    /// currently closest to their keys, without changing their expiration time. Passing `None`
+    /// disables replication.
+    ///
+    /// If unspecified, the records are replicated every hour.
+    pub fn with_record_replication_interval(mut self, interval: Option<Duration>) -> Self {
+        self.replication_interval = interval;
+        self
+    }
+
+    /// Set the interval for republishing the provider records of the local node.
+    ///
+    /// On each republish the local node is announced again as a provider of the keys passed to
+    /// [`KademliaHandle::start_providing()`] to the peers currently closest to them. Passing `None`
+    /// disables republishing, in which case the provider records announced to remote peers expire
+    /// once the provider TTL has elapsed.
+    ///
+    /// The interval should be shorter than the provider TTL. If unspecified, the provider records
+    /// are republished every 12 hours.
+    pub fn with_provider_republish_interval(mut self, interval: Option<Duration>) -> Self {
+        self.provider_republish_interval = interval;
+        self
+    }
+
+    /// Set the store for the records and provider records.
+    ///
+    /// If unspecified, the records are stored in a [`MemoryStore`] with the default configuration.
+    /// Use [`FileStore`](super::FileStore) to persist the records across restarts.
+    pub fn with_record_store(mut self, record_store: impl RecordStore + 'static) -> Self {
+        self.record_store = Box::new(record_store);
+        self
+    }
+
     /// Build Kademlia [`Config`].
     pub fn build(self) -> (Config, KademliaHandle) {
         Config::new(
             self.replication_factor,
+            self.kbucket_size,
+            self.disjoint_paths,
+            self.parallelism_factor,
+            self.query_timeout,
             self.known_peers,
             self.protocol_names,
             self.update_mode,
             self.validation_mode,
+            self.mode,
             self.record_ttl,
+            self.provider_ttl,
+            self.refresh_interval,
+            self.republish_interval,
+            self.replication_interval,
+            self.provider_republish_interval,
+            self.record_store,
         )
     }
 }
@@ -19,17 +19,27 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    protocol::libp2p::kademlia::{PeerRecord, QueryId, Record, RecordKey},
+    protocol::libp2p::kademlia::{
+        types::{ConnectionType, KademliaPeer},
+        ContentProvider, PeerRecord, QueryId, Record, RecordKey,
+    },
     PeerId,
 };
 
 use futures::Stream;
 use multiaddr::Multiaddr;
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::{
+    mpsc::{Receiver, Sender},
+    oneshot,
+};
 
 use std::{
     num::NonZeroUsize,
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
     time::{Duration, Instant},
 };
@@ -72,6 +82,82 @@ pub enum IncomingRecordValidationMode {
     Automatic,
 }
 
+/// Kademlia mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Query the DHT but don't accept inbound requests or advertise the protocol over Identify.
+    Client,
+
+    /// Accept inbound requests and advertise the protocol over Identify.
+    Server,
+
+    /// Run in server mode while the local node has confirmed external addresses and in client
+    /// mode otherwise.
+    ///
+    /// External addresses are confirmed with
+    /// [`Litep2p::add_external_address()`](crate::Litep2p::add_external_address).
+    Auto,
+}
+
+/// Configuration of a single query.
+///
+/// Fields left as `None` use the defaults set with [`ConfigBuilder`](super::ConfigBuilder).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct QueryConfig {
+    /// Number of requests the query has in flight at the same time, `α`.
+    pub parallelism: Option<usize>,
+
+    /// Number of results the query looks for.
+    ///
+    /// For `FIND_NODE` queries this is the number of closest peers returned and for `GET_VALUE`
+    /// queries the number of records required by [`Quorum::All`]. Zero is treated as one.
+    pub num_results: Option<usize>,
+
+    /// Time after which the query is finished with the results found so far.
+    ///
+    /// The query fails if it hasn't found any results by then.
+    pub timeout: Option<Duration>,
+
+    /// Number of disjoint paths used by the lookup.
+    pub disjoint_paths: Option<usize>,
+}
+
+/// Peer stored in the routing table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingTablePeer {
+    /// Peer ID.
+    pub peer: PeerId,
+
+    /// Known addresses of the peer.
+    pub addresses: Vec<Multiaddr>,
+
+    /// Connection type of the peer.
+    pub connection: ConnectionType,
+}
+
+impl From<KademliaPeer> for RoutingTablePeer {
+    fn from(peer: KademliaPeer) -> Self {
+        Self {
+            peer: peer.peer,
+            addresses: peer.addresses,
+            connection: peer.connection,
+        }
+    }
+}
+
+/// Snapshot of a k-bucket of the routing table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KBucketSnapshot {
+    /// Index of the k-bucket.
+    ///
+    /// The k-bucket holds the peers whose distance to the local node is in `[2^index,
+    /// 2^(index+1))`.
+    pub index: usize,
+
+    /// Peers of the k-bucket, ordered from the least recently seen to the most recently seen.
+    pub peers: Vec<RoutingTablePeer>,
+}
+
 /// Kademlia commands.
 #[derive(Debug)]
 pub(crate) enum KademliaCommand {
@@ -91,6 +177,9 @@ pub(crate) enum KademliaCommand {
 
         /// Query ID for the query.
         query_id: QueryId,
+
+        /// Query configuration.
+        config: QueryConfig,
     },
 
     /// Store record to DHT.
@@ -129,6 +218,9 @@ pub(crate) enum KademliaCommand {
 
         /// Query ID for the query.
         query_id: QueryId,
+
+        /// Query configuration.
+        config: QueryConfig,
     },
 
     /// Store record locally.
@@ -136,6 +228,81 @@ pub(crate) enum KademliaCommand {
         // Record.
         record: Record,
     },
+
+    /// Start providing the key and announce it to the peers closest to the key.
+    StartProviding {
+        /// Provided key.
+        key: RecordKey,
+
+        /// Query ID for the query.
+        query_id: QueryId,
+    },
+
+    /// Stop providing the key.
+    StopProviding {
+        /// Provided key.
+        key: RecordKey,
+    },
+
+    /// Get providers of the key from DHT.
+    GetProviders {
+        /// Provided key.
+        key: RecordKey,
+
+        /// Query ID for the query.
+        query_id: QueryId,
+    },
+
+    /// Bootstrap the routing table.
+    Bootstrap,
+
+    /// Local node is reachable at a new address.
+    AddListenAddress {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Local node is no longer reachable at the address.
+    RemoveListenAddress {
+        /// Listen address.
+        address: Multiaddr,
+    },
+
+    /// Switch between server and client mode.
+    SetServerMode {
+        /// Whether inbound requests are accepted.
+        enabled: bool,
+    },
+
+    /// Stop an ongoing query.
+    StopQuery {
+        /// Query ID of the query.
+        query_id: QueryId,
+    },
+
+    /// Get snapshot of the routing table.
+    RoutingTableSnapshot {
+        /// Channel for sending the non-empty k-buckets.
+        tx: oneshot::Sender<Vec<KBucketSnapshot>>,
+    },
+
+    /// Remove peer from the routing table.
+    RemovePeer {
+        /// Peer ID.
+        peer: PeerId,
+    },
+
+    /// Get closest peers to `key` from the routing table.
+    ClosestLocalPeers {
+        /// Key.
+        key: RecordKey,
+
+        /// Maximum number of peers returned.
+        limit: usize,
+
+        /// Channel for sending the peers.
+        tx: oneshot::Sender<Vec<RoutingTablePeer>>,
+    },
 }
 
 /// Kademlia events.
@@ -153,6 +320,25 @@ pub enum KademliaEvent {
         peers: Vec<(PeerId, Vec<Multiaddr>)>,
     },
 
+    /// `FIND_NODE` query received a response from a peer.
+    ///
+    /// The query continues until [`KademliaEvent::FindNodeSuccess`] or
+    /// [`KademliaEvent::QueryFailed`] is emitted, unless it's stopped with
+    /// [`KademliaHandle::stop_query()`].
+    FindNodeProgress {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Target of the query.
+        target: PeerId,
+
+        /// Nodes returned by the peer that responded, and their addresses.
+        peers: Vec<(PeerId, Vec<Multiaddr>)>,
+
+        /// Number of progress events emitted for the query so far, starting from 1.
+        step: usize,
+    },
+
     /// Routing table update.
     ///
     /// Kademlia has discovered one or more peers that should be added to the routing table.
@@ -175,6 +361,22 @@ pub enum KademliaEvent {
         records: RecordsType,
     },
 
+    /// `GET_VALUE` query found a record.
+    ///
+    /// The query continues until [`KademliaEvent::GetRecordSuccess`] or
+    /// [`KademliaEvent::QueryFailed`] is emitted, unless it's stopped with
+    /// [`KademliaHandle::stop_query()`].
+    GetRecordPartialResult {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Found record.
+        record: PeerRecord,
+
+        /// Number of records found by the query so far, starting from 1.
+        step: usize,
+    },
+
     /// `PUT_VALUE` query succeeded.
     PutRecordSucess {
         /// Query ID.
@@ -199,6 +401,42 @@ pub enum KademliaEvent {
         /// Record.
         record: Record,
     },
+
+    /// `GET_PROVIDERS` query succeeded.
+    GetProvidersSuccess {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Provided key.
+        provided_key: RecordKey,
+
+        /// Found providers.
+        providers: Vec<ContentProvider>,
+    },
+
+    /// Bootstrap of the routing table has completed.
+    ///
+    /// Emitted both for bootstraps started with [`KademliaHandle::bootstrap()`] and for the
+    /// periodic routing table refreshes. Failures of the random lookups don't fail the bootstrap.
+    BootstrapCompleted,
+
+    /// Bootstrap of the routing table has failed because the lookup of the local node didn't
+    /// reach any peer, for example because no peers are known.
+    ///
+    /// Emitted both for bootstraps started with [`KademliaHandle::bootstrap()`] and for the
+    /// periodic routing table refreshes.
+    BootstrapFailed,
+
+    /// Incoming `ADD_PROVIDER` request received.
+    ///
+    /// The provider record has been stored in the local store.
+    IncomingProvider {
+        /// Provided key.
+        provided_key: RecordKey,
+
+        /// Provider.
+        provider: ContentProvider,
+    },
 }
 
 /// The type of the DHT records.
@@ -221,8 +459,8 @@ pub struct KademliaHandle {
     /// RX channel for receiving events from `Kademlia`.
     event_rx: Receiver<KademliaEvent>,
 
-    /// Next query ID.
-    next_query_id: usize,
+    /// Next query ID, shared with `Kademlia`.
+    next_query_id: Arc<AtomicUsize>,
 
     /// Default TTL for the records.
     record_ttl: Duration,
@@ -234,21 +472,19 @@ impl KademliaHandle {
         cmd_tx: Sender<KademliaCommand>,
         event_rx: Receiver<KademliaEvent>,
         record_ttl: Duration,
+        next_query_id: Arc<AtomicUsize>,
     ) -> Self {
         Self {
             cmd_tx,
             event_rx,
-            next_query_id: 0usize,
+            next_query_id,
             record_ttl,
         }
     }
 
     /// Allocate next query ID.
     fn next_query_id(&mut self) -> QueryId {
-        let query_id = self.next_query_id;
-        self.next_query_id += 1;
-
-        QueryId(query_id)
+        QueryId(self.next_query_id.fetch_add(1, Ordering::Relaxed))
     }
 
     /// Add known peer.
@@ -258,8 +494,33 @@ impl KademliaHandle {
 
     /// Send `FIND_NODE` query to known peers.
     pub async fn find_node(&mut self, peer: PeerId) -> QueryId {
+        self.find_node_with(peer, QueryConfig::default()).await
+    }
+
+    /// Send `FIND_NODE` query to known peers using `disjoint_paths` disjoint lookup paths.
+    pub async fn find_node_disjoint(&mut self, peer: PeerId, disjoint_paths: usize) -> QueryId {
+        self.find_node_with(
+            peer,
+            QueryConfig {
+                disjoint_paths: Some(disjoint_paths),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Send `FIND_NODE` query to known peers using `config` instead of the default query
+    /// configuration.
+    pub async fn find_node_with(&mut self, peer: PeerId, config: QueryConfig) -> QueryId {
         let query_id = self.next_query_id();
-        let _ = self.cmd_tx.send(KademliaCommand::FindNode { peer, query_id }).await;
+        let _ = self
+            .cmd_tx
+            .send(KademliaCommand::FindNode {
+                peer,
+                query_id,
+                config,
+            })
+            .await;
 
         query_id
     }
@@ -299,6 +560,34 @@ impl KademliaHandle {
 
     /// Get record from DHT.
     pub async fn get_record(&mut self, key: RecordKey, quorum: Quorum) -> QueryId {
+        self.get_record_with(key, quorum, QueryConfig::default()).await
+    }
+
+    /// Get record from DHT using `disjoint_paths` disjoint lookup paths.
+    pub async fn get_record_disjoint(
+        &mut self,
+        key: RecordKey,
+        quorum: Quorum,
+        disjoint_paths: usize,
+    ) -> QueryId {
+        self.get_record_with(
+            key,
+            quorum,
+            QueryConfig {
+                disjoint_paths: Some(disjoint_paths),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Get record from DHT using `config` instead of the default query configuration.
+    pub async fn get_record_with(
+        &mut self,
+        key: RecordKey,
+        quorum: Quorum,
+        config: QueryConfig,
+    ) -> QueryId {
         let query_id = self.next_query_id();
         let _ = self
             .cmd_tx
@@ -306,6 +595,7 @@ impl KademliaHandle {
                 key,
                 quorum,
                 query_id,
+                config,
             })
             .await;
 
@@ -320,6 +610,84 @@ impl KademliaHandle {
         let _ = self.cmd_tx.send(KademliaCommand::StoreRecord { record }).await;
     }
 
+    /// Start providing `key` and announce the local node as a provider to the peers closest to
+    /// `key`.
+    ///
+    /// The key is provided until [`KademliaHandle::stop_providing()`] is called and the provider
+    /// record is republished periodically, as configured with
+    /// [`ConfigBuilder::with_provider_republish_interval()`](super::ConfigBuilder::with_provider_republish_interval).
+    pub async fn start_providing(&mut self, key: RecordKey) -> QueryId {
+        let query_id = self.next_query_id();
+        let _ = self.cmd_tx.send(KademliaCommand::StartProviding { key, query_id }).await;
+
+        query_id
+    }
+
+    /// Stop providing `key`.
+    ///
+    /// The provider records announced to remote peers expire on their own.
+    pub async fn stop_providing(&mut self, key: RecordKey) {
+        let _ = self.cmd_tx.send(KademliaCommand::StopProviding { key }).await;
+    }
+
+    /// Stop query.
+    ///
+    /// `FIND_NODE` and `GET_VALUE` queries finish with the results found so far and other queries
+    /// fail. Stopping a query that has already finished has no effect.
+    pub async fn stop_query(&self, query_id: QueryId) {
+        let _ = self.cmd_tx.send(KademliaCommand::StopQuery { query_id }).await;
+    }
+
+    /// Get snapshot of the routing table.
+    ///
+    /// Returns the non-empty k-buckets ordered by their index, or an empty list if the protocol
+    /// has exited.
+    pub async fn routing_table_snapshot(&self) -> Vec<KBucketSnapshot> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.cmd_tx.send(KademliaCommand::RoutingTableSnapshot { tx }).await;
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Remove `peer` from the routing table.
+    ///
+    /// The peer is not added back to the routing table when it's discovered again, unless it's
+    /// added with [`KademliaHandle::add_known_peer()`]. The peer is not banned, so it can still
+    /// connect to the local node.
+    pub async fn remove_peer(&self, peer: PeerId) {
+        let _ = self.cmd_tx.send(KademliaCommand::RemovePeer { peer }).await;
+    }
+
+    /// Get at most `limit` closest peers to `key` from the routing table without querying the
+    /// network.
+    ///
+    /// Closest peers to a peer ID are found with `RecordKey::from(peer.to_bytes())`.
+    pub async fn closest_local_peers(&self, key: RecordKey, limit: usize) -> Vec<RoutingTablePeer> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.cmd_tx.send(KademliaCommand::ClosestLocalPeers { key, limit, tx }).await;
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Get providers of `key` from DHT.
+    pub async fn get_providers(&mut self, key: RecordKey) -> QueryId {
+        let query_id = self.next_query_id();
+        let _ = self.cmd_tx.send(KademliaCommand::GetProviders { key, query_id }).await;
+
+        query_id
+    }
+
+    /// Bootstrap the routing table.
+    ///
+    /// Looks up the closest peers to the local node and then performs a random lookup for each
+    /// k-bucket further away than the closest known peer. [`KademliaEvent::BootstrapCompleted`]
+    /// is emitted once all lookups have finished, or [`KademliaEvent::BootstrapFailed`] if the
+    /// lookup of the local node fails. If a bootstrap is already in progress, the call
+    /// has no effect.
+    pub async fn bootstrap(&self) {
+        let _ = self.cmd_tx.send(KademliaCommand::Bootstrap).await;
+    }
+
     /// Try to add known peer and if the channel is clogged, return an error.
     pub fn try_add_known_peer(&self, peer: PeerId, addresses: Vec<Multiaddr>) -> Result<(), ()> {
         self.cmd_tx
@@ -331,7 +699,11 @@ impl KademliaHandle {
     pub fn try_find_node(&mut self, peer: PeerId) -> Result<QueryId, ()> {
         let query_id = self.next_query_id();
         self.cmd_tx
-            .try_send(KademliaCommand::FindNode { peer, query_id })
+            .try_send(KademliaCommand::FindNode {
+                peer,
+                query_id,
+                config: QueryConfig::default(),
+            })
             .map(|_| query_id)
             .map_err(|_| ())
     }
@@ -377,6 +749,7 @@ impl KademliaHandle {
                 key,
                 quorum,
                 query_id,
+                config: QueryConfig::default(),
             })
             .map(|_| query_id)
             .map_err(|_| ())
@@ -389,6 +762,44 @@ impl KademliaHandle {
 
         self.cmd_tx.try_send(KademliaCommand::StoreRecord { record }).map_err(|_| ())
     }
+
+    /// Try to bootstrap the routing table and if the channel is clogged, return an error.
+    pub fn try_bootstrap(&self) -> Result<(), ()> {
+        self.cmd_tx.try_send(KademliaCommand::Bootstrap).map_err(|_| ())
+    }
+
+    /// Try to start providing `key` and if the channel is clogged, return an error.
+    pub fn try_start_providing(&mut self, key: RecordKey) -> Result<QueryId, ()> {
+        let query_id = self.next_query_id();
+        self.cmd_tx
+            .try_send(KademliaCommand::StartProviding { key, query_id })
+            .map(|_| query_id)
+            .map_err(|_| ())
+    }
+
+    /// Try to stop providing `key` and if the channel is clogged, return an error.
+    pub fn try_stop_providing(&mut self, key: RecordKey) -> Result<(), ()> {
+        self.cmd_tx.try_send(KademliaCommand::StopProviding { key }).map_err(|_| ())
+    }
+
+    /// Try to stop query and if the channel is clogged, return an error.
+    pub fn try_stop_query(&self, query_id: QueryId) -> Result<(), ()> {
+        self.cmd_tx.try_send(KademliaCommand::StopQuery { query_id }).map_err(|_| ())
+    }
+
+    /// Try to remove peer from the routing table and if the channel is clogged, return an error.
+    pub fn try_remove_peer(&self, peer: PeerId) -> Result<(), ()> {
+        self.cmd_tx.try_send(KademliaCommand::RemovePeer { peer }).map_err(|_| ())
+    }
+
+    /// Try to initiate `GET_PROVIDERS` query and if the channel is clogged, return an error.
+    pub fn try_get_providers(&mut self, key: RecordKey) -> Result<QueryId, ()> {
+        let query_id = self.next_query_id();
+        self.cmd_tx
+            .try_send(KademliaCommand::GetProviders { key, query_id })
+            .map(|_| query_id)
+            .map_err(|_| ())
+    }
 }
 
 impl Stream for KademliaHandle {
@@ -18,15 +18,20 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::protocol::libp2p::kademlia::{
-    record::{Key as RecordKey, Record},
-    schema,
-    types::KademliaPeer,
+use crate::{
+    protocol::libp2p::kademlia::{
+        record::{Key as RecordKey, ProviderRecord, Record},
+        schema,
+        types::{ConnectionType, KademliaPeer},
+    },
+    PeerId,
 };
 
 use bytes::{Bytes, BytesMut};
 use prost::Message;
 
+use std::time::{Duration, Instant};
+
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::ipfs::kademlia::message";
 
@@ -59,6 +64,27 @@ pub enum KademliaMessage {
         /// Peers closest to key.
         peers: Vec<KademliaPeer>,
     },
+
+    /// `ADD_PROVIDER` message.
+    AddProvider {
+        /// Key of the provided content.
+        key: RecordKey,
+
+        /// Providers of the content.
+        providers: Vec<KademliaPeer>,
+    },
+
+    /// `GET_PROVIDERS` message.
+    GetProviders {
+        /// Key.
+        key: Option<RecordKey>,
+
+        /// Peers closest to key.
+        peers: Vec<KademliaPeer>,
+
+        /// Providers of the content.
+        providers: Vec<KademliaPeer>,
+    },
 }
 
 impl KademliaMessage {
@@ -78,14 +104,25 @@ impl KademliaMessage {
     }
 
     /// Create `PUT_VALUE` message for `record`.
-    // TODO: set ttl
+    ///
+    /// The publisher and the remaining TTL of the record are sent along with it so that the
+    /// receiver doesn't keep the record for longer than the sender would.
     pub fn put_value(record: Record) -> Bytes {
+        // zero TTL means that the record doesn't expire, so round the remaining TTL up to at least
+        // one second
+        let ttl = record.expires.map_or(0u32, |expires| {
+            let remaining = expires.saturating_duration_since(Instant::now());
+            u32::try_from(remaining.as_secs()).unwrap_or(u32::MAX).max(1)
+        });
+
         let message = schema::kademlia::Message {
             key: record.key.clone().into(),
             r#type: schema::kademlia::MessageType::PutValue.into(),
             record: Some(schema::kademlia::Record {
                 key: record.key.into(),
                 value: record.value,
+                publisher: record.publisher.map_or_else(Vec::new, |peer| peer.to_bytes()),
+                ttl,
                 ..Default::default()
             }),
             cluster_level_raw: 10,
@@ -154,6 +191,75 @@ impl KademliaMessage {
         buf
     }
 
+    /// Create `ADD_PROVIDER` message for `provider`.
+    pub fn add_provider(provider: ProviderRecord) -> Bytes {
+        let peer = KademliaPeer::new(
+            provider.provider,
+            provider.addresses,
+            ConnectionType::Connected,
+        );
+
+        let message = schema::kademlia::Message {
+            key: provider.key.into(),
+            r#type: schema::kademlia::MessageType::AddProvider.into(),
+            provider_peers: vec![(&peer).into()],
+            cluster_level_raw: 10,
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("BytesMut to provide needed capacity");
+
+        buf.freeze()
+    }
+
+    /// Create `GET_PROVIDERS` message for `key`.
+    pub fn get_providers_request(key: RecordKey) -> Bytes {
+        let message = schema::kademlia::Message {
+            key: key.into(),
+            r#type: schema::kademlia::MessageType::GetProviders.into(),
+            cluster_level_raw: 10,
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("BytesMut to provide needed capacity");
+
+        buf.freeze()
+    }
+
+    /// Create `GET_PROVIDERS` response.
+    pub fn get_providers_response(
+        key: RecordKey,
+        providers: Vec<ProviderRecord>,
+        peers: Vec<KademliaPeer>,
+    ) -> Vec<u8> {
+        let providers = providers
+            .into_iter()
+            .map(|provider| {
+                KademliaPeer::new(
+                    provider.provider,
+                    provider.addresses,
+                    ConnectionType::NotConnected,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let message = schema::kademlia::Message {
+            key: key.to_vec(),
+            cluster_level_raw: 10,
+            r#type: schema::kademlia::MessageType::GetProviders.into(),
+            closer_peers: peers.iter().map(|peer| peer.into()).collect(),
+            provider_peers: providers.iter().map(|peer| peer.into()).collect(),
+            ..Default::default()
+        };
+
+        let mut buf = Vec::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("Vec<u8> to provide needed capacity");
+
+        buf
+    }
+
     /// Get [`KademliaMessage`] from bytes.
     pub fn from_bytes(bytes: BytesMut) -> Option<Self> {
         match schema::kademlia::Message::decode(bytes) {
@@ -174,7 +280,13 @@ impl KademliaMessage {
                     let record = message.record?;
 
                     Some(Self::PutValue {
-                        record: Record::new(record.key, record.value),
+                        record: Record {
+                            publisher: PeerId::from_bytes(&record.publisher).ok(),
+                            expires: (record.ttl > 0).then(|| {
+                                Instant::now() + Duration::from_secs(u64::from(record.ttl))
+                            }),
+                            ..Record::new(record.key, record.value)
+                        },
                     })
                 }
                 1 => {
@@ -195,6 +307,34 @@ impl KademliaMessage {
                             .collect(),
                     })
                 }
+                2 => {
+                    if message.key.is_empty() {
+                        tracing::debug!(target: LOG_TARGET, "`ADD_PROVIDER` message without key");
+                        return None;
+                    }
+
+                    Some(Self::AddProvider {
+                        key: RecordKey::from(message.key),
+                        providers: message
+                            .provider_peers
+                            .iter()
+                            .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                            .collect(),
+                    })
+                }
+                3 => Some(Self::GetProviders {
+                    key: (!message.key.is_empty()).then(|| RecordKey::from(message.key)),
+                    peers: message
+                        .closer_peers
+                        .iter()
+                        .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                        .collect(),
+                    providers: message
+                        .provider_peers
+                        .iter()
+                        .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                        .collect(),
+                }),
                 message => {
                     tracing::warn!(target: LOG_TARGET, ?message, "unhandled message");
                     None
@@ -26,15 +26,14 @@ use crate::{
         libp2p::kademlia::{
             bucket::KBucketEntry,
             executor::{QueryContext, QueryExecutor, QueryResult},
-            handle::KademliaCommand,
             message::KademliaMessage,
             query::{QueryAction, QueryEngine},
             routing_table::RoutingTable,
-            store::MemoryStore,
-            types::{ConnectionType, KademliaPeer, Key},
+            types::{KademliaPeer, Key},
         },
         Direction, TransportEvent, TransportService,
     },
+    shutdown::ShutdownSignal,
     substream::Substream,
     types::SubstreamId,
     PeerId,
@@ -45,21 +44,32 @@ use futures::StreamExt;
 use multiaddr::Multiaddr;
 use tokio::sync::mpsc::{Receiver, Sender};
 
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+pub(crate) use self::handle::KademliaCommand;
 pub use self::handle::RecordsType;
 pub use config::{Config, ConfigBuilder};
 pub use handle::{
-    IncomingRecordValidationMode, KademliaEvent, KademliaHandle, Quorum, RoutingTableUpdateMode,
+    IncomingRecordValidationMode, KBucketSnapshot, KademliaEvent, KademliaHandle, Mode,
+    QueryConfig, Quorum, RoutingTablePeer, RoutingTableUpdateMode,
 };
 pub use query::QueryId;
-pub use record::{Key as RecordKey, PeerRecord, Record};
+pub use record::{ContentProvider, Key as RecordKey, PeerRecord, ProviderRecord, Record};
+pub use store::{FileStore, MemoryStore, MemoryStoreConfig, RecordStore};
+pub use types::ConnectionType;
 
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::ipfs::kademlia";
 
-/// Parallelism factor, `α`.
-const PARALLELISM_FACTOR: usize = 3;
+/// Interval at which the modifications of the record store are persisted.
+const STORE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
 mod bucket;
 mod config;
@@ -76,16 +86,37 @@ mod schema {
     pub(super) mod kademlia {
         include!(concat!(env!("OUT_DIR"), "/kademlia.rs"));
     }
+
+    pub(super) mod kademlia_store {
+        include!(concat!(env!("OUT_DIR"), "/kademlia_store.rs"));
+    }
 }
 
 /// Peer action.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum PeerAction {
     /// Send `FIND_NODE` message to peer.
     SendFindNode(QueryId),
 
     /// Send `PUT_VALUE` message to peer.
     SendPutValue(Bytes),
+
+    /// Send `ADD_PROVIDER` message to peer.
+    SendAddProvider(Bytes),
+
+    /// Send `FIND_NODE` message to peer to check that it's still alive.
+    SendProbe(QueryId),
+}
+
+impl PeerAction {
+    /// Get the query waiting for a response from the peer, if any.
+    fn query(&self) -> Option<QueryId> {
+        match self {
+            Self::SendFindNode(query) | Self::SendProbe(query) => Some(*query),
+            Self::SendPutValue(_) | Self::SendAddProvider(_) => None,
+        }
+    }
 }
 
 /// Peer context.
@@ -109,6 +140,17 @@ impl PeerContext {
     }
 }
 
+/// Context of an ongoing bootstrap.
+struct BootstrapContext {
+    /// Lookup of the local node, if still in progress.
+    ///
+    /// The random lookups are started once it has finished.
+    self_lookup: Option<QueryId>,
+
+    /// Pending random lookups.
+    random_lookups: HashSet<QueryId>,
+}
+
 /// Main Kademlia object.
 pub(crate) struct Kademlia {
     /// Transport service.
@@ -133,7 +175,46 @@ pub(crate) struct Kademlia {
     replication_factor: usize,
 
     /// Record store.
-    store: MemoryStore,
+    store: Box<dyn RecordStore>,
+
+    /// Default TTL for the records.
+    record_ttl: Duration,
+
+    /// TTL for the provider records received from remote peers.
+    provider_ttl: Duration,
+
+    /// Addresses the local node is reachable at, advertised in the local provider records.
+    listen_addresses: Vec<Multiaddr>,
+
+    /// Interval for refreshing the routing table, if enabled.
+    refresh_interval: Option<Duration>,
+
+    /// Ongoing bootstrap, if any.
+    bootstrap: Option<BootstrapContext>,
+
+    /// Interval for republishing the records published by the local node, if enabled.
+    republish_interval: Option<Duration>,
+
+    /// Interval for replicating the records stored by the local node, if enabled.
+    replication_interval: Option<Duration>,
+
+    /// Interval for republishing the provider records of the local node, if enabled.
+    provider_republish_interval: Option<Duration>,
+
+    /// Ongoing republish and replication queries, which are not reported to the user.
+    republish_queries: HashSet<QueryId>,
+
+    /// Pending probes of the least-recently-seen nodes of full k-buckets.
+    probes: HashMap<QueryId, PeerId>,
+
+    /// Peers removed from the routing table by the user.
+    ///
+    /// The peers are not added back to the routing table when they're discovered, only when
+    /// the user adds them explicitly.
+    removed_peers: HashSet<PeerId>,
+
+    /// Next query ID, shared with `KademliaHandle`.
+    next_query_id: Arc<AtomicUsize>,
 
     /// Pending outbound substreams.
     pending_substreams: HashMap<SubstreamId, PeerId>,
@@ -147,33 +228,52 @@ pub(crate) struct Kademlia {
     /// Incoming records validation mode.
     validation_mode: IncomingRecordValidationMode,
 
+    /// Whether inbound requests are accepted.
+    ///
+    /// In [`Mode::Auto`], `Litep2p` enables the server mode once the local node has confirmed
+    /// external addresses.
+    server_mode: bool,
+
     /// Query engine.
     engine: QueryEngine,
 
     /// Query executor.
     executor: QueryExecutor,
+
+    /// Shutdown signal.
+    shutdown: ShutdownSignal,
 }
 
 impl Kademlia {
     /// Create new [`Kademlia`].
-    pub(crate) fn new(mut service: TransportService, config: Config) -> Self {
+    pub(crate) fn new(
+        mut service: TransportService,
+        config: Config,
+        listen_addresses: Vec<Multiaddr>,
+        shutdown: ShutdownSignal,
+    ) -> Self {
         let local_peer_id = service.local_peer_id;
         let local_key = Key::from(service.local_peer_id);
-        let mut routing_table = RoutingTable::new(local_key.clone());
+        let mut routing_table = RoutingTable::new(local_key.clone(), config.kbucket_size);
+        let mut probes = Vec::new();
 
         for (peer, addresses) in config.known_peers {
             tracing::trace!(target: LOG_TARGET, ?peer, ?addresses, "add bootstrap peer");
 
-            routing_table.add_known_peer(peer, addresses.clone(), ConnectionType::NotConnected);
+            probes.extend(routing_table.add_known_peer(
+                peer,
+                addresses.clone(),
+                ConnectionType::NotConnected,
+            ));
             service.add_known_address(&peer, addresses.into_iter());
         }
 
-        Self {
+        let mut kademlia = Self {
             service,
             routing_table,
             peers: HashMap::new(),
             cmd_rx: config.cmd_rx,
-            store: MemoryStore::new(),
+            store: config.record_store,
             event_tx: config.event_tx,
             _local_key: local_key,
             pending_dials: HashMap::new(),
@@ -181,9 +281,35 @@ impl Kademlia {
             pending_substreams: HashMap::new(),
             update_mode: config.update_mode,
             validation_mode: config.validation_mode,
+            server_mode: config.mode == Mode::Server,
+            record_ttl: config.record_ttl,
+            provider_ttl: config.provider_ttl,
+            listen_addresses,
+            refresh_interval: config.refresh_interval,
+            bootstrap: None,
+            republish_interval: config.republish_interval,
+            replication_interval: config.replication_interval,
+            provider_republish_interval: config.provider_republish_interval,
+            republish_queries: HashSet::new(),
+            probes: HashMap::new(),
+            removed_peers: HashSet::new(),
+            next_query_id: config.next_query_id,
             replication_factor: config.replication_factor,
-            engine: QueryEngine::new(local_peer_id, config.replication_factor, PARALLELISM_FACTOR),
+            engine: QueryEngine::new(
+                local_peer_id,
+                config.replication_factor,
+                config.parallelism_factor,
+                config.disjoint_paths,
+                config.query_timeout,
+            ),
+            shutdown,
+        };
+
+        for peer in probes {
+            kademlia.probe(peer);
         }
+
+        kademlia
     }
 
     /// Connection established to remote peer.
@@ -195,6 +321,7 @@ impl Kademlia {
                 if let KBucketEntry::Occupied(entry) = self.routing_table.entry(Key::from(peer)) {
                     entry.connection = ConnectionType::Connected;
                 }
+                self.routing_table.on_peer_seen(peer);
 
                 let Some(actions) = self.pending_dials.remove(&peer) else {
                     entry.insert(PeerContext::new());
@@ -204,6 +331,7 @@ impl Kademlia {
                 // go over all pending actions, open substreams and save the state to `PeerContext`
                 // from which it will be later queried when the substream opens
                 let mut context = PeerContext::new();
+                let mut failed_queries = Vec::new();
 
                 for action in actions {
                     match self.service.open_substream(peer) {
@@ -219,14 +347,17 @@ impl Kademlia {
                                 "connection established to peer but failed to open substream",
                             );
 
-                            if let PeerAction::SendFindNode(query_id) = action {
-                                self.engine.register_response_failure(query_id, peer);
-                            }
+                            failed_queries.extend(action.query());
                         }
                     }
                 }
 
                 entry.insert(context);
+
+                for query_id in failed_queries {
+                    self.register_response_failure(query_id, peer);
+                }
+
                 Ok(())
             }
             Entry::Occupied(_) => Err(Error::PeerAlreadyExists(peer)),
@@ -239,19 +370,19 @@ impl Kademlia {
     /// or because the connection was closed.
     ///
     /// The peer is kept in the routing table but its connection state is set
-    /// as `NotConnected`, meaning it can be evicted from a k-bucket if another
-    /// peer that shares the bucket connects.
+    /// as `NotConnected`. It's evicted from its k-bucket only if it fails to
+    /// respond to a probe once the k-bucket is full.
     async fn disconnect_peer(&mut self, peer: PeerId, query: Option<QueryId>) {
         tracing::trace!(target: LOG_TARGET, ?peer, ?query, "disconnect peer");
 
         if let Some(query) = query {
-            self.engine.register_response_failure(query, peer);
+            self.register_response_failure(query, peer);
         }
 
         if let Some(PeerContext { pending_actions }) = self.peers.remove(&peer) {
             pending_actions.into_iter().for_each(|(_, action)| {
-                if let PeerAction::SendFindNode(query_id) = action {
-                    self.engine.register_response_failure(query_id, peer);
+                if let Some(query_id) = action.query() {
+                    self.register_response_failure(query_id, peer);
                 }
             });
         }
@@ -327,6 +458,17 @@ impl Kademlia {
 
                 self.executor.send_message(peer, message, substream);
             }
+            Some(PeerAction::SendAddProvider(message)) => {
+                tracing::trace!(target: LOG_TARGET, ?peer, "send `ADD_PROVIDER` message");
+
+                self.executor.send_message(peer, message, substream);
+            }
+            Some(PeerAction::SendProbe(query)) => {
+                tracing::trace!(target: LOG_TARGET, ?peer, ?query, "send probe");
+
+                let message = KademliaMessage::find_node(self.service.local_peer_id.to_bytes());
+                self.executor.send_request_read_response(peer, Some(query), message, substream);
+            }
         }
 
         Ok(())
@@ -336,6 +478,15 @@ impl Kademlia {
     async fn on_inbound_substream(&mut self, peer: PeerId, substream: Substream) {
         tracing::trace!(target: LOG_TARGET, ?peer, "inbound substream opened");
 
+        // in client mode the protocol is not negotiated for inbound substreams but substreams
+        // negotiated before switching to client mode may still arrive
+        if !self.server_mode {
+            tracing::trace!(target: LOG_TARGET, ?peer, "client mode, reject inbound substream");
+
+            let _ = substream.close().await;
+            return;
+        }
+
         self.executor.read_message(peer, None, substream);
     }
 
@@ -359,14 +510,18 @@ impl Kademlia {
         for info in peers {
             self.service.add_known_address(&info.peer, info.addresses.iter().cloned());
 
-            if std::matches!(self.update_mode, RoutingTableUpdateMode::Automatic) {
-                self.routing_table.add_known_peer(
+            if std::matches!(self.update_mode, RoutingTableUpdateMode::Automatic)
+                && !self.removed_peers.contains(&info.peer)
+            {
+                if let Some(probe) = self.routing_table.add_known_peer(
                     info.peer,
                     info.addresses.clone(),
                     self.peers
                         .get(&info.peer)
                         .map_or(ConnectionType::NotConnected, |_| ConnectionType::Connected),
-                );
+                ) {
+                    self.probe(probe);
+                }
             }
         }
     }
@@ -381,6 +536,16 @@ impl Kademlia {
     ) -> crate::Result<()> {
         tracing::trace!(target: LOG_TARGET, ?peer, ?query_id, "handle message from peer");
 
+        if let Some(query) = query_id {
+            if self.probes.remove(&query).is_some() {
+                tracing::trace!(target: LOG_TARGET, ?peer, ?query, "peer responded to probe");
+
+                self.routing_table.on_probe_result(peer, true);
+                return Ok(());
+            }
+        }
+        self.routing_table.on_peer_seen(peer);
+
         match KademliaMessage::from_bytes(message).ok_or(Error::InvalidData)? {
             ref message @ KademliaMessage::FindNode {
                 ref target,
@@ -397,7 +562,7 @@ impl Kademlia {
 
                         // update routing table and inform user about the update
                         self.update_routing_table(peers).await;
-                        self.engine.register_response(query_id, peer, message.clone());
+                        self.register_response(query_id, peer, message.clone()).await;
                     }
                     None => {
                         tracing::trace!(
@@ -416,7 +581,7 @@ impl Kademlia {
                     }
                 }
             }
-            KademliaMessage::PutValue { record } => {
+            KademliaMessage::PutValue { mut record } => {
                 tracing::trace!(
                     target: LOG_TARGET,
                     ?peer,
@@ -424,6 +589,12 @@ impl Kademlia {
                     "handle `PUT_VALUE` message",
                 );
 
+                // keep the record for the TTL it was sent with but no longer than the configured
+                // TTL so that replicating the record doesn't extend its lifetime
+                let expires = Instant::now() + self.record_ttl;
+                record.expires =
+                    Some(record.expires.map_or(expires, |received| received.min(expires)));
+
                 if let IncomingRecordValidationMode::Automatic = self.validation_mode {
                     self.store.put(record.clone());
                 }
@@ -448,7 +619,7 @@ impl Kademlia {
 
                         // update routing table and inform user about the update
                         self.update_routing_table(peers).await;
-                        self.engine.register_response(query_id, peer, message.clone());
+                        self.register_response(query_id, peer, message.clone()).await;
                     }
                     (None, Some(key)) => {
                         tracing::trace!(
@@ -478,11 +649,369 @@ impl Kademlia {
                     ),
                 }
             }
+            KademliaMessage::AddProvider { key, providers } => {
+                tracing::trace!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    ?key,
+                    ?providers,
+                    "handle `ADD_PROVIDER` message",
+                );
+
+                // peers are only allowed to announce themselves as providers
+                for provider in providers.into_iter().filter(|provider| provider.peer == peer) {
+                    self.service.add_known_address(&peer, provider.addresses.iter().cloned());
+
+                    let record = ProviderRecord {
+                        key: key.clone(),
+                        provider: peer,
+                        addresses: provider.addresses.clone(),
+                        expires: Instant::now() + self.provider_ttl,
+                    };
+
+                    if self.store.put_provider(record) {
+                        let _ = self
+                            .event_tx
+                            .send(KademliaEvent::IncomingProvider {
+                                provided_key: key.clone(),
+                                provider: ContentProvider {
+                                    peer,
+                                    addresses: provider.addresses,
+                                },
+                            })
+                            .await;
+                    }
+                }
+            }
+            ref message @ KademliaMessage::GetProviders {
+                ref key,
+                ref peers,
+                ref providers,
+            } => {
+                match (query_id, key) {
+                    (Some(query_id), _) => {
+                        tracing::trace!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            ?query_id,
+                            ?peers,
+                            ?providers,
+                            "handle `GET_PROVIDERS` response",
+                        );
+
+                        // update routing table and inform user about the update
+                        self.update_routing_table(peers).await;
+                        self.register_response(query_id, peer, message.clone()).await;
+                    }
+                    (None, Some(key)) => {
+                        tracing::trace!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            ?key,
+                            "handle `GET_PROVIDERS` request",
+                        );
+
+                        let providers = self.stored_providers(key);
+                        let closest_peers = self
+                            .routing_table
+                            .closest(Key::from(key.to_vec()), self.replication_factor);
+
+                        let message = KademliaMessage::get_providers_response(
+                            key.clone(),
+                            providers,
+                            closest_peers,
+                        );
+                        self.executor.send_message(peer, message.into(), substream);
+                    }
+                    (None, None) => tracing::debug!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?message,
+                        "both query and provided key missing, unable to handle message",
+                    ),
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Register response from `peer` to `query`, reporting the progress of the query to the user.
+    async fn register_response(&mut self, query: QueryId, peer: PeerId, message: KademliaMessage) {
+        if let Some(action) = self.engine.register_response(query, peer, message) {
+            if let Err((query, peer)) = self.on_query_action(action).await {
+                self.disconnect_peer(peer, Some(query)).await;
+            }
+        }
+    }
+
+    /// Get providers of `key` from the local store.
+    ///
+    /// The provider record of the local node carries its current listen addresses.
+    fn stored_providers(&mut self, key: &RecordKey) -> Vec<ProviderRecord> {
+        let local_peer_id = self.service.local_peer_id;
+
+        self.store
+            .get_providers(key)
+            .into_iter()
+            .map(|mut record| {
+                if record.provider == local_peer_id {
+                    record.addresses = self.listen_addresses.clone();
+                }
+
+                record
+            })
+            .collect()
+    }
+
+    /// Send message to `peer`, dialing the peer if it's not connected.
+    fn send_to_peer(&mut self, peer: PeerId, action: PeerAction) {
+        match self.service.open_substream(peer) {
+            Ok(substream_id) => {
+                self.pending_substreams.insert(substream_id, peer);
+                self.peers.entry(peer).or_default().pending_actions.insert(substream_id, action);
+            }
+            Err(_) => match self.service.dial(&peer) {
+                Ok(_) => self.pending_dials.entry(peer).or_default().push(action),
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?action,
+                        ?error,
+                        "failed to dial peer",
+                    );
+
+                    if let Some(query) = action.query() {
+                        self.register_response_failure(query, peer);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Add `peer` to the routing table at the request of the user.
+    fn add_known_peer(&mut self, peer: PeerId, addresses: Vec<Multiaddr>) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?addresses, "add known peer");
+
+        self.removed_peers.remove(&peer);
+
+        if let Some(probe) = self.routing_table.add_known_peer(
+            peer,
+            addresses.clone(),
+            self.peers
+                .get(&peer)
+                .map_or(ConnectionType::NotConnected, |_| ConnectionType::Connected),
+        ) {
+            self.probe(probe);
+        }
+        self.service.add_known_address(&peer, addresses.into_iter());
+    }
+
+    /// Remove `peer` from the routing table at the request of the user.
+    ///
+    /// The pending probes of `peer` are discarded and `peer` is not added back to the routing
+    /// table until the user adds it with [`Kademlia::add_known_peer()`].
+    fn remove_peer(&mut self, peer: PeerId) {
+        tracing::debug!(target: LOG_TARGET, ?peer, "remove peer from routing table");
+
+        self.routing_table.remove(peer);
+        self.probes.retain(|_, probed| probed != &peer);
+        self.removed_peers.insert(peer);
+    }
+
+    /// Probe `peer`, the least-recently-seen node of a full k-bucket.
+    ///
+    /// If `peer` fails to respond, it's replaced by the pending entry of the k-bucket.
+    fn probe(&mut self, peer: PeerId) {
+        if self.probes.values().any(|probed| probed == &peer) {
+            return;
+        }
+
+        let query = self.next_query_id();
+
+        tracing::trace!(target: LOG_TARGET, ?peer, ?query, "probe least-recently-seen peer");
+
+        self.probes.insert(query, peer);
+        self.send_to_peer(peer, PeerAction::SendProbe(query));
+    }
+
+    /// Register failure to get a response from `peer` for `query`.
+    fn register_response_failure(&mut self, query: QueryId, peer: PeerId) {
+        if self.probes.remove(&query).is_some() {
+            tracing::debug!(target: LOG_TARGET, ?peer, ?query, "peer failed to respond to probe");
+
+            self.routing_table.on_probe_result(peer, false);
+            return;
+        }
+
+        self.engine.register_response_failure(query, peer);
+    }
+
+    /// Allocate next query ID.
+    fn next_query_id(&mut self) -> QueryId {
+        QueryId(self.next_query_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Start bootstrapping the routing table by looking up the local node.
+    fn start_bootstrap(&mut self) {
+        if self.bootstrap.is_some() {
+            tracing::debug!(target: LOG_TARGET, "bootstrap already in progress");
+            return;
+        }
+
+        let query_id = self.next_query_id();
+        let local_peer_id = self.service.local_peer_id;
+
+        tracing::debug!(target: LOG_TARGET, ?query_id, "start bootstrap");
+
+        self.engine.start_find_node(
+            query_id,
+            local_peer_id,
+            self.routing_table
+                .closest(Key::from(local_peer_id), self.replication_factor)
+                .into(),
+            QueryConfig::default(),
+        );
+        self.bootstrap = Some(BootstrapContext {
+            self_lookup: Some(query_id),
+            random_lookups: HashSet::new(),
+        });
+    }
+
+    /// Handle finished bootstrap lookup.
+    ///
+    /// Once the lookup of the local node has succeeded, the random lookups refreshing the k-buckets
+    /// are started. If it fails, the bootstrap fails without starting them. Returns `false` if
+    /// `query` isn't part of the ongoing bootstrap.
+    async fn on_bootstrap_lookup_finished(&mut self, query: QueryId, succeeded: bool) -> bool {
+        let Some(bootstrap) = self.bootstrap.as_mut() else {
+            return false;
+        };
+
+        if bootstrap.self_lookup == Some(query) {
+            bootstrap.self_lookup = None;
+
+            if !succeeded {
+                tracing::debug!(target: LOG_TARGET, ?query, "self-lookup failed, bootstrap failed");
+
+                self.bootstrap = None;
+                let _ = self.event_tx.send(KademliaEvent::BootstrapFailed).await;
+                return true;
+            }
+
+            for target in self.routing_table.refresh_targets() {
+                let query_id = QueryId(self.next_query_id.fetch_add(1, Ordering::Relaxed));
+
+                self.engine.start_find_node(
+                    query_id,
+                    target,
+                    self.routing_table.closest(Key::from(target), self.replication_factor).into(),
+                    QueryConfig::default(),
+                );
+                bootstrap.random_lookups.insert(query_id);
+            }
+
+            tracing::debug!(
+                target: LOG_TARGET,
+                num_lookups = ?bootstrap.random_lookups.len(),
+                "self-lookup finished, start random lookups",
+            );
+        } else if !bootstrap.random_lookups.remove(&query) {
+            return false;
+        }
+
+        if bootstrap.self_lookup.is_none() && bootstrap.random_lookups.is_empty() {
+            tracing::debug!(target: LOG_TARGET, "bootstrap completed");
+
+            self.bootstrap = None;
+            let _ = self.event_tx.send(KademliaEvent::BootstrapCompleted).await;
+        }
+
+        true
+    }
+
+    /// Store `record` to the peers closest to its key without reporting the query to the user.
+    fn start_republish(&mut self, record: Record) {
+        let query_id = self.next_query_id();
+        let key = Key::new(record.key.clone());
+
+        self.engine.start_put_record(
+            query_id,
+            record,
+            self.routing_table.closest(key, self.replication_factor).into(),
+        );
+        self.republish_queries.insert(query_id);
+    }
+
+    /// Republish the records published by the local node, resetting their expiration time.
+    fn republish_records(&mut self) {
+        let local_peer_id = self.service.local_peer_id;
+        let expires = Instant::now() + self.record_ttl;
+        let records = self
+            .store
+            .iter()
+            .filter(|record| record.publisher == Some(local_peer_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        tracing::debug!(target: LOG_TARGET, num_records = ?records.len(), "republish records");
+
+        for mut record in records {
+            record.expires = Some(expires);
+            self.store.put(record.clone());
+            self.start_republish(record);
+        }
+    }
+
+    /// Replicate the records published by remote peers to the peers closest to their keys.
+    fn replicate_records(&mut self) {
+        let local_peer_id = self.service.local_peer_id;
+        let records = self
+            .store
+            .iter()
+            .filter(|record| record.publisher != Some(local_peer_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        tracing::debug!(target: LOG_TARGET, num_records = ?records.len(), "replicate records");
+
+        for record in records {
+            self.start_republish(record);
+        }
+    }
+
+    /// Announce the local node again as a provider of the keys it provides, resetting the
+    /// expiration time of the provider records.
+    fn republish_providers(&mut self) {
+        let expires = Instant::now() + self.provider_ttl;
+        let keys = self
+            .store
+            .local_providers()
+            .map(|record| record.key.clone())
+            .collect::<Vec<_>>();
+
+        tracing::debug!(target: LOG_TARGET, num_providers = ?keys.len(), "republish providers");
+
+        for key in keys {
+            let provider = ProviderRecord {
+                key: key.clone(),
+                provider: self.service.local_peer_id,
+                addresses: self.listen_addresses.clone(),
+                expires,
+            };
+            self.store.put_local_provider(provider.clone());
+
+            let query_id = self.next_query_id();
+            self.engine.start_add_provider(
+                query_id,
+                provider,
+                self.routing_table.closest(Key::new(key), self.replication_factor).into(),
+            );
+            self.republish_queries.insert(query_id);
+        }
+    }
+
     /// Failed to open substream to remote peer.
     async fn on_substream_open_failure(&mut self, substream_id: SubstreamId, error: Error) {
         tracing::trace!(
@@ -502,10 +1031,8 @@ impl Kademlia {
         };
 
         if let Some(context) = self.peers.get_mut(&peer) {
-            let query = match context.pending_actions.remove(&substream_id) {
-                Some(PeerAction::SendFindNode(query)) => Some(query),
-                _ => None,
-            };
+            let query =
+                context.pending_actions.remove(&substream_id).and_then(|action| action.query());
 
             self.disconnect_peer(peer, query).await;
         }
@@ -520,7 +1047,7 @@ impl Kademlia {
         };
 
         for action in actions {
-            if let PeerAction::SendFindNode(query_id) = action {
+            if let Some(query_id) = action.query() {
                 tracing::trace!(
                     target: LOG_TARGET,
                     ?peer,
@@ -529,7 +1056,7 @@ impl Kademlia {
                     "report failure for pending query",
                 );
 
-                self.engine.register_response_failure(query_id, peer);
+                self.register_response_failure(query_id, peer);
             }
         }
     }
@@ -583,6 +1110,10 @@ impl Kademlia {
                 peers,
                 query,
             } => {
+                if self.on_bootstrap_lookup_finished(query, true).await {
+                    return Ok(());
+                }
+
                 tracing::debug!(
                     target: LOG_TARGET,
                     ?query,
@@ -601,52 +1132,97 @@ impl Kademlia {
                     .await;
                 Ok(())
             }
-            QueryAction::PutRecordToFoundNodes { record, peers } => {
+            QueryAction::FindNodeProgress {
+                query,
+                target,
+                peers,
+                step,
+            } => {
+                // progress of the bootstrap lookups is not reported
+                if self.bootstrap.as_ref().is_some_and(|bootstrap| {
+                    bootstrap.self_lookup == Some(query)
+                        || bootstrap.random_lookups.contains(&query)
+                }) {
+                    return Ok(());
+                }
+
+                let _ = self
+                    .event_tx
+                    .send(KademliaEvent::FindNodeProgress {
+                        query_id: query,
+                        target,
+                        peers: peers.into_iter().map(|info| (info.peer, info.addresses)).collect(),
+                        step,
+                    })
+                    .await;
+                Ok(())
+            }
+            QueryAction::PutRecordToFoundNodes {
+                query,
+                record,
+                peers,
+            } => {
+                self.republish_queries.remove(&query);
+
                 tracing::trace!(
                     target: LOG_TARGET,
+                    ?query,
                     record_key = ?record.key,
                     num_peers = ?peers.len(),
                     "store record to found peers",
                 );
-                let key = record.key.clone();
                 let message = KademliaMessage::put_value(record);
 
                 for peer in peers {
-                    match self.service.open_substream(peer.peer) {
-                        Ok(substream_id) => {
-                            self.pending_substreams.insert(substream_id, peer.peer);
-                            self.peers
-                                .entry(peer.peer)
-                                .or_default()
-                                .pending_actions
-                                .insert(substream_id, PeerAction::SendPutValue(message.clone()));
-                        }
-                        Err(_) => match self.service.dial(&peer.peer) {
-                            Ok(_) => match self.pending_dials.entry(peer.peer) {
-                                Entry::Occupied(entry) => {
-                                    entry
-                                        .into_mut()
-                                        .push(PeerAction::SendPutValue(message.clone()));
-                                }
-                                Entry::Vacant(entry) => {
-                                    entry.insert(vec![PeerAction::SendPutValue(message.clone())]);
-                                }
-                            },
-                            Err(error) => {
-                                tracing::debug!(
-                                    target: LOG_TARGET,
-                                    ?peer,
-                                    ?key,
-                                    ?error,
-                                    "failed to dial peer",
-                                );
-                            }
-                        },
-                    }
+                    self.send_to_peer(peer.peer, PeerAction::SendPutValue(message.clone()));
                 }
 
                 Ok(())
             }
+            QueryAction::AddProviderToFoundNodes {
+                query,
+                provider,
+                peers,
+            } => {
+                self.republish_queries.remove(&query);
+
+                tracing::trace!(
+                    target: LOG_TARGET,
+                    provided_key = ?provider.key,
+                    num_peers = ?peers.len(),
+                    "announce provider to found peers",
+                );
+                let message = KademliaMessage::add_provider(provider);
+
+                for peer in peers {
+                    self.send_to_peer(peer.peer, PeerAction::SendAddProvider(message.clone()));
+                }
+
+                Ok(())
+            }
+            QueryAction::GetProvidersQueryDone {
+                query_id,
+                provided_key,
+                providers,
+            } => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?query_id,
+                    ?provided_key,
+                    num_providers = ?providers.len(),
+                    "`GET_PROVIDERS` succeeded",
+                );
+
+                let _ = self
+                    .event_tx
+                    .send(KademliaEvent::GetProvidersSuccess {
+                        query_id,
+                        provided_key,
+                        providers,
+                    })
+                    .await;
+                Ok(())
+            }
             QueryAction::GetRecordQueryDone { query_id, records } => {
                 // Considering this gives a view of all peers and their records, some peers may have
                 // outdated records. Store only the record which is backed by most
@@ -682,7 +1258,33 @@ impl Kademlia {
                     .await;
                 Ok(())
             }
+            QueryAction::GetRecordPartialResult {
+                query_id,
+                record,
+                step,
+            } => {
+                tracing::trace!(target: LOG_TARGET, ?query_id, ?step, "`GET_VALUE` found record");
+
+                let _ = self
+                    .event_tx
+                    .send(KademliaEvent::GetRecordPartialResult {
+                        query_id,
+                        record,
+                        step,
+                    })
+                    .await;
+                Ok(())
+            }
             QueryAction::QueryFailed { query } => {
+                if self.on_bootstrap_lookup_finished(query, false).await {
+                    return Ok(());
+                }
+
+                if self.republish_queries.remove(&query) {
+                    tracing::debug!(target: LOG_TARGET, ?query, "failed to republish record or provider");
+                    return Ok(());
+                }
+
                 tracing::debug!(target: LOG_TARGET, ?query, "query failed");
 
                 let _ = self.event_tx.send(KademliaEvent::QueryFailed { query_id: query }).await;
@@ -692,10 +1294,33 @@ impl Kademlia {
         }
     }
 
+    /// Persist the record store before the event loop exits.
+    async fn close_store(&mut self) {
+        let mut store = std::mem::replace(&mut self.store, Box::new(MemoryStore::new()));
+
+        if let Err(error) = tokio::task::spawn_blocking(move || store.close()).await {
+            tracing::warn!(target: LOG_TARGET, ?error, "failed to close record store");
+        }
+    }
+
     /// [`Kademlia`] event loop.
     pub async fn run(mut self) -> crate::Result<()> {
         tracing::debug!(target: LOG_TARGET, "starting kademlia event loop");
 
+        let make_timer = |interval: Option<Duration>| {
+            interval.map(|interval| {
+                let mut timer =
+                    tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+                timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                timer
+            })
+        };
+        let mut refresh_timer = make_timer(self.refresh_interval);
+        let mut republish_timer = make_timer(self.republish_interval);
+        let mut replication_timer = make_timer(self.replication_interval);
+        let mut provider_republish_timer = make_timer(self.provider_republish_interval);
+        let mut flush_timer = make_timer(Some(STORE_FLUSH_INTERVAL)).expect("timer to exist");
+
         loop {
             // poll `QueryEngine` for next actions.
             while let Some(action) = self.engine.next_action() {
@@ -704,6 +1329,9 @@ impl Kademlia {
                 }
             }
 
+            // wake up when the next query times out
+            let query_deadline = self.engine.next_deadline();
+
             tokio::select! {
                 event = self.service.next() => match event {
                     Some(TransportEvent::ConnectionEstablished { peer, .. }) => {
@@ -711,7 +1339,7 @@ impl Kademlia {
                             tracing::debug!(target: LOG_TARGET, ?error, "failed to handle established connection");
                         }
                     }
-                    Some(TransportEvent::ConnectionClosed { peer }) => {
+                    Some(TransportEvent::ConnectionClosed { peer, .. }) => {
                         self.disconnect_peer(peer, None).await;
                     }
                     Some(TransportEvent::SubstreamOpened { peer, direction, substream, .. }) => {
@@ -734,7 +1362,10 @@ impl Kademlia {
                         self.on_substream_open_failure(substream, error).await;
                     }
                     Some(TransportEvent::DialFailure { peer, address }) => self.on_dial_failure(peer, address),
-                    None => return Err(Error::EssentialTaskClosed),
+                    None => {
+                        self.close_store().await;
+                        return Err(Error::EssentialTaskClosed);
+                    }
                 },
                 context = self.executor.next() => {
                     let QueryContext { peer, query_id, result } = context.unwrap();
@@ -764,20 +1395,41 @@ impl Kademlia {
                         }
                     }
                 }
+                _ = async { refresh_timer.as_mut().expect("timer to exist").tick().await }, if refresh_timer.is_some() => {
+                    tracing::trace!(target: LOG_TARGET, "refresh routing table");
+                    self.start_bootstrap();
+                }
+                _ = async { republish_timer.as_mut().expect("timer to exist").tick().await }, if republish_timer.is_some() => {
+                    self.republish_records();
+                }
+                _ = async { replication_timer.as_mut().expect("timer to exist").tick().await }, if replication_timer.is_some() => {
+                    self.replicate_records();
+                }
+                _ = async { provider_republish_timer.as_mut().expect("timer to exist").tick().await }, if provider_republish_timer.is_some() => {
+                    self.republish_providers();
+                }
+                _ = flush_timer.tick() => {
+                    self.store.flush();
+                }
+                _ = async { tokio::time::sleep_until(query_deadline.expect("deadline to exist").into()).await }, if query_deadline.is_some() => {
+                    tracing::trace!(target: LOG_TARGET, "query deadline expired");
+                }
                 command = self.cmd_rx.recv() => {
                     match command {
-                        Some(KademliaCommand::FindNode { peer, query_id }) => {
-                            tracing::debug!(target: LOG_TARGET, ?peer, ?query_id, "starting `FIND_NODE` query");
+                        Some(KademliaCommand::FindNode { peer, query_id, config }) => {
+                            tracing::debug!(target: LOG_TARGET, ?peer, ?query_id, ?config, "starting `FIND_NODE` query");
 
                             self.engine.start_find_node(
                                 query_id,
                                 peer,
-                                self.routing_table.closest(Key::from(peer), self.replication_factor).into()
+                                self.routing_table.closest(Key::from(peer), self.replication_factor).into(),
+                                config,
                             );
                         }
-                        Some(KademliaCommand::PutRecord { record, query_id }) => {
+                        Some(KademliaCommand::PutRecord { mut record, query_id }) => {
                             tracing::debug!(target: LOG_TARGET, ?query_id, key = ?record.key, "store record to DHT");
 
+                            record.publisher.get_or_insert(self.service.local_peer_id);
                             let key = Key::new(record.key.clone());
 
                             self.store.put(record.clone());
@@ -788,9 +1440,11 @@ impl Kademlia {
                                 self.routing_table.closest(key, self.replication_factor).into(),
                             );
                         }
-                        Some(KademliaCommand::PutRecordToPeers { record, query_id, peers, update_local_store }) => {
+                        Some(KademliaCommand::PutRecordToPeers { mut record, query_id, peers, update_local_store }) => {
                             tracing::debug!(target: LOG_TARGET, ?query_id, key = ?record.key, "store record to DHT to specified peers");
 
+                            record.publisher.get_or_insert(self.service.local_peer_id);
+
                             if update_local_store {
                                 self.store.put(record.clone());
                             }
@@ -803,7 +1457,6 @@ impl Kademlia {
 
                                 match self.routing_table.entry(Key::from(peer)) {
                                     KBucketEntry::Occupied(entry) => Some(entry.clone()),
-                                    KBucketEntry::Vacant(entry) if !entry.addresses.is_empty() => Some(entry.clone()),
                                     _ => None,
                                 }
                             }).collect();
@@ -814,7 +1467,7 @@ impl Kademlia {
                                 peers,
                             );
                         }
-                        Some(KademliaCommand::GetRecord { key, quorum, query_id }) => {
+                        Some(KademliaCommand::GetRecord { key, quorum, query_id, config }) => {
                             tracing::debug!(target: LOG_TARGET, ?key, "get record from DHT");
 
                             match (self.store.get(&key), quorum) {
@@ -831,28 +1484,14 @@ impl Kademlia {
                                         self.routing_table.closest(Key::new(key.clone()), self.replication_factor).into(),
                                         quorum,
                                         if record.is_some() { 1 } else { 0 },
+                                        config,
                                     );
                                 }
                             }
 
                         }
                         Some(KademliaCommand::AddKnownPeer { peer, addresses }) => {
-                            tracing::trace!(
-                                target: LOG_TARGET,
-                                ?peer,
-                                ?addresses,
-                                "add known peer",
-                            );
-
-                            self.routing_table.add_known_peer(
-                                peer,
-                                addresses.clone(),
-                                self.peers
-                                    .get(&peer)
-                                    .map_or(ConnectionType::NotConnected, |_| ConnectionType::Connected),
-                            );
-                            self.service.add_known_address(&peer, addresses.into_iter());
-
+                            self.add_known_peer(peer, addresses);
                         }
                         Some(KademliaCommand::StoreRecord { record }) => {
                             tracing::debug!(
@@ -863,9 +1502,108 @@ impl Kademlia {
 
                             self.store.put(record);
                         }
-                        None => return Err(Error::EssentialTaskClosed),
+                        Some(KademliaCommand::StartProviding { key, query_id }) => {
+                            tracing::debug!(target: LOG_TARGET, ?query_id, ?key, "start providing key");
+
+                            let provider = ProviderRecord {
+                                key: key.clone(),
+                                provider: self.service.local_peer_id,
+                                addresses: self.listen_addresses.clone(),
+                                expires: Instant::now() + self.provider_ttl,
+                            };
+                            self.store.put_local_provider(provider.clone());
+
+                            self.engine.start_add_provider(
+                                query_id,
+                                provider,
+                                self.routing_table.closest(Key::new(key), self.replication_factor).into(),
+                            );
+                        }
+                        Some(KademliaCommand::StopProviding { key }) => {
+                            tracing::debug!(target: LOG_TARGET, ?key, "stop providing key");
+
+                            self.store.remove_local_provider(&key);
+                        }
+                        Some(KademliaCommand::GetProviders { key, query_id }) => {
+                            tracing::debug!(target: LOG_TARGET, ?query_id, ?key, "get providers from DHT");
+
+                            let known_providers = self
+                                .stored_providers(&key)
+                                .into_iter()
+                                .map(|record| ContentProvider {
+                                    peer: record.provider,
+                                    addresses: record.addresses,
+                                })
+                                .collect();
+
+                            self.engine.start_get_providers(
+                                query_id,
+                                key.clone(),
+                                self.routing_table.closest(Key::new(key), self.replication_factor).into(),
+                                known_providers,
+                            );
+                        }
+                        Some(KademliaCommand::Bootstrap) => self.start_bootstrap(),
+                        Some(KademliaCommand::AddListenAddress { address }) => {
+                            if !self.listen_addresses.contains(&address) {
+                                self.listen_addresses.push(address);
+                            }
+                        }
+                        Some(KademliaCommand::RemoveListenAddress { address }) => {
+                            self.listen_addresses.retain(|listen_address| listen_address != &address);
+                        }
+                        Some(KademliaCommand::SetServerMode { enabled }) => {
+                            tracing::debug!(target: LOG_TARGET, ?enabled, "set server mode");
+
+                            self.server_mode = enabled;
+                        }
+                        Some(KademliaCommand::RoutingTableSnapshot { tx }) => {
+                            tracing::trace!(target: LOG_TARGET, "get routing table snapshot");
+
+                            let _ = tx.send(
+                                self.routing_table
+                                    .buckets()
+                                    .map(|(index, peers)| KBucketSnapshot {
+                                        index,
+                                        peers: peers.iter().cloned().map(Into::into).collect(),
+                                    })
+                                    .collect(),
+                            );
+                        }
+                        Some(KademliaCommand::RemovePeer { peer }) => {
+                            self.remove_peer(peer);
+                        }
+                        Some(KademliaCommand::ClosestLocalPeers { key, limit, tx }) => {
+                            tracing::trace!(target: LOG_TARGET, ?key, ?limit, "get closest local peers");
+
+                            let _ = tx.send(
+                                self.routing_table
+                                    .closest(Key::new(key), limit)
+                                    .into_iter()
+                                    .map(Into::into)
+                                    .collect(),
+                            );
+                        }
+                        Some(KademliaCommand::StopQuery { query_id }) => {
+                            tracing::debug!(target: LOG_TARGET, ?query_id, "stop query");
+
+                            if let Some(action) = self.engine.stop_query(query_id) {
+                                if let Err((query, peer)) = self.on_query_action(action).await {
+                                    self.disconnect_peer(peer, Some(query)).await;
+                                }
+                            }
+                        }
+                        None => {
+                            self.close_store().await;
+                            return Err(Error::EssentialTaskClosed);
+                        }
                     }
                 },
+                _ = self.shutdown.draining() => {
+                    tracing::debug!(target: LOG_TARGET, "shutting down kademlia");
+                    self.close_store().await;
+                    return Ok(());
+                }
             }
         }
     }
@@ -877,14 +1615,15 @@ mod tests {
 
     use super::*;
     use crate::{
-        codec::ProtocolCodec, crypto::ed25519::Keypair, transport::manager::TransportManager,
-        types::protocol::ProtocolName, BandwidthSink,
+        codec::ProtocolCodec, crypto::ed25519::Keypair, protocol::InnerTransportEvent,
+        transport::manager::TransportManager, types::protocol::ProtocolName, BandwidthSink,
     };
     use tokio::sync::mpsc::channel;
 
     #[allow(unused)]
     struct Context {
         _cmd_tx: Sender<KademliaCommand>,
+        _transport_tx: Sender<InnerTransportEvent>,
         event_rx: Receiver<KademliaEvent>,
     }
 
@@ -897,12 +1636,13 @@ mod tests {
         );
 
         let peer = PeerId::random();
-        let (transport_service, _tx) = TransportService::new(
+        let (transport_service, _transport_tx) = TransportService::new(
             peer,
             ProtocolName::from("/kad/1"),
             Vec::new(),
             Default::default(),
             handle,
+            Default::default(),
         );
         let (event_tx, event_rx) = channel(64);
         let (_cmd_tx, cmd_rx) = channel(64);
@@ -912,19 +1652,61 @@ mod tests {
             known_peers: HashMap::new(),
             codec: ProtocolCodec::UnsignedVarint(None),
             replication_factor: 20usize,
+            kbucket_size: 20usize,
+            disjoint_paths: 1usize,
+            parallelism_factor: 3usize,
+            query_timeout: None,
             update_mode: RoutingTableUpdateMode::Automatic,
             validation_mode: IncomingRecordValidationMode::Automatic,
+            mode: Mode::Server,
+            record_ttl: Duration::from_secs(36 * 60 * 60),
+            provider_ttl: Duration::from_secs(48 * 60 * 60),
+            refresh_interval: None,
+            republish_interval: None,
+            replication_interval: None,
+            provider_republish_interval: None,
+            record_store: Box::new(MemoryStore::new()),
+            next_query_id: Arc::new(AtomicUsize::new(0usize)),
             event_tx,
             cmd_rx,
+            cmd_tx: _cmd_tx.clone(),
         };
 
         (
-            Kademlia::new(transport_service, config),
-            Context { _cmd_tx, event_rx },
+            Kademlia::new(
+                transport_service,
+                config,
+                Vec::new(),
+                ShutdownSignal::default(),
+            ),
+            Context {
+                _cmd_tx,
+                _transport_tx,
+                event_rx,
+            },
             manager,
         )
     }
 
+    #[tokio::test]
+    async fn store_is_persisted_on_shutdown() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let mut shutdown = crate::shutdown::Shutdown::new();
+        let path =
+            std::env::temp_dir().join(format!("litep2p-kademlia-store-{}", rand::random::<u64>()));
+
+        kademlia.shutdown = shutdown.draining_signal();
+        kademlia.store = Box::new(FileStore::open(&path).unwrap());
+        kademlia.store.put(Record::new(vec![1, 2, 3], vec![4, 5, 6]));
+
+        let handle = tokio::spawn(kademlia.run());
+        tokio::time::timeout(Duration::from_secs(5), shutdown.drain()).await.unwrap();
+        assert!(handle.await.unwrap().is_ok());
+        assert_eq!(FileStore::open(&path).unwrap().iter().count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[tokio::test]
     async fn check_get_records_update() {
         let (mut kademlia, _context, _manager) = make_kademlia();
@@ -996,4 +1778,316 @@ mod tests {
         let record = kademlia.store.get(&key).unwrap();
         assert_eq!(record.value, vec![0x2]);
     }
+
+    #[tokio::test]
+    async fn inbound_record_expires() {
+        use crate::mock::substream::MockSubstream;
+
+        let (mut kademlia, mut context, _manager) = make_kademlia();
+        let peer = PeerId::random();
+        let record = Record::new(RecordKey::from(vec![1, 2, 3]), vec![4, 5, 6]);
+        assert!(record.expires.is_none());
+
+        let message = KademliaMessage::put_value(record.clone());
+        let substream = Substream::new_mock(
+            peer,
+            SubstreamId::from(0usize),
+            Box::new(MockSubstream::new()),
+        );
+        kademlia
+            .on_message_received(peer, None, BytesMut::from(&message[..]), substream)
+            .await
+            .unwrap();
+
+        let expires = kademlia.store.get(&record.key).unwrap().expires.unwrap();
+        assert!(expires > Instant::now() + kademlia.record_ttl - Duration::from_secs(60));
+
+        match context.event_rx.recv().await {
+            Some(KademliaEvent::IncomingRecord { record: incoming }) => {
+                assert_eq!(incoming.value, record.value);
+                assert_eq!(incoming.expires, Some(expires));
+            }
+            event => panic!("invalid event: {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn replicated_record_expires_at_original_deadline() {
+        use crate::mock::substream::MockSubstream;
+
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let publisher = PeerId::random();
+        let expires = Instant::now() + Duration::from_secs(2);
+        let record = Record {
+            key: RecordKey::from(vec![1, 2, 3]),
+            value: vec![4, 5, 6],
+            publisher: Some(publisher),
+            expires: Some(expires),
+        };
+
+        // the record is replicated to the local node twice, the second copy being sent after the
+        // local node has itself replicated the record
+        for _ in 0..2 {
+            let copy = kademlia.store.get(&record.key).cloned().unwrap_or(record.clone());
+            let message = KademliaMessage::put_value(copy);
+            let substream = Substream::new_mock(
+                PeerId::random(),
+                SubstreamId::from(0usize),
+                Box::new(MockSubstream::new()),
+            );
+            kademlia
+                .on_message_received(
+                    PeerId::random(),
+                    None,
+                    BytesMut::from(&message[..]),
+                    substream,
+                )
+                .await
+                .unwrap();
+
+            kademlia.replicate_records();
+
+            let stored = kademlia.store.get(&record.key).unwrap();
+            assert_eq!(stored.publisher, Some(publisher));
+            assert!(stored.expires.unwrap() <= expires + Duration::from_secs(1));
+        }
+
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        assert!(kademlia.store.get(&record.key).is_none());
+    }
+
+    #[tokio::test]
+    async fn bootstrap_fails_without_peers() {
+        let (mut kademlia, mut context, _manager) = make_kademlia();
+
+        kademlia.start_bootstrap();
+        assert!(kademlia.bootstrap.is_some());
+
+        // bootstrap in progress, the request is ignored
+        kademlia.start_bootstrap();
+
+        while let Some(action) = kademlia.engine.next_action() {
+            assert!(kademlia.on_query_action(action).await.is_ok());
+        }
+
+        // the failed self-lookup fails the bootstrap without starting the random lookups
+        assert!(kademlia.bootstrap.is_none());
+        assert!(std::matches!(
+            context.event_rx.try_recv(),
+            Ok(KademliaEvent::BootstrapFailed)
+        ));
+        assert!(context.event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn bootstrap_starts_random_lookups() {
+        let (mut kademlia, mut context, _manager) = make_kademlia();
+
+        let peer = PeerId::random();
+        kademlia.routing_table.add_known_peer(
+            peer,
+            vec!["/ip6/::1/tcp/8888".parse().unwrap()],
+            ConnectionType::NotConnected,
+        );
+
+        kademlia.start_bootstrap();
+        let self_lookup = kademlia.bootstrap.as_ref().unwrap().self_lookup.unwrap();
+
+        // self-lookup finishes and the random lookups are started
+        let action = QueryAction::FindNodeQuerySucceeded {
+            query: self_lookup,
+            target: kademlia.service.local_peer_id,
+            peers: vec![],
+        };
+        assert!(kademlia.on_query_action(action).await.is_ok());
+
+        let random_lookups = kademlia.bootstrap.as_ref().unwrap().random_lookups.clone();
+        assert!(!random_lookups.is_empty());
+        assert!(!random_lookups.contains(&self_lookup));
+
+        // unrelated queries are reported to the user
+        let action = QueryAction::QueryFailed {
+            query: QueryId(1337),
+        };
+        assert!(kademlia.on_query_action(action).await.is_ok());
+        assert!(std::matches!(
+            context.event_rx.try_recv(),
+            Ok(KademliaEvent::QueryFailed {
+                query_id: QueryId(1337)
+            })
+        ));
+
+        for query in random_lookups {
+            assert!(context.event_rx.try_recv().is_err());
+
+            let action = QueryAction::QueryFailed { query };
+            assert!(kademlia.on_query_action(action).await.is_ok());
+        }
+
+        assert!(kademlia.bootstrap.is_none());
+        assert!(std::matches!(
+            context.event_rx.try_recv(),
+            Ok(KademliaEvent::BootstrapCompleted)
+        ));
+    }
+
+    #[tokio::test]
+    async fn republish_resets_record_expiration() {
+        let (mut kademlia, mut context, _manager) = make_kademlia();
+
+        let local_peer_id = kademlia.service.local_peer_id;
+        let expires = Instant::now() + Duration::from_secs(10);
+        let local_record = Record {
+            key: RecordKey::from(vec![1, 2, 3]),
+            value: vec![0x1],
+            publisher: Some(local_peer_id),
+            expires: Some(expires),
+        };
+        let remote_record = Record {
+            key: RecordKey::from(vec![4, 5, 6]),
+            value: vec![0x2],
+            publisher: None,
+            expires: Some(expires),
+        };
+        kademlia.store.put(local_record.clone());
+        kademlia.store.put(remote_record.clone());
+
+        // only the record published by the local node is republished
+        kademlia.republish_records();
+        assert_eq!(kademlia.republish_queries.len(), 1);
+        assert!(kademlia.store.get(&local_record.key).unwrap().expires.unwrap() > expires);
+        assert_eq!(
+            kademlia.store.get(&remote_record.key).unwrap().expires,
+            Some(expires)
+        );
+
+        // the failed republish is not reported to the user
+        while let Some(action) = kademlia.engine.next_action() {
+            assert!(kademlia.on_query_action(action).await.is_ok());
+        }
+        assert!(kademlia.republish_queries.is_empty());
+        assert!(context.event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn replication_keeps_record_expiration() {
+        let (mut kademlia, mut context, _manager) = make_kademlia();
+
+        let local_peer_id = kademlia.service.local_peer_id;
+        let expires = Instant::now() + Duration::from_secs(10);
+        let local_record = Record {
+            key: RecordKey::from(vec![1, 2, 3]),
+            value: vec![0x1],
+            publisher: Some(local_peer_id),
+            expires: Some(expires),
+        };
+        let remote_record = Record {
+            key: RecordKey::from(vec![4, 5, 6]),
+            value: vec![0x2],
+            publisher: Some(PeerId::random()),
+            expires: Some(expires),
+        };
+        kademlia.store.put(local_record.clone());
+        kademlia.store.put(remote_record.clone());
+
+        // only the record published by a remote peer is replicated
+        kademlia.replicate_records();
+        assert_eq!(kademlia.republish_queries.len(), 1);
+        assert_eq!(
+            kademlia.store.get(&remote_record.key).unwrap().expires,
+            Some(expires)
+        );
+
+        let peer = PeerId::random();
+        let query = *kademlia.republish_queries.iter().next().unwrap();
+        let action = QueryAction::PutRecordToFoundNodes {
+            query,
+            record: remote_record.clone(),
+            peers: vec![KademliaPeer::new(
+                peer,
+                vec![],
+                ConnectionType::NotConnected,
+            )],
+        };
+        assert!(kademlia.on_query_action(action).await.is_ok());
+        assert!(kademlia.republish_queries.is_empty());
+        assert!(context.event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn removed_peer_is_not_added_back_automatically() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+        let discovered =
+            KademliaPeer::new(peer, vec![address.clone()], ConnectionType::NotConnected);
+
+        kademlia.update_routing_table(&[discovered.clone()]).await;
+        kademlia.probes.insert(QueryId(1337), peer);
+        kademlia.remove_peer(peer);
+
+        assert!(kademlia.probes.is_empty());
+        assert!(std::matches!(
+            kademlia.routing_table.entry(Key::from(peer)),
+            KBucketEntry::Vacant(_)
+        ));
+
+        // discovering the peer again doesn't add it back
+        kademlia.update_routing_table(&[discovered]).await;
+        assert!(std::matches!(
+            kademlia.routing_table.entry(Key::from(peer)),
+            KBucketEntry::Vacant(_)
+        ));
+
+        // but adding it explicitly does
+        kademlia.add_known_peer(peer, vec![address]);
+        assert!(std::matches!(
+            kademlia.routing_table.entry(Key::from(peer)),
+            KBucketEntry::Occupied(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn unreachable_peer_is_replaced_by_pending_peer() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        kademlia.routing_table = RoutingTable::new(kademlia._local_key.clone(), 1);
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+
+        // find two peers sharing a k-bucket
+        let (first, second) = loop {
+            let first = PeerId::random();
+            assert!(kademlia
+                .routing_table
+                .add_known_peer(first, vec![address.clone()], ConnectionType::NotConnected)
+                .is_none());
+
+            let second = PeerId::random();
+            match kademlia.routing_table.add_known_peer(
+                second,
+                vec![address.clone()],
+                ConnectionType::NotConnected,
+            ) {
+                Some(probed) => {
+                    assert_eq!(probed, first);
+                    break (first, second);
+                }
+                None => {
+                    kademlia.routing_table = RoutingTable::new(kademlia._local_key.clone(), 1);
+                }
+            }
+        };
+
+        // the address of `first` is not known to the transport so the probe fails immediately
+        kademlia.probe(first);
+
+        assert!(kademlia.probes.is_empty());
+        assert!(std::matches!(
+            kademlia.routing_table.entry(Key::from(first)),
+            KBucketEntry::Full(_)
+        ));
+        assert!(std::matches!(
+            kademlia.routing_table.entry(Key::from(second)),
+            KBucketEntry::Occupied(_)
+        ));
+    }
 }
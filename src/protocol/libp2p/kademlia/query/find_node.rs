@@ -46,6 +46,12 @@ pub struct FindNodeConfig<T: Clone + Into<Vec<u8>>> {
     /// Parallelism factor.
     pub parallelism_factor: usize,
 
+    /// Number of disjoint paths used for the lookup.
+    ///
+    /// Each peer is queried by exactly one path and each path has at most `parallelism_factor`
+    /// pending requests.
+    pub disjoint_paths: usize,
+
     /// Query ID.
     pub query: QueryId,
 
@@ -74,8 +80,14 @@ pub struct FindNodeContext<T: Clone + Into<Vec<u8>>> {
     /// Candidates.
     pub candidates: BTreeMap<Distance, KademliaPeer>,
 
-    /// Responses.
-    pub responses: BTreeMap<Distance, KademliaPeer>,
+    /// Disjoint path of each candidate, pending and queried peer.
+    paths: HashMap<PeerId, usize>,
+
+    /// Responses received on each disjoint path.
+    ///
+    /// Each path terminates based on its own responses so that a path fed with adversarial peers
+    /// cannot end the lookup of the other paths.
+    path_responses: Vec<BTreeMap<Distance, KademliaPeer>>,
 }
 
 impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
@@ -88,19 +100,40 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
             candidates.insert(distance, candidate.clone());
         }
 
+        // distribute the initial candidates evenly between the disjoint paths
+        let paths = candidates
+            .values()
+            .enumerate()
+            .map(|(index, candidate)| (candidate.peer, index % config.disjoint_paths))
+            .collect();
+
         let kad_message = KademliaMessage::find_node(config.target.clone().into_preimage());
+        let path_responses = vec![BTreeMap::new(); config.disjoint_paths];
 
         Self {
             config,
             kad_message,
 
             candidates,
+            paths,
             pending: HashMap::new(),
             queried: HashSet::new(),
-            responses: BTreeMap::new(),
+            path_responses,
         }
     }
 
+    /// Get the closest responses of all disjoint paths, ordered by their distance to the target.
+    pub fn responses(&self) -> Vec<KademliaPeer> {
+        self.path_responses
+            .iter()
+            .flat_map(|responses| responses.iter())
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .take(self.config.replication_factor)
+            .cloned()
+            .collect()
+    }
+
     /// Register response failure for `peer`.
     pub fn register_response_failure(&mut self, peer: PeerId) {
         let Some(peer) = self.pending.remove(&peer) else {
@@ -126,22 +159,24 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
 
         // always mark the peer as queried to prevent it getting queried again
         self.queried.insert(peer.peer);
+        let path = self.paths.get(&peer.peer).copied().unwrap_or_default();
+        let responses = &mut self.path_responses[path];
 
-        if self.responses.len() < self.config.replication_factor {
-            self.responses.insert(distance, peer);
+        if responses.len() < self.config.replication_factor {
+            responses.insert(distance, peer);
         } else {
             // Update the furthest peer if this response is closer.
             // Find the furthest distance.
             let furthest_distance =
-                self.responses.last_entry().map(|entry| *entry.key()).unwrap_or(distance);
+                responses.last_entry().map(|entry| *entry.key()).unwrap_or(distance);
 
             // The response received from the peer is closer than the furthest response.
             if distance < furthest_distance {
-                self.responses.insert(distance, peer);
+                responses.insert(distance, peer);
 
                 // Remove the furthest entry.
-                if self.responses.len() > self.config.replication_factor {
-                    self.responses.pop_last();
+                if responses.len() > self.config.replication_factor {
+                    responses.pop_last();
                 }
             }
         }
@@ -166,7 +201,9 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
         });
 
         for candidate in to_query_candidate {
+            // the candidate stays on the path that discovered it first
             let distance = self.config.target.distance(&candidate.key);
+            self.paths.entry(candidate.peer).or_insert(path);
             self.candidates.insert(distance, candidate);
         }
     }
@@ -180,11 +217,24 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
         })
     }
 
-    /// Schedule next peer for outbound `FIND_NODE` query.
-    fn schedule_next_peer(&mut self) -> Option<QueryAction> {
-        tracing::trace!(target: LOG_TARGET, query = ?self.config.query, "get next peer");
+    /// Get the closest candidate of `path`.
+    fn next_candidate(&self, path: usize) -> Option<(&Distance, &KademliaPeer)> {
+        self.candidates
+            .iter()
+            .find(|(_, candidate)| self.paths.get(&candidate.peer) == Some(&path))
+    }
+
+    /// Get the number of pending requests of `path`.
+    fn num_pending(&self, path: usize) -> usize {
+        self.pending.keys().filter(|peer| self.paths.get(peer) == Some(&path)).count()
+    }
 
-        let (_, candidate) = self.candidates.pop_first()?;
+    /// Schedule next peer of `path` for outbound `FIND_NODE` query.
+    fn schedule_next_peer(&mut self, path: usize) -> Option<QueryAction> {
+        tracing::trace!(target: LOG_TARGET, query = ?self.config.query, ?path, "get next peer");
+
+        let distance = *self.next_candidate(path)?.0;
+        let candidate = self.candidates.remove(&distance)?;
 
         self.pending.insert(candidate.peer, candidate.clone());
 
@@ -195,19 +245,37 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
         })
     }
 
-    /// Check if the query cannot make any progress.
+    /// Check if `path` has finished.
     ///
-    /// Returns true when there are no pending responses and no candidates to query.
+    /// A path has finished when it has no pending responses and either no candidates left to
+    /// query or enough responses and no candidate closer than the furthest of them.
+    fn is_path_done(&self, path: usize) -> bool {
+        if self.num_pending(path) > 0 {
+            return false;
+        }
+
+        let responses = &self.path_responses[path];
+
+        match (self.next_candidate(path), responses.last_key_value()) {
+            (None, _) => true,
+            (Some((candidate_distance, _)), Some((worst_response_distance, _))) =>
+                responses.len() >= self.config.replication_factor
+                    && candidate_distance >= worst_response_distance,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Check if every disjoint path has finished.
     fn is_done(&self) -> bool {
-        self.pending.is_empty() && self.candidates.is_empty()
+        (0..self.config.disjoint_paths).all(|path| self.is_path_done(path))
     }
 
     /// Get next action for a `FIND_NODE` query.
     pub fn next_action(&mut self) -> Option<QueryAction> {
-        // If we cannot make progress, return the final result.
-        // A query failed when we are not able to identify one single peer.
+        // The query finishes once every disjoint path has finished, at which point the results of
+        // the paths are merged. A query failed when we are not able to identify one single peer.
         if self.is_done() {
-            return if self.responses.is_empty() {
+            return if self.path_responses.iter().all(|responses| responses.is_empty()) {
                 Some(QueryAction::QueryFailed {
                     query: self.config.query,
                 })
@@ -218,36 +286,29 @@ impl<T: Clone + Into<Vec<u8>>> FindNodeContext<T> {
             };
         }
 
-        // At this point, we either have pending responses or candidates to query; and we need more
-        // results. Ensure we do not exceed the parallelism factor.
-        if self.pending.len() == self.config.parallelism_factor {
-            return None;
-        }
-
-        // Schedule the next peer to fill up the responses.
-        if self.responses.len() < self.config.replication_factor {
-            return self.schedule_next_peer();
-        }
+        // Each disjoint path progresses independently, must not exceed the parallelism factor and
+        // continues until it has found enough responses and there are no better candidates.
+        for path in 0..self.config.disjoint_paths {
+            if self.num_pending(path) == self.config.parallelism_factor {
+                continue;
+            }
 
-        // We can finish the query here, but check if there is a better candidate for the query.
-        match (
-            self.candidates.first_key_value(),
-            self.responses.last_key_value(),
-        ) {
-            (Some((_, candidate_peer)), Some((worst_response_distance, _))) => {
-                let first_candidate_distance = self.config.target.distance(&candidate_peer.key);
-                if first_candidate_distance < *worst_response_distance {
-                    return self.schedule_next_peer();
+            let responses = &self.path_responses[path];
+            let schedule = responses.len() < self.config.replication_factor
+                || match (self.next_candidate(path), responses.last_key_value()) {
+                    (Some((candidate_distance, _)), Some((worst_response_distance, _))) =>
+                        candidate_distance < worst_response_distance,
+                    _ => false,
+                };
+
+            if schedule {
+                if let Some(action) = self.schedule_next_peer(path) {
+                    return Some(action);
                 }
             }
-
-            _ => (),
         }
 
-        // We have found enough responses and there are no better candidates to query.
-        Some(QueryAction::QuerySucceeded {
-            query: self.config.query,
-        })
+        None
     }
 }
 
@@ -261,6 +322,7 @@ mod tests {
             local_peer_id: PeerId::random(),
             replication_factor: 20,
             parallelism_factor: 10,
+            disjoint_paths: 1,
             query: QueryId(0),
             target: Key::new(vec![1, 2, 3].into()),
         }
@@ -292,6 +354,7 @@ mod tests {
         let config = FindNodeConfig {
             parallelism_factor: 1,
             replication_factor: 1,
+            disjoint_paths: 1,
             target: Key::from(target),
             local_peer_id: PeerId::random(),
             query: QueryId(0),
@@ -385,20 +448,20 @@ mod tests {
         context.register_response(peer_a, vec![]);
         assert_eq!(context.pending.len(), 2);
         assert_eq!(context.queried.len(), 1);
-        assert_eq!(context.responses.len(), 1);
+        assert_eq!(context.responses().len(), 1);
 
         // Provide different response from peer b with peer d as candidate.
         context.register_response(peer_b, vec![peer_to_kad(peer_d.clone())]);
         assert_eq!(context.pending.len(), 1);
         assert_eq!(context.queried.len(), 2);
-        assert_eq!(context.responses.len(), 2);
+        assert_eq!(context.responses().len(), 2);
         assert_eq!(context.candidates.len(), 1);
 
         // Peer C fails.
         context.register_response_failure(peer_c);
         assert!(context.pending.is_empty());
         assert_eq!(context.queried.len(), 3);
-        assert_eq!(context.responses.len(), 2);
+        assert_eq!(context.responses().len(), 2);
 
         // Drain the last candidate.
         let event = context.next_action().unwrap();
@@ -510,6 +573,7 @@ mod tests {
         let config = FindNodeConfig {
             parallelism_factor: 3,
             replication_factor: 3,
+            disjoint_paths: 1,
             target,
             local_peer_id: PeerId::random(),
             query: QueryId(0),
@@ -567,9 +631,126 @@ mod tests {
         // updated, which would have produced [peer[0], peer[1], peer[5]].
 
         // Check the responses.
-        let responses = context.responses.values().map(|peer| peer.peer).collect::<Vec<_>>();
+        let responses = context.responses().iter().map(|peer| peer.peer).collect::<Vec<_>>();
         // Note: peers are returned in order closest to the target, our `peers` input is sorted in
         // decreasing order.
         assert_eq!(responses, [peers[5], peers[4], peers[3]]);
     }
+
+    #[test]
+    fn disjoint_paths_respect_parallelism() {
+        let config = FindNodeConfig {
+            parallelism_factor: 1,
+            disjoint_paths: 2,
+            ..default_config()
+        };
+
+        let in_peers = (0..4).map(|_| peer_to_kad(PeerId::random())).collect();
+        let mut context = FindNodeContext::new(config, in_peers);
+
+        // each path has one request in flight
+        for num in 0..2 {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { .. } => assert_eq!(context.pending.len(), num + 1),
+                _ => panic!("Unexpected event"),
+            }
+        }
+
+        assert!(context.next_action().is_none());
+        assert_eq!(context.candidates.len(), 2);
+    }
+
+    #[test]
+    fn disjoint_paths_query_distinct_peers() {
+        let config = FindNodeConfig {
+            parallelism_factor: 1,
+            disjoint_paths: 2,
+            ..default_config()
+        };
+
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let peer_c = PeerId::random();
+        let in_peers = [peer_a, peer_b].iter().map(|peer| peer_to_kad(*peer)).collect();
+        let mut context = FindNodeContext::new(config, in_peers);
+
+        for _ in 0..2 {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { .. } => {}
+                _ => panic!("Unexpected event"),
+            }
+        }
+
+        // both paths discover the same peer which is then queried only by the first path
+        context.register_response(peer_a, vec![peer_to_kad(peer_c)]);
+        context.register_response(peer_b, vec![peer_to_kad(peer_c)]);
+
+        match context.next_action().unwrap() {
+            QueryAction::SendMessage { peer, .. } => assert_eq!(peer, peer_c),
+            _ => panic!("Unexpected event"),
+        }
+        assert!(context.next_action().is_none());
+
+        context.register_response(peer_c, vec![]);
+
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QuerySucceeded { query: QueryId(0) });
+        assert_eq!(context.responses().len(), 3);
+    }
+
+    #[test]
+    fn adversarial_path_does_not_terminate_other_paths() {
+        let target = Key::from(PeerId::random());
+        let mut peers = (0..8).map(|_| PeerId::random()).collect::<Vec<_>>();
+        peers.sort_by_key(|peer| target.distance(&Key::from(*peer)));
+
+        let config = FindNodeConfig {
+            parallelism_factor: 1,
+            replication_factor: 2,
+            disjoint_paths: 2,
+            target,
+            local_peer_id: PeerId::random(),
+            query: QueryId(0),
+        };
+
+        // the closer initial peer is assigned to the first path which is adversarial
+        let in_peers = [peers[2], peers[7]].iter().map(|peer| peer_to_kad(*peer)).collect();
+        let mut context = FindNodeContext::new(config, in_peers);
+
+        for expected in [peers[2], peers[7]] {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { peer, .. } => assert_eq!(peer, expected),
+                _ => panic!("Unexpected event"),
+            }
+        }
+
+        // the adversarial path fills its results with peers closer than anything the honest
+        // path can find
+        context.register_response(peers[2], vec![peer_to_kad(peers[0]), peer_to_kad(peers[1])]);
+
+        for expected in [peers[0], peers[1]] {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { peer, .. } => assert_eq!(peer, expected),
+                _ => panic!("Unexpected event"),
+            }
+            context.register_response(expected, vec![]);
+        }
+
+        // the honest path keeps going even though its candidate is further than the results of
+        // the adversarial path
+        context.register_response(peers[7], vec![peer_to_kad(peers[5])]);
+
+        match context.next_action().unwrap() {
+            QueryAction::SendMessage { peer, .. } => assert_eq!(peer, peers[5]),
+            _ => panic!("Unexpected event"),
+        }
+        context.register_response(peers[5], vec![]);
+
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QuerySucceeded { query: QueryId(0) });
+
+        // the results of the paths are merged
+        let responses = context.responses().iter().map(|peer| peer.peer).collect::<Vec<_>>();
+        assert_eq!(responses, [peers[0], peers[1]]);
+    }
 }
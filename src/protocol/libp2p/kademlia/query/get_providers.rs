@@ -0,0 +1,381 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use bytes::Bytes;
+
+use crate::{
+    protocol::libp2p::kademlia::{
+        message::KademliaMessage,
+        query::{QueryAction, QueryId},
+        record::{ContentProvider, Key as RecordKey},
+        types::{Distance, KademliaPeer, Key},
+    },
+    PeerId,
+};
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::ipfs::kademlia::query::get_providers";
+
+/// The configuration needed to instantiate a new [`GetProvidersContext`].
+#[derive(Debug)]
+pub struct GetProvidersConfig {
+    /// Local peer ID.
+    pub local_peer_id: PeerId,
+
+    /// Replication factor.
+    pub replication_factor: usize,
+
+    /// Parallelism factor.
+    pub parallelism_factor: usize,
+
+    /// Query ID.
+    pub query: QueryId,
+
+    /// Target key.
+    pub target: Key<RecordKey>,
+
+    /// Providers known before the query was started (ie extracted from local storage).
+    pub known_providers: Vec<ContentProvider>,
+}
+
+/// Context for `GET_PROVIDERS` queries.
+#[derive(Debug)]
+pub struct GetProvidersContext {
+    /// Query immutable config.
+    pub config: GetProvidersConfig,
+
+    /// Cached Kademlia message to send.
+    kad_message: Bytes,
+
+    /// Peers from whom the `QueryEngine` is waiting to hear a response.
+    pub pending: HashMap<PeerId, KademliaPeer>,
+
+    /// Queried candidates.
+    ///
+    /// These are the peers for whom the query has already been sent
+    /// and who have either returned their closest peers or failed to answer.
+    pub queried: HashSet<PeerId>,
+
+    /// Candidates.
+    pub candidates: BTreeMap<Distance, KademliaPeer>,
+
+    /// Closest peers that responded to the query.
+    pub responses: BTreeMap<Distance, KademliaPeer>,
+
+    /// Found providers.
+    pub found_providers: HashMap<PeerId, ContentProvider>,
+}
+
+impl GetProvidersContext {
+    /// Create new [`GetProvidersContext`].
+    pub fn new(mut config: GetProvidersConfig, in_peers: VecDeque<KademliaPeer>) -> Self {
+        let mut candidates = BTreeMap::new();
+
+        for candidate in &in_peers {
+            let distance = config.target.distance(&candidate.key);
+            candidates.insert(distance, candidate.clone());
+        }
+
+        let kad_message =
+            KademliaMessage::get_providers_request(config.target.clone().into_preimage());
+        let found_providers = std::mem::take(&mut config.known_providers)
+            .into_iter()
+            .map(|provider| (provider.peer, provider))
+            .collect();
+
+        Self {
+            config,
+            kad_message,
+
+            candidates,
+            pending: HashMap::new(),
+            queried: HashSet::new(),
+            responses: BTreeMap::new(),
+            found_providers,
+        }
+    }
+
+    /// Get the found providers.
+    pub fn found_providers(self) -> Vec<ContentProvider> {
+        self.found_providers.into_values().collect()
+    }
+
+    /// Register response failure for `peer`.
+    pub fn register_response_failure(&mut self, peer: PeerId) {
+        let Some(peer) = self.pending.remove(&peer) else {
+            tracing::trace!(target: LOG_TARGET, ?peer, "pending peer doesn't exist");
+            return;
+        };
+
+        self.queried.insert(peer.peer);
+    }
+
+    /// Register `GET_PROVIDERS` response from `peer`.
+    pub fn register_response(
+        &mut self,
+        peer: PeerId,
+        providers: Vec<KademliaPeer>,
+        peers: Vec<KademliaPeer>,
+    ) {
+        let Some(peer) = self.pending.remove(&peer) else {
+            tracing::trace!(target: LOG_TARGET, ?peer, "received response from peer but didn't expect it");
+            return;
+        };
+
+        for provider in providers {
+            let entry = self.found_providers.entry(provider.peer).or_insert(ContentProvider {
+                peer: provider.peer,
+                addresses: Vec::new(),
+            });
+
+            for address in provider.addresses {
+                if !entry.addresses.contains(&address) {
+                    entry.addresses.push(address);
+                }
+            }
+        }
+
+        // always mark the peer as queried to prevent it getting queried again
+        self.queried.insert(peer.peer);
+
+        // keep track of the `replication_factor` closest peers that responded
+        let distance = self.config.target.distance(&peer.key);
+        self.responses.insert(distance, peer);
+
+        if self.responses.len() > self.config.replication_factor {
+            self.responses.pop_last();
+        }
+
+        let to_query_candidate = peers.into_iter().filter_map(|peer| {
+            // Peer already produced a response.
+            if self.queried.contains(&peer.peer) {
+                return None;
+            }
+
+            // Peer was queried, awaiting response.
+            if self.pending.contains_key(&peer.peer) {
+                return None;
+            }
+
+            // Local node.
+            if self.config.local_peer_id == peer.peer {
+                return None;
+            }
+
+            Some(peer)
+        });
+
+        for candidate in to_query_candidate {
+            let distance = self.config.target.distance(&candidate.key);
+            self.candidates.insert(distance, candidate);
+        }
+    }
+
+    /// Get next action for `peer`.
+    pub fn next_peer_action(&mut self, peer: &PeerId) -> Option<QueryAction> {
+        self.pending.contains_key(peer).then_some(QueryAction::SendMessage {
+            query: self.config.query,
+            peer: *peer,
+            message: self.kad_message.clone(),
+        })
+    }
+
+    /// Schedule next peer for outbound `GET_PROVIDERS` query.
+    fn schedule_next_peer(&mut self) -> Option<QueryAction> {
+        tracing::trace!(target: LOG_TARGET, query = ?self.config.query, "get next peer");
+
+        let (_, candidate) = self.candidates.pop_first()?;
+        let peer = candidate.peer;
+
+        self.pending.insert(candidate.peer, candidate);
+
+        Some(QueryAction::SendMessage {
+            query: self.config.query,
+            peer,
+            message: self.kad_message.clone(),
+        })
+    }
+
+    /// Check if the query cannot make any progress.
+    ///
+    /// Returns true when there are no pending responses and no candidates to query.
+    fn is_done(&self) -> bool {
+        self.pending.is_empty() && self.candidates.is_empty()
+    }
+
+    /// Get next action for a `GET_PROVIDERS` query.
+    pub fn next_action(&mut self) -> Option<QueryAction> {
+        // If we cannot make progress, return the final result.
+        // A query failed when we are not able to identify one single provider.
+        if self.is_done() {
+            return if self.found_providers.is_empty() {
+                Some(QueryAction::QueryFailed {
+                    query: self.config.query,
+                })
+            } else {
+                Some(QueryAction::QuerySucceeded {
+                    query: self.config.query,
+                })
+            };
+        }
+
+        // Ensure we do not exceed the parallelism factor.
+        if self.pending.len() == self.config.parallelism_factor {
+            return None;
+        }
+
+        // Keep querying until the closest peers to the key have responded.
+        if self.responses.len() < self.config.replication_factor {
+            return self.schedule_next_peer();
+        }
+
+        // Check if there is a candidate closer than the furthest peer that responded.
+        if let (Some((_, candidate)), Some((worst_response_distance, _))) = (
+            self.candidates.first_key_value(),
+            self.responses.last_key_value(),
+        ) {
+            if self.config.target.distance(&candidate.key) < *worst_response_distance {
+                return self.schedule_next_peer();
+            }
+        }
+
+        // Wait for the pending responses before reporting the providers.
+        if !self.pending.is_empty() {
+            return None;
+        }
+
+        if self.found_providers.is_empty() {
+            Some(QueryAction::QueryFailed {
+                query: self.config.query,
+            })
+        } else {
+            Some(QueryAction::QuerySucceeded {
+                query: self.config.query,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::libp2p::kademlia::types::ConnectionType;
+
+    fn default_config() -> GetProvidersConfig {
+        GetProvidersConfig {
+            local_peer_id: PeerId::random(),
+            replication_factor: 20,
+            parallelism_factor: 10,
+            query: QueryId(0),
+            target: Key::new(vec![1, 2, 3].into()),
+            known_providers: Vec::new(),
+        }
+    }
+
+    fn peer_to_kad(peer: PeerId) -> KademliaPeer {
+        KademliaPeer {
+            peer,
+            key: Key::from(peer),
+            addresses: vec![],
+            connection: ConnectionType::Connected,
+        }
+    }
+
+    #[test]
+    fn completes_when_no_candidates() {
+        let mut context = GetProvidersContext::new(default_config(), VecDeque::new());
+        assert!(context.is_done());
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QueryFailed { query: QueryId(0) });
+
+        let config = GetProvidersConfig {
+            known_providers: vec![ContentProvider {
+                peer: PeerId::random(),
+                addresses: vec![],
+            }],
+            ..default_config()
+        };
+        let mut context = GetProvidersContext::new(config, VecDeque::new());
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QuerySucceeded { query: QueryId(0) });
+    }
+
+    #[test]
+    fn providers_are_merged() {
+        let config = GetProvidersConfig {
+            parallelism_factor: 2,
+            replication_factor: 2,
+            ..default_config()
+        };
+
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let provider = PeerId::random();
+        let address_a: multiaddr::Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        let address_b: multiaddr::Multiaddr = "/ip4/127.0.0.1/tcp/2".parse().unwrap();
+
+        let in_peers = [peer_a, peer_b].iter().map(|peer| peer_to_kad(*peer)).collect();
+        let mut context = GetProvidersContext::new(config, in_peers);
+
+        for _ in 0..2 {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { query, .. } => assert_eq!(query, QueryId(0)),
+                _ => panic!("Unexpected event"),
+            }
+        }
+        assert!(context.next_action().is_none());
+
+        context.register_response(
+            peer_a,
+            vec![KademliaPeer::new(
+                provider,
+                vec![address_a.clone()],
+                ConnectionType::NotConnected,
+            )],
+            vec![],
+        );
+        // Still waiting for the response from peer b.
+        assert!(context.next_action().is_none());
+
+        context.register_response(
+            peer_b,
+            vec![KademliaPeer::new(
+                provider,
+                vec![address_a.clone(), address_b.clone()],
+                ConnectionType::NotConnected,
+            )],
+            vec![],
+        );
+
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QuerySucceeded { query: QueryId(0) });
+
+        let providers = context.found_providers();
+        assert_eq!(
+            providers,
+            vec![ContentProvider {
+                peer: provider,
+                addresses: vec![address_a, address_b],
+            }]
+        );
+    }
+}
@@ -56,6 +56,12 @@ pub struct GetRecordConfig {
     /// Parallelism factor.
     pub parallelism_factor: usize,
 
+    /// Number of disjoint paths used for the lookup.
+    ///
+    /// Each peer is queried by exactly one path and each path has at most `parallelism_factor`
+    /// pending requests.
+    pub disjoint_paths: usize,
+
     /// Query ID.
     pub query: QueryId,
 
@@ -100,6 +106,9 @@ pub struct GetRecordContext {
     /// Candidates.
     pub candidates: BTreeMap<Distance, KademliaPeer>,
 
+    /// Disjoint path of each candidate, pending and queried peer.
+    paths: HashMap<PeerId, usize>,
+
     /// Found records.
     pub found_records: Vec<PeerRecord>,
 }
@@ -114,6 +123,13 @@ impl GetRecordContext {
             candidates.insert(distance, candidate.clone());
         }
 
+        // distribute the initial candidates evenly between the disjoint paths
+        let paths = candidates
+            .values()
+            .enumerate()
+            .map(|(index, candidate)| (candidate.peer, index % config.disjoint_paths))
+            .collect();
+
         let kad_message = KademliaMessage::get_record(config.target.clone().into_preimage());
 
         Self {
@@ -121,6 +137,7 @@ impl GetRecordContext {
             kad_message,
 
             candidates,
+            paths,
             pending: HashMap::new(),
             queried: HashSet::new(),
             found_records: Vec::new(),
@@ -166,6 +183,7 @@ impl GetRecordContext {
         // Add the queried peer to `queried` and all new peers which haven't been
         // queried to `candidates`
         self.queried.insert(peer.peer);
+        let path = self.paths.get(&peer.peer).copied().unwrap_or_default();
 
         let to_query_candidate = peers.into_iter().filter_map(|peer| {
             // Peer already produced a response.
@@ -187,7 +205,9 @@ impl GetRecordContext {
         });
 
         for candidate in to_query_candidate {
+            // the candidate stays on the path that discovered it first
             let distance = self.config.target.distance(&candidate.key);
+            self.paths.entry(candidate.peer).or_insert(path);
             self.candidates.insert(distance, candidate);
         }
     }
@@ -202,11 +222,21 @@ impl GetRecordContext {
         })
     }
 
-    /// Schedule next peer for outbound `GET_VALUE` query.
-    fn schedule_next_peer(&mut self) -> Option<QueryAction> {
-        tracing::trace!(target: LOG_TARGET, query = ?self.config.query, "get next peer");
+    /// Get the number of pending requests of `path`.
+    fn num_pending(&self, path: usize) -> usize {
+        self.pending.keys().filter(|peer| self.paths.get(peer) == Some(&path)).count()
+    }
+
+    /// Schedule next peer of `path` for outbound `GET_VALUE` query.
+    fn schedule_next_peer(&mut self, path: usize) -> Option<QueryAction> {
+        tracing::trace!(target: LOG_TARGET, query = ?self.config.query, ?path, "get next peer");
 
-        let (_, candidate) = self.candidates.pop_first()?;
+        let distance = self
+            .candidates
+            .iter()
+            .find(|(_, candidate)| self.paths.get(&candidate.peer) == Some(&path))
+            .map(|(distance, _)| *distance)?;
+        let candidate = self.candidates.remove(&distance)?;
 
         let peer = candidate.peer;
 
@@ -256,12 +286,19 @@ impl GetRecordContext {
         }
 
         // At this point, we either have pending responses or candidates to query; and we need more
-        // records. Ensure we do not exceed the parallelism factor.
-        if self.pending.len() == self.config.parallelism_factor {
-            return None;
+        // records. Each disjoint path progresses independently and must not exceed the
+        // parallelism factor.
+        for path in 0..self.config.disjoint_paths {
+            if self.num_pending(path) == self.config.parallelism_factor {
+                continue;
+            }
+
+            if let Some(action) = self.schedule_next_peer(path) {
+                return Some(action);
+            }
         }
 
-        self.schedule_next_peer()
+        None
     }
 }
 
@@ -277,6 +314,7 @@ mod tests {
             known_records: 0,
             replication_factor: 20,
             parallelism_factor: 10,
+            disjoint_paths: 1,
             query: QueryId(0),
             target: Key::new(vec![1, 2, 3].into()),
         }
@@ -505,4 +543,40 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn disjoint_paths_collect_records() {
+        let key = vec![1, 2, 3];
+        let config = GetRecordConfig {
+            parallelism_factor: 1,
+            disjoint_paths: 2,
+            quorum: Quorum::N(std::num::NonZeroUsize::new(2).unwrap()),
+            ..default_config()
+        };
+
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let in_peers = [peer_a, peer_b].iter().map(|peer| peer_to_kad(*peer)).collect();
+        let mut context = GetRecordContext::new(config, in_peers);
+
+        // both paths query their peer at the same time
+        for num in 0..2 {
+            match context.next_action().unwrap() {
+                QueryAction::SendMessage { .. } => assert_eq!(context.pending.len(), num + 1),
+                _ => panic!("Unexpected event"),
+            }
+        }
+        assert!(context.next_action().is_none());
+
+        // the quorum is reached with the records found by both paths
+        let record = Record::new(key.clone(), vec![1, 2, 3]);
+        context.register_response(peer_a, Some(record.clone()), vec![]);
+        assert!(context.next_action().is_none());
+
+        context.register_response(peer_b, Some(record), vec![]);
+
+        let event = context.next_action().unwrap();
+        assert_eq!(event, QueryAction::QuerySucceeded { query: QueryId(0) });
+        assert_eq!(context.found_records().len(), 2);
+    }
 }
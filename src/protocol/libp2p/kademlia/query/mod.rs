@@ -23,23 +23,28 @@ use crate::{
         message::KademliaMessage,
         query::{
             find_node::{FindNodeConfig, FindNodeContext},
+            get_providers::{GetProvidersConfig, GetProvidersContext},
             get_record::{GetRecordConfig, GetRecordContext},
         },
-        record::{Key as RecordKey, Record},
+        record::{ContentProvider, Key as RecordKey, ProviderRecord, Record},
         types::{KademliaPeer, Key},
-        PeerRecord, Quorum,
+        PeerRecord, QueryConfig, Quorum,
     },
     PeerId,
 };
 
 use bytes::Bytes;
 
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
 
 use self::find_many_nodes::FindManyNodesContext;
 
 mod find_many_nodes;
 mod find_node;
+mod get_providers;
 mod get_record;
 
 /// Logging target for the file.
@@ -83,6 +88,21 @@ enum QueryType {
         /// Context for the `GET_VALUE` query.
         context: GetRecordContext,
     },
+
+    /// `ADD_PROVIDER` query.
+    AddProvider {
+        /// Provider record that needs to be announced.
+        provider: ProviderRecord,
+
+        /// Context for the `FIND_NODE` query.
+        context: FindNodeContext<RecordKey>,
+    },
+
+    /// `GET_PROVIDERS` query.
+    GetProviders {
+        /// Context for the `GET_PROVIDERS` query.
+        context: GetProvidersContext,
+    },
 }
 
 /// Query action.
@@ -112,9 +132,27 @@ pub enum QueryAction {
         peers: Vec<KademliaPeer>,
     },
 
+    /// `FIND_NODE` query received a response.
+    FindNodeProgress {
+        /// Query ID.
+        query: QueryId,
+
+        /// Target peer.
+        target: PeerId,
+
+        /// Peers returned by the peer that responded.
+        peers: Vec<KademliaPeer>,
+
+        /// Number of progress actions emitted for the query so far.
+        step: usize,
+    },
+
     /// Store the record to nodes closest to target key.
     // TODO: horrible name
     PutRecordToFoundNodes {
+        /// Query ID.
+        query: QueryId,
+
         /// Target peer.
         record: Record,
 
@@ -131,6 +169,42 @@ pub enum QueryAction {
         records: Vec<PeerRecord>,
     },
 
+    /// `GET_VALUE` query found a record.
+    GetRecordPartialResult {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Found record.
+        record: PeerRecord,
+
+        /// Number of progress actions emitted for the query so far.
+        step: usize,
+    },
+
+    /// Announce the provider record to nodes closest to the provided key.
+    AddProviderToFoundNodes {
+        /// Query ID.
+        query: QueryId,
+
+        /// Provider record.
+        provider: ProviderRecord,
+
+        /// Peers to whom the `ADD_PROVIDER` must be sent to.
+        peers: Vec<KademliaPeer>,
+    },
+
+    /// `GET_PROVIDERS` query succeeded.
+    GetProvidersQueryDone {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Provided key.
+        provided_key: RecordKey,
+
+        /// Found providers.
+        providers: Vec<ContentProvider>,
+    },
+
     // TODO: remove
     /// Query succeeded.
     QuerySucceeded {
@@ -156,8 +230,20 @@ pub struct QueryEngine {
     /// Parallelism factor.
     parallelism_factor: usize,
 
+    /// Default number of disjoint paths used by the lookups.
+    disjoint_paths: usize,
+
+    /// Default query timeout, if any.
+    query_timeout: Option<Duration>,
+
     /// Active queries.
     queries: HashMap<QueryId, QueryType>,
+
+    /// Deadlines of the queries that have a timeout.
+    deadlines: HashMap<QueryId, Instant>,
+
+    /// Number of progress actions emitted for each query.
+    steps: HashMap<QueryId, usize>,
 }
 
 impl QueryEngine {
@@ -166,21 +252,42 @@ impl QueryEngine {
         local_peer_id: PeerId,
         replication_factor: usize,
         parallelism_factor: usize,
+        disjoint_paths: usize,
+        query_timeout: Option<Duration>,
     ) -> Self {
         Self {
             local_peer_id,
             replication_factor,
             parallelism_factor,
+            disjoint_paths: disjoint_paths.max(1),
+            query_timeout,
             queries: HashMap::new(),
+            deadlines: HashMap::new(),
+            steps: HashMap::new(),
         }
     }
 
+    /// Set the deadline of `query` if it has a timeout.
+    fn set_deadline(&mut self, query: QueryId, timeout: Option<Duration>) {
+        if let Some(timeout) = timeout.or(self.query_timeout) {
+            self.deadlines.insert(query, Instant::now() + timeout);
+        }
+    }
+
+    /// Get the earliest deadline of the active queries, if any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
     /// Start `FIND_NODE` query.
+    ///
+    /// The fields of `query_config` which are `None` are set to the defaults of the engine.
     pub fn start_find_node(
         &mut self,
         query_id: QueryId,
         target: PeerId,
         candidates: VecDeque<KademliaPeer>,
+        query_config: QueryConfig,
     ) -> QueryId {
         tracing::debug!(
             target: LOG_TARGET,
@@ -193,12 +300,14 @@ impl QueryEngine {
         let target = Key::from(target);
         let config = FindNodeConfig {
             local_peer_id: self.local_peer_id,
-            replication_factor: self.replication_factor,
-            parallelism_factor: self.parallelism_factor,
+            replication_factor: query_config.num_results.unwrap_or(self.replication_factor).max(1),
+            parallelism_factor: query_config.parallelism.unwrap_or(self.parallelism_factor).max(1),
+            disjoint_paths: query_config.disjoint_paths.unwrap_or(self.disjoint_paths).max(1),
             query: query_id,
             target,
         };
 
+        self.set_deadline(query_id, query_config.timeout);
         self.queries.insert(
             query_id,
             QueryType::FindNode {
@@ -229,6 +338,7 @@ impl QueryEngine {
             local_peer_id: self.local_peer_id,
             replication_factor: self.replication_factor,
             parallelism_factor: self.parallelism_factor,
+            disjoint_paths: self.disjoint_paths,
             query: query_id,
             target,
         };
@@ -271,6 +381,8 @@ impl QueryEngine {
     }
 
     /// Start `GET_VALUE` query.
+    ///
+    /// The fields of `query_config` which are `None` are set to the defaults of the engine.
     pub fn start_get_record(
         &mut self,
         query_id: QueryId,
@@ -278,6 +390,7 @@ impl QueryEngine {
         candidates: VecDeque<KademliaPeer>,
         quorum: Quorum,
         count: usize,
+        query_config: QueryConfig,
     ) -> QueryId {
         tracing::debug!(
             target: LOG_TARGET,
@@ -292,12 +405,14 @@ impl QueryEngine {
             local_peer_id: self.local_peer_id,
             known_records: count,
             quorum,
-            replication_factor: self.replication_factor,
-            parallelism_factor: self.parallelism_factor,
+            replication_factor: query_config.num_results.unwrap_or(self.replication_factor).max(1),
+            parallelism_factor: query_config.parallelism.unwrap_or(self.parallelism_factor).max(1),
+            disjoint_paths: query_config.disjoint_paths.unwrap_or(self.disjoint_paths).max(1),
             query: query_id,
             target,
         };
 
+        self.set_deadline(query_id, query_config.timeout);
         self.queries.insert(
             query_id,
             QueryType::GetRecord {
@@ -308,6 +423,77 @@ impl QueryEngine {
         query_id
     }
 
+    /// Start `ADD_PROVIDER` query.
+    pub fn start_add_provider(
+        &mut self,
+        query_id: QueryId,
+        provider: ProviderRecord,
+        candidates: VecDeque<KademliaPeer>,
+    ) -> QueryId {
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?query_id,
+            target = ?provider.key,
+            num_peers = ?candidates.len(),
+            "start `ADD_PROVIDER` query"
+        );
+
+        let target = Key::new(provider.key.clone());
+        let config = FindNodeConfig {
+            local_peer_id: self.local_peer_id,
+            replication_factor: self.replication_factor,
+            parallelism_factor: self.parallelism_factor,
+            disjoint_paths: self.disjoint_paths,
+            query: query_id,
+            target,
+        };
+
+        self.queries.insert(
+            query_id,
+            QueryType::AddProvider {
+                provider,
+                context: FindNodeContext::new(config, candidates),
+            },
+        );
+
+        query_id
+    }
+
+    /// Start `GET_PROVIDERS` query.
+    pub fn start_get_providers(
+        &mut self,
+        query_id: QueryId,
+        key: RecordKey,
+        candidates: VecDeque<KademliaPeer>,
+        known_providers: Vec<ContentProvider>,
+    ) -> QueryId {
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?query_id,
+            ?key,
+            num_peers = ?candidates.len(),
+            "start `GET_PROVIDERS` query"
+        );
+
+        let config = GetProvidersConfig {
+            local_peer_id: self.local_peer_id,
+            replication_factor: self.replication_factor,
+            parallelism_factor: self.parallelism_factor,
+            query: query_id,
+            target: Key::new(key),
+            known_providers,
+        };
+
+        self.queries.insert(
+            query_id,
+            QueryType::GetProviders {
+                context: GetProvidersContext::new(config, candidates),
+            },
+        );
+
+        query_id
+    }
+
     /// Register response failure from a queried peer.
     pub fn register_response_failure(&mut self, query: QueryId, peer: PeerId) {
         tracing::trace!(target: LOG_TARGET, ?query, ?peer, "register response failure");
@@ -328,11 +514,24 @@ impl QueryEngine {
             Some(QueryType::GetRecord { context }) => {
                 context.register_response_failure(peer);
             }
+            Some(QueryType::AddProvider { context, .. }) => {
+                context.register_response_failure(peer);
+            }
+            Some(QueryType::GetProviders { context }) => {
+                context.register_response_failure(peer);
+            }
         }
     }
 
     /// Register that `response` received from `peer`.
-    pub fn register_response(&mut self, query: QueryId, peer: PeerId, message: KademliaMessage) {
+    ///
+    /// Returns the progress of the query, if the query reports one.
+    pub fn register_response(
+        &mut self,
+        query: QueryId,
+        peer: PeerId,
+        message: KademliaMessage,
+    ) -> Option<QueryAction> {
         tracing::trace!(target: LOG_TARGET, ?query, ?peer, "register response");
 
         match self.queries.get_mut(&query) {
@@ -341,29 +540,121 @@ impl QueryEngine {
             }
             Some(QueryType::FindNode { context }) => match message {
                 KademliaMessage::FindNode { peers, .. } => {
-                    context.register_response(peer, peers);
+                    context.register_response(peer, peers.clone());
+
+                    let step = self.steps.entry(query).or_default();
+                    *step += 1;
+
+                    return Some(QueryAction::FindNodeProgress {
+                        query,
+                        target: context.config.target.clone().into_preimage(),
+                        peers,
+                        step: *step,
+                    });
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
                 }
-                _ => unreachable!(),
             },
             Some(QueryType::PutRecord { context, .. }) => match message {
                 KademliaMessage::FindNode { peers, .. } => {
                     context.register_response(peer, peers);
                 }
-                _ => unreachable!(),
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
+                }
             },
             Some(QueryType::PutRecordToPeers { context, .. }) => match message {
                 KademliaMessage::FindNode { peers, .. } => {
                     context.register_response(peer, peers);
                 }
-                _ => unreachable!(),
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
+                }
             },
             Some(QueryType::GetRecord { context }) => match message {
                 KademliaMessage::GetRecord { record, peers, .. } => {
+                    let num_records = context.found_records.len();
                     context.register_response(peer, record, peers);
+
+                    if context.found_records.len() > num_records {
+                        let step = self.steps.entry(query).or_default();
+                        *step += 1;
+
+                        return Some(QueryAction::GetRecordPartialResult {
+                            query_id: query,
+                            record: context.found_records.last().expect("record to exist").clone(),
+                            step: *step,
+                        });
+                    }
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
+                }
+            },
+            Some(QueryType::AddProvider { context, .. }) => match message {
+                KademliaMessage::FindNode { peers, .. } => {
+                    context.register_response(peer, peers);
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
+                }
+            },
+            Some(QueryType::GetProviders { context }) => match message {
+                KademliaMessage::GetProviders {
+                    providers, peers, ..
+                } => {
+                    context.register_response(peer, providers, peers);
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response for the query",
+                    );
+                    context.register_response_failure(peer);
                 }
-                _ => unreachable!(),
             },
         }
+
+        None
     }
 
     /// Get next action for `peer` from the [`QueryEngine`].
@@ -379,23 +670,30 @@ impl QueryEngine {
             Some(QueryType::PutRecord { context, .. }) => context.next_peer_action(peer),
             Some(QueryType::PutRecordToPeers { context, .. }) => context.next_peer_action(peer),
             Some(QueryType::GetRecord { context }) => context.next_peer_action(peer),
+            Some(QueryType::AddProvider { context, .. }) => context.next_peer_action(peer),
+            Some(QueryType::GetProviders { context }) => context.next_peer_action(peer),
         }
     }
 
     /// Handle query success by returning the queried value(s)
     /// and removing the query from [`QueryEngine`].
     fn on_query_succeeded(&mut self, query: QueryId) -> QueryAction {
+        self.deadlines.remove(&query);
+        self.steps.remove(&query);
+
         match self.queries.remove(&query).expect("query to exist") {
             QueryType::FindNode { context } => QueryAction::FindNodeQuerySucceeded {
                 query,
+                peers: context.responses(),
                 target: context.config.target.into_preimage(),
-                peers: context.responses.into_values().collect::<Vec<_>>(),
             },
             QueryType::PutRecord { record, context } => QueryAction::PutRecordToFoundNodes {
+                query,
                 record,
-                peers: context.responses.into_values().collect::<Vec<_>>(),
+                peers: context.responses(),
             },
             QueryType::PutRecordToPeers { record, context } => QueryAction::PutRecordToFoundNodes {
+                query,
                 record,
                 peers: context.peers_to_report,
             },
@@ -403,6 +701,16 @@ impl QueryEngine {
                 query_id: context.config.query,
                 records: context.found_records(),
             },
+            QueryType::AddProvider { provider, context } => QueryAction::AddProviderToFoundNodes {
+                query,
+                provider,
+                peers: context.responses(),
+            },
+            QueryType::GetProviders { context } => QueryAction::GetProvidersQueryDone {
+                query_id: context.config.query,
+                provided_key: context.config.target.clone().into_preimage(),
+                providers: context.found_providers(),
+            },
         }
     }
 
@@ -410,18 +718,60 @@ impl QueryEngine {
     /// returning the appropriate [`QueryAction`] to user.
     fn on_query_failed(&mut self, query: QueryId) -> QueryAction {
         let _ = self.queries.remove(&query).expect("query to exist");
+        self.deadlines.remove(&query);
+        self.steps.remove(&query);
 
         QueryAction::QueryFailed { query }
     }
 
+    /// Finish the query before it has completed, returning the results found so far.
+    ///
+    /// The query fails if no results were found.
+    fn finish_query(&mut self, query: QueryId) -> QueryAction {
+        let has_results = match self.queries.get(&query) {
+            Some(QueryType::FindNode { context }) => !context.responses().is_empty(),
+            Some(QueryType::GetRecord { context }) =>
+                context.config.known_records + context.found_records.len() > 0,
+            _ => false,
+        };
+
+        match has_results {
+            true => self.on_query_succeeded(query),
+            false => self.on_query_failed(query),
+        }
+    }
+
+    /// Stop `query`, finishing it with the results found so far.
+    ///
+    /// Returns `None` if the query doesn't exist.
+    pub fn stop_query(&mut self, query: QueryId) -> Option<QueryAction> {
+        tracing::debug!(target: LOG_TARGET, ?query, "stop query");
+
+        self.queries.contains_key(&query).then(|| self.finish_query(query))
+    }
+
     /// Get next action from the [`QueryEngine`].
     pub fn next_action(&mut self) -> Option<QueryAction> {
+        let now = Instant::now();
+
+        if let Some(query) = self
+            .deadlines
+            .iter()
+            .find_map(|(query, deadline)| (*deadline <= now).then_some(*query))
+        {
+            tracing::debug!(target: LOG_TARGET, ?query, "query timed out");
+
+            return Some(self.finish_query(query));
+        }
+
         for (_, state) in self.queries.iter_mut() {
             let action = match state {
                 QueryType::FindNode { context } => context.next_action(),
                 QueryType::PutRecord { context, .. } => context.next_action(),
                 QueryType::PutRecordToPeers { context, .. } => context.next_action(),
                 QueryType::GetRecord { context } => context.next_action(),
+                QueryType::AddProvider { context, .. } => context.next_action(),
+                QueryType::GetProviders { context } => context.next_action(),
             };
 
             match action {
@@ -466,7 +816,7 @@ mod tests {
             .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
             .try_init();
 
-        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
         let target_peer = PeerId::random();
         let _target_key = Key::from(target_peer);
 
@@ -480,6 +830,7 @@ mod tests {
                 KademliaPeer::new(PeerId::random(), vec![], ConnectionType::NotConnected),
             ]
             .into(),
+            QueryConfig::default(),
         );
 
         for _ in 0..4 {
@@ -495,9 +846,42 @@ mod tests {
         assert!(engine.next_action().is_none());
     }
 
+    #[test]
+    fn unexpected_response_counts_as_failure() {
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
+        let query = engine.start_find_node(
+            QueryId(1337),
+            PeerId::random(),
+            vec![KademliaPeer::new(
+                PeerId::random(),
+                vec![],
+                ConnectionType::NotConnected,
+            )]
+            .into(),
+            QueryConfig::default(),
+        );
+
+        let Some(QueryAction::SendMessage { peer, .. }) = engine.next_action() else {
+            panic!("invalid action");
+        };
+
+        // `GET_PROVIDERS` response to a `FIND_NODE` query is ignored and the peer is failed
+        let message = KademliaMessage::GetProviders {
+            key: None,
+            peers: vec![],
+            providers: vec![],
+        };
+        assert!(engine.register_response(query, peer, message).is_none());
+
+        match engine.next_action() {
+            Some(QueryAction::QueryFailed { query: failed }) => assert_eq!(failed, query),
+            action => panic!("invalid action: {action:?}"),
+        }
+    }
+
     #[test]
     fn lookup_paused() {
-        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
         let target_peer = PeerId::random();
         let _target_key = Key::from(target_peer);
 
@@ -511,6 +895,7 @@ mod tests {
                 KademliaPeer::new(PeerId::random(), vec![], ConnectionType::NotConnected),
             ]
             .into(),
+            QueryConfig::default(),
         );
 
         for _ in 0..3 {
@@ -526,7 +911,7 @@ mod tests {
             .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
             .try_init();
 
-        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
         let target_peer = make_peer_id(0, 0);
         let target_key = Key::from(target_peer);
 
@@ -554,6 +939,7 @@ mod tests {
                 ConnectionType::NotConnected,
             )]
             .into(),
+            QueryConfig::default(),
         );
 
         let action = engine.next_action();
@@ -624,7 +1010,7 @@ mod tests {
             .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
             .try_init();
 
-        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
         let record_key = RecordKey::new(&vec![1, 2, 3, 4]);
         let target_key = Key::new(record_key.clone());
         let original_record = Record::new(record_key.clone(), vec![1, 3, 3, 7, 1, 3, 3, 8]);
@@ -708,7 +1094,7 @@ mod tests {
         }
 
         let peers = match engine.next_action() {
-            Some(QueryAction::PutRecordToFoundNodes { peers, record }) => {
+            Some(QueryAction::PutRecordToFoundNodes { peers, record, .. }) => {
                 assert_eq!(peers.len(), 4);
                 assert_eq!(record.key, original_record.key);
                 assert_eq!(record.value, original_record.value);
@@ -732,6 +1118,7 @@ mod tests {
             .into(),
             Quorum::All,
             3,
+            QueryConfig::default(),
         );
 
         for _ in 0..4 {
@@ -772,4 +1159,252 @@ mod tests {
             _ => panic!("invalid event received"),
         }
     }
+
+    #[test]
+    fn find_node_query_times_out() {
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
+        let peers = (0..2).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+        let query = engine.start_find_node(
+            QueryId(1342),
+            PeerId::random(),
+            peers
+                .iter()
+                .map(|peer| KademliaPeer::new(*peer, vec![], ConnectionType::NotConnected))
+                .collect(),
+            QueryConfig {
+                parallelism: Some(1),
+                timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        );
+        assert!(engine.next_deadline().is_some());
+
+        // only one request is in flight at a time
+        let Some(QueryAction::SendMessage { peer, .. }) = engine.next_action() else {
+            panic!("invalid action");
+        };
+        assert!(engine.next_action().is_none());
+
+        engine.register_response(
+            query,
+            peer,
+            KademliaMessage::FindNode {
+                target: Vec::new(),
+                peers: vec![],
+            },
+        );
+
+        let Some(QueryAction::SendMessage { .. }) = engine.next_action() else {
+            panic!("invalid action");
+        };
+        assert!(engine.next_action().is_none());
+
+        // the second peer doesn't respond before the timeout and the query finishes with
+        // the response of the first peer
+        std::thread::sleep(Duration::from_millis(150));
+
+        match engine.next_action() {
+            Some(QueryAction::FindNodeQuerySucceeded {
+                query: succeeded,
+                peers: found,
+                ..
+            }) => {
+                assert_eq!(succeeded, query);
+                assert_eq!(found.len(), 1);
+                assert_eq!(found[0].peer, peer);
+            }
+            action => panic!("invalid action: {action:?}"),
+        }
+
+        assert!(engine.next_deadline().is_none());
+        assert!(engine.next_action().is_none());
+    }
+
+    #[test]
+    fn find_node_with_zero_results_finishes() {
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
+        let peer = PeerId::random();
+
+        let query = engine.start_find_node(
+            QueryId(1343),
+            PeerId::random(),
+            vec![KademliaPeer::new(
+                peer,
+                vec![],
+                ConnectionType::NotConnected,
+            )]
+            .into(),
+            QueryConfig {
+                num_results: Some(0),
+                ..Default::default()
+            },
+        );
+
+        let Some(QueryAction::SendMessage {
+            peer: contacted, ..
+        }) = engine.next_action()
+        else {
+            panic!("invalid action");
+        };
+        assert_eq!(contacted, peer);
+
+        engine.register_response(
+            query,
+            peer,
+            KademliaMessage::FindNode {
+                target: Vec::new(),
+                peers: vec![],
+            },
+        );
+
+        // the query looks for at least one result
+        match engine.next_action() {
+            Some(QueryAction::FindNodeQuerySucceeded {
+                query: succeeded,
+                peers: found,
+                ..
+            }) => {
+                assert_eq!(succeeded, query);
+                assert_eq!(found.len(), 1);
+                assert_eq!(found[0].peer, peer);
+            }
+            action => panic!("invalid action: {action:?}"),
+        }
+    }
+
+    #[test]
+    fn find_node_reports_progress_and_can_be_stopped() {
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
+        let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let target = PeerId::random();
+
+        let query = engine.start_find_node(
+            QueryId(1344),
+            target,
+            peers
+                .iter()
+                .map(|peer| KademliaPeer::new(*peer, vec![], ConnectionType::NotConnected))
+                .collect(),
+            QueryConfig::default(),
+        );
+
+        let mut contacted = Vec::new();
+        while let Some(QueryAction::SendMessage { peer, .. }) = engine.next_action() {
+            contacted.push(peer);
+        }
+        assert_eq!(contacted.len(), 3);
+
+        for (i, peer) in contacted.iter().take(2).enumerate() {
+            let discovered = PeerId::random();
+
+            match engine.register_response(
+                query,
+                *peer,
+                KademliaMessage::FindNode {
+                    target: Vec::new(),
+                    peers: vec![KademliaPeer::new(
+                        discovered,
+                        vec![],
+                        ConnectionType::NotConnected,
+                    )],
+                },
+            ) {
+                Some(QueryAction::FindNodeProgress {
+                    query: progress,
+                    target: progress_target,
+                    peers: found,
+                    step,
+                }) => {
+                    assert_eq!(progress, query);
+                    assert_eq!(progress_target, target);
+                    assert_eq!(found.len(), 1);
+                    assert_eq!(found[0].peer, discovered);
+                    assert_eq!(step, i + 1);
+                }
+                action => panic!("invalid action: {action:?}"),
+            }
+        }
+
+        // stopping the query finishes it with the peers that have responded so far
+        match engine.stop_query(query) {
+            Some(QueryAction::FindNodeQuerySucceeded {
+                query: stopped,
+                peers: found,
+                ..
+            }) => {
+                assert_eq!(stopped, query);
+                assert_eq!(found.len(), 2);
+            }
+            action => panic!("invalid action: {action:?}"),
+        }
+
+        assert!(engine.stop_query(query).is_none());
+    }
+
+    #[test]
+    fn stopping_query_without_results_fails_it() {
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize, 1usize, None);
+
+        let query = engine.start_get_record(
+            QueryId(1345),
+            RecordKey::new(&vec![1, 2, 3]),
+            vec![KademliaPeer::new(
+                PeerId::random(),
+                vec![],
+                ConnectionType::NotConnected,
+            )]
+            .into(),
+            Quorum::One,
+            0,
+            QueryConfig::default(),
+        );
+
+        let Some(QueryAction::SendMessage { .. }) = engine.next_action() else {
+            panic!("invalid action");
+        };
+
+        assert_eq!(
+            engine.stop_query(query),
+            Some(QueryAction::QueryFailed { query })
+        );
+        assert!(engine.next_action().is_none());
+    }
+
+    #[test]
+    fn query_without_results_fails_on_timeout() {
+        let mut engine = QueryEngine::new(
+            PeerId::random(),
+            20usize,
+            3usize,
+            1usize,
+            Some(Duration::from_millis(50)),
+        );
+
+        let query = engine.start_get_record(
+            QueryId(1343),
+            RecordKey::new(&vec![1, 2, 3]),
+            vec![KademliaPeer::new(
+                PeerId::random(),
+                vec![],
+                ConnectionType::NotConnected,
+            )]
+            .into(),
+            Quorum::One,
+            0,
+            QueryConfig::default(),
+        );
+
+        let Some(QueryAction::SendMessage { .. }) = engine.next_action() else {
+            panic!("invalid action");
+        };
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(
+            engine.next_action(),
+            Some(QueryAction::QueryFailed { query })
+        );
+        assert!(engine.next_action().is_none());
+    }
 }
@@ -22,6 +22,7 @@
 use crate::PeerId;
 
 use bytes::Bytes;
+use multiaddr::Multiaddr;
 use multihash::Multihash;
 
 use std::{borrow::Borrow, time::Instant};
@@ -118,3 +119,36 @@ pub struct PeerRecord {
     /// The provided record.
     pub record: Record,
 }
+
+/// A provider record announcing that `provider` can serve the content identified by `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderRecord {
+    /// Key of the provided content.
+    pub key: Key,
+
+    /// Peer providing the content.
+    pub provider: PeerId,
+
+    /// Known addresses of the provider.
+    pub addresses: Vec<Multiaddr>,
+
+    /// The expiration time as measured by a local, monotonic clock.
+    pub expires: Instant,
+}
+
+impl ProviderRecord {
+    /// Checks whether the provider record is expired w.r.t. the given `Instant`.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires
+    }
+}
+
+/// Peer providing content, as returned by a `GET_PROVIDERS` query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentProvider {
+    /// Peer ID of the provider.
+    pub peer: PeerId,
+
+    /// Known addresses of the provider.
+    pub addresses: Vec<Multiaddr>,
+}
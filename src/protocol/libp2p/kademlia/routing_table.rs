@@ -32,9 +32,17 @@ use crate::{
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
 
+use std::time::Instant;
+
 /// Number of k-buckets.
 const NUM_BUCKETS: usize = 256;
 
+/// Number of random peer IDs sampled when looking for routing table refresh targets.
+///
+/// Half of the sampled keys fall into the furthest k-bucket, a quarter into the next one and so on,
+/// so this covers roughly the eight furthest k-buckets.
+const REFRESH_SAMPLES: usize = 256;
+
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::ipfs::kademlia::routing_table";
 
@@ -98,11 +106,15 @@ impl BucketIndex {
 }
 
 impl RoutingTable {
-    /// Create new [`RoutingTable`].
-    pub fn new(local_key: Key<PeerId>) -> Self {
+    /// Create new [`RoutingTable`] where each k-bucket holds at most `bucket_size` nodes.
+    ///
+    /// Zero is treated as one.
+    pub fn new(local_key: Key<PeerId>, bucket_size: usize) -> Self {
+        let bucket_size = bucket_size.max(1);
+
         RoutingTable {
             local_key,
-            buckets: (0..NUM_BUCKETS).map(|_| KBucket::new()).collect(),
+            buckets: (0..NUM_BUCKETS).map(|_| KBucket::new(bucket_size)).collect(),
         }
     }
 
@@ -123,13 +135,17 @@ impl RoutingTable {
     /// Add known peer to [`RoutingTable`].
     ///
     /// In order to bootstrap the lookup process, the routing table must be aware of at least one
-    /// node and of its addresses. The insert operation is ignored
+    /// node and of its addresses. The insert operation is ignored if no addresses are given.
+    ///
+    /// If the k-bucket of `peer` is full, `peer` is stored as the pending entry of the k-bucket
+    /// and the least-recently-seen node of the k-bucket is returned. The caller must probe that
+    /// node and report the outcome with [`RoutingTable::on_probe_result()`].
     pub fn add_known_peer(
         &mut self,
         peer: PeerId,
         addresses: Vec<Multiaddr>,
         connection: ConnectionType,
-    ) {
+    ) -> Option<PeerId> {
         tracing::trace!(
             target: LOG_TARGET,
             ?peer,
@@ -155,25 +171,104 @@ impl RoutingTable {
             (KBucketEntry::Occupied(entry), false) => {
                 entry.addresses = addresses;
             }
-            (mut entry @ KBucketEntry::Vacant(_), false) => {
+            (KBucketEntry::Vacant(entry), false) => {
                 entry.insert(KademliaPeer::new(peer, addresses, connection));
             }
+            (KBucketEntry::Full(entry), false) => {
+                tracing::trace!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    "k-bucket full, store peer as pending entry",
+                );
+
+                return entry.insert_pending(
+                    KademliaPeer::new(peer, addresses, connection),
+                    Instant::now(),
+                );
+            }
             (KBucketEntry::LocalNode, _) => tracing::warn!(
                 target: LOG_TARGET,
                 ?peer,
                 "tried to add local node to routing table",
             ),
-            (KBucketEntry::NoSlot, _) => tracing::trace!(
-                target: LOG_TARGET,
-                ?peer,
-                "routing table full, cannot add new entry",
-            ),
             (_, true) => tracing::debug!(
                 target: LOG_TARGET,
                 ?peer,
                 "tried to add zero addresses to the routing table",
             ),
         }
+
+        None
+    }
+
+    /// Mark `peer` as the most-recently-seen node of its k-bucket.
+    pub fn on_peer_seen(&mut self, peer: PeerId) {
+        let key = Key::from(peer);
+
+        if let Some(index) = BucketIndex::new(&self.local_key.distance(&key)) {
+            self.buckets[index.get()].on_node_seen(&key);
+        }
+    }
+
+    /// Report the result of probing `peer`.
+    ///
+    /// If `peer` didn't respond, it's evicted from its k-bucket and replaced by the pending entry.
+    pub fn on_probe_result(&mut self, peer: PeerId, alive: bool) {
+        let key = Key::from(peer);
+
+        if let Some(index) = BucketIndex::new(&self.local_key.distance(&key)) {
+            self.buckets[index.get()].on_probe_result(&key, alive);
+        }
+    }
+
+    /// Remove `peer` from the routing table.
+    ///
+    /// The pending entry of the k-bucket, if any, takes the place of `peer`. If `peer` is the
+    /// pending entry, only the pending entry is removed.
+    pub fn remove(&mut self, peer: PeerId) -> Option<KademliaPeer> {
+        let key = Key::from(peer);
+        let index = BucketIndex::new(&self.local_key.distance(&key))?;
+
+        self.buckets[index.get()].remove(&key)
+    }
+
+    /// Get iterator over the non-empty k-buckets and their indices.
+    pub fn buckets(&self) -> impl Iterator<Item = (usize, &[KademliaPeer])> {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, bucket)| !bucket.nodes().is_empty())
+            .map(|(index, bucket)| (index, bucket.nodes()))
+    }
+
+    /// Get targets for the random lookups which refresh the routing table.
+    ///
+    /// Returns at most one random peer ID for each k-bucket from the closest non-empty k-bucket to
+    /// the furthest one. The distance of a lookup target cannot be chosen since keys are hashes of
+    /// the peer IDs, so the targets are found by sampling random peer IDs and the k-buckets closer
+    /// to the local node, which are covered by the lookup of the local node, may not get a target.
+    pub fn refresh_targets(&self) -> Vec<PeerId> {
+        let Some(closest) = self
+            .buckets
+            .iter()
+            .position(|bucket| bucket.closest_iter(&self.local_key).next().is_some())
+        else {
+            return Vec::new();
+        };
+
+        let mut targets: Vec<Option<PeerId>> = vec![None; NUM_BUCKETS - closest];
+
+        for _ in 0..REFRESH_SAMPLES {
+            let peer = PeerId::random();
+
+            if let Some(index) = BucketIndex::new(&self.local_key.distance(&Key::from(peer))) {
+                if index.get() >= closest && targets[index.get() - closest].is_none() {
+                    targets[index.get() - closest] = Some(peer);
+                }
+            }
+        }
+
+        targets.into_iter().flatten().collect()
     }
 
     /// Get `limit` closest peers to `target` from the k-buckets.
@@ -280,13 +375,15 @@ mod tests {
     fn closest_peers() {
         let own_peer_id = PeerId::random();
         let own_key = Key::from(own_peer_id);
-        let mut table = RoutingTable::new(own_key.clone());
+        let mut table = RoutingTable::new(own_key.clone(), 20);
 
         for _ in 0..60 {
             let peer = PeerId::random();
             let key = Key::from(peer);
-            let mut entry = table.entry(key.clone());
-            entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected));
+
+            if let KBucketEntry::Vacant(entry) = table.entry(key.clone()) {
+                entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected));
+            }
         }
 
         let target = Key::from(PeerId::random());
@@ -317,51 +414,59 @@ mod tests {
         (Key::from_bytes(key_bytes, peer), peer)
     }
 
+    // generate random peer whose `Key` falls in to specified k-bucket.
+    fn random_peer_in_bucket(own_key: &Key<PeerId>, bucket_index: usize) -> PeerId {
+        loop {
+            let peer = PeerId::random();
+
+            if BucketIndex::new(&own_key.distance(&Key::from(peer)))
+                == Some(BucketIndex(bucket_index))
+            {
+                return peer;
+            }
+        }
+    }
+
     #[test]
     fn add_peer_to_empty_table() {
         let own_peer_id = PeerId::random();
         let own_key = Key::from(own_peer_id);
-        let mut table = RoutingTable::new(own_key.clone());
+        let mut table = RoutingTable::new(own_key.clone(), 20);
 
         // verify that local peer id resolves to special entry
-        assert_eq!(table.entry(own_key), KBucketEntry::LocalNode);
+        assert!(std::matches!(table.entry(own_key), KBucketEntry::LocalNode));
 
         let peer = PeerId::random();
         let key = Key::from(peer);
-        let mut test = table.entry(key.clone());
         let addresses = vec![];
 
-        assert!(std::matches!(test, KBucketEntry::Vacant(_)));
-        test.insert(KademliaPeer::new(
-            peer,
-            addresses.clone(),
-            ConnectionType::Connected,
-        ));
-
-        assert_eq!(
-            table.entry(key.clone()),
-            KBucketEntry::Occupied(&mut KademliaPeer::new(
+        match table.entry(key.clone()) {
+            KBucketEntry::Vacant(entry) => entry.insert(KademliaPeer::new(
                 peer,
                 addresses.clone(),
                 ConnectionType::Connected,
-            ))
-        );
+            )),
+            state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+        }
 
         match table.entry(key.clone()) {
             KBucketEntry::Occupied(entry) => {
+                assert_eq!(
+                    *entry,
+                    KademliaPeer::new(peer, addresses.clone(), ConnectionType::Connected)
+                );
                 entry.connection = ConnectionType::NotConnected;
             }
             state => panic!("invalid state for `KBucketEntry`: {state:?}"),
         }
 
-        assert_eq!(
-            table.entry(key.clone()),
-            KBucketEntry::Occupied(&mut KademliaPeer::new(
-                peer,
-                addresses,
-                ConnectionType::NotConnected,
-            ))
-        );
+        match table.entry(key.clone()) {
+            KBucketEntry::Occupied(entry) => assert_eq!(
+                *entry,
+                KademliaPeer::new(peer, addresses, ConnectionType::NotConnected)
+            ),
+            state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+        }
     }
 
     #[test]
@@ -369,87 +474,127 @@ mod tests {
         let mut rng = rand::thread_rng();
         let own_peer_id = PeerId::random();
         let own_key = Key::from(own_peer_id);
-        let mut table = RoutingTable::new(own_key.clone());
+        let mut table = RoutingTable::new(own_key.clone(), 20);
 
         // add 20 nodes to the same k-bucket
         for _ in 0..20 {
             let (key, peer) = random_peer(&mut rng, own_key.clone(), 254);
-            let mut entry = table.entry(key.clone());
 
-            assert!(std::matches!(entry, KBucketEntry::Vacant(_)));
-            entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected));
+            match table.entry(key.clone()) {
+                KBucketEntry::Vacant(entry) =>
+                    entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected)),
+                state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+            }
         }
 
-        // try to add another peer and verify the peer is rejected
-        // because the k-bucket is full of connected nodes
-        let peer = PeerId::random();
-        let distance = BucketIndex(254).rand_distance(&mut rng);
-        let key_bytes = own_key.for_distance(distance);
-        let key = Key::from_bytes(key_bytes, peer);
-
-        let entry = table.entry(key.clone());
-        assert!(std::matches!(entry, KBucketEntry::NoSlot));
+        // try to add another peer and verify the k-bucket is full
+        let (key, _) = random_peer(&mut rng, own_key.clone(), 254);
+        assert!(std::matches!(table.entry(key), KBucketEntry::Full(_)));
     }
 
     #[test]
-    #[ignore]
-    fn peer_disconnects_and_is_evicted() {
+    fn custom_bucket_size() {
         let mut rng = rand::thread_rng();
         let own_peer_id = PeerId::random();
         let own_key = Key::from(own_peer_id);
-        let mut table = RoutingTable::new(own_key.clone());
+        let mut table = RoutingTable::new(own_key.clone(), 5);
 
-        // add 20 nodes to the same k-bucket
+        for _ in 0..5 {
+            let (key, peer) = random_peer(&mut rng, own_key.clone(), 254);
+
+            match table.entry(key.clone()) {
+                KBucketEntry::Vacant(entry) =>
+                    entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected)),
+                state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+            }
+        }
+
+        let (key, _) = random_peer(&mut rng, own_key.clone(), 254);
+        assert!(std::matches!(table.entry(key), KBucketEntry::Full(_)));
+    }
+
+    #[test]
+    fn unresponsive_peer_is_replaced_by_pending_peer() {
+        let own_peer_id = PeerId::random();
+        let own_key = Key::from(own_peer_id);
+        let mut table = RoutingTable::new(own_key.clone(), 20);
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+
+        // fill the k-bucket
         let peers = (0..20)
             .map(|_| {
-                let (key, peer) = random_peer(&mut rng, own_key.clone(), 253);
-                let mut entry = table.entry(key.clone());
-
-                assert!(std::matches!(entry, KBucketEntry::Vacant(_)));
-                entry.insert(KademliaPeer::new(peer, vec![], ConnectionType::Connected));
+                let peer = random_peer_in_bucket(&own_key, 255);
+                assert!(table
+                    .add_known_peer(peer, vec![address.clone()], ConnectionType::NotConnected)
+                    .is_none());
 
-                (peer, key)
+                peer
             })
             .collect::<Vec<_>>();
 
-        // try to add another peer and verify the peer is rejected
-        // because the k-bucket is full of connected nodes
-        let peer = PeerId::random();
-        let distance = BucketIndex(253).rand_distance(&mut rng);
-        let key_bytes = own_key.for_distance(distance);
-        let key = Key::from_bytes(key_bytes, peer);
-
-        let entry = table.entry(key.clone());
-        assert!(std::matches!(entry, KBucketEntry::NoSlot));
+        // the k-bucket is full so the new peer becomes the pending entry
+        // and the least-recently-seen peer must be probed
+        let peer = random_peer_in_bucket(&own_key, 255);
+        assert_eq!(
+            table.add_known_peer(peer, vec![address.clone()], ConnectionType::NotConnected),
+            Some(peers[0]),
+        );
+        assert!(std::matches!(
+            table.entry(Key::from(peer)),
+            KBucketEntry::Full(_)
+        ));
 
-        // disconnect random peer
-        match table.entry(peers[3].1.clone()) {
-            KBucketEntry::Occupied(entry) => {
-                entry.connection = ConnectionType::NotConnected;
-            }
-            _ => panic!("invalid state for node"),
-        }
+        // the probe fails and the pending peer takes the place of the probed peer
+        table.on_probe_result(peers[0], false);
 
-        // try to add the previously rejected peer again and verify it's added
-        let mut entry = table.entry(key.clone());
-        assert!(std::matches!(entry, KBucketEntry::Vacant(_)));
-        entry.insert(KademliaPeer::new(
-            peer,
-            vec!["/ip6/::1/tcp/8888".parse().unwrap()],
-            ConnectionType::CanConnect,
+        assert!(std::matches!(
+            table.entry(Key::from(peers[0])),
+            KBucketEntry::Full(_)
+        ));
+        assert!(std::matches!(
+            table.entry(Key::from(peer)),
+            KBucketEntry::Occupied(_)
         ));
+    }
+
+    #[test]
+    fn responsive_peer_is_not_replaced() {
+        let own_peer_id = PeerId::random();
+        let own_key = Key::from(own_peer_id);
+        let mut table = RoutingTable::new(own_key.clone(), 20);
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+
+        let peers = (0..20)
+            .map(|_| {
+                let peer = random_peer_in_bucket(&own_key, 255);
+                assert!(table
+                    .add_known_peer(peer, vec![address.clone()], ConnectionType::NotConnected)
+                    .is_none());
+
+                peer
+            })
+            .collect::<Vec<_>>();
+
+        // the first peer was seen recently so the second peer is the one that must be probed
+        table.on_peer_seen(peers[0]);
 
-        // verify the node is still there
-        let entry = table.entry(key.clone());
-        let addresses = vec!["/ip6/::1/tcp/8888".parse().unwrap()];
+        let peer = random_peer_in_bucket(&own_key, 255);
         assert_eq!(
-            entry,
-            KBucketEntry::Occupied(&mut KademliaPeer::new(
-                peer,
-                addresses,
-                ConnectionType::CanConnect,
-            ))
+            table.add_known_peer(peer, vec![address.clone()], ConnectionType::NotConnected),
+            Some(peers[1]),
         );
+
+        // the probe succeeds and the pending peer is discarded
+        table.on_probe_result(peers[1], true);
+
+        assert!(std::matches!(
+            table.entry(Key::from(peers[1])),
+            KBucketEntry::Occupied(_)
+        ));
+        assert!(std::matches!(
+            table.entry(Key::from(peer)),
+            KBucketEntry::Full(_)
+        ));
     }
 
     #[test]
@@ -457,39 +602,34 @@ mod tests {
         let mut rng = rand::thread_rng();
         let own_peer_id = PeerId::random();
         let own_key = Key::from(own_peer_id);
-        let mut table = RoutingTable::new(own_key.clone());
+        let mut table = RoutingTable::new(own_key.clone(), 20);
 
         // add 19 disconnected nodes to the same k-bucket
-        let _peers = (0..19)
-            .map(|_| {
-                let (key, peer) = random_peer(&mut rng, own_key.clone(), 252);
-                let mut entry = table.entry(key.clone());
+        for _ in 0..19 {
+            let (key, peer) = random_peer(&mut rng, own_key.clone(), 252);
 
-                assert!(std::matches!(entry, KBucketEntry::Vacant(_)));
-                entry.insert(KademliaPeer::new(
+            match table.entry(key.clone()) {
+                KBucketEntry::Vacant(entry) => entry.insert(KademliaPeer::new(
                     peer,
                     vec![],
                     ConnectionType::NotConnected,
-                ));
-
-                (peer, key)
-            })
-            .collect::<Vec<_>>();
+                )),
+                state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+            }
+        }
 
         // try to add another peer and verify it's accepted as there is
         // still room in the k-bucket for the node
-        let peer = PeerId::random();
-        let distance = BucketIndex(252).rand_distance(&mut rng);
-        let key_bytes = own_key.for_distance(distance);
-        let key = Key::from_bytes(key_bytes, peer);
+        let (key, peer) = random_peer(&mut rng, own_key.clone(), 252);
 
-        let mut entry = table.entry(key.clone());
-        assert!(std::matches!(entry, KBucketEntry::Vacant(_)));
-        entry.insert(KademliaPeer::new(
-            peer,
-            vec!["/ip6/::1/tcp/8888".parse().unwrap()],
-            ConnectionType::CanConnect,
-        ));
+        match table.entry(key.clone()) {
+            KBucketEntry::Vacant(entry) => entry.insert(KademliaPeer::new(
+                peer,
+                vec!["/ip6/::1/tcp/8888".parse().unwrap()],
+                ConnectionType::CanConnect,
+            )),
+            state => panic!("invalid state for `KBucketEntry`: {state:?}"),
+        }
     }
 
     #[test]
@@ -525,4 +665,100 @@ mod tests {
         }
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn refresh_targets() {
+        let own_peer_id = PeerId::random();
+        let own_key = Key::from(own_peer_id);
+        let mut table = RoutingTable::new(own_key.clone(), 20);
+
+        // no targets for an empty routing table
+        assert!(table.refresh_targets().is_empty());
+
+        let peer = PeerId::random();
+        table.add_known_peer(
+            peer,
+            vec!["/ip6/::1/tcp/8888".parse().unwrap()],
+            ConnectionType::Connected,
+        );
+        let closest = BucketIndex::new(&own_key.distance(&Key::from(peer))).unwrap();
+
+        let targets = table.refresh_targets();
+        assert!(!targets.is_empty());
+
+        // each target falls into a distinct k-bucket that isn't closer than the closest peer
+        let mut indices = targets
+            .iter()
+            .map(|target| BucketIndex::new(&own_key.distance(&Key::from(*target))).unwrap().get())
+            .collect::<Vec<_>>();
+        assert!(indices.iter().all(|index| *index >= closest.get()));
+
+        let num_targets = indices.len();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), num_targets);
+    }
+
+    #[test]
+    fn zero_bucket_size_holds_one_node() {
+        let own_key = Key::from(PeerId::random());
+        let mut table = RoutingTable::new(own_key.clone(), 0);
+
+        let first = random_peer_in_bucket(&own_key, 255);
+        let second = random_peer_in_bucket(&own_key, 255);
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+
+        assert!(table
+            .add_known_peer(first, vec![address.clone()], ConnectionType::Connected)
+            .is_none());
+        assert_eq!(
+            table.add_known_peer(second, vec![address], ConnectionType::NotConnected),
+            Some(first)
+        );
+        assert_eq!(table.buckets().next().unwrap().1.len(), 1);
+    }
+
+    #[test]
+    fn remove_peer() {
+        let own_key = Key::from(PeerId::random());
+        let mut table = RoutingTable::new(own_key.clone(), 1);
+
+        // fill the k-bucket and add a pending entry for it
+        let first = random_peer_in_bucket(&own_key, 255);
+        let second = random_peer_in_bucket(&own_key, 255);
+        let address: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
+
+        assert!(table
+            .add_known_peer(first, vec![address.clone()], ConnectionType::Connected)
+            .is_none());
+        assert_eq!(
+            table.add_known_peer(second, vec![address.clone()], ConnectionType::NotConnected),
+            Some(first)
+        );
+
+        // removing the pending entry leaves the k-bucket intact
+        assert_eq!(table.remove(second).map(|peer| peer.peer), Some(second));
+        assert_eq!(
+            table.add_known_peer(second, vec![address], ConnectionType::NotConnected),
+            Some(first)
+        );
+
+        let buckets = table.buckets().collect::<Vec<_>>();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].0, 255);
+        assert_eq!(buckets[0].1.len(), 1);
+        assert_eq!(buckets[0].1[0].peer, first);
+        assert_eq!(buckets[0].1[0].connection, ConnectionType::Connected);
+
+        // the pending entry takes the place of the removed peer
+        assert_eq!(table.remove(first).map(|peer| peer.peer), Some(first));
+        assert!(table.remove(first).is_none());
+
+        let buckets = table.buckets().collect::<Vec<_>>();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].1[0].peer, second);
+
+        assert_eq!(table.remove(second).map(|peer| peer.peer), Some(second));
+        assert!(table.buckets().next().is_none());
+    }
 }
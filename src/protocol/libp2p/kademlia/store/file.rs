@@ -0,0 +1,510 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! File-backed record store.
+
+use crate::{
+    protocol::libp2p::kademlia::{
+        record::{Key, ProviderRecord, Record},
+        schema,
+        store::{MemoryStore, MemoryStoreConfig, RecordStore},
+    },
+    PeerId,
+};
+
+use multiaddr::Multiaddr;
+use parking_lot::Mutex;
+use prost::Message;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::ipfs::kademlia::store::file";
+
+/// Record store persisting its contents to a file.
+///
+/// The records are kept in a [`MemoryStore`], allowing the records and provider records to survive
+/// restarts of the node. Modifications only mark the store dirty and the whole store is written to
+/// the file when it's flushed, which Kademlia does periodically, and when Kademlia exits.
+#[derive(Debug)]
+pub struct FileStore {
+    /// Path to the file.
+    path: PathBuf,
+
+    /// In-memory copy of the store.
+    store: MemoryStore,
+
+    /// Whether the store has been modified since it was last flushed.
+    dirty: bool,
+
+    /// Generation of the latest snapshot of the store.
+    generation: u64,
+
+    /// Generation of the snapshot last written to the file.
+    ///
+    /// Snapshots are written on blocking threads, so the lock ensures that an older snapshot
+    /// never replaces a newer one.
+    persisted: Arc<Mutex<u64>>,
+}
+
+impl FileStore {
+    /// Open the store at `path` with the default [`MemoryStoreConfig`].
+    ///
+    /// The records previously persisted to `path` are loaded, skipping the expired ones. If `path`
+    /// doesn't exist, the store is empty and the file is created once the store is modified.
+    pub fn open(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        Self::open_with_config(path, MemoryStoreConfig::default())
+    }
+
+    /// Open the store at `path` with the provided configuration.
+    pub fn open_with_config(
+        path: impl Into<PathBuf>,
+        config: MemoryStoreConfig,
+    ) -> crate::Result<Self> {
+        let path = path.into();
+        let mut store = MemoryStore::with_config(config);
+
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let persisted = schema::kademlia_store::Store::decode(bytes.as_slice())?;
+
+                for record in persisted.records.into_iter().filter_map(decode_record) {
+                    store.put(record);
+                }
+
+                for record in persisted
+                    .providers
+                    .into_iter()
+                    .filter_map(|record| decode_provider(record, false))
+                {
+                    store.put_provider(record);
+                }
+
+                // local provider records are kept until removed, even if they have expired
+                for record in persisted
+                    .local_providers
+                    .into_iter()
+                    .filter_map(|record| decode_provider(record, true))
+                {
+                    store.put_local_provider(record);
+                }
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(Self {
+            path,
+            store,
+            dirty: false,
+            generation: 0,
+            persisted: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    /// Take a snapshot of the store, returning its generation and the encoded store.
+    fn snapshot(&mut self) -> (u64, Vec<u8>) {
+        let persisted = schema::kademlia_store::Store {
+            records: self.store.iter().map(encode_record).collect(),
+            providers: self.store.providers.values().flatten().map(encode_provider).collect(),
+            local_providers: self.store.local_providers.values().map(encode_provider).collect(),
+        };
+        self.generation += 1;
+        self.dirty = false;
+
+        (self.generation, persisted.encode_to_vec())
+    }
+}
+
+// Kademlia closes the store when its event loop exits, so this only catches the stores dropped
+// without being closed, e.g., when the task running Kademlia is aborted.
+impl Drop for FileStore {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Write snapshot of the store to `path`, unless a newer snapshot has already been written.
+///
+/// The store is first written to a temporary file which then replaces the old file, so that
+/// a failed write doesn't corrupt the persisted store.
+fn write_snapshot(path: &Path, persisted: &Mutex<u64>, generation: u64, bytes: Vec<u8>) {
+    let mut persisted = persisted.lock();
+
+    if *persisted >= generation {
+        return;
+    }
+
+    let temporary_path = path.with_extension("tmp");
+
+    match std::fs::write(&temporary_path, bytes)
+        .and_then(|_| std::fs::rename(&temporary_path, path))
+    {
+        Ok(()) => *persisted = generation,
+        Err(error) => tracing::warn!(
+            target: LOG_TARGET,
+            ?path,
+            ?error,
+            "failed to persist record store",
+        ),
+    }
+}
+
+impl RecordStore for FileStore {
+    fn get(&mut self, key: &Key) -> Option<&Record> {
+        self.store.get(key)
+    }
+
+    fn put(&mut self, record: Record) -> bool {
+        let stored = self.store.put(record);
+        self.dirty |= stored;
+        stored
+    }
+
+    fn remove(&mut self, key: &Key) {
+        self.store.remove(key);
+        self.dirty = true;
+    }
+
+    fn iter(&mut self) -> Box<dyn Iterator<Item = &Record> + '_> {
+        self.store.iter()
+    }
+
+    fn get_providers(&mut self, key: &Key) -> Vec<ProviderRecord> {
+        self.store.get_providers(key)
+    }
+
+    fn put_provider(&mut self, record: ProviderRecord) -> bool {
+        let stored = self.store.put_provider(record);
+        self.dirty |= stored;
+
+        stored
+    }
+
+    fn remove_provider(&mut self, key: &Key, provider: &PeerId) {
+        self.store.remove_provider(key, provider);
+        self.dirty = true;
+    }
+
+    fn put_local_provider(&mut self, record: ProviderRecord) {
+        self.store.put_local_provider(record);
+        self.dirty = true;
+    }
+
+    fn remove_local_provider(&mut self, key: &Key) -> Option<ProviderRecord> {
+        let record = self.store.remove_local_provider(key);
+        self.dirty |= record.is_some();
+
+        record
+    }
+
+    fn local_providers(&mut self) -> Box<dyn Iterator<Item = &ProviderRecord> + '_> {
+        self.store.local_providers()
+    }
+
+    /// Write the store to the file if it has been modified since the last flush.
+    ///
+    /// When called from within a Tokio runtime, the file is written on a blocking thread.
+    fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        let (generation, bytes) = self.snapshot();
+        let path = self.path.clone();
+        let persisted = Arc::clone(&self.persisted);
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || write_snapshot(&path, &persisted, generation, bytes));
+            }
+            Err(_) => write_snapshot(&path, &persisted, generation, bytes),
+        }
+    }
+
+    fn close(&mut self) {
+        if self.dirty {
+            let (generation, bytes) = self.snapshot();
+            write_snapshot(&self.path, &self.persisted, generation, bytes);
+        }
+    }
+}
+
+/// Convert `expires` to seconds since the UNIX epoch.
+fn to_unix_time(expires: Instant) -> u64 {
+    let expires = SystemTime::now() + expires.saturating_duration_since(Instant::now());
+
+    expires.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+/// Convert seconds since the UNIX epoch to [`Instant`], returning `None` if the time has passed.
+fn from_unix_time(expires: u64) -> Option<Instant> {
+    let expires = UNIX_EPOCH + Duration::from_secs(expires);
+
+    expires
+        .duration_since(SystemTime::now())
+        .ok()
+        .map(|remaining| Instant::now() + remaining)
+}
+
+fn encode_record(record: &Record) -> schema::kademlia_store::Record {
+    schema::kademlia_store::Record {
+        key: record.key.to_vec(),
+        value: record.value.clone(),
+        publisher: record.publisher.map_or(Vec::new(), |publisher| publisher.to_bytes()),
+        expires: record.expires.map_or(0, to_unix_time),
+    }
+}
+
+fn decode_record(record: schema::kademlia_store::Record) -> Option<Record> {
+    let publisher = match record.publisher.is_empty() {
+        true => None,
+        false => Some(PeerId::from_bytes(&record.publisher).ok()?),
+    };
+    let expires = match record.expires {
+        0 => None,
+        expires => Some(from_unix_time(expires)?),
+    };
+
+    Some(Record {
+        key: Key::from(record.key),
+        value: record.value,
+        publisher,
+        expires,
+    })
+}
+
+fn encode_provider(record: &ProviderRecord) -> schema::kademlia_store::ProviderRecord {
+    schema::kademlia_store::ProviderRecord {
+        key: record.key.to_vec(),
+        provider: record.provider.to_bytes(),
+        addresses: record.addresses.iter().map(|address| address.to_vec()).collect(),
+        expires: to_unix_time(record.expires),
+    }
+}
+
+/// Decode provider record, returning `None` if the record has expired unless `keep_expired` is set.
+fn decode_provider(
+    record: schema::kademlia_store::ProviderRecord,
+    keep_expired: bool,
+) -> Option<ProviderRecord> {
+    let expires = match from_unix_time(record.expires) {
+        Some(expires) => expires,
+        None if keep_expired => Instant::now(),
+        None => return None,
+    };
+
+    Some(ProviderRecord {
+        key: Key::from(record.key),
+        provider: PeerId::from_bytes(&record.provider).ok()?,
+        addresses: record
+            .addresses
+            .into_iter()
+            .filter_map(|address| Multiaddr::try_from(address).ok())
+            .collect(),
+        expires,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_path() -> PathBuf {
+        std::env::temp_dir().join(format!("litep2p-kademlia-store-{}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn missing_file_opens_empty_store() {
+        let mut store = FileStore::open(temporary_path()).unwrap();
+
+        assert_eq!(store.iter().count(), 0);
+    }
+
+    #[test]
+    fn invalid_file_is_rejected() {
+        let path = temporary_path();
+        std::fs::write(&path, [0xff, 0xff, 0xff]).unwrap();
+
+        assert!(FileStore::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn store_survives_reopening() {
+        let path = temporary_path();
+        let expires = Instant::now() + Duration::from_secs(60 * 60);
+        let record = Record {
+            key: Key::from(vec![1, 2, 3]),
+            value: vec![4, 5, 6],
+            publisher: Some(PeerId::random()),
+            expires: Some(expires),
+        };
+        let permanent_record = Record::new(vec![7, 8, 9], vec![1]);
+        let provider = ProviderRecord {
+            key: Key::from(vec![1, 2, 3]),
+            provider: PeerId::random(),
+            addresses: vec!["/ip6/::1/tcp/8888".parse().unwrap()],
+            expires,
+        };
+        let local_provider = ProviderRecord {
+            key: Key::from(vec![4, 5, 6]),
+            provider: PeerId::random(),
+            addresses: vec![],
+            expires,
+        };
+
+        {
+            let mut store = FileStore::open(&path).unwrap();
+            store.put(record.clone());
+            store.put(permanent_record.clone());
+            assert!(store.put_provider(provider.clone()));
+            store.put_local_provider(local_provider.clone());
+        }
+
+        let mut store = FileStore::open(&path).unwrap();
+
+        let stored = store.get(&record.key).unwrap().clone();
+        assert_eq!(stored.value, record.value);
+        assert_eq!(stored.publisher, record.publisher);
+        let stored_expires = stored.expires.unwrap();
+        assert!(stored_expires <= expires + Duration::from_secs(1));
+        assert!(stored_expires + Duration::from_secs(1) >= expires);
+
+        assert_eq!(store.get(&permanent_record.key), Some(&permanent_record));
+
+        let providers = store.get_providers(&provider.key);
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].provider, provider.provider);
+        assert_eq!(providers[0].addresses, provider.addresses);
+        assert_eq!(store.get_providers(&local_provider.key).len(), 1);
+
+        // removals are persisted as well
+        store.remove(&record.key);
+        store.remove_local_provider(&local_provider.key);
+        store.flush();
+
+        let mut store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get(&record.key), None);
+        assert!(store.get_providers(&local_provider.key).is_empty());
+        assert_eq!(store.iter().count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejected_record_does_not_modify_store() {
+        let path = temporary_path();
+        let mut store = FileStore::open_with_config(
+            &path,
+            MemoryStoreConfig {
+                max_record_size_bytes: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(!store.put(Record::new(vec![1, 2, 3], vec![4, 5, 6])));
+        assert!(!store.dirty);
+
+        store.flush();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn modifications_are_written_on_flush() {
+        let path = temporary_path();
+        let mut store = FileStore::open(&path).unwrap();
+
+        store.put(Record::new(vec![1, 2, 3], vec![4, 5, 6]));
+        assert!(!path.exists());
+
+        store.flush();
+        assert_eq!(FileStore::open(&path).unwrap().iter().count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn flush_writes_on_blocking_thread() {
+        let path = temporary_path();
+        let mut store = FileStore::open(&path).unwrap();
+
+        store.put(Record::new(vec![1, 2, 3], vec![4, 5, 6]));
+        store.flush();
+        store.put(Record::new(vec![4, 5, 6], vec![7, 8, 9]));
+        store.flush();
+
+        // the latest snapshot is eventually written
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while *store.persisted.lock() != store.generation {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(FileStore::open(&path).unwrap().iter().count(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn expired_local_providers_are_kept() {
+        let path = temporary_path();
+        let key = Key::from(vec![1, 2, 3]);
+        let expires = Instant::now() + Duration::from_secs(60 * 60);
+        let remote = ProviderRecord {
+            key: key.clone(),
+            provider: PeerId::random(),
+            addresses: vec![],
+            expires,
+        };
+        let local = ProviderRecord {
+            key: key.clone(),
+            provider: PeerId::random(),
+            addresses: vec![],
+            expires,
+        };
+
+        // persist both records as expired
+        let mut remote = encode_provider(&remote);
+        let mut local = encode_provider(&local);
+        remote.expires = 1;
+        local.expires = 1;
+        let persisted = schema::kademlia_store::Store {
+            records: vec![],
+            providers: vec![remote],
+            local_providers: vec![local.clone()],
+        };
+        std::fs::write(&path, persisted.encode_to_vec()).unwrap();
+
+        // like `MemoryStore`, the local provider is kept while the remote one is dropped
+        let mut store = FileStore::open(&path).unwrap();
+        let providers = store.get_providers(&key);
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].provider.to_bytes(), local.provider);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,619 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Record store implementations for Kademlia.
+
+use crate::{
+    protocol::libp2p::kademlia::{
+        record::{Key, ProviderRecord, Record},
+        types::Key as KademliaKey,
+    },
+    PeerId,
+};
+
+use std::collections::{hash_map::Entry, HashMap};
+
+pub use file::FileStore;
+
+mod file;
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::ipfs::kademlia::store";
+
+/// Storage for the records and provider records of Kademlia.
+///
+/// [`MemoryStore`] is used by default. A different implementation can be provided with
+/// [`ConfigBuilder::with_record_store()`](super::ConfigBuilder::with_record_store).
+pub trait RecordStore: std::fmt::Debug + Send {
+    /// Get record for `key`, if it exists and hasn't expired.
+    fn get(&mut self, key: &Key) -> Option<&Record>;
+
+    /// Store record. Returns `true` if the record was stored.
+    fn put(&mut self, record: Record) -> bool;
+
+    /// Remove record for `key`.
+    fn remove(&mut self, key: &Key);
+
+    /// Iterate over the stored records that haven't expired.
+    fn iter(&mut self) -> Box<dyn Iterator<Item = &Record> + '_>;
+
+    /// Get providers for `key`, including the local node if it provides `key`.
+    fn get_providers(&mut self, key: &Key) -> Vec<ProviderRecord>;
+
+    /// Store provider record of a remote peer. Returns `true` if the record was stored.
+    fn put_provider(&mut self, record: ProviderRecord) -> bool;
+
+    /// Remove provider record of `provider` for `key`.
+    fn remove_provider(&mut self, key: &Key, provider: &PeerId);
+
+    /// Store provider record of the local node.
+    fn put_local_provider(&mut self, record: ProviderRecord);
+
+    /// Remove provider record of the local node for `key`.
+    fn remove_local_provider(&mut self, key: &Key) -> Option<ProviderRecord>;
+
+    /// Iterate over the provider records of the local node.
+    fn local_providers(&mut self) -> Box<dyn Iterator<Item = &ProviderRecord> + '_>;
+
+    /// Persist the modifications made to the store, if the store is backed by storage.
+    ///
+    /// Called periodically by Kademlia. The default implementation does nothing.
+    fn flush(&mut self) {}
+
+    /// Persist the store before Kademlia exits, returning once the store has been persisted.
+    ///
+    /// Called on a blocking thread. The default implementation calls [`RecordStore::flush()`].
+    fn close(&mut self) {
+        self.flush()
+    }
+}
+
+/// Memory store.
+#[derive(Debug)]
+pub struct MemoryStore {
+    /// Records.
+    records: HashMap<Key, Record>,
+    /// Provider records.
+    providers: HashMap<Key, Vec<ProviderRecord>>,
+    /// Provider records of the local node.
+    local_providers: HashMap<Key, ProviderRecord>,
+    /// Configuration.
+    config: MemoryStoreConfig,
+}
+
+impl MemoryStore {
+    /// Create new [`MemoryStore`].
+    pub fn new() -> Self {
+        Self {
+            records: HashMap::new(),
+            providers: HashMap::new(),
+            local_providers: HashMap::new(),
+            config: MemoryStoreConfig::default(),
+        }
+    }
+
+    /// Create new [`MemoryStore`] with the provided configuration.
+    pub fn with_config(config: MemoryStoreConfig) -> Self {
+        Self {
+            records: HashMap::new(),
+            providers: HashMap::new(),
+            local_providers: HashMap::new(),
+            config,
+        }
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordStore for MemoryStore {
+    /// Try to get record from local store for `key`.
+    fn get(&mut self, key: &Key) -> Option<&Record> {
+        let is_expired = self
+            .records
+            .get(key)
+            .map_or(false, |record| record.is_expired(std::time::Instant::now()));
+
+        if is_expired {
+            self.records.remove(key);
+            None
+        } else {
+            self.records.get(key)
+        }
+    }
+
+    /// Store record.
+    fn put(&mut self, record: Record) -> bool {
+        if record.value.len() >= self.config.max_record_size_bytes {
+            tracing::warn!(
+                target: LOG_TARGET,
+                key = ?record.key,
+                publisher = ?record.publisher,
+                size = record.value.len(),
+                max_size = self.config.max_record_size_bytes,
+                "discarding a DHT record that exceeds the configured size limit",
+            );
+            return false;
+        }
+
+        let len = self.records.len();
+        match self.records.entry(record.key.clone()) {
+            Entry::Occupied(mut entry) => {
+                // Lean towards the new record.
+                if let (Some(stored_record_ttl), Some(new_record_ttl)) =
+                    (entry.get().expires, record.expires)
+                {
+                    if stored_record_ttl > new_record_ttl {
+                        return false;
+                    }
+                }
+
+                entry.insert(record);
+            }
+
+            Entry::Vacant(entry) => {
+                if len >= self.config.max_records {
+                    tracing::warn!(
+                        target: LOG_TARGET,
+                        max_records = self.config.max_records,
+                        "discarding a DHT record, because maximum memory store size reached",
+                    );
+                    return false;
+                }
+
+                entry.insert(record);
+            }
+        }
+
+        true
+    }
+
+    /// Remove record for `key`.
+    fn remove(&mut self, key: &Key) {
+        self.records.remove(key);
+    }
+
+    /// Iterate over the stored records.
+    ///
+    /// Expired records are removed from the store.
+    fn iter(&mut self) -> Box<dyn Iterator<Item = &Record> + '_> {
+        let now = std::time::Instant::now();
+        self.records.retain(|_, record| !record.is_expired(now));
+
+        Box::new(self.records.values())
+    }
+
+    /// Get providers for `key`, including the local node if it provides `key`.
+    ///
+    /// Expired provider records are removed from the store.
+    fn get_providers(&mut self, key: &Key) -> Vec<ProviderRecord> {
+        let now = std::time::Instant::now();
+        let mut providers: Vec<ProviderRecord> =
+            self.local_providers.get(key).cloned().into_iter().collect();
+
+        if let Entry::Occupied(mut entry) = self.providers.entry(key.clone()) {
+            entry.get_mut().retain(|provider| !provider.is_expired(now));
+
+            if entry.get().is_empty() {
+                entry.remove();
+            } else {
+                providers.extend(entry.get().iter().cloned());
+            }
+        }
+
+        providers
+    }
+
+    /// Store provider record of a remote peer.
+    ///
+    /// If the limit of provided keys is reached, expired records are purged to make room for a new
+    /// key. If the provider is already known, its record is refreshed. Otherwise, if the limit of
+    /// providers for the key is reached, the record replaces an expired one or the provider
+    /// furthest from the key, if the new provider is closer to the key than it. Returns `true`
+    /// if the record was stored.
+    fn put_provider(&mut self, record: ProviderRecord) -> bool {
+        let now = std::time::Instant::now();
+
+        if record.is_expired(now) {
+            return false;
+        }
+
+        // make room for the new key by dropping the expired records and the keys left without
+        // providers before checking the limit
+        if !self.providers.contains_key(&record.key)
+            && self.providers.len() >= self.config.max_provider_keys
+        {
+            self.providers.retain(|_, providers| {
+                providers.retain(|provider| !provider.is_expired(now));
+                !providers.is_empty()
+            });
+        }
+
+        let len = self.providers.len();
+        let providers = match self.providers.entry(record.key.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                if len >= self.config.max_provider_keys {
+                    tracing::warn!(
+                        target: LOG_TARGET,
+                        max_provider_keys = self.config.max_provider_keys,
+                        "discarding a provider record, because maximum number of provided keys reached",
+                    );
+                    return false;
+                }
+
+                entry.insert(Vec::new())
+            }
+        };
+
+        if let Some(existing) =
+            providers.iter_mut().find(|provider| provider.provider == record.provider)
+        {
+            *existing = record;
+            return true;
+        }
+
+        providers.retain(|provider| !provider.is_expired(now));
+
+        if providers.len() < self.config.max_providers_per_key {
+            providers.push(record);
+            return true;
+        }
+
+        let target = KademliaKey::new(record.key.clone());
+        let distance = target.distance(&KademliaKey::from(record.provider));
+        let furthest = providers
+            .iter()
+            .enumerate()
+            .map(|(index, provider)| {
+                (
+                    index,
+                    target.distance(&KademliaKey::from(provider.provider)),
+                )
+            })
+            .max_by_key(|(_, distance)| *distance);
+
+        match furthest {
+            Some((index, furthest_distance)) if distance < furthest_distance => {
+                providers[index] = record;
+                true
+            }
+            _ => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    key = ?record.key,
+                    provider = ?record.provider,
+                    max_providers_per_key = self.config.max_providers_per_key,
+                    "discarding a provider record, because maximum number of providers for the key reached",
+                );
+                false
+            }
+        }
+    }
+
+    /// Remove provider record of `provider` for `key`.
+    fn remove_provider(&mut self, key: &Key, provider: &PeerId) {
+        if let Entry::Occupied(mut entry) = self.providers.entry(key.clone()) {
+            entry.get_mut().retain(|record| &record.provider != provider);
+
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Store provider record of the local node.
+    ///
+    /// Local provider records are not subject to the provider limits and are kept until
+    /// removed with [`RecordStore::remove_local_provider()`].
+    fn put_local_provider(&mut self, record: ProviderRecord) {
+        self.local_providers.insert(record.key.clone(), record);
+    }
+
+    /// Remove provider record of the local node for `key`.
+    fn remove_local_provider(&mut self, key: &Key) -> Option<ProviderRecord> {
+        self.local_providers.remove(key)
+    }
+
+    /// Iterate over the provider records of the local node.
+    fn local_providers(&mut self) -> Box<dyn Iterator<Item = &ProviderRecord> + '_> {
+        Box::new(self.local_providers.values())
+    }
+}
+
+/// Memory store configuration.
+#[derive(Debug, Clone)]
+pub struct MemoryStoreConfig {
+    /// Maximum number of records to store.
+    pub max_records: usize,
+
+    /// Maximum size of a record in bytes.
+    pub max_record_size_bytes: usize,
+
+    /// Maximum number of keys for which provider records are stored.
+    pub max_provider_keys: usize,
+
+    /// Maximum number of providers stored per key.
+    pub max_providers_per_key: usize,
+}
+
+impl Default for MemoryStoreConfig {
+    fn default() -> Self {
+        Self {
+            max_records: 1024,
+            max_record_size_bytes: 65 * 1024,
+            max_provider_keys: 1024,
+            max_providers_per_key: 20,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3]);
+        let record = Record::new(key.clone(), vec![4, 5, 6]);
+
+        store.put(record.clone());
+        assert_eq!(store.get(&key), Some(&record));
+
+        store.remove(&key);
+        assert_eq!(store.get(&key), None);
+    }
+
+    #[test]
+    fn test_memory_store_length() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_records: 1,
+            max_record_size_bytes: 1024,
+            ..Default::default()
+        });
+
+        let key1 = Key::from(vec![1, 2, 3]);
+        let key2 = Key::from(vec![4, 5, 6]);
+        let record1 = Record::new(key1.clone(), vec![4, 5, 6]);
+        let record2 = Record::new(key2.clone(), vec![7, 8, 9]);
+
+        store.put(record1.clone());
+        store.put(record2.clone());
+
+        assert_eq!(store.get(&key1), Some(&record1));
+        assert_eq!(store.get(&key2), None);
+    }
+
+    #[test]
+    fn test_memory_store_remove_old_records() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3]);
+        let record = Record {
+            key: key.clone(),
+            value: vec![4, 5, 6],
+            publisher: None,
+            expires: Some(std::time::Instant::now() - std::time::Duration::from_secs(5)),
+        };
+        // Record is already expired.
+        assert!(record.is_expired(std::time::Instant::now()));
+
+        store.put(record.clone());
+        assert_eq!(store.get(&key), None);
+    }
+
+    #[test]
+    fn test_memory_store_replace_new_records() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3]);
+        let record1 = Record {
+            key: key.clone(),
+            value: vec![4, 5, 6],
+            publisher: None,
+            expires: Some(std::time::Instant::now() + std::time::Duration::from_secs(100)),
+        };
+        let record2 = Record {
+            key: key.clone(),
+            value: vec![4, 5, 6],
+            publisher: None,
+            expires: Some(std::time::Instant::now() + std::time::Duration::from_secs(1000)),
+        };
+
+        store.put(record1.clone());
+        assert_eq!(store.get(&key), Some(&record1));
+
+        store.put(record2.clone());
+        assert_eq!(store.get(&key), Some(&record2));
+    }
+
+    #[test]
+    fn test_memory_store_records() {
+        let mut store = MemoryStore::new();
+        let record1 = Record::new(Key::from(vec![1, 2, 3]), vec![4, 5, 6]);
+        let record2 = Record {
+            key: Key::from(vec![4, 5, 6]),
+            value: vec![7, 8, 9],
+            publisher: None,
+            expires: Some(std::time::Instant::now() + std::time::Duration::from_secs(1)),
+        };
+
+        store.put(record1.clone());
+        store.put(record2.clone());
+        assert_eq!(store.iter().count(), 2);
+
+        // Expired records are removed from the store.
+        store.records.get_mut(&record2.key).unwrap().expires =
+            Some(std::time::Instant::now() - std::time::Duration::from_secs(1));
+        assert_eq!(store.iter().collect::<Vec<_>>(), vec![&record1]);
+        assert_eq!(store.get(&record2.key), None);
+    }
+
+    #[test]
+    fn test_memory_store_max_record_size() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_records: 1024,
+            max_record_size_bytes: 2,
+            ..Default::default()
+        });
+
+        let key = Key::from(vec![1, 2, 3]);
+        let record = Record::new(key.clone(), vec![4, 5]);
+        store.put(record.clone());
+        assert_eq!(store.get(&key), None);
+
+        let record = Record::new(key.clone(), vec![4]);
+        store.put(record.clone());
+        assert_eq!(store.get(&key), Some(&record));
+    }
+
+    fn provider_record(key: &Key, provider: PeerId, ttl: u64) -> ProviderRecord {
+        ProviderRecord {
+            key: key.clone(),
+            provider,
+            addresses: vec![],
+            expires: std::time::Instant::now() + std::time::Duration::from_secs(ttl),
+        }
+    }
+
+    #[test]
+    fn test_memory_store_providers() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3]);
+        let record = provider_record(&key, PeerId::random(), 100);
+
+        assert!(store.put_provider(record.clone()));
+        assert_eq!(store.get_providers(&key), vec![record.clone()]);
+
+        // Storing the record again refreshes it instead of adding a duplicate.
+        let refreshed = provider_record(&key, record.provider, 1000);
+        assert!(store.put_provider(refreshed.clone()));
+        assert_eq!(store.get_providers(&key), vec![refreshed]);
+
+        store.remove_provider(&key, &record.provider);
+        assert!(store.get_providers(&key).is_empty());
+    }
+
+    #[test]
+    fn test_memory_store_expired_providers() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3]);
+        let mut record = provider_record(&key, PeerId::random(), 100);
+
+        // Already expired records are not stored.
+        record.expires = std::time::Instant::now() - std::time::Duration::from_secs(5);
+        assert!(!store.put_provider(record.clone()));
+
+        // Records that expire after being stored are not returned.
+        record.expires = std::time::Instant::now() + std::time::Duration::from_millis(10);
+        assert!(store.put_provider(record));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(store.get_providers(&key).is_empty());
+    }
+
+    #[test]
+    fn test_memory_store_providers_per_key_limit() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_providers_per_key: 2,
+            ..Default::default()
+        });
+        let key = Key::from(vec![1, 2, 3]);
+        let target = KademliaKey::new(key.clone());
+
+        // Sort providers by their distance to the key, closest first.
+        let mut peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+        peers.sort_by_key(|peer| target.distance(&KademliaKey::from(*peer)));
+
+        assert!(store.put_provider(provider_record(&key, peers[1], 100)));
+        assert!(store.put_provider(provider_record(&key, peers[2], 100)));
+
+        // The closest provider replaces the furthest one.
+        assert!(store.put_provider(provider_record(&key, peers[0], 100)));
+        let providers = store.get_providers(&key);
+        assert_eq!(providers.len(), 2);
+        assert!(providers.iter().all(|record| record.provider != peers[2]));
+
+        // The furthest provider is rejected.
+        assert!(!store.put_provider(provider_record(&key, peers[2], 100)));
+    }
+
+    #[test]
+    fn test_memory_store_provider_keys_limit() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_provider_keys: 1,
+            ..Default::default()
+        });
+        let key1 = Key::from(vec![1, 2, 3]);
+        let key2 = Key::from(vec![4, 5, 6]);
+
+        assert!(store.put_provider(provider_record(&key1, PeerId::random(), 100)));
+        assert!(!store.put_provider(provider_record(&key2, PeerId::random(), 100)));
+        assert!(store.get_providers(&key2).is_empty());
+    }
+
+    #[test]
+    fn test_memory_store_provider_keys_limit_purges_expired() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_provider_keys: 2,
+            ..Default::default()
+        });
+        let key1 = Key::from(vec![1, 2, 3]);
+        let key2 = Key::from(vec![4, 5, 6]);
+        let key3 = Key::from(vec![7, 8, 9]);
+
+        assert!(store.put_provider(provider_record(&key1, PeerId::random(), 100)));
+        assert!(store.put_provider(provider_record(&key2, PeerId::random(), 100)));
+
+        // All records of the table expire.
+        for providers in store.providers.values_mut() {
+            for provider in providers {
+                provider.expires = std::time::Instant::now() - std::time::Duration::from_secs(1);
+            }
+        }
+
+        let record = provider_record(&key3, PeerId::random(), 100);
+        assert!(store.put_provider(record.clone()));
+        assert_eq!(store.get_providers(&key3), vec![record]);
+        assert_eq!(store.providers.len(), 1);
+    }
+
+    #[test]
+    fn test_memory_store_local_providers() {
+        let mut store = MemoryStore::with_config(MemoryStoreConfig {
+            max_providers_per_key: 1,
+            ..Default::default()
+        });
+        let key = Key::from(vec![1, 2, 3]);
+        let local = provider_record(&key, PeerId::random(), 100);
+        let remote = provider_record(&key, PeerId::random(), 100);
+
+        store.put_local_provider(local.clone());
+        assert!(store.put_provider(remote.clone()));
+        assert_eq!(
+            store.get_providers(&key),
+            vec![local.clone(), remote.clone()]
+        );
+
+        assert_eq!(store.remove_local_provider(&key), Some(local));
+        assert_eq!(store.get_providers(&key), vec![remote]);
+    }
+}
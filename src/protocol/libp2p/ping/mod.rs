@@ -178,7 +178,7 @@ impl Ping {
                     Some(TransportEvent::ConnectionEstablished { peer, .. }) => {
                         let _ = self.on_connection_established(peer);
                     }
-                    Some(TransportEvent::ConnectionClosed { peer }) => {
+                    Some(TransportEvent::ConnectionClosed { peer, .. }) => {
                         self.on_connection_closed(peer);
                     }
                     Some(TransportEvent::SubstreamOpened {
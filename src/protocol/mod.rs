@@ -24,7 +24,7 @@ use crate::{
     codec::ProtocolCodec,
     error::Error,
     substream::Substream,
-    transport::Endpoint,
+    transport::{ConnectionCloseReason, Endpoint},
     types::{protocol::ProtocolName, SubstreamId},
     PeerId,
 };
@@ -33,8 +33,8 @@ use multiaddr::Multiaddr;
 
 use std::fmt::Debug;
 
-pub(crate) use connection::Permit;
-pub(crate) use protocol_set::{InnerTransportEvent, ProtocolCommand, ProtocolSet};
+pub(crate) use connection::{ConnectionHandle, Permit};
+pub(crate) use protocol_set::{InnerTransportEvent, ProtocolCommand, ProtocolSet, ProtocolUpdates};
 
 pub use transport_service::TransportService;
 
@@ -73,6 +73,9 @@ pub enum TransportEvent {
     ConnectionClosed {
         /// Peer ID.
         peer: PeerId,
+
+        /// Reason why the last connection to the peer was closed.
+        reason: ConnectionCloseReason,
     },
 
     /// Failed to dial peer.
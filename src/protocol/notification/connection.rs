@@ -19,7 +19,8 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    protocol::notification::handle::NotificationEventHandle, substream::Substream, PeerId,
+    metrics::QueueDepth, protocol::notification::handle::NotificationEventHandle,
+    substream::Substream, PeerId,
 };
 
 use bytes::BytesMut;
@@ -71,6 +72,9 @@ pub(crate) struct Connection {
 
     /// Next notification to send, if any.
     next_notification: Option<Vec<u8>>,
+
+    /// Depth of the outbound notification queue.
+    queue_depth: QueueDepth,
 }
 
 /// Notify [`NotificationProtocol`](super::NotificationProtocol) that the connection was closed.
@@ -94,6 +98,7 @@ impl Connection {
         notif_tx: Sender<(PeerId, BytesMut)>,
         async_rx: Receiver<Vec<u8>>,
         sync_rx: Receiver<Vec<u8>>,
+        queue_depth: QueueDepth,
     ) -> (Self, oneshot::Sender<()>) {
         let (tx, rx) = oneshot::channel();
 
@@ -109,6 +114,7 @@ impl Connection {
                 conn_closed_tx,
                 next_notification: None,
                 notif_tx: PollSender::new(notif_tx),
+                queue_depth,
             },
             tx,
         )
@@ -129,6 +135,11 @@ impl Connection {
         let _ = self.inbound.close().await;
         let _ = self.outbound.close().await;
 
+        // notifications that were still queued are discarded
+        self.queue_depth.dequeued(
+            self.async_rx.len() + self.sync_rx.len() + self.next_notification.is_some() as usize,
+        );
+
         if std::matches!(notify_protocol, NotifyProtocol::Yes) {
             let _ = self.conn_closed_tx.send(self.peer).await;
         }
@@ -239,6 +250,8 @@ impl Stream for Connection {
                     })),
             }
 
+            this.queue_depth.dequeued(1);
+
             if let Err(_) = this.outbound.start_send_unpin(notification.into()) {
                 return Poll::Ready(Some(ConnectionEvent::CloseConnection {
                     notify: NotifyProtocol::Yes,
@@ -20,6 +20,7 @@
 
 use crate::{
     error::Error,
+    metrics::QueueDepth,
     protocol::notification::types::{
         Direction, InnerNotificationEvent, NotificationCommand, NotificationError,
         NotificationEvent, ValidationResult,
@@ -132,15 +133,24 @@ pub struct NotificationSink {
 
     /// TX channel for sending notifications asynchronously.
     async_tx: Sender<Vec<u8>>,
+
+    /// Depth of the outbound notification queue.
+    queue_depth: QueueDepth,
 }
 
 impl NotificationSink {
     /// Create new [`NotificationSink`].
-    pub(crate) fn new(peer: PeerId, sync_tx: Sender<Vec<u8>>, async_tx: Sender<Vec<u8>>) -> Self {
+    pub(crate) fn new(
+        peer: PeerId,
+        sync_tx: Sender<Vec<u8>>,
+        async_tx: Sender<Vec<u8>>,
+        queue_depth: QueueDepth,
+    ) -> Self {
         Self {
             peer,
             async_tx,
             sync_tx,
+            queue_depth,
         }
     }
 
@@ -148,9 +158,15 @@ impl NotificationSink {
     ///
     /// If the channel is clogged, [`NotificationError::ChannelClogged`] is returned.
     pub fn send_sync_notification(&self, notification: Vec<u8>) -> Result<(), NotificationError> {
-        self.sync_tx.try_send(notification).map_err(|error| match error {
-            TrySendError::Closed(_) => NotificationError::NoConnection,
-            TrySendError::Full(_) => NotificationError::ChannelClogged,
+        self.queue_depth.enqueued(1);
+
+        self.sync_tx.try_send(notification).map_err(|error| {
+            self.queue_depth.dequeued(1);
+
+            match error {
+                TrySendError::Closed(_) => NotificationError::NoConnection,
+                TrySendError::Full(_) => NotificationError::ChannelClogged,
+            }
         })
     }
 
@@ -160,10 +176,12 @@ impl NotificationSink {
     /// Returns [`Error::PeerDoesntExist(PeerId)`](crate::error::Error::PeerDoesntExist)
     /// if the connection has been closed.
     pub async fn send_async_notification(&self, notification: Vec<u8>) -> crate::Result<()> {
-        self.async_tx
-            .send(notification)
-            .await
-            .map_err(|_| Error::PeerDoesntExist(self.peer))
+        self.queue_depth.enqueued(1);
+
+        self.async_tx.send(notification).await.map_err(|_| {
+            self.queue_depth.dequeued(1);
+            Error::PeerDoesntExist(self.peer)
+        })
     }
 }
 
@@ -1505,7 +1505,8 @@ impl NotificationProtocol {
 
                 let (async_tx, async_rx) = channel(self.async_channel_size);
                 let (sync_tx, sync_rx) = channel(self.sync_channel_size);
-                let sink = NotificationSink::new(peer, sync_tx, async_tx);
+                let queue_depth = self.service.metrics().notification_queue(&self.protocol);
+                let sink = NotificationSink::new(peer, sync_tx, async_tx, queue_depth.clone());
 
                 // start connection handler for the peer which only deals with sending/receiving
                 // notifications
@@ -1527,6 +1528,7 @@ impl NotificationProtocol {
                     self.notif_tx.clone(),
                     async_rx,
                     sync_rx,
+                    queue_depth,
                 );
 
                 context.state = PeerState::Open { shutdown };
@@ -1719,7 +1721,7 @@ impl NotificationProtocol {
                         );
                     }
                 }
-                Some(TransportEvent::ConnectionClosed { peer }) => {
+                Some(TransportEvent::ConnectionClosed { peer, .. }) => {
                     if let Err(error) = self.on_connection_closed(peer).await {
                         tracing::debug!(
                             target: LOG_TARGET,
@@ -62,6 +62,7 @@ fn make_notification_protocol() -> (
         Vec::new(),
         std::sync::Arc::new(Default::default()),
         handle,
+        Default::default(),
     );
     let (config, handle) = NotificationConfig::new(
         ProtocolName::from("/notif/1"),
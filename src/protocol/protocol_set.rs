@@ -25,21 +25,25 @@ use crate::{
         connection::{ConnectionHandle, Permit},
         Direction, TransportEvent,
     },
+    rate_limit::{RateLimiter, SubstreamRateLimiter},
     substream::Substream,
     transport::{
-        manager::{ProtocolContext, TransportManagerEvent},
-        Endpoint,
+        manager::{ProtocolContext, SupportedTransport, TransportManagerEvent},
+        ConnectionCloseReason, Endpoint,
     },
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
     PeerId,
 };
 
-use futures::{stream::FuturesUnordered, Stream, StreamExt};
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, Stream, StreamExt};
 use multiaddr::Multiaddr;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{
+    mpsc::{channel, Receiver, Sender},
+    watch,
+};
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     pin::Pin,
     sync::{
@@ -77,6 +81,9 @@ pub enum InnerTransportEvent {
 
         /// Connection ID.
         connection: ConnectionId,
+
+        /// Reason why the connection was closed.
+        reason: ConnectionCloseReason,
     },
 
     /// Failed to dial peer.
@@ -200,9 +207,15 @@ pub enum ProtocolCommand {
     },
 
     /// Forcibly close the connection, even if other protocols have substreams open over it.
-    ForceClose,
+    ForceClose {
+        /// Reason for closing the connection.
+        reason: ConnectionCloseReason,
+    },
 }
 
+/// Installed protocols, updated when protocols are registered or unregistered at runtime.
+pub(crate) type ProtocolUpdates = watch::Receiver<HashMap<ProtocolName, ProtocolContext>>;
+
 /// Supported protocol information.
 ///
 /// Each connection gets a copy of [`ProtocolSet`] which allows it to interact
@@ -215,6 +228,19 @@ pub struct ProtocolSet {
     rx: Receiver<ProtocolCommand>,
     next_substream_id: Arc<AtomicUsize>,
     fallback_names: HashMap<ProtocolName, ProtocolName>,
+    rate_limiter: RateLimiter,
+
+    /// Remote peer and endpoint, set once the connection has been reported established.
+    established: Option<(PeerId, Endpoint)>,
+
+    /// Protocols which have been informed that the connection was established.
+    informed: HashSet<ProtocolName>,
+
+    /// Pending update of the installed protocols.
+    next_update: Option<BoxFuture<'static, Option<ProtocolUpdates>>>,
+
+    /// Pending reports of the connection to protocols registered after it was established.
+    pending_reports: FuturesUnordered<BoxFuture<'static, ()>>,
 }
 
 impl ProtocolSet {
@@ -222,11 +248,32 @@ impl ProtocolSet {
         connection_id: ConnectionId,
         mgr_tx: Sender<TransportManagerEvent>,
         next_substream_id: Arc<AtomicUsize>,
-        protocols: HashMap<ProtocolName, ProtocolContext>,
+        mut protocols: ProtocolUpdates,
+        rate_limiter: RateLimiter,
     ) -> Self {
         let (tx, rx) = channel(256);
+        let installed = protocols.borrow_and_update().clone();
 
-        let fallback_names = protocols
+        ProtocolSet {
+            rx,
+            mgr_tx,
+            fallback_names: Self::fallback_names(&installed),
+            protocols: installed,
+            next_substream_id,
+            rate_limiter,
+            established: None,
+            informed: HashSet::new(),
+            next_update: Some(Self::next_update(protocols)),
+            pending_reports: FuturesUnordered::new(),
+            connection: ConnectionHandle::new(connection_id, tx),
+        }
+    }
+
+    /// Map fallback names of `protocols` to their main protocol names.
+    fn fallback_names(
+        protocols: &HashMap<ProtocolName, ProtocolContext>,
+    ) -> HashMap<ProtocolName, ProtocolName> {
+        protocols
             .iter()
             .flat_map(|(protocol, context)| {
                 context
@@ -235,15 +282,70 @@ impl ProtocolSet {
                     .map(|fallback| (fallback.clone(), protocol.clone()))
                     .collect::<HashMap<_, _>>()
             })
-            .collect();
+            .collect()
+    }
 
-        ProtocolSet {
-            rx,
-            mgr_tx,
-            protocols,
-            next_substream_id,
-            fallback_names,
-            connection: ConnectionHandle::new(connection_id, tx),
+    /// Wait until the installed protocols are updated.
+    ///
+    /// Resolves to `None` if `TransportManager` has exited.
+    fn next_update(mut protocols: ProtocolUpdates) -> BoxFuture<'static, Option<ProtocolUpdates>> {
+        async move { protocols.changed().await.ok().map(|_| protocols) }.boxed()
+    }
+
+    /// Update the installed protocols.
+    ///
+    /// If the connection has already been reported established and is still open, newly
+    /// registered protocols are informed of the connection.
+    fn on_protocols_updated(&mut self, protocols: HashMap<ProtocolName, ProtocolContext>) {
+        let added = protocols
+            .iter()
+            .filter(|(protocol, context)| {
+                !self
+                    .protocols
+                    .get(*protocol)
+                    .is_some_and(|installed| installed.tx.same_channel(&context.tx))
+            })
+            .map(|(protocol, context)| (protocol.clone(), context.tx.clone()))
+            .collect::<Vec<_>>();
+
+        tracing::debug!(
+            target: LOG_TARGET,
+            connection_id = ?self.connection.connection_id(),
+            num_protocols = protocols.len(),
+            num_added = added.len(),
+            "installed protocols updated",
+        );
+
+        self.fallback_names = Self::fallback_names(&protocols);
+        self.informed.retain(|protocol| {
+            protocols.contains_key(protocol) && !added.iter().any(|(added, _)| added == protocol)
+        });
+        self.protocols = protocols;
+
+        let Some((peer, endpoint)) = &self.established else {
+            return;
+        };
+        let Some(connection_handle) = self.connection.upgrade() else {
+            return;
+        };
+
+        for (protocol, tx) in added {
+            self.informed.insert(protocol);
+
+            let peer = *peer;
+            let endpoint = endpoint.clone();
+            let connection_handle = connection_handle.clone();
+
+            self.pending_reports.push(Box::pin(async move {
+                let _ = tx
+                    .send(InnerTransportEvent::ConnectionEstablished {
+                        peer,
+                        connection: endpoint.connection_id(),
+                        endpoint,
+                        sender: connection_handle,
+                    })
+                    .await;
+            }));
         }
     }
 
@@ -257,15 +359,30 @@ impl ProtocolSet {
         SubstreamId::from(self.next_substream_id.fetch_add(1usize, Ordering::Relaxed))
     }
 
-    /// Get the list of all supported protocols.
+    /// Get the list of all protocols negotiated for inbound substreams.
     pub fn protocols(&self) -> Vec<ProtocolName> {
         self.protocols
-            .keys()
+            .iter()
+            .filter(|(_, context)| context.inbound)
+            .flat_map(|(protocol, context)| {
+                std::iter::once(protocol).chain(context.fallback_names.iter())
+            })
             .cloned()
-            .chain(self.fallback_names.keys().cloned())
             .collect()
     }
 
+    /// Get rate limiter for a substream of `protocol` opened over `transport`, if the substream
+    /// is throttled.
+    pub fn rate_limiter(
+        &self,
+        transport: SupportedTransport,
+        protocol: &ProtocolName,
+    ) -> Option<SubstreamRateLimiter> {
+        let protocol = self.fallback_names.get(protocol).unwrap_or(protocol);
+
+        self.rate_limiter.substream(transport, protocol)
+    }
+
     /// Report to `protocol` that substream was opened for `peer`.
     pub async fn report_substream_open(
         &mut self,
@@ -281,9 +398,17 @@ impl ProtocolSet {
             None => (protocol, None),
         };
 
-        self.protocols
-            .get_mut(&protocol)
-            .ok_or(Error::ProtocolNotSupported(protocol.to_string()))?
+        let Some(context) = self.protocols.get_mut(&protocol) else {
+            tracing::debug!(
+                target: LOG_TARGET,
+                %protocol,
+                ?peer,
+                "protocol was unregistered, dropping substream",
+            );
+            return Ok(());
+        };
+
+        context
             .tx
             .send(InnerTransportEvent::SubstreamOpened {
                 peer,
@@ -297,13 +422,13 @@ impl ProtocolSet {
     }
 
     /// Get codec used by the protocol.
-    pub fn protocol_codec(&self, protocol: &ProtocolName) -> ProtocolCodec {
-        // NOTE: `protocol` must exist in `self.protocol` as it was negotiated
-        // using the protocols from this set
+    ///
+    /// Fails if the protocol was unregistered after the substream was negotiated.
+    pub fn protocol_codec(&self, protocol: &ProtocolName) -> crate::Result<ProtocolCodec> {
         self.protocols
             .get(self.fallback_names.get(protocol).map_or(protocol, |protocol| protocol))
-            .expect("protocol to exist")
-            .codec
+            .map(|context| context.codec)
+            .ok_or_else(|| Error::ProtocolNotSupported(protocol.to_string()))
     }
 
     /// Report to `protocol` that connection failed to open substream for `peer`.
@@ -321,9 +446,12 @@ impl ProtocolSet {
             "failed to open substream",
         );
 
-        self.protocols
-            .get_mut(&protocol)
-            .ok_or(Error::ProtocolNotSupported(protocol.to_string()))?
+        let Some(context) = self.protocols.get_mut(&protocol) else {
+            tracing::debug!(target: LOG_TARGET, %protocol, "protocol was unregistered");
+            return Ok(());
+        };
+
+        context
             .tx
             .send(InnerTransportEvent::SubstreamOpenFailure { substream, error })
             .await
@@ -336,6 +464,9 @@ impl ProtocolSet {
         peer: PeerId,
         endpoint: Endpoint,
     ) -> crate::Result<()> {
+        self.established = Some((peer, endpoint.clone()));
+        self.informed = self.protocols.keys().cloned().collect();
+
         let connection_handle = self.connection.downgrade();
         let mut futures = self
             .protocols
@@ -364,7 +495,14 @@ impl ProtocolSet {
             }
         }
 
-        Ok(())
+        self.mgr_tx
+            .send(TransportManagerEvent::ConnectionEstablished {
+                peer,
+                connection: endpoint.connection_id(),
+                handle: self.connection.clone(),
+            })
+            .await
+            .map_err(From::from)
     }
 
     /// Report to protocols that a connection was closed.
@@ -372,16 +510,23 @@ impl ProtocolSet {
         &mut self,
         peer: PeerId,
         connection_id: ConnectionId,
+        reason: ConnectionCloseReason,
     ) -> crate::Result<()> {
+        // protocols registered after the connection was established must be informed of the
+        // connection before they're informed it has been closed
+        while self.pending_reports.next().await.is_some() {}
+
         let mut futures = self
             .protocols
-            .values()
+            .iter()
+            .filter_map(|(protocol, sender)| self.informed.contains(protocol).then_some(sender))
             .map(|sender| async move {
                 sender
                     .tx
                     .send(InnerTransportEvent::ConnectionClosed {
                         peer,
                         connection: connection_id,
+                        reason,
                     })
                     .await
             })
@@ -397,6 +542,7 @@ impl ProtocolSet {
             .send(TransportManagerEvent::ConnectionClosed {
                 peer,
                 connection: connection_id,
+                reason,
             })
             .await
             .map_err(From::from)
@@ -407,6 +553,22 @@ impl Stream for ProtocolSet {
     type Item = ProtocolCommand;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Some(Poll::Ready(update)) =
+            self.next_update.as_mut().map(|future| future.poll_unpin(cx))
+        {
+            match update {
+                None => self.next_update = None,
+                Some(mut protocols) => {
+                    let installed = protocols.borrow_and_update().clone();
+
+                    self.next_update = Some(Self::next_update(protocols));
+                    self.on_protocols_updated(installed);
+                }
+            }
+        }
+
+        while let Poll::Ready(Some(())) = self.pending_reports.poll_next_unpin(cx) {}
+
         self.rx.poll_recv(cx)
     }
 }
@@ -426,7 +588,7 @@ mod tests {
             ConnectionId::from(0usize),
             tx,
             Default::default(),
-            HashMap::from_iter([(
+            watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
@@ -435,8 +597,11 @@ mod tests {
                         ProtocolName::from("/notif/1/fallback/1"),
                         ProtocolName::from("/notif/1/fallback/2"),
                     ],
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
+            Default::default(),
         );
 
         let expected_protocols = HashSet::from([
@@ -473,7 +638,7 @@ mod tests {
             ConnectionId::from(0usize),
             tx,
             Default::default(),
-            HashMap::from_iter([(
+            watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
@@ -482,8 +647,11 @@ mod tests {
                         ProtocolName::from("/notif/1/fallback/1"),
                         ProtocolName::from("/notif/1/fallback/2"),
                     ],
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
+            Default::default(),
         );
 
         protocol_set
@@ -520,7 +688,7 @@ mod tests {
             ConnectionId::from(0usize),
             tx,
             Default::default(),
-            HashMap::from_iter([(
+            watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
@@ -529,8 +697,11 @@ mod tests {
                         ProtocolName::from("/notif/1/fallback/1"),
                         ProtocolName::from("/notif/1/fallback/2"),
                     ],
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
+            Default::default(),
         );
 
         protocol_set
@@ -557,4 +728,178 @@ mod tests {
             _ => panic!("invalid event received"),
         }
     }
+
+    #[tokio::test]
+    async fn protocol_registered_after_connection_established() {
+        let (tx, _rx) = channel(64);
+        let (tx1, mut rx1) = channel(64);
+        let (tx2, mut rx2) = channel(64);
+        let context1 = ProtocolContext {
+            tx: tx1,
+            codec: ProtocolCodec::Identity(32),
+            fallback_names: Vec::new(),
+            inbound: true,
+        };
+        let (protocols_tx, protocols_rx) = watch::channel(HashMap::from_iter([(
+            ProtocolName::from("/notif/1"),
+            context1.clone(),
+        )]));
+
+        let mut protocol_set = ProtocolSet::new(
+            ConnectionId::from(0usize),
+            tx,
+            Default::default(),
+            protocols_rx,
+            Default::default(),
+        );
+
+        let peer = PeerId::random();
+        let endpoint = Endpoint::dialer(Multiaddr::empty(), ConnectionId::from(0usize));
+        protocol_set.report_connection_established(peer, endpoint).await.unwrap();
+
+        // the active handle held by the protocol keeps the connection open
+        let _handle = match rx1.recv().await.unwrap() {
+            InnerTransportEvent::ConnectionEstablished {
+                peer: remote,
+                sender,
+                ..
+            } => {
+                assert_eq!(remote, peer);
+                sender
+            }
+            _ => panic!("invalid event received"),
+        };
+
+        protocols_tx.send_replace(HashMap::from_iter([
+            (ProtocolName::from("/notif/1"), context1),
+            (
+                ProtocolName::from("/notif/2"),
+                ProtocolContext {
+                    tx: tx2,
+                    codec: ProtocolCodec::Identity(32),
+                    fallback_names: Vec::new(),
+                    inbound: true,
+                },
+            ),
+        ]));
+
+        // poll the protocol set so it processes the update
+        futures::future::poll_fn(|cx| {
+            let _ = protocol_set.poll_next_unpin(cx);
+            Poll::Ready(())
+        })
+        .await;
+
+        assert!(protocol_set.protocols().contains(&ProtocolName::from("/notif/2")));
+        assert!(std::matches!(
+            protocol_set.protocol_codec(&ProtocolName::from("/notif/2")),
+            Ok(ProtocolCodec::Identity(32))
+        ));
+
+        // only the new protocol is informed of the connection
+        match rx2.recv().await.unwrap() {
+            InnerTransportEvent::ConnectionEstablished { peer: remote, .. } => {
+                assert_eq!(remote, peer)
+            }
+            _ => panic!("invalid event received"),
+        }
+        assert!(rx1.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn substream_for_unregistered_protocol_is_dropped() {
+        let (tx, _rx) = channel(64);
+        let (tx1, _rx1) = channel(64);
+        let (protocols_tx, protocols_rx) = watch::channel(HashMap::from_iter([(
+            ProtocolName::from("/notif/1"),
+            ProtocolContext {
+                tx: tx1,
+                codec: ProtocolCodec::Identity(32),
+                fallback_names: Vec::new(),
+                inbound: true,
+            },
+        )]));
+
+        let mut protocol_set = ProtocolSet::new(
+            ConnectionId::from(0usize),
+            tx,
+            Default::default(),
+            protocols_rx,
+            Default::default(),
+        );
+
+        protocols_tx.send_replace(HashMap::new());
+        futures::future::poll_fn(|cx| {
+            let _ = protocol_set.poll_next_unpin(cx);
+            Poll::Ready(())
+        })
+        .await;
+
+        assert!(protocol_set.protocols().is_empty());
+        assert!(protocol_set.protocol_codec(&ProtocolName::from("/notif/1")).is_err());
+
+        protocol_set
+            .report_substream_open(
+                PeerId::random(),
+                ProtocolName::from("/notif/1"),
+                Direction::Inbound,
+                Substream::new_mock(
+                    PeerId::random(),
+                    SubstreamId::from(0usize),
+                    Box::new(MockSubstream::new()),
+                ),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn protocol_with_inbound_disabled_is_not_negotiated() {
+        let (tx, _rx) = channel(64);
+        let (tx1, _rx1) = channel(64);
+        let mut context = ProtocolContext {
+            tx: tx1,
+            codec: ProtocolCodec::Identity(32),
+            fallback_names: vec![ProtocolName::from("/notif/1/fallback")],
+            inbound: false,
+        };
+        let (protocols_tx, protocols_rx) = watch::channel(HashMap::from_iter([(
+            ProtocolName::from("/notif/1"),
+            context.clone(),
+        )]));
+
+        let mut protocol_set = ProtocolSet::new(
+            ConnectionId::from(0usize),
+            tx,
+            Default::default(),
+            protocols_rx,
+            Default::default(),
+        );
+
+        // outbound substreams can still be opened
+        assert!(protocol_set.protocols().is_empty());
+        assert!(std::matches!(
+            protocol_set.protocol_codec(&ProtocolName::from("/notif/1")),
+            Ok(ProtocolCodec::Identity(32))
+        ));
+
+        context.inbound = true;
+        protocols_tx.send_replace(HashMap::from_iter([(
+            ProtocolName::from("/notif/1"),
+            context,
+        )]));
+        futures::future::poll_fn(|cx| {
+            let _ = protocol_set.poll_next_unpin(cx);
+            Poll::Ready(())
+        })
+        .await;
+
+        assert_eq!(
+            protocol_set.protocols().into_iter().collect::<HashSet<_>>(),
+            HashSet::from_iter([
+                ProtocolName::from("/notif/1"),
+                ProtocolName::from("/notif/1/fallback"),
+            ]),
+        );
+    }
 }
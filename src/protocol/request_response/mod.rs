@@ -27,6 +27,7 @@ use crate::{
         request_response::handle::{InnerRequestResponseEvent, RequestResponseCommand},
         Direction, TransportEvent, TransportService,
     },
+    shutdown::ShutdownSignal,
     substream::{Substream, SubstreamSet},
     types::{protocol::ProtocolName, RequestId, SubstreamId},
     PeerId,
@@ -49,7 +50,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 pub use config::{Config, ConfigBuilder};
@@ -181,11 +182,17 @@ pub(crate) struct RequestResponseProtocol {
 
     /// Maximum concurrent inbound requests, if specified.
     max_concurrent_inbound_requests: Option<usize>,
+
+    /// Shutdown signal.
+    shutdown: ShutdownSignal,
+
+    /// Whether the protocol is draining its in-flight requests before exiting.
+    draining: bool,
 }
 
 impl RequestResponseProtocol {
     /// Create new [`RequestResponseProtocol`].
-    pub(crate) fn new(service: TransportService, config: Config) -> Self {
+    pub(crate) fn new(service: TransportService, config: Config, shutdown: ShutdownSignal) -> Self {
         Self {
             service,
             peers: HashMap::new(),
@@ -201,9 +208,20 @@ impl RequestResponseProtocol {
             pending_inbound_requests: SubstreamSet::new(),
             pending_outbound_responses: FuturesUnordered::new(),
             max_concurrent_inbound_requests: config.max_concurrent_inbound_request,
+            shutdown,
+            draining: false,
         }
     }
 
+    /// Check if there are no in-flight requests or responses.
+    fn is_idle(&self) -> bool {
+        self.pending_dials.is_empty()
+            && self.pending_outbound.is_empty()
+            && self.pending_inbound.is_empty()
+            && self.pending_inbound_requests.is_empty()
+            && self.pending_outbound_responses.is_empty()
+    }
+
     /// Get next ephemeral request ID.
     fn next_request_id(&mut self) -> RequestId {
         RequestId::from(self.next_request_id.fetch_add(1usize, Ordering::Relaxed))
@@ -357,10 +375,13 @@ impl RequestResponseProtocol {
 
         let request_timeout = self.timeout;
         let protocol = self.protocol.clone();
+        let metrics = self.service.metrics().clone();
         let (tx, rx) = oneshot::channel();
         self.pending_outbound_cancels.insert(request_id, tx);
 
         self.pending_inbound.push(Box::pin(async move {
+            let started = Instant::now();
+
             match tokio::time::timeout(request_timeout, substream.send_framed(request.into())).await
             {
                 Err(_) => (
@@ -422,6 +443,7 @@ impl RequestResponseProtocol {
                         }
                         event = substream.next() => match event {
                             Some(Ok(response)) => {
+                                metrics.on_request_completed(&protocol, started.elapsed());
                                 (peer, request_id, fallback_protocol, Ok(response.freeze().into()))
                             }
                             _ => (peer, request_id, fallback_protocol, Err(RequestResponseError::Rejected)),
@@ -870,6 +892,11 @@ impl RequestResponseProtocol {
         tracing::debug!(target: LOG_TARGET, "starting request-response event loop");
 
         loop {
+            if self.draining && self.is_idle() {
+                tracing::debug!(target: LOG_TARGET, protocol = %self.protocol, "requests drained, exiting");
+                return;
+            }
+
             tokio::select! {
                 // events coming from the network have higher priority than user commands as all user commands are
                 // responses to network behaviour so ensure that the commands operate on the most up to date information.
@@ -879,7 +906,7 @@ impl RequestResponseProtocol {
                     Some(TransportEvent::ConnectionEstablished { peer, .. }) => {
                         let _ = self.on_connection_established(peer).await;
                     }
-                    Some(TransportEvent::ConnectionClosed { peer }) => {
+                    Some(TransportEvent::ConnectionClosed { peer, .. }) => {
                         self.on_connection_closed(peer).await;
                     }
                     Some(TransportEvent::SubstreamOpened {
@@ -992,6 +1019,10 @@ impl RequestResponseProtocol {
                         }
                     }
                 },
+                _ = self.shutdown.draining(), if !self.draining => {
+                    tracing::debug!(target: LOG_TARGET, protocol = %self.protocol, "draining requests");
+                    self.draining = true;
+                }
             }
         }
     }
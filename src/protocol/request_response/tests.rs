@@ -60,12 +60,13 @@ fn protocol() -> (
         Vec::new(),
         std::sync::Arc::new(Default::default()),
         handle,
+        Default::default(),
     );
     let (config, handle) =
         ConfigBuilder::new(ProtocolName::from("/req/1")).with_max_size(1024).build();
 
     (
-        RequestResponseProtocol::new(transport_service, config),
+        RequestResponseProtocol::new(transport_service, config, Default::default()),
         handle,
         manager,
         tx,
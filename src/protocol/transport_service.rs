@@ -20,8 +20,9 @@
 
 use crate::{
     error::Error,
+    metrics::Metrics,
     protocol::{connection::ConnectionHandle, InnerTransportEvent, TransportEvent},
-    transport::{manager::TransportManagerHandle, Endpoint},
+    transport::{manager::TransportManagerHandle, ConnectionCloseReason, Endpoint},
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
     PeerId, DEFAULT_CHANNEL_SIZE,
 };
@@ -124,6 +125,9 @@ pub struct TransportService {
 
     /// Pending keep-alive timeouts.
     keep_alive_timeouts: FuturesUnordered<BoxFuture<'static, (PeerId, ConnectionId)>>,
+
+    /// Metrics.
+    metrics: Metrics,
 }
 
 impl TransportService {
@@ -134,6 +138,7 @@ impl TransportService {
         fallback_names: Vec<ProtocolName>,
         next_substream_id: Arc<AtomicUsize>,
         transport_handle: TransportManagerHandle,
+        metrics: Metrics,
     ) -> (Self, Sender<InnerTransportEvent>) {
         let (tx, rx) = channel(DEFAULT_CHANNEL_SIZE);
 
@@ -145,6 +150,7 @@ impl TransportService {
                 fallback_names,
                 transport_handle,
                 next_substream_id,
+                metrics,
                 connections: HashMap::new(),
                 keep_alive_timeouts: FuturesUnordered::new(),
             },
@@ -152,6 +158,11 @@ impl TransportService {
         )
     }
 
+    /// Get metrics.
+    pub(crate) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     /// Handle connection established event.
     fn on_connection_established(
         &mut self,
@@ -208,6 +219,7 @@ impl TransportService {
         &mut self,
         peer: PeerId,
         connection_id: ConnectionId,
+        reason: ConnectionCloseReason,
     ) -> Option<TransportEvent> {
         let Some(context) = self.connections.get_mut(&peer) else {
             tracing::warn!(
@@ -229,7 +241,7 @@ impl TransportService {
             match context.secondary.take() {
                 None => {
                     self.connections.remove(&peer);
-                    return Some(TransportEvent::ConnectionClosed { peer });
+                    return Some(TransportEvent::ConnectionClosed { peer, reason });
                 }
                 Some(handle) => {
                     tracing::debug!(
@@ -351,10 +363,10 @@ impl TransportService {
         );
 
         if let Some(ref mut connection) = connection.secondary {
-            let _ = connection.force_close();
+            let _ = connection.force_close(ConnectionCloseReason::LocalClose);
         }
 
-        connection.primary.force_close()
+        connection.primary.force_close(ConnectionCloseReason::LocalClose)
     }
 }
 
@@ -377,11 +389,14 @@ impl Stream for TransportService {
                         return Poll::Ready(Some(event));
                     }
                 }
-                Some(InnerTransportEvent::ConnectionClosed { peer, connection }) => {
-                    if let Some(event) = self.on_connection_closed(peer, connection) {
+                Some(InnerTransportEvent::ConnectionClosed {
+                    peer,
+                    connection,
+                    reason,
+                }) =>
+                    if let Some(event) = self.on_connection_closed(peer, connection, reason) {
                         return Poll::Ready(Some(event));
-                    }
-                }
+                    },
                 Some(event) => return Poll::Ready(Some(event.into())),
             }
         }
@@ -431,6 +446,7 @@ mod tests {
             cmd_tx,
             HashSet::new(),
             Default::default(),
+            Default::default(),
         );
 
         let (service, sender) = TransportService::new(
@@ -439,6 +455,7 @@ mod tests {
             Vec::new(),
             Arc::new(AtomicUsize::new(0usize)),
             handle,
+            Default::default(),
         );
 
         (service, sender, cmd_rx)
@@ -636,6 +653,7 @@ mod tests {
             .send(InnerTransportEvent::ConnectionClosed {
                 peer,
                 connection: ConnectionId::from(1usize),
+                reason: ConnectionCloseReason::LocalClose,
             })
             .await
             .unwrap();
@@ -711,6 +729,7 @@ mod tests {
             .send(InnerTransportEvent::ConnectionClosed {
                 peer,
                 connection: ConnectionId::from(0usize),
+                reason: ConnectionCloseReason::LocalClose,
             })
             .await
             .unwrap();
@@ -733,15 +752,18 @@ mod tests {
             .send(InnerTransportEvent::ConnectionClosed {
                 peer,
                 connection: ConnectionId::from(1usize),
+                reason: ConnectionCloseReason::RemoteGoAway,
             })
             .await
             .unwrap();
 
         if let Some(TransportEvent::ConnectionClosed {
             peer: disconnected_peer,
+            reason,
         }) = service.next().await
         {
             assert_eq!(disconnected_peer, peer);
+            assert_eq!(reason, ConnectionCloseReason::RemoteGoAway);
         } else {
             panic!("expected event from `TransportService`");
         };
@@ -797,6 +819,7 @@ mod tests {
             .send(InnerTransportEvent::ConnectionClosed {
                 peer,
                 connection: ConnectionId::from(1337usize),
+                reason: ConnectionCloseReason::LocalClose,
             })
             .await
             .unwrap();
@@ -804,6 +827,7 @@ mod tests {
         // verify that the protocols are notified of the connection closing as well
         if let Some(TransportEvent::ConnectionClosed {
             peer: connected_peer,
+            ..
         }) = service.next().await
         {
             assert_eq!(connected_peer, peer);
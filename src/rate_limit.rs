@@ -0,0 +1,427 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Token bucket rate limiting of substream traffic.
+
+use crate::{transport::manager::SupportedTransport, ProtocolName};
+
+use futures::FutureExt;
+use parking_lot::Mutex;
+use tokio::time::Sleep;
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Token bucket rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Number of bytes per second.
+    bytes_per_second: u64,
+
+    /// Maximum number of bytes that can be transferred in a burst.
+    burst: u64,
+}
+
+impl RateLimit {
+    /// Create new [`RateLimit`] of `bytes_per_second`.
+    ///
+    /// The burst size defaults to one second worth of traffic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is zero.
+    pub fn new(bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second != 0, "rate limit must be non-zero");
+
+        Self {
+            bytes_per_second,
+            burst: bytes_per_second,
+        }
+    }
+
+    /// Set the maximum number of bytes that can be transferred in a burst.
+    ///
+    /// Burst size is at least one byte.
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        self.burst = burst.max(1u64);
+        self
+    }
+
+    /// Get the number of bytes per second.
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// Get the burst size.
+    pub fn burst(&self) -> u64 {
+        self.burst
+    }
+}
+
+/// Inbound and outbound rate limits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Limits {
+    /// Inbound limit.
+    inbound: Option<RateLimit>,
+
+    /// Outbound limit.
+    outbound: Option<RateLimit>,
+}
+
+/// Rate limit configuration.
+///
+/// Limits can be configured globally, per transport and per protocol. Each configured limit is
+/// a token bucket shared by all substreams it applies to and a substream is throttled by all of
+/// the limits that apply to it.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimitConfig {
+    /// Limits applied to all substreams.
+    global: Limits,
+
+    /// Limits applied to substreams of a transport.
+    transports: HashMap<SupportedTransport, Limits>,
+
+    /// Limits applied to substreams of a protocol.
+    protocols: HashMap<ProtocolName, Limits>,
+}
+
+impl RateLimitConfig {
+    /// Limit the traffic of all substreams.
+    pub fn with_global_limit(
+        mut self,
+        inbound: Option<RateLimit>,
+        outbound: Option<RateLimit>,
+    ) -> Self {
+        self.global = Limits { inbound, outbound };
+        self
+    }
+
+    /// Limit the traffic of substreams opened over `transport`.
+    pub fn with_transport_limit(
+        mut self,
+        transport: SupportedTransport,
+        inbound: Option<RateLimit>,
+        outbound: Option<RateLimit>,
+    ) -> Self {
+        self.transports.insert(transport, Limits { inbound, outbound });
+        self
+    }
+
+    /// Limit the traffic of substreams of `protocol`.
+    ///
+    /// Substreams negotiated using one of the fallback names of the protocol are also limited.
+    pub fn with_protocol_limit(
+        mut self,
+        protocol: ProtocolName,
+        inbound: Option<RateLimit>,
+        outbound: Option<RateLimit>,
+    ) -> Self {
+        self.protocols.insert(protocol, Limits { inbound, outbound });
+        self
+    }
+}
+
+/// Token bucket.
+///
+/// Transfers are allowed to take the bucket into debt so a transfer never has to be split. Once in
+/// debt, transfers are delayed until the bucket has been refilled.
+#[derive(Debug)]
+struct TokenBucket {
+    /// Rate limit.
+    limit: RateLimit,
+
+    /// Available tokens and the time they were last updated.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// Create new [`TokenBucket`] which starts full.
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new((limit.burst as f64, Instant::now())),
+        }
+    }
+
+    /// Refill the bucket and get the number of available tokens.
+    fn refill(&self, state: &mut (f64, Instant)) -> f64 {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(state.1).as_secs_f64();
+
+        state.0 =
+            (state.0 + elapsed * self.limit.bytes_per_second as f64).min(self.limit.burst as f64);
+        state.1 = now;
+        state.0
+    }
+
+    /// Get the time until tokens are available, if the bucket is empty.
+    fn delay(&self) -> Option<Duration> {
+        let mut state = self.state.lock();
+
+        match self.refill(&mut state) {
+            tokens if tokens > 0f64 => None,
+            tokens => Some(Duration::from_secs_f64(
+                (1f64 - tokens) / self.limit.bytes_per_second as f64,
+            )),
+        }
+    }
+
+    /// Consume `bytes` tokens.
+    fn consume(&self, bytes: usize) {
+        let mut state = self.state.lock();
+
+        self.refill(&mut state);
+        state.0 -= bytes as f64;
+    }
+}
+
+/// Inbound and outbound token buckets.
+#[derive(Debug, Default)]
+struct Buckets {
+    /// Inbound bucket.
+    inbound: Option<Arc<TokenBucket>>,
+
+    /// Outbound bucket.
+    outbound: Option<Arc<TokenBucket>>,
+}
+
+impl From<Limits> for Buckets {
+    fn from(limits: Limits) -> Self {
+        Self {
+            inbound: limits.inbound.map(|limit| Arc::new(TokenBucket::new(limit))),
+            outbound: limits.outbound.map(|limit| Arc::new(TokenBucket::new(limit))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct InnerRateLimiter {
+    /// Global buckets.
+    global: Buckets,
+
+    /// Buckets of each transport.
+    transports: HashMap<SupportedTransport, Buckets>,
+
+    /// Buckets of each protocol.
+    protocols: HashMap<ProtocolName, Buckets>,
+}
+
+/// Rate limiter shared by all connections.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RateLimiter(Arc<InnerRateLimiter>);
+
+impl RateLimiter {
+    /// Create new [`RateLimiter`] from `config`.
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self(Arc::new(InnerRateLimiter {
+            global: config.global.into(),
+            transports: config
+                .transports
+                .into_iter()
+                .map(|(transport, limits)| (transport, limits.into()))
+                .collect(),
+            protocols: config
+                .protocols
+                .into_iter()
+                .map(|(protocol, limits)| (protocol, limits.into()))
+                .collect(),
+        }))
+    }
+
+    /// Create rate limiter for a substream of `protocol` opened over `transport`.
+    ///
+    /// Returns `None` if no limits apply to the substream.
+    pub(crate) fn substream(
+        &self,
+        transport: SupportedTransport,
+        protocol: &ProtocolName,
+    ) -> Option<SubstreamRateLimiter> {
+        let buckets = [
+            Some(&self.0.global),
+            self.0.transports.get(&transport),
+            self.0.protocols.get(protocol),
+        ];
+        let inbound = buckets
+            .iter()
+            .flatten()
+            .filter_map(|buckets| buckets.inbound.clone())
+            .collect::<Vec<_>>();
+        let outbound = buckets
+            .iter()
+            .flatten()
+            .filter_map(|buckets| buckets.outbound.clone())
+            .collect::<Vec<_>>();
+
+        if inbound.is_empty() && outbound.is_empty() {
+            return None;
+        }
+
+        Some(SubstreamRateLimiter {
+            inbound: Throttle::new(inbound),
+            outbound: Throttle::new(outbound),
+        })
+    }
+}
+
+/// Token buckets of one direction and the timer for waiting until they have been refilled.
+struct Throttle {
+    /// Token buckets.
+    buckets: Vec<Arc<TokenBucket>>,
+
+    /// Timer which expires once the buckets have been refilled.
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Throttle {
+    fn new(buckets: Vec<Arc<TokenBucket>>) -> Self {
+        Self {
+            buckets,
+            delay: None,
+        }
+    }
+
+    /// Poll until all buckets have tokens available.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(delay) = &mut self.delay {
+                futures::ready!(delay.poll_unpin(cx));
+                self.delay = None;
+            }
+
+            match self.buckets.iter().filter_map(|bucket| bucket.delay()).max() {
+                None => return Poll::Ready(()),
+                Some(delay) => self.delay = Some(Box::pin(tokio::time::sleep(delay))),
+            }
+        }
+    }
+
+    /// Consume `bytes` tokens from all buckets.
+    fn consume(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+
+        for bucket in &self.buckets {
+            bucket.consume(bytes);
+        }
+    }
+}
+
+/// Rate limiter of a single substream.
+pub(crate) struct SubstreamRateLimiter {
+    /// Inbound throttle.
+    inbound: Throttle,
+
+    /// Outbound throttle.
+    outbound: Throttle,
+}
+
+impl SubstreamRateLimiter {
+    /// Poll until data can be read from the substream.
+    pub(crate) fn poll_inbound(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inbound.poll_ready(cx)
+    }
+
+    /// Poll until data can be written to the substream.
+    pub(crate) fn poll_outbound(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.outbound.poll_ready(cx)
+    }
+
+    /// Record `bytes` read from the substream.
+    pub(crate) fn on_inbound(&self, bytes: usize) {
+        self.inbound.consume(bytes);
+    }
+
+    /// Record `bytes` written to the substream.
+    pub(crate) fn on_outbound(&self, bytes: usize) {
+        self.outbound.consume(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_limits() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+        assert!(limiter.substream(SupportedTransport::Tcp, &ProtocolName::from("/1")).is_none());
+
+        let limiter = RateLimiter::new(RateLimitConfig::default().with_protocol_limit(
+            ProtocolName::from("/2"),
+            Some(RateLimit::new(1024)),
+            None,
+        ));
+        assert!(limiter.substream(SupportedTransport::Tcp, &ProtocolName::from("/1")).is_none());
+        assert!(limiter.substream(SupportedTransport::Tcp, &ProtocolName::from("/2")).is_some());
+    }
+
+    #[tokio::test]
+    async fn substream_is_throttled() {
+        let limiter = RateLimiter::new(
+            RateLimitConfig::default()
+                .with_transport_limit(
+                    SupportedTransport::Tcp,
+                    None,
+                    Some(RateLimit::new(1000).with_burst(100)),
+                )
+                .with_global_limit(Some(RateLimit::new(1000)), None),
+        );
+        let mut substream =
+            limiter.substream(SupportedTransport::Tcp, &ProtocolName::from("/1")).unwrap();
+
+        // tokens are available until the burst has been consumed
+        futures::future::poll_fn(|cx| substream.poll_outbound(cx)).await;
+        substream.on_outbound(150);
+
+        let started = Instant::now();
+        futures::future::poll_fn(|cx| substream.poll_outbound(cx)).await;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        // inbound is limited only by the global limit
+        futures::future::poll_fn(|cx| substream.poll_inbound(cx)).await;
+        substream.on_inbound(500);
+        futures::future::poll_fn(|cx| substream.poll_inbound(cx)).await;
+    }
+
+    #[tokio::test]
+    async fn buckets_are_shared_between_substreams() {
+        let limiter = RateLimiter::new(RateLimitConfig::default().with_protocol_limit(
+            ProtocolName::from("/1"),
+            Some(RateLimit::new(1000).with_burst(100)),
+            None,
+        ));
+        let substream1 =
+            limiter.substream(SupportedTransport::Tcp, &ProtocolName::from("/1")).unwrap();
+        let mut substream2 =
+            limiter.substream(SupportedTransport::Quic, &ProtocolName::from("/1")).unwrap();
+
+        substream1.on_inbound(150);
+
+        let started = Instant::now();
+        futures::future::poll_fn(|cx| substream2.poll_inbound(cx)).await;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Shutdown coordination of the spawned protocol tasks.
+
+use tokio::sync::{mpsc, watch};
+
+use std::{future::Future, pin::Pin};
+
+/// Shutdown phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    /// Node is running.
+    Running,
+
+    /// Protocols with in-flight exchanges should finish them and exit.
+    Draining,
+
+    /// All protocols must exit.
+    Stopped,
+}
+
+/// Signal which allows a spawned task to observe the shutdown.
+///
+/// A task is considered to have exited once all clones of its signal have been dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct ShutdownSignal {
+    /// RX channel for receiving the shutdown phase.
+    rx: watch::Receiver<Phase>,
+
+    /// Guard which keeps the task accounted for until it exits.
+    _task: Option<mpsc::Sender<()>>,
+
+    /// Guard which keeps the draining task accounted for until it exits.
+    _drain: Option<mpsc::Sender<()>>,
+}
+
+impl Default for ShutdownSignal {
+    /// Create signal which never fires.
+    fn default() -> Self {
+        let (_tx, rx) = watch::channel(Phase::Running);
+
+        Self {
+            rx,
+            _task: None,
+            _drain: None,
+        }
+    }
+}
+
+impl ShutdownSignal {
+    /// Wait until the shutdown has reached `phase`.
+    async fn wait_for(&mut self, phase: Phase) {
+        loop {
+            if *self.rx.borrow_and_update() >= phase {
+                return;
+            }
+
+            if self.rx.changed().await.is_err() {
+                return futures::future::pending().await;
+            }
+        }
+    }
+
+    /// Wait until the task should finish its in-flight exchanges and exit.
+    pub(crate) async fn draining(&mut self) {
+        self.wait_for(Phase::Draining).await
+    }
+
+    /// Wait until the task must exit.
+    pub(crate) async fn stopped(&mut self) {
+        self.wait_for(Phase::Stopped).await
+    }
+}
+
+/// Set of tasks whose exit is awaited.
+#[derive(Debug)]
+struct TaskSet {
+    /// TX channel given to each task, dropped once the set is awaited.
+    tx: Option<mpsc::Sender<()>>,
+
+    /// RX channel which is closed once all tasks have exited.
+    rx: mpsc::Receiver<()>,
+}
+
+impl TaskSet {
+    fn new() -> Self {
+        let (tx, rx) = mpsc::channel(1);
+
+        Self { tx: Some(tx), rx }
+    }
+
+    /// Wait until all tasks of the set have exited.
+    async fn wait(&mut self) {
+        self.tx.take();
+        while self.rx.recv().await.is_some() {}
+    }
+}
+
+/// Coordinator of the shutdown of spawned protocol tasks.
+#[derive(Debug)]
+pub(crate) struct Shutdown {
+    /// TX channel for broadcasting the shutdown phase.
+    tx: watch::Sender<Phase>,
+
+    /// All spawned tasks.
+    tasks: TaskSet,
+
+    /// Tasks which drain their in-flight exchanges before exiting.
+    draining: TaskSet,
+}
+
+impl Shutdown {
+    /// Create new [`Shutdown`].
+    pub(crate) fn new() -> Self {
+        Self {
+            tx: watch::channel(Phase::Running).0,
+            tasks: TaskSet::new(),
+            draining: TaskSet::new(),
+        }
+    }
+
+    /// Create signal for a task which finishes its in-flight exchanges before exiting.
+    pub(crate) fn draining_signal(&self) -> ShutdownSignal {
+        ShutdownSignal {
+            rx: self.tx.subscribe(),
+            _task: self.tasks.tx.clone(),
+            _drain: self.draining.tx.clone(),
+        }
+    }
+
+    /// Wrap `future` so that it's accounted for and stops executing once the shutdown stops
+    /// all tasks.
+    pub(crate) fn track<F>(&self, future: F) -> Pin<Box<dyn Future<Output = ()> + Send>>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut signal = ShutdownSignal {
+            rx: self.tx.subscribe(),
+            _task: self.tasks.tx.clone(),
+            _drain: None,
+        };
+
+        Box::pin(async move {
+            tokio::select! {
+                _ = future => {}
+                _ = signal.stopped() => {}
+            }
+        })
+    }
+
+    /// Signal tasks to finish their in-flight exchanges and wait until they have exited.
+    pub(crate) async fn drain(&mut self) {
+        self.tx.send_replace(Phase::Draining);
+        self.draining.wait().await;
+    }
+
+    /// Signal all tasks to exit and wait until they have exited.
+    pub(crate) async fn stop(&mut self) {
+        self.tx.send_replace(Phase::Stopped);
+        self.tasks.wait().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn tracked_tasks_are_stopped() {
+        let mut shutdown = Shutdown::new();
+        let (tx, mut rx) = mpsc::channel::<()>(1);
+
+        tokio::spawn(shutdown.track(async move {
+            let _tx = tx;
+            futures::future::pending::<()>().await
+        }));
+
+        tokio::time::timeout(Duration::from_secs(5), shutdown.stop()).await.unwrap();
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn draining_tasks_exit_on_their_own() {
+        let mut shutdown = Shutdown::new();
+        let mut signal = shutdown.draining_signal();
+
+        tokio::spawn(shutdown.track(async move {
+            signal.draining().await;
+        }));
+
+        tokio::time::timeout(Duration::from_secs(5), shutdown.drain()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), shutdown.stop()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn default_signal_never_fires() {
+        let mut signal = ShutdownSignal::default();
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), signal.draining())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -24,7 +24,8 @@
 use crate::{
     codec::ProtocolCodec,
     error::{Error, SubstreamError},
-    transport::{quic, tcp, webrtc, websocket},
+    rate_limit::SubstreamRateLimiter,
+    transport::{memory, quic, tcp, webrtc, websocket},
     types::SubstreamId,
     PeerId,
 };
@@ -53,6 +54,7 @@ macro_rules! poll_flush {
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_flush($cx),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_flush($cx),
             SubstreamType::WebRtc(substream) => Pin::new(substream).poll_flush($cx),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_flush($cx),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
@@ -66,6 +68,7 @@ macro_rules! poll_write {
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_write($cx, $frame),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_write($cx, $frame),
             SubstreamType::WebRtc(substream) => Pin::new(substream).poll_write($cx, $frame),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_write($cx, $frame),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
@@ -79,12 +82,31 @@ macro_rules! poll_read {
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_read($cx, $buffer),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_read($cx, $buffer),
             SubstreamType::WebRtc(substream) => Pin::new(substream).poll_read($cx, $buffer),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_read($cx, $buffer),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
     }};
 }
 
+macro_rules! poll_read_limited {
+    ($this:expr, $cx:ident, $buffer:expr) => {{
+        if let Some(rate_limiter) = &mut $this.rate_limiter {
+            futures::ready!(rate_limiter.poll_inbound($cx));
+        }
+
+        let buffer = $buffer;
+        let filled = buffer.filled().len();
+        let result = futures::ready!(poll_read!(&mut $this.substream, $cx, buffer));
+
+        if let Some(rate_limiter) = &$this.rate_limiter {
+            rate_limiter.on_inbound(buffer.filled().len() - filled);
+        }
+
+        Poll::Ready(result)
+    }};
+}
+
 macro_rules! poll_shutdown {
     ($substream:expr, $cx:ident) => {{
         match $substream {
@@ -92,6 +114,7 @@ macro_rules! poll_shutdown {
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_shutdown($cx),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_shutdown($cx),
             SubstreamType::WebRtc(substream) => Pin::new(substream).poll_shutdown($cx),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_shutdown($cx),
             #[cfg(test)]
             SubstreamType::Mock(substream) => {
                 let _ = Pin::new(substream).poll_close($cx);
@@ -153,6 +176,7 @@ enum SubstreamType {
     WebSocket(websocket::Substream),
     Quic(quic::Substream),
     WebRtc(webrtc::Substream),
+    Memory(memory::Substream),
     #[cfg(test)]
     Mock(Box<dyn crate::mock::substream::Substream>),
 }
@@ -164,6 +188,7 @@ impl fmt::Debug for SubstreamType {
             Self::WebSocket(_) => write!(f, "WebSocket"),
             Self::Quic(_) => write!(f, "Quic"),
             Self::WebRtc(_) => write!(f, "WebRtc"),
+            Self::Memory(_) => write!(f, "Memory"),
             #[cfg(test)]
             Self::Mock(_) => write!(f, "Mock"),
         }
@@ -194,6 +219,9 @@ pub struct Substream {
     /// Protocol codec.
     codec: ProtocolCodec,
 
+    /// Rate limiter, if the substream is throttled.
+    rate_limiter: Option<SubstreamRateLimiter>,
+
     pending_out_frames: VecDeque<Bytes>,
     pending_out_bytes: usize,
     pending_out_frame: Option<Bytes>,
@@ -224,11 +252,13 @@ impl Substream {
         substream_id: SubstreamId,
         substream: SubstreamType,
         codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
     ) -> Self {
         Self {
             peer,
             substream,
             codec,
+            rate_limiter,
             substream_id,
             read_buffer: BytesMut::zeroed(1024),
             offset: 0usize,
@@ -247,10 +277,17 @@ impl Substream {
         substream_id: SubstreamId,
         substream: tcp::Substream,
         codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
     ) -> Self {
         tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for tcp");
 
-        Self::new(peer, substream_id, SubstreamType::Tcp(substream), codec)
+        Self::new(
+            peer,
+            substream_id,
+            SubstreamType::Tcp(substream),
+            codec,
+            rate_limiter,
+        )
     }
 
     /// Create new [`Substream`] for WebSocket.
@@ -259,6 +296,7 @@ impl Substream {
         substream_id: SubstreamId,
         substream: websocket::Substream,
         codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
     ) -> Self {
         tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for websocket");
 
@@ -267,6 +305,7 @@ impl Substream {
             substream_id,
             SubstreamType::WebSocket(substream),
             codec,
+            rate_limiter,
         )
     }
 
@@ -276,10 +315,17 @@ impl Substream {
         substream_id: SubstreamId,
         substream: quic::Substream,
         codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
     ) -> Self {
         tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for quic");
 
-        Self::new(peer, substream_id, SubstreamType::Quic(substream), codec)
+        Self::new(
+            peer,
+            substream_id,
+            SubstreamType::Quic(substream),
+            codec,
+            rate_limiter,
+        )
     }
 
     /// Create new [`Substream`] for WebRTC.
@@ -288,10 +334,36 @@ impl Substream {
         substream_id: SubstreamId,
         substream: webrtc::Substream,
         codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
     ) -> Self {
         tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for webrtc");
 
-        Self::new(peer, substream_id, SubstreamType::WebRtc(substream), codec)
+        Self::new(
+            peer,
+            substream_id,
+            SubstreamType::WebRtc(substream),
+            codec,
+            rate_limiter,
+        )
+    }
+
+    /// Create new [`Substream`] for the memory transport.
+    pub(crate) fn new_memory(
+        peer: PeerId,
+        substream_id: SubstreamId,
+        substream: memory::Substream,
+        codec: ProtocolCodec,
+        rate_limiter: Option<SubstreamRateLimiter>,
+    ) -> Self {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for memory");
+
+        Self::new(
+            peer,
+            substream_id,
+            SubstreamType::Memory(substream),
+            codec,
+            rate_limiter,
+        )
     }
 
     /// Create new [`Substream`] for mocking.
@@ -308,6 +380,7 @@ impl Substream {
             substream_id,
             SubstreamType::Mock(substream),
             ProtocolCodec::Unspecified,
+            None,
         )
     }
 
@@ -318,6 +391,7 @@ impl Substream {
             SubstreamType::WebSocket(mut substream) => substream.shutdown().await,
             SubstreamType::Quic(mut substream) => substream.shutdown().await,
             SubstreamType::WebRtc(mut substream) => substream.shutdown().await,
+            SubstreamType::Memory(mut substream) => substream.shutdown().await,
             #[cfg(test)]
             SubstreamType::Mock(mut substream) => {
                 let _ = futures::SinkExt::close(&mut substream).await;
@@ -364,10 +438,38 @@ impl Substream {
             "send framed"
         );
 
-        match &mut self.substream {
+        if let Some(rate_limiter) = &mut self.rate_limiter {
+            futures::future::poll_fn(|cx| rate_limiter.poll_outbound(cx)).await;
+        }
+        let frame_len = bytes.len();
+
+        let result = match &mut self.substream {
             #[cfg(test)]
             SubstreamType::Mock(ref mut substream) =>
                 futures::SinkExt::send(substream, bytes).await,
+            SubstreamType::Memory(ref mut substream) => match self.codec {
+                ProtocolCodec::Unspecified => panic!("codec is unspecified"),
+                ProtocolCodec::Identity(payload_size) =>
+                    Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::UnsignedVarint(max_size) => {
+                    check_size!(max_size, bytes.len());
+
+                    let mut buffer = [0u8; 10];
+                    let len = unsigned_varint::encode::usize(bytes.len(), &mut buffer);
+                    let mut offset = 0;
+
+                    while offset < len.len() {
+                        offset += substream.write(&len[offset..]).await?;
+                    }
+
+                    while bytes.has_remaining() {
+                        let nwritten = substream.write(&bytes).await?;
+                        bytes.advance(nwritten);
+                    }
+
+                    substream.flush().await.map_err(From::from)
+                }
+            },
             SubstreamType::Tcp(ref mut substream) => match self.codec {
                 ProtocolCodec::Unspecified => panic!("codec is unspecified"),
                 ProtocolCodec::Identity(payload_size) =>
@@ -451,7 +553,13 @@ impl Substream {
                     substream.flush().await.map_err(From::from)
                 }
             },
+        };
+
+        if let (Ok(()), Some(rate_limiter)) = (&result, &self.rate_limiter) {
+            rate_limiter.on_outbound(frame_len);
         }
+
+        result
     }
 }
 
@@ -461,7 +569,8 @@ impl tokio::io::AsyncRead for Substream {
         cx: &mut Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
-        poll_read!(&mut self.substream, cx, buf)
+        let this = &mut *self;
+        poll_read_limited!(this, cx, buf)
     }
 }
 
@@ -471,7 +580,19 @@ impl tokio::io::AsyncWrite for Substream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, std::io::Error>> {
-        poll_write!(&mut self.substream, cx, buf)
+        let this = &mut *self;
+
+        if let Some(rate_limiter) = &mut this.rate_limiter {
+            futures::ready!(rate_limiter.poll_outbound(cx));
+        }
+
+        let nwritten = futures::ready!(poll_write!(&mut this.substream, cx, buf))?;
+
+        if let Some(rate_limiter) = &this.rate_limiter {
+            rate_limiter.on_outbound(nwritten);
+        }
+
+        Poll::Ready(Ok(nwritten))
     }
 
     fn poll_flush(
@@ -529,7 +650,7 @@ impl Stream for Substream {
                     let mut read_buf =
                         ReadBuf::new(&mut this.read_buffer[this.offset..payload_size]);
 
-                    match futures::ready!(poll_read!(&mut this.substream, cx, &mut read_buf)) {
+                    match futures::ready!(poll_read_limited!(this, cx, &mut read_buf)) {
                         Ok(_) => {
                             let nread = read_buf.filled().len();
                             if nread == 0 {
@@ -570,11 +691,7 @@ impl Stream for Substream {
                                     ReadBuf::new(&mut this.read_buffer[this.offset..]);
                                 this.current_frame_size = Some(frame_size);
 
-                                match futures::ready!(poll_read!(
-                                    &mut this.substream,
-                                    cx,
-                                    &mut read_buf
-                                )) {
+                                match futures::ready!(poll_read_limited!(this, cx, &mut read_buf)) {
                                     Err(_error) => return Poll::Ready(None),
                                     Ok(_) => {
                                         let nread = match read_buf.filled().len() {
@@ -604,11 +721,7 @@ impl Stream for Substream {
                                 let mut read_buf =
                                     ReadBuf::new(&mut this.size_vec[this.offset..this.offset + 1]);
 
-                                match futures::ready!(poll_read!(
-                                    &mut this.substream,
-                                    cx,
-                                    &mut read_buf
-                                )) {
+                                match futures::ready!(poll_read_limited!(this, cx, &mut read_buf)) {
                                     Err(_error) => return Poll::Ready(None),
                                     Ok(_) => {
                                         if read_buf.filled().is_empty() {
@@ -708,6 +821,16 @@ impl Sink<Bytes> for Substream {
         delegate_poll_flush!(&mut self.substream, cx);
 
         loop {
+            if self.pending_out_frame.is_none() && self.pending_out_frames.is_empty() {
+                break;
+            }
+
+            if let Some(rate_limiter) = &mut self.rate_limiter {
+                if rate_limiter.poll_outbound(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+
             let mut pending_frame = match self.pending_out_frame.take() {
                 Some(frame) => frame,
                 None => match self.pending_out_frames.pop_front() {
@@ -723,6 +846,9 @@ impl Sink<Bytes> for Substream {
                     break;
                 }
                 Poll::Ready(Ok(nwritten)) => {
+                    if let Some(rate_limiter) = &self.rate_limiter {
+                        rate_limiter.on_outbound(nwritten);
+                    }
                     pending_frame.advance(nwritten);
 
                     if !pending_frame.is_empty() {
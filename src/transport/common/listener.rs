@@ -22,11 +22,14 @@
 
 use crate::{error::AddressError, Error, PeerId};
 
-use futures::Stream;
+use futures::{Future, Stream};
 use multiaddr::{Multiaddr, Protocol};
 use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
 use socket2::{Domain, Socket, Type};
-use tokio::net::{TcpListener as TokioTcpListener, TcpStream};
+use tokio::{
+    net::{TcpListener as TokioTcpListener, TcpStream},
+    time::Sleep,
+};
 use trust_dns_resolver::{
     config::{ResolverConfig, ResolverOpts},
     TokioAsyncResolver,
@@ -38,11 +41,21 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::transport::listener";
 
+/// Initial delay before a listener accepts connections again after failing to accept one.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Maximum delay before a listener accepts connections again after failing to accept one.
+///
+/// Persistent errors, such as running out of file descriptors, double the delay on every failure
+/// until this limit is reached.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(5);
+
 /// Address type.
 #[derive(Debug)]
 pub enum AddressType {
@@ -132,6 +145,25 @@ pub enum DialAddresses {
 }
 
 impl DialAddresses {
+    /// Combine the dial addresses of two listeners.
+    pub fn merge(self, other: DialAddresses) -> DialAddresses {
+        match (self, other) {
+            (
+                DialAddresses::Reuse { listen_addresses },
+                DialAddresses::Reuse {
+                    listen_addresses: other,
+                },
+            ) => DialAddresses::Reuse {
+                listen_addresses: Arc::new(
+                    listen_addresses.iter().chain(other.iter()).copied().collect(),
+                ),
+            },
+            (reuse @ DialAddresses::Reuse { .. }, DialAddresses::NoReuse)
+            | (DialAddresses::NoReuse, reuse @ DialAddresses::Reuse { .. }) => reuse,
+            (DialAddresses::NoReuse, DialAddresses::NoReuse) => DialAddresses::NoReuse,
+        }
+    }
+
     /// Get local dial address for an outbound connection.
     pub fn local_dial_address(&self, remote_address: &IpAddr) -> Result<Option<SocketAddr>, ()> {
         match self {
@@ -161,12 +193,30 @@ impl DialAddresses {
     }
 }
 
+/// Bound listening socket.
+struct Listener {
+    /// Listening socket.
+    listener: TokioTcpListener,
+    /// Local address the socket is bound to.
+    local_address: SocketAddr,
+    /// Local addresses the socket is reachable at.
+    listen_addresses: Vec<SocketAddr>,
+    /// Delay before accepting again after an accept error.
+    backoff: Option<Pin<Box<Sleep>>>,
+    /// Delay used for the next accept error.
+    next_backoff: Duration,
+}
+
 /// Socket listening to zero or more addresses.
 pub struct SocketListener {
-    /// Listeners.
-    listeners: Vec<TokioTcpListener>,
+    /// Bound listeners.
+    listeners: Vec<Listener>,
     /// The index in the listeners from which the polling is resumed.
     poll_index: usize,
+    /// Whether `SO_REUSEPORT` is set for the listening sockets.
+    reuse_port: bool,
+    /// Whether `TCP_NODELAY` is set for the listening sockets.
+    nodelay: bool,
 }
 
 /// Trait to convert between `Multiaddr` and `SocketAddr`.
@@ -226,6 +276,24 @@ impl GetSocketAddr for WebSocketAddress {
     }
 }
 
+/// Secure WebSocket helper to convert between `Multiaddr` and `SocketAddr`.
+pub struct SecureWebSocketAddress;
+
+impl GetSocketAddr for SecureWebSocketAddress {
+    fn multiaddr_to_socket_address(
+        address: &Multiaddr,
+    ) -> crate::Result<(AddressType, Option<PeerId>)> {
+        multiaddr_to_socket_address(address, SocketListenerType::WebSocket)
+    }
+
+    fn socket_address_to_multiaddr(address: &SocketAddr) -> Multiaddr {
+        Multiaddr::empty()
+            .with(Protocol::from(address.ip()))
+            .with(Protocol::Tcp(address.port()))
+            .with(Protocol::Wss(std::borrow::Cow::Borrowed("/")))
+    }
+}
+
 impl SocketListener {
     /// Create new [`SocketListener`]
     pub fn new<T: GetSocketAddr>(
@@ -233,109 +301,170 @@ impl SocketListener {
         reuse_port: bool,
         nodelay: bool,
     ) -> (Self, Vec<Multiaddr>, DialAddresses) {
-        let (listeners, listen_addresses): (_, Vec<Vec<_>>) = addresses
+        let listeners = addresses
             .into_iter()
             .filter_map(|address| {
-                let address = match T::multiaddr_to_socket_address(&address).ok()?.0 {
-                    AddressType::Dns { address, port, .. } => {
-                        tracing::debug!(
-                            target: LOG_TARGET,
-                            ?address,
-                            ?port,
-                            "dns not supported as bind address"
-                        );
-
-                        return None;
-                    }
-                    AddressType::Socket(address) => address,
-                };
-
-                let socket = if address.is_ipv4() {
-                    Socket::new(Domain::IPV4, Type::STREAM, Some(socket2::Protocol::TCP)).ok()?
-                } else {
-                    let socket =
-                        Socket::new(Domain::IPV6, Type::STREAM, Some(socket2::Protocol::TCP))
-                            .ok()?;
-                    socket.set_only_v6(true).ok()?;
-                    socket
-                };
-
-                socket.set_nodelay(nodelay).ok()?;
-                socket.set_nonblocking(true).ok()?;
-                socket.set_reuse_address(true).ok()?;
-                #[cfg(unix)]
-                if reuse_port {
-                    socket.set_reuse_port(true).ok()?;
-                }
-                socket.bind(&address.into()).ok()?;
-                socket.listen(1024).ok()?;
-
-                let socket: std::net::TcpListener = socket.into();
-                let listener = TokioTcpListener::from_std(socket).ok()?;
-                let local_address = listener.local_addr().ok()?;
-
-                let listen_addresses = if address.ip().is_unspecified() {
-                    match NetworkInterface::show() {
-                        Ok(ifaces) => ifaces
-                            .into_iter()
-                            .flat_map(|record| {
-                                record.addr.into_iter().filter_map(|iface_address| {
-                                    match (iface_address, address.is_ipv4()) {
-                                        (Addr::V4(inner), true) => Some(SocketAddr::new(
-                                            IpAddr::V4(inner.ip),
-                                            local_address.port(),
-                                        )),
-                                        (Addr::V6(inner), false) =>
-                                            match inner.ip.segments().first() {
-                                                Some(0xfe80) => None,
-                                                _ => Some(SocketAddr::new(
-                                                    IpAddr::V6(inner.ip),
-                                                    local_address.port(),
-                                                )),
-                                            },
-                                        _ => None,
-                                    }
-                                })
-                            })
-                            .collect(),
-                        Err(error) => {
-                            tracing::warn!(
-                                target: LOG_TARGET,
-                                ?error,
-                                "failed to fetch network interfaces",
-                            );
-
-                            return None;
-                        }
-                    }
-                } else {
-                    vec![local_address]
-                };
+                let address = Self::bind_address::<T>(&address).ok()?;
 
-                Some((listener, listen_addresses))
+                Self::bind(address, reuse_port, nodelay).ok()
             })
-            .unzip();
+            .collect::<Vec<_>>();
+
+        let listen_multi_addresses = listeners
+            .iter()
+            .flat_map(|listener| {
+                listener.listen_addresses.iter().map(T::socket_address_to_multiaddr)
+            })
+            .collect();
+
+        let listener = Self {
+            listeners,
+            poll_index: 0,
+            reuse_port,
+            nodelay,
+        };
+        let dial_addresses = listener.dial_addresses();
+
+        (listener, listen_multi_addresses, dial_addresses)
+    }
 
-        let listen_addresses = listen_addresses.into_iter().flatten().collect::<Vec<_>>();
+    /// Start listening on `address`.
+    ///
+    /// Returns the addresses the new listener is reachable at.
+    pub fn listen_on<T: GetSocketAddr>(
+        &mut self,
+        address: &Multiaddr,
+    ) -> crate::Result<Vec<Multiaddr>> {
+        let address = Self::bind_address::<T>(address)?;
+        let listener = Self::bind(address, self.reuse_port, self.nodelay)?;
         let listen_multi_addresses =
-            listen_addresses.iter().map(T::socket_address_to_multiaddr).collect();
+            listener.listen_addresses.iter().map(T::socket_address_to_multiaddr).collect();
+
+        self.listeners.push(listener);
+
+        Ok(listen_multi_addresses)
+    }
 
-        let dial_addresses = if reuse_port {
+    /// Stop the listener which is either bound to or reachable at `address`.
+    ///
+    /// Returns the addresses the removed listener was reachable at or `None` if no listener
+    /// matched `address`.
+    pub fn remove_listener<T: GetSocketAddr>(
+        &mut self,
+        address: &Multiaddr,
+    ) -> Option<Vec<Multiaddr>> {
+        let address = Self::bind_address::<T>(address).ok()?;
+        let index = self.listeners.iter().position(|listener| {
+            listener.listen_addresses.contains(&address) || listener.local_address == address
+        })?;
+        let listener = self.listeners.remove(index);
+
+        tracing::debug!(target: LOG_TARGET, ?address, "listener removed");
+
+        Some(listener.listen_addresses.iter().map(T::socket_address_to_multiaddr).collect())
+    }
+
+    /// Get local addresses to use for outbound connections.
+    pub fn dial_addresses(&self) -> DialAddresses {
+        if self.reuse_port {
             DialAddresses::Reuse {
-                listen_addresses: Arc::new(listen_addresses),
+                listen_addresses: Arc::new(
+                    self.listeners
+                        .iter()
+                        .flat_map(|listener| listener.listen_addresses.iter().copied())
+                        .collect(),
+                ),
             }
         } else {
             DialAddresses::NoReuse
+        }
+    }
+
+    /// Extract the socket address to bind to from `address`.
+    fn bind_address<T: GetSocketAddr>(address: &Multiaddr) -> crate::Result<SocketAddr> {
+        match T::multiaddr_to_socket_address(address)?.0 {
+            AddressType::Dns { address, port, .. } => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?address,
+                    ?port,
+                    "dns not supported as bind address"
+                );
+
+                Err(Error::AddressError(AddressError::InvalidProtocol))
+            }
+            AddressType::Socket(address) => Ok(address),
+        }
+    }
+
+    /// Bind a listening socket to `address`.
+    fn bind(address: SocketAddr, reuse_port: bool, nodelay: bool) -> io::Result<Listener> {
+        let socket = if address.is_ipv4() {
+            Socket::new(Domain::IPV4, Type::STREAM, Some(socket2::Protocol::TCP))?
+        } else {
+            let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(socket2::Protocol::TCP))?;
+            socket.set_only_v6(true)?;
+            socket
         };
 
-        (
-            Self {
-                listeners,
-                poll_index: 0,
-            },
-            listen_multi_addresses,
-            dial_addresses,
-        )
+        socket.set_nodelay(nodelay)?;
+        socket.set_nonblocking(true)?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        if reuse_port {
+            socket.set_reuse_port(true)?;
+        }
+        socket.bind(&address.into())?;
+        socket.listen(1024)?;
+
+        let socket: std::net::TcpListener = socket.into();
+        let listener = TokioTcpListener::from_std(socket)?;
+        let local_address = listener.local_addr()?;
+
+        let listen_addresses = if address.ip().is_unspecified() {
+            match NetworkInterface::show() {
+                Ok(ifaces) => ifaces
+                    .into_iter()
+                    .flat_map(|record| {
+                        record.addr.into_iter().filter_map(|iface_address| {
+                            match (iface_address, address.is_ipv4()) {
+                                (Addr::V4(inner), true) => Some(SocketAddr::new(
+                                    IpAddr::V4(inner.ip),
+                                    local_address.port(),
+                                )),
+                                (Addr::V6(inner), false) => match inner.ip.segments().first() {
+                                    Some(0xfe80) => None,
+                                    _ => Some(SocketAddr::new(
+                                        IpAddr::V6(inner.ip),
+                                        local_address.port(),
+                                    )),
+                                },
+                                _ => None,
+                            }
+                        })
+                    })
+                    .collect(),
+                Err(error) => {
+                    tracing::warn!(
+                        target: LOG_TARGET,
+                        ?error,
+                        "failed to fetch network interfaces",
+                    );
+
+                    return Err(io::Error::other(error.to_string()));
+                }
+            }
+        } else {
+            vec![local_address]
+        };
+
+        Ok(Listener {
+            listener,
+            local_address,
+            listen_addresses,
+            backoff: None,
+            next_backoff: MIN_ACCEPT_BACKOFF,
+        })
     }
 }
 
@@ -413,15 +542,16 @@ fn multiaddr_to_socket_address(
     match ty {
         SocketListenerType::Tcp => (),
         SocketListenerType::WebSocket => {
-            // verify that `/ws`/`/wss` is part of the multi address
+            // verify that `/ws`/`/wss`/`/tls/ws` is part of the multi address
             match iter.next() {
                 Some(Protocol::Ws(_address)) => {}
                 Some(Protocol::Wss(_address)) => {}
+                Some(Protocol::Tls) if std::matches!(iter.next(), Some(Protocol::Ws(_))) => {}
                 protocol => {
                     tracing::error!(
                         target: LOG_TARGET,
                         ?protocol,
-                        "invalid protocol, expected `Ws` or `Wss`"
+                        "invalid protocol, expected `Ws`, `Wss` or `Tls/Ws`"
                     );
                     return Err(Error::AddressError(AddressError::InvalidProtocol));
                 }
@@ -446,7 +576,9 @@ fn multiaddr_to_socket_address(
 }
 
 impl Stream for SocketListener {
-    type Item = io::Result<(TcpStream, SocketAddr)>;
+    /// Accepted connection and its remote address or, if accepting failed, the local address of
+    /// the listener and the error.
+    type Item = Result<(TcpStream, SocketAddr), (SocketAddr, io::Error)>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         if self.listeners.is_empty() {
@@ -458,13 +590,34 @@ impl Stream for SocketListener {
             let current = (self.poll_index + index) % len;
             let listener = &mut self.listeners[current];
 
-            match listener.poll_accept(cx) {
+            if let Some(backoff) = &mut listener.backoff {
+                if backoff.as_mut().poll(cx).is_pending() {
+                    continue;
+                }
+                listener.backoff = None;
+            }
+
+            match listener.listener.poll_accept(cx) {
                 Poll::Pending => {}
                 Poll::Ready(Err(error)) => {
+                    let local_address = listener.local_address;
+
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?local_address,
+                        backoff = ?listener.next_backoff,
+                        "delay accepting connections",
+                    );
+
+                    listener.backoff = Some(Box::pin(tokio::time::sleep(listener.next_backoff)));
+                    listener.next_backoff =
+                        std::cmp::min(listener.next_backoff * 2, MAX_ACCEPT_BACKOFF);
+
                     self.poll_index = (self.poll_index + 1) % len;
-                    return Poll::Ready(Some(Err(error)));
+                    return Poll::Ready(Some(Err((local_address, error))));
                 }
                 Poll::Ready(Ok((stream, address))) => {
+                    listener.next_backoff = MIN_ACCEPT_BACKOFF;
                     self.poll_index = (self.poll_index + 1) % len;
                     return Poll::Ready(Some(Ok((stream, address))));
                 }
@@ -568,6 +721,23 @@ mod tests {
             SocketListenerType::WebSocket,
         )
         .is_err());
+        assert!(multiaddr_to_socket_address(
+            &"/ip4/127.0.0.1/tcp/8888/wss".parse().expect("valid multiaddress"),
+            SocketListenerType::WebSocket,
+        )
+        .is_ok());
+        assert!(multiaddr_to_socket_address(
+            &"/ip6/::1/tcp/8888/tls/ws/p2p/12D3KooWT2ouvz5uMmCvHJGzAGRHiqDts5hzXR7NdoQ27pGdzp9Q"
+                .parse()
+                .expect("valid multiaddress"),
+            SocketListenerType::WebSocket,
+        )
+        .is_ok());
+        assert!(multiaddr_to_socket_address(
+            &"/ip4/127.0.0.1/tcp/8888/tls".parse().expect("valid multiaddress"),
+            SocketListenerType::WebSocket,
+        )
+        .is_err());
         assert!(multiaddr_to_socket_address(
             &"/ip6/::1/tcp/8888/p2p/12D3KooWT2ouvz5uMmCvHJGzAGRHiqDts5hzXR7NdoQ27pGdzp9Q"
                 .parse()
@@ -726,6 +896,41 @@ mod tests {
         assert!(res1.is_ok() && res2.is_ok());
     }
 
+    #[tokio::test]
+    async fn add_and_remove_listener_tcp() {
+        let (mut listener, _, _) = SocketListener::new::<TcpAddress>(Vec::new(), true, false);
+
+        let listen_addresses = listener
+            .listen_on::<TcpAddress>(&"/ip4/127.0.0.1/tcp/0".parse().unwrap())
+            .unwrap();
+        assert_eq!(listen_addresses.len(), 1);
+
+        let Some(Protocol::Tcp(port)) = listen_addresses[0].iter().nth(1) else {
+            panic!("invalid address");
+        };
+
+        match listener.dial_addresses() {
+            DialAddresses::Reuse { listen_addresses } => assert_eq!(listen_addresses.len(), 1),
+            DialAddresses::NoReuse => panic!("expected `Reuse`"),
+        }
+
+        let (res1, res2) = tokio::join!(
+            listener.next(),
+            TcpStream::connect(format!("127.0.0.1:{port}"))
+        );
+        assert!(res1.unwrap().is_ok() && res2.is_ok());
+
+        assert_eq!(
+            listener.remove_listener::<TcpAddress>(&listen_addresses[0]),
+            Some(listen_addresses.clone()),
+        );
+        assert_eq!(
+            listener.remove_listener::<TcpAddress>(&listen_addresses[0]),
+            None,
+        );
+        assert!(TcpStream::connect(format!("127.0.0.1:{port}")).await.is_err());
+    }
+
     #[tokio::test]
     async fn local_dial_address() {
         let dial_addresses = DialAddresses::Reuse {
@@ -751,4 +956,36 @@ mod tests {
             ))),
         );
     }
+
+    #[test]
+    fn merge_dial_addresses() {
+        let first: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let second: SocketAddr = "[::1]:9999".parse().unwrap();
+
+        let merged = DialAddresses::Reuse {
+            listen_addresses: Arc::new(vec![first]),
+        }
+        .merge(DialAddresses::Reuse {
+            listen_addresses: Arc::new(vec![second]),
+        });
+
+        match merged {
+            DialAddresses::Reuse { listen_addresses } =>
+                assert_eq!(*listen_addresses, vec![first, second]),
+            DialAddresses::NoReuse => panic!("expected `Reuse`"),
+        }
+
+        match DialAddresses::NoReuse.merge(DialAddresses::Reuse {
+            listen_addresses: Arc::new(vec![second]),
+        }) {
+            DialAddresses::Reuse { listen_addresses } =>
+                assert_eq!(*listen_addresses, vec![second]),
+            DialAddresses::NoReuse => panic!("expected `Reuse`"),
+        }
+
+        assert!(std::matches!(
+            DialAddresses::NoReuse.merge(DialAddresses::NoReuse),
+            DialAddresses::NoReuse
+        ));
+    }
 }
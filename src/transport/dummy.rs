@@ -91,6 +91,14 @@ impl Transport for DummyTransport {
 
     /// Cancel opening connections.
     fn cancel(&mut self, _: ConnectionId) {}
+
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        Ok(vec![address])
+    }
+
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        Ok(vec![address.clone()])
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,363 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Peer bans and IP address filters.
+
+use crate::{transport::manager::limits::ConnectionRejectReason, PeerId};
+
+use multiaddr::{Multiaddr, Protocol};
+use parking_lot::RwLock;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Error returned when parsing [`IpNetwork`] fails.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IpNetworkError {
+    #[error("Invalid IP address: `{0}`")]
+    InvalidAddress(String),
+    #[error("Invalid prefix length: `{0}`")]
+    InvalidPrefix(String),
+}
+
+/// IP network in CIDR notation, e.g., `10.0.0.0/8` or `fe80::/10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpNetwork {
+    /// Network address.
+    address: IpAddr,
+
+    /// Prefix length.
+    prefix: u8,
+}
+
+impl IpNetwork {
+    /// Create new [`IpNetwork`].
+    ///
+    /// Returns an error if `prefix` is longer than the address.
+    pub fn new(address: IpAddr, prefix: u8) -> Result<Self, IpNetworkError> {
+        let max_prefix = match address {
+            IpAddr::V4(_) => 32u8,
+            IpAddr::V6(_) => 128u8,
+        };
+
+        if prefix > max_prefix {
+            return Err(IpNetworkError::InvalidPrefix(prefix.to_string()));
+        }
+
+        Ok(Self { address, prefix })
+    }
+
+    /// Get network address.
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// Get prefix length.
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Check if `address` belongs to the network.
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<IpAddr> for IpNetwork {
+    fn from(address: IpAddr) -> Self {
+        let prefix = match address {
+            IpAddr::V4(_) => 32u8,
+            IpAddr::V6(_) => 128u8,
+        };
+
+        Self { address, prefix }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = IpNetworkError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((address, prefix)) = value.split_once('/') else {
+            return IpAddr::from_str(value)
+                .map(From::from)
+                .map_err(|_| IpNetworkError::InvalidAddress(value.to_string()));
+        };
+
+        let address = IpAddr::from_str(address)
+            .map_err(|_| IpNetworkError::InvalidAddress(address.to_string()))?;
+        let prefix =
+            u8::from_str(prefix).map_err(|_| IpNetworkError::InvalidPrefix(prefix.to_string()))?;
+
+        Self::new(address, prefix)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// Extract IP address from `address`, if it has one.
+pub(crate) fn ip_address(address: &Multiaddr) -> Option<IpAddr> {
+    match address.iter().next() {
+        Some(Protocol::Ip4(address)) => Some(IpAddr::V4(address)),
+        Some(Protocol::Ip6(address)) => Some(IpAddr::V6(address)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct BanListInner {
+    /// Banned peers and the time their ban expires.
+    banned_peers: HashMap<PeerId, Instant>,
+
+    /// Networks that are allowed to connect.
+    ///
+    /// If empty, all networks that are not denied are allowed.
+    allowed_networks: Vec<IpNetwork>,
+
+    /// Networks that are denied.
+    denied_networks: Vec<IpNetwork>,
+}
+
+/// Banned peers and IP address filters.
+///
+/// Shared between [`TransportManager`](crate::transport::manager::TransportManager),
+/// [`TransportManagerHandle`](crate::transport::manager::TransportManagerHandle) and the
+/// installed transports.
+#[derive(Debug, Clone, Default)]
+pub struct BanList {
+    inner: Arc<RwLock<BanListInner>>,
+}
+
+impl BanList {
+    /// Ban `peer` for `duration`.
+    ///
+    /// If the peer was already banned, the ban is replaced.
+    pub(crate) fn ban_peer(&self, peer: PeerId, duration: Duration) {
+        let now = Instant::now();
+        let mut inner = self.inner.write();
+
+        inner.banned_peers.retain(|_, expires| *expires > now);
+        inner.banned_peers.insert(
+            peer,
+            now.checked_add(duration).unwrap_or(now + Duration::from_secs(u32::MAX as u64)),
+        );
+    }
+
+    /// Unban `peer`.
+    ///
+    /// Returns `true` if the peer was banned.
+    pub(crate) fn unban_peer(&self, peer: &PeerId) -> bool {
+        self.inner
+            .write()
+            .banned_peers
+            .remove(peer)
+            .is_some_and(|expires| expires > Instant::now())
+    }
+
+    /// Set allowed and denied networks.
+    pub(crate) fn set_networks(&self, allowed: Vec<IpNetwork>, denied: Vec<IpNetwork>) {
+        let mut inner = self.inner.write();
+
+        inner.allowed_networks = allowed;
+        inner.denied_networks = denied;
+    }
+
+    /// Check if `peer` is banned.
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.inner
+            .read()
+            .banned_peers
+            .get(peer)
+            .is_some_and(|expires| *expires > Instant::now())
+    }
+
+    /// Check if connections to and from `address` are allowed.
+    ///
+    /// Denied networks take precedence over allowed networks. IPv4-mapped IPv6 addresses are
+    /// matched against the networks as IPv4 addresses.
+    pub fn is_ip_allowed(&self, address: &IpAddr) -> bool {
+        let address = address.to_canonical();
+        let inner = self.inner.read();
+
+        if inner.denied_networks.iter().any(|network| network.contains(&address)) {
+            return false;
+        }
+
+        inner.allowed_networks.is_empty()
+            || inner.allowed_networks.iter().any(|network| network.contains(&address))
+    }
+
+    /// Check if a connection to `peer` over `address` is allowed.
+    ///
+    /// Addresses that don't start with an IP address, such as DNS addresses, are not filtered.
+    pub(crate) fn check(
+        &self,
+        peer: &PeerId,
+        address: &Multiaddr,
+    ) -> Result<(), ConnectionRejectReason> {
+        if self.is_banned(peer) {
+            return Err(ConnectionRejectReason::Banned);
+        }
+
+        match ip_address(address) {
+            Some(ip) if !self.is_ip_allowed(&ip) => Err(ConnectionRejectReason::AddressDenied),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ip_network() {
+        let network: IpNetwork = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(network.prefix(), 8);
+        assert_eq!(network.to_string(), "10.0.0.0/8");
+
+        let network: IpNetwork = "127.0.0.1".parse().unwrap();
+        assert_eq!(network.prefix(), 32);
+
+        let network: IpNetwork = "fe80::/10".parse().unwrap();
+        assert_eq!(network.prefix(), 10);
+
+        assert!(std::matches!(
+            "10.0.0.0/33".parse::<IpNetwork>(),
+            Err(IpNetworkError::InvalidPrefix(_))
+        ));
+        assert!(std::matches!(
+            "10.0.0/8".parse::<IpNetwork>(),
+            Err(IpNetworkError::InvalidAddress(_))
+        ));
+        assert!(std::matches!(
+            "10.0.0.0/a".parse::<IpNetwork>(),
+            Err(IpNetworkError::InvalidPrefix(_))
+        ));
+    }
+
+    #[test]
+    fn ip_network_contains() {
+        let network: IpNetwork = "192.168.0.0/16".parse().unwrap();
+        assert!(network.contains(&"192.168.1.1".parse().unwrap()));
+        assert!(!network.contains(&"192.169.1.1".parse().unwrap()));
+        assert!(!network.contains(&"::1".parse().unwrap()));
+
+        let network: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(network.contains(&"1.2.3.4".parse().unwrap()));
+
+        let network: IpNetwork = "fe80::/10".parse().unwrap();
+        assert!(network.contains(&"fe80::1".parse().unwrap()));
+        assert!(!network.contains(&"::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ban_and_unban_peer() {
+        let ban_list = BanList::default();
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+
+        assert!(ban_list.check(&peer, &address).is_ok());
+
+        ban_list.ban_peer(peer, Duration::from_secs(60));
+        assert!(ban_list.is_banned(&peer));
+        assert_eq!(
+            ban_list.check(&peer, &address),
+            Err(ConnectionRejectReason::Banned)
+        );
+
+        assert!(ban_list.unban_peer(&peer));
+        assert!(!ban_list.is_banned(&peer));
+        assert!(!ban_list.unban_peer(&peer));
+    }
+
+    #[test]
+    fn ban_expires() {
+        let ban_list = BanList::default();
+        let peer = PeerId::random();
+
+        ban_list.ban_peer(peer, Duration::ZERO);
+        assert!(!ban_list.is_banned(&peer));
+
+        ban_list.ban_peer(peer, Duration::MAX);
+        assert!(ban_list.is_banned(&peer));
+    }
+
+    #[test]
+    fn allowed_and_denied_networks() {
+        let ban_list = BanList::default();
+        let peer = PeerId::random();
+
+        ban_list.set_networks(
+            vec!["10.0.0.0/8".parse().unwrap()],
+            vec!["10.1.0.0/16".parse().unwrap()],
+        );
+
+        assert!(ban_list.is_ip_allowed(&"10.2.0.1".parse().unwrap()));
+        assert!(!ban_list.is_ip_allowed(&"10.1.0.1".parse().unwrap()));
+        assert!(!ban_list.is_ip_allowed(&"192.168.0.1".parse().unwrap()));
+        assert_eq!(
+            ban_list.check(&peer, &"/ip4/10.1.2.3/tcp/8888".parse().unwrap()),
+            Err(ConnectionRejectReason::AddressDenied)
+        );
+
+        // dns addresses are not filtered
+        assert!(ban_list.check(&peer, &"/dns/example.com/tcp/8888".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_are_matched_as_ipv4() {
+        let ban_list = BanList::default();
+        let peer = PeerId::random();
+
+        ban_list.set_networks(
+            vec!["10.0.0.0/8".parse().unwrap()],
+            vec!["10.1.0.0/16".parse().unwrap()],
+        );
+
+        assert!(ban_list.is_ip_allowed(&"::ffff:10.2.0.1".parse().unwrap()));
+        assert!(!ban_list.is_ip_allowed(&"::ffff:10.1.0.1".parse().unwrap()));
+        assert!(!ban_list.is_ip_allowed(&"::ffff:192.168.0.1".parse().unwrap()));
+        assert_eq!(
+            ban_list.check(&peer, &"/ip6/::ffff:10.1.2.3/tcp/8888".parse().unwrap()),
+            Err(ConnectionRejectReason::AddressDenied)
+        );
+    }
+}
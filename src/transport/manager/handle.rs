@@ -22,11 +22,15 @@ use crate::{
     crypto::ed25519::Keypair,
     error::{AddressError, Error},
     executor::Executor,
-    protocol::ProtocolSet,
+    metrics::Metrics,
+    protocol::{ProtocolSet, ProtocolUpdates},
+    rate_limit::RateLimiter,
     transport::manager::{
         address::{AddressRecord, AddressStore},
+        ban::BanList,
+        limits::{ConnectionRejectReason, PendingIncomingLimit},
         types::{PeerContext, PeerState, SupportedTransport},
-        ProtocolContext, TransportManagerEvent, LOG_TARGET,
+        TransportManagerEvent, LOG_TARGET,
     },
     types::{protocol::ProtocolName, ConnectionId},
     BandwidthSink, PeerId,
@@ -77,6 +81,9 @@ pub struct TransportManagerHandle {
 
     /// Local listen addresess.
     listen_addresses: Arc<RwLock<HashSet<Multiaddr>>>,
+
+    /// Banned peers and IP address filters.
+    ban_list: BanList,
 }
 
 impl TransportManagerHandle {
@@ -87,6 +94,7 @@ impl TransportManagerHandle {
         cmd_tx: Sender<InnerTransportManagerCommand>,
         supported_transport: HashSet<SupportedTransport>,
         listen_addresses: Arc<RwLock<HashSet<Multiaddr>>>,
+        ban_list: BanList,
     ) -> Self {
         Self {
             peers,
@@ -94,6 +102,7 @@ impl TransportManagerHandle {
             local_peer_id,
             listen_addresses,
             supported_transport,
+            ban_list,
         }
     }
 
@@ -116,6 +125,9 @@ impl TransportManagerHandle {
                     return false;
                 },
             Some(Protocol::Dns(_)) | Some(Protocol::Dns4(_)) | Some(Protocol::Dns6(_)) => {}
+            Some(Protocol::Memory(_)) =>
+                return std::matches!(iter.next(), Some(Protocol::P2p(_)))
+                    && self.supported_transport.contains(&SupportedTransport::Memory),
             _ => return false,
         }
 
@@ -136,6 +148,8 @@ impl TransportManagerHandle {
                 self.supported_transport.contains(&SupportedTransport::Quic),
             ) {
                 (Some(Protocol::QuicV1), true) => true,
+                (Some(Protocol::WebRTC), _) =>
+                    self.supported_transport.contains(&SupportedTransport::WebRtc),
                 _ => false,
             },
             _ => false,
@@ -213,12 +227,16 @@ impl TransportManagerHandle {
 
     /// Dial peer using `PeerId`.
     ///
-    /// Returns an error if the peer is unknown or the peer is already connected.
+    /// Returns an error if the peer is unknown, banned or already connected.
     pub fn dial(&self, peer: &PeerId) -> crate::Result<()> {
         if peer == &self.local_peer_id {
             return Err(Error::TriedToDialSelf);
         }
 
+        if self.ban_list.is_banned(peer) {
+            return Err(Error::ConnectionRejected(ConnectionRejectReason::Banned));
+        }
+
         {
             match self.peers.read().get(peer) {
                 Some(PeerContext {
@@ -263,12 +281,16 @@ impl TransportManagerHandle {
 
     /// Dial peer using `Multiaddr`.
     ///
-    /// Returns an error if address it not valid.
+    /// Returns an error if address it not valid or if the address or the peer is banned.
     pub fn dial_address(&self, address: Multiaddr) -> crate::Result<()> {
         if !address.iter().any(|protocol| std::matches!(protocol, Protocol::P2p(_))) {
             return Err(Error::AddressError(AddressError::PeerIdMissing));
         }
 
+        if let Some(peer) = PeerId::try_from_multiaddr(&address) {
+            self.ban_list.check(&peer, &address).map_err(Error::ConnectionRejected)?;
+        }
+
         self.cmd_tx
             .try_send(InnerTransportManagerCommand::DialAddress { address })
             .map_err(|error| match error {
@@ -282,12 +304,16 @@ impl TransportManagerHandle {
 pub struct TransportHandle {
     pub keypair: Keypair,
     pub tx: Sender<TransportManagerEvent>,
-    pub protocols: HashMap<ProtocolName, ProtocolContext>,
+    pub protocols: ProtocolUpdates,
     pub next_connection_id: Arc<AtomicUsize>,
     pub next_substream_id: Arc<AtomicUsize>,
     pub protocol_names: Vec<ProtocolName>,
     pub bandwidth_sink: BandwidthSink,
     pub executor: Arc<dyn Executor>,
+    pub pending_incoming: PendingIncomingLimit,
+    pub ban_list: BanList,
+    pub metrics: Metrics,
+    pub rate_limiter: RateLimiter,
 }
 
 impl TransportHandle {
@@ -297,6 +323,7 @@ impl TransportHandle {
             self.tx.clone(),
             self.next_substream_id.clone(),
             self.protocols.clone(),
+            self.rate_limiter.clone(),
         )
     }
 
@@ -327,6 +354,7 @@ mod tests {
                 peers: Default::default(),
                 supported_transport: HashSet::new(),
                 listen_addresses: Default::default(),
+                ban_list: Default::default(),
             },
             cmd_rx,
         )
@@ -345,6 +373,42 @@ mod tests {
         assert!(handle.supported_transport(&address));
     }
 
+    #[test]
+    fn memory_supported() {
+        let (mut handle, _rx) = make_transport_manager_handle();
+        let address = Multiaddr::empty()
+            .with(Protocol::Memory(1337))
+            .with(Protocol::P2p(Multihash::from(PeerId::random())));
+        assert!(!handle.supported_transport(&address));
+
+        handle.supported_transport.insert(SupportedTransport::Memory);
+        assert!(handle.supported_transport(&address));
+
+        // peer id is required
+        assert!(!handle.supported_transport(&Multiaddr::empty().with(Protocol::Memory(1337))));
+    }
+
+    #[test]
+    fn webrtc_supported() {
+        let (mut handle, _rx) = make_transport_manager_handle();
+        let address = Multiaddr::empty()
+            .with(Protocol::Ip4(std::net::Ipv4Addr::new(127, 0, 0, 1)))
+            .with(Protocol::Udp(8888))
+            .with(Protocol::WebRTC)
+            .with(Protocol::Certhash(
+                Multihash::wrap(0x12, &[0u8; 32]).unwrap(),
+            ))
+            .with(Protocol::P2p(Multihash::from(PeerId::random())));
+        assert!(!handle.supported_transport(&address));
+
+        // quic support doesn't enable webrtc addresses
+        handle.supported_transport.insert(SupportedTransport::Quic);
+        assert!(!handle.supported_transport(&address));
+
+        handle.supported_transport.insert(SupportedTransport::WebRtc);
+        assert!(handle.supported_transport(&address));
+    }
+
     #[test]
     fn transport_not_supported() {
         let (handle, _rx) = make_transport_manager_handle();
@@ -582,6 +646,7 @@ mod tests {
             cmd_tx,
             peers: Default::default(),
             supported_transport: HashSet::new(),
+            ban_list: Default::default(),
             listen_addresses: Arc::new(RwLock::new(HashSet::from_iter([
                 "/ip6/::1/tcp/8888".parse().expect("valid multiaddress"),
                 "/ip4/127.0.0.1/tcp/8888".parse().expect("valid multiaddress"),
@@ -0,0 +1,467 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Connection limits and connection gating for
+//! [`TransportManager`](crate::transport::manager::TransportManager).
+
+use crate::{
+    transport::{manager::ban::ip_address, Endpoint},
+    types::ConnectionId,
+    PeerId,
+};
+
+use multiaddr::Multiaddr;
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Connection limits configuration.
+///
+/// All limits are disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimitsConfig {
+    /// Maximum number of established inbound connections.
+    max_incoming_connections: Option<usize>,
+
+    /// Maximum number of established and pending outbound connections.
+    max_outgoing_connections: Option<usize>,
+
+    /// Maximum number of established connections per peer.
+    max_connections_per_peer: Option<usize>,
+
+    /// Maximum number of inbound connections that are still being negotiated.
+    max_pending_incoming_connections: Option<usize>,
+
+    /// Maximum number of established connections per remote IP address.
+    max_connections_per_ip: Option<usize>,
+}
+
+impl ConnectionLimitsConfig {
+    /// Configure the maximum number of established inbound connections.
+    pub fn with_max_incoming_connections(mut self, limit: usize) -> Self {
+        self.max_incoming_connections = Some(limit);
+        self
+    }
+
+    /// Configure the maximum number of outbound connections.
+    ///
+    /// Dials that are in progress count towards the limit.
+    pub fn with_max_outgoing_connections(mut self, limit: usize) -> Self {
+        self.max_outgoing_connections = Some(limit);
+        self
+    }
+
+    /// Configure the maximum number of established connections per peer.
+    ///
+    /// Note that `litep2p` never keeps more than two connections open to the same peer.
+    pub fn with_max_connections_per_peer(mut self, limit: usize) -> Self {
+        self.max_connections_per_peer = Some(limit);
+        self
+    }
+
+    /// Configure the maximum number of inbound connections that can be negotiating
+    /// the security and multiplexing protocols at the same time.
+    pub fn with_max_pending_incoming_connections(mut self, limit: usize) -> Self {
+        self.max_pending_incoming_connections = Some(limit);
+        self
+    }
+
+    /// Configure the maximum number of established connections per remote IP address.
+    pub fn with_max_connections_per_ip(mut self, limit: usize) -> Self {
+        self.max_connections_per_ip = Some(limit);
+        self
+    }
+}
+
+/// Connection limits error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ConnectionLimitsError {
+    #[error("Maximum number of incoming connections exceeded")]
+    MaxIncomingConnectionsExceeded,
+    #[error("Maximum number of outgoing connections exceeded")]
+    MaxOutgoingConnectionsExceeded,
+    #[error("Maximum number of connections per peer exceeded")]
+    MaxConnectionsPerPeerExceeded,
+    #[error("Maximum number of pending incoming connections exceeded")]
+    MaxPendingIncomingConnectionsExceeded,
+    #[error("Maximum number of connections per IP address exceeded")]
+    MaxConnectionsPerIpExceeded,
+}
+
+/// Reason why a connection or a dial attempt was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ConnectionRejectReason {
+    #[error("Connection limit exceeded: `{0}`")]
+    LimitExceeded(ConnectionLimitsError),
+    #[error("Connection denied by the connection gater")]
+    Gated,
+    #[error("Peer is banned")]
+    Banned,
+    #[error("Remote address is denied")]
+    AddressDenied,
+}
+
+/// Connection gater.
+///
+/// Allows the user to veto dial attempts and negotiated connections before
+/// [`TransportManager`](crate::transport::manager::TransportManager) accepts them.
+///
+/// Both methods default to allowing everything.
+pub trait ConnectionGater: Send + Sync {
+    /// Check whether `address` of `peer` is allowed to be dialed.
+    fn allow_dial(&self, _peer: &PeerId, _address: &Multiaddr) -> bool {
+        true
+    }
+
+    /// Check whether a negotiated connection to `peer` is allowed to be accepted.
+    ///
+    /// Called for both inbound and outbound connections, after the security and multiplexing
+    /// protocols have been negotiated and before the connection is reported to protocols.
+    fn allow_connection(&self, _peer: &PeerId, _endpoint: &Endpoint) -> bool {
+        true
+    }
+}
+
+/// Limit for inbound connections that are still being negotiated.
+///
+/// The limit is shared between all installed transports.
+#[derive(Debug, Clone, Default)]
+pub struct PendingIncomingLimit {
+    /// Maximum number of pending inbound connections.
+    max: Option<usize>,
+
+    /// Number of pending inbound connections.
+    pending: Arc<AtomicUsize>,
+}
+
+impl PendingIncomingLimit {
+    /// Create new [`PendingIncomingLimit`].
+    fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            pending: Arc::new(AtomicUsize::new(0usize)),
+        }
+    }
+
+    /// Try to acquire a permit for negotiating an inbound connection.
+    ///
+    /// The permit must be held until the negotiation has concluded.
+    pub fn try_acquire(&self) -> Result<PendingIncomingPermit, ConnectionLimitsError> {
+        self.pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                match self.max {
+                    Some(max) if pending >= max => None,
+                    _ => Some(pending + 1),
+                }
+            })
+            .map_err(|_| ConnectionLimitsError::MaxPendingIncomingConnectionsExceeded)?;
+
+        Ok(PendingIncomingPermit {
+            pending: Arc::clone(&self.pending),
+        })
+    }
+}
+
+/// Permit for a pending inbound connection.
+///
+/// The permit is released when it's dropped.
+#[derive(Debug)]
+pub struct PendingIncomingPermit {
+    /// Number of pending inbound connections.
+    pending: Arc<AtomicUsize>,
+}
+
+impl Drop for PendingIncomingPermit {
+    fn drop(&mut self) {
+        self.pending.fetch_sub(1usize, Ordering::AcqRel);
+    }
+}
+
+/// Established connection tracked by [`ConnectionLimits`].
+#[derive(Debug)]
+struct ConnectionRecord {
+    /// Remote peer ID.
+    peer: PeerId,
+
+    /// Remote IP address, if known.
+    ip: Option<IpAddr>,
+
+    /// Is the connection inbound.
+    inbound: bool,
+}
+
+/// Connection limits.
+#[derive(Debug)]
+pub(crate) struct ConnectionLimits {
+    /// Limits configuration.
+    config: ConnectionLimitsConfig,
+
+    /// Established connections.
+    connections: HashMap<ConnectionId, ConnectionRecord>,
+
+    /// Number of established inbound connections.
+    num_incoming: usize,
+
+    /// Number of established outbound connections.
+    num_outgoing: usize,
+
+    /// Number of established connections per peer.
+    per_peer: HashMap<PeerId, usize>,
+
+    /// Number of established connections per IP address.
+    per_ip: HashMap<IpAddr, usize>,
+
+    /// Pending inbound connection limit.
+    pending_incoming: PendingIncomingLimit,
+}
+
+impl ConnectionLimits {
+    /// Create new [`ConnectionLimits`].
+    pub(crate) fn new(config: ConnectionLimitsConfig) -> Self {
+        Self {
+            pending_incoming: PendingIncomingLimit::new(config.max_pending_incoming_connections),
+            config,
+            connections: HashMap::new(),
+            num_incoming: 0usize,
+            num_outgoing: 0usize,
+            per_peer: HashMap::new(),
+            per_ip: HashMap::new(),
+        }
+    }
+
+    /// Get handle to the pending inbound connection limit.
+    pub(crate) fn pending_incoming(&self) -> PendingIncomingLimit {
+        self.pending_incoming.clone()
+    }
+
+    /// Check if a new dial can be started while `num_pending` dials are in progress.
+    pub(crate) fn can_dial(&self, num_pending: usize) -> Result<(), ConnectionLimitsError> {
+        match self.config.max_outgoing_connections {
+            Some(max) if self.num_outgoing + num_pending >= max =>
+                Err(ConnectionLimitsError::MaxOutgoingConnectionsExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if a negotiated connection to `peer` can be accepted.
+    ///
+    /// Outbound connections are not checked against the outbound connection limit because
+    /// it was already checked when the dial was started.
+    pub(crate) fn can_accept_connection(
+        &self,
+        peer: &PeerId,
+        endpoint: &Endpoint,
+    ) -> Result<(), ConnectionLimitsError> {
+        if let Some(max) = self.config.max_incoming_connections {
+            if endpoint.is_listener() && self.num_incoming >= max {
+                return Err(ConnectionLimitsError::MaxIncomingConnectionsExceeded);
+            }
+        }
+
+        if let Some(max) = self.config.max_connections_per_peer {
+            if self.per_peer.get(peer).copied().unwrap_or(0usize) >= max {
+                return Err(ConnectionLimitsError::MaxConnectionsPerPeerExceeded);
+            }
+        }
+
+        if let (Some(max), Some(ip)) = (
+            self.config.max_connections_per_ip,
+            ip_address(endpoint.address()),
+        ) {
+            if self.per_ip.get(&ip).copied().unwrap_or(0usize) >= max {
+                return Err(ConnectionLimitsError::MaxConnectionsPerIpExceeded);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Register accepted connection.
+    pub(crate) fn on_connection_established(&mut self, peer: PeerId, endpoint: &Endpoint) {
+        let ip = ip_address(endpoint.address());
+        let inbound = endpoint.is_listener();
+
+        if self
+            .connections
+            .insert(
+                endpoint.connection_id(),
+                ConnectionRecord { peer, ip, inbound },
+            )
+            .is_some()
+        {
+            debug_assert!(false);
+            return;
+        }
+
+        match inbound {
+            true => self.num_incoming += 1,
+            false => self.num_outgoing += 1,
+        }
+        *self.per_peer.entry(peer).or_default() += 1;
+
+        if let Some(ip) = ip {
+            *self.per_ip.entry(ip).or_default() += 1;
+        }
+    }
+
+    /// Unregister closed connection.
+    pub(crate) fn on_connection_closed(&mut self, connection_id: ConnectionId) {
+        let Some(ConnectionRecord { peer, ip, inbound }) = self.connections.remove(&connection_id)
+        else {
+            return;
+        };
+
+        match inbound {
+            true => self.num_incoming -= 1,
+            false => self.num_outgoing -= 1,
+        }
+
+        if let Some(count) = self.per_peer.get_mut(&peer) {
+            *count -= 1;
+
+            if *count == 0 {
+                self.per_peer.remove(&peer);
+            }
+        }
+
+        if let Some(ip) = ip {
+            if let Some(count) = self.per_ip.get_mut(&ip) {
+                *count -= 1;
+
+                if *count == 0 {
+                    self.per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(address: &str, connection_id: usize) -> Endpoint {
+        Endpoint::listener(address.parse().unwrap(), ConnectionId::from(connection_id))
+    }
+
+    fn dialer(address: &str, connection_id: usize) -> Endpoint {
+        Endpoint::dialer(address.parse().unwrap(), ConnectionId::from(connection_id))
+    }
+
+    #[test]
+    fn incoming_limit() {
+        let mut limits = ConnectionLimits::new(
+            ConnectionLimitsConfig::default().with_max_incoming_connections(1),
+        );
+
+        let endpoint = listener("/ip4/127.0.0.1/tcp/1", 0usize);
+        assert!(limits.can_accept_connection(&PeerId::random(), &endpoint).is_ok());
+        limits.on_connection_established(PeerId::random(), &endpoint);
+
+        assert_eq!(
+            limits.can_accept_connection(&PeerId::random(), &listener("/ip4/127.0.0.1/tcp/2", 1)),
+            Err(ConnectionLimitsError::MaxIncomingConnectionsExceeded),
+        );
+
+        // outbound connections are not affected
+        assert!(limits
+            .can_accept_connection(&PeerId::random(), &dialer("/ip4/127.0.0.1/tcp/3", 2))
+            .is_ok());
+
+        limits.on_connection_closed(ConnectionId::from(0usize));
+        assert!(limits
+            .can_accept_connection(&PeerId::random(), &listener("/ip4/127.0.0.1/tcp/2", 1))
+            .is_ok());
+    }
+
+    #[test]
+    fn outgoing_limit() {
+        let mut limits = ConnectionLimits::new(
+            ConnectionLimitsConfig::default().with_max_outgoing_connections(2),
+        );
+
+        assert!(limits.can_dial(1usize).is_ok());
+        assert_eq!(
+            limits.can_dial(2usize),
+            Err(ConnectionLimitsError::MaxOutgoingConnectionsExceeded)
+        );
+
+        limits.on_connection_established(PeerId::random(), &dialer("/ip4/127.0.0.1/tcp/1", 0));
+        assert!(limits.can_dial(0usize).is_ok());
+        assert!(limits.can_dial(1usize).is_err());
+
+        limits.on_connection_closed(ConnectionId::from(0usize));
+        assert!(limits.can_dial(1usize).is_ok());
+    }
+
+    #[test]
+    fn per_peer_and_per_ip_limits() {
+        let mut limits = ConnectionLimits::new(
+            ConnectionLimitsConfig::default()
+                .with_max_connections_per_peer(1)
+                .with_max_connections_per_ip(2),
+        );
+        let peer = PeerId::random();
+
+        limits.on_connection_established(peer, &listener("/ip4/1.1.1.1/tcp/1", 0));
+        assert_eq!(
+            limits.can_accept_connection(&peer, &dialer("/ip4/2.2.2.2/tcp/1", 1)),
+            Err(ConnectionLimitsError::MaxConnectionsPerPeerExceeded),
+        );
+
+        limits.on_connection_established(PeerId::random(), &listener("/ip4/1.1.1.1/tcp/2", 2));
+        assert_eq!(
+            limits.can_accept_connection(&PeerId::random(), &listener("/ip4/1.1.1.1/tcp/3", 3)),
+            Err(ConnectionLimitsError::MaxConnectionsPerIpExceeded),
+        );
+        assert!(limits
+            .can_accept_connection(&PeerId::random(), &listener("/ip4/2.2.2.2/tcp/3", 3))
+            .is_ok());
+
+        limits.on_connection_closed(ConnectionId::from(0usize));
+        assert!(limits.can_accept_connection(&peer, &listener("/ip4/1.1.1.1/tcp/3", 3)).is_ok());
+        assert!(!limits.per_peer.contains_key(&peer));
+    }
+
+    #[test]
+    fn pending_incoming_limit() {
+        let limit = PendingIncomingLimit::new(Some(2usize));
+
+        let permit1 = limit.try_acquire().unwrap();
+        let _permit2 = limit.try_acquire().unwrap();
+        assert_eq!(
+            limit.try_acquire().unwrap_err(),
+            ConnectionLimitsError::MaxPendingIncomingConnectionsExceeded
+        );
+
+        drop(permit1);
+        assert!(limit.try_acquire().is_ok());
+
+        // unlimited by default
+        let limit = PendingIncomingLimit::default();
+        let _permits = (0..100).map(|_| limit.try_acquire().unwrap()).collect::<Vec<_>>();
+    }
+}
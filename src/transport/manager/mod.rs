@@ -23,14 +23,20 @@ use crate::{
     crypto::ed25519::Keypair,
     error::{AddressError, Error},
     executor::Executor,
-    protocol::{InnerTransportEvent, TransportService},
+    metrics::Metrics,
+    protocol::{ConnectionHandle, InnerTransportEvent, TransportService},
+    rate_limit::{RateLimitConfig, RateLimiter},
     transport::{
         manager::{
             address::{AddressRecord, AddressStore},
+            ban::{BanList, IpNetwork},
             handle::InnerTransportManagerCommand,
+            limits::{
+                ConnectionGater, ConnectionLimits, ConnectionLimitsConfig, ConnectionRejectReason,
+            },
             types::{PeerContext, PeerState},
         },
-        Endpoint, Transport, TransportEvent,
+        ConnectionCloseReason, Endpoint, Transport, TransportEvent,
     },
     types::{protocol::ProtocolName, ConnectionId},
     BandwidthSink, PeerId,
@@ -41,7 +47,10 @@ use indexmap::IndexMap;
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
 use parking_lot::RwLock;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{
+    mpsc::{channel, error::TrySendError, Receiver, Sender},
+    watch,
+};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -51,6 +60,7 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
+    time::Duration,
 };
 
 pub use handle::{TransportHandle, TransportManagerHandle};
@@ -59,10 +69,11 @@ pub use types::SupportedTransport;
 mod address;
 mod types;
 
+pub(crate) mod ban;
 pub(crate) mod handle;
+pub(crate) mod limits;
 
 // TODO: store `Multiaddr` in `Arc`
-// TODO: limit number of peers and addresses
 // TODO: rename constants
 // TODO: add lots of documentation
 
@@ -86,6 +97,18 @@ enum ConnectionEstablishedResult {
 
 /// [`crate::transport::manager::TransportManager`] events.
 pub enum TransportManagerEvent {
+    /// Connection established to remote peer and reported to protocols.
+    ConnectionEstablished {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Connection ID.
+        connection: ConnectionId,
+
+        /// Inactive handle to the connection.
+        handle: ConnectionHandle,
+    },
+
     /// Connection closed to remote peer.
     ConnectionClosed {
         /// Peer ID.
@@ -93,6 +116,9 @@ pub enum TransportManagerEvent {
 
         /// Connection ID.
         connection: ConnectionId,
+
+        /// Reason why the connection was closed.
+        reason: ConnectionCloseReason,
     },
 }
 
@@ -107,6 +133,9 @@ pub struct ProtocolContext {
 
     /// Fallback names for the protocol.
     pub fallback_names: Vec<ProtocolName>,
+
+    /// Whether inbound substreams are negotiated for the protocol.
+    pub inbound: bool,
 }
 
 impl ProtocolContext {
@@ -120,6 +149,7 @@ impl ProtocolContext {
             tx,
             codec,
             fallback_names,
+            inbound: true,
         }
     }
 }
@@ -213,6 +243,9 @@ pub struct TransportManager {
     /// All names (main and fallback(s)) of the installed protocols.
     protocol_names: HashSet<ProtocolName>,
 
+    /// TX channel for publishing the installed protocols to transports and open connections.
+    protocols_tx: watch::Sender<HashMap<ProtocolName, ProtocolContext>>,
+
     /// Listen addresses.
     listen_addresses: Arc<RwLock<HashSet<Multiaddr>>>,
 
@@ -242,6 +275,27 @@ pub struct TransportManager {
 
     /// Pending connections.
     pending_connections: HashMap<ConnectionId, PeerId>,
+
+    /// Connection limits.
+    connection_limits: ConnectionLimits,
+
+    /// Connection gater, if installed.
+    connection_gater: Option<Arc<dyn ConnectionGater>>,
+
+    /// Banned peers and IP address filters.
+    ban_list: BanList,
+
+    /// Handles to established connections, used to close connections of banned peers.
+    connection_handles: HashMap<ConnectionId, (PeerId, ConnectionHandle)>,
+
+    /// Transports of established connections.
+    connection_transports: HashMap<ConnectionId, SupportedTransport>,
+
+    /// Metrics.
+    metrics: Metrics,
+
+    /// Rate limiter for substream traffic.
+    rate_limiter: RateLimiter,
 }
 
 impl TransportManager {
@@ -258,12 +312,14 @@ impl TransportManager {
         let (cmd_tx, cmd_rx) = channel(256);
         let (event_tx, event_rx) = channel(256);
         let listen_addresses = Arc::new(RwLock::new(HashSet::new()));
+        let ban_list = BanList::default();
         let handle = TransportManagerHandle::new(
             local_peer_id,
             peers.clone(),
             cmd_tx,
             supported_transports,
             Arc::clone(&listen_addresses),
+            ban_list.clone(),
         );
 
         (
@@ -280,8 +336,16 @@ impl TransportManager {
                 protocols: HashMap::new(),
                 transports: TransportContext::new(),
                 protocol_names: HashSet::new(),
+                protocols_tx: watch::channel(HashMap::new()).0,
                 transport_manager_handle: handle.clone(),
                 pending_connections: HashMap::new(),
+                connection_limits: ConnectionLimits::new(ConnectionLimitsConfig::default()),
+                connection_gater: None,
+                ban_list,
+                connection_handles: HashMap::new(),
+                connection_transports: HashMap::new(),
+                metrics: Metrics::default(),
+                rate_limiter: RateLimiter::default(),
                 next_substream_id: Arc::new(AtomicUsize::new(0usize)),
                 next_connection_id: Arc::new(AtomicUsize::new(0usize)),
             },
@@ -289,9 +353,11 @@ impl TransportManager {
         )
     }
 
-    /// Get iterator to installed protocols.
+    /// Get iterator to installed protocols which accept inbound substreams.
     pub fn protocols(&self) -> impl Iterator<Item = &ProtocolName> {
-        self.protocols.keys()
+        self.protocols
+            .iter()
+            .filter_map(|(protocol, context)| context.inbound.then_some(protocol))
     }
 
     /// Get iterator to installed transports
@@ -299,6 +365,75 @@ impl TransportManager {
         self.transports.keys()
     }
 
+    /// Set connection limits.
+    ///
+    /// Must be called before transports acquire their handles.
+    pub(crate) fn set_connection_limits(&mut self, config: ConnectionLimitsConfig) {
+        self.connection_limits = ConnectionLimits::new(config);
+    }
+
+    /// Set connection gater.
+    pub(crate) fn set_connection_gater(&mut self, gater: Arc<dyn ConnectionGater>) {
+        self.connection_gater = Some(gater);
+    }
+
+    /// Set metrics.
+    ///
+    /// Must be called before protocols and transports are registered.
+    pub(crate) fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
+    /// Set rate limits for substream traffic.
+    ///
+    /// Must be called before transports are registered.
+    pub(crate) fn set_rate_limits(&mut self, config: RateLimitConfig) {
+        self.rate_limiter = RateLimiter::new(config);
+    }
+
+    /// Set allowed and denied IP networks.
+    ///
+    /// If `allowed` is not empty, only connections to and from the allowed networks are
+    /// accepted. Denied networks take precedence over allowed networks.
+    pub(crate) fn set_ip_networks(&mut self, allowed: Vec<IpNetwork>, denied: Vec<IpNetwork>) {
+        self.ban_list.set_networks(allowed, denied);
+    }
+
+    /// Ban `peer` for `duration`.
+    ///
+    /// Open connections to the peer are closed and new connections are rejected until the ban
+    /// expires or the peer is unbanned.
+    pub fn ban_peer(&mut self, peer: PeerId, duration: Duration) {
+        tracing::debug!(target: LOG_TARGET, ?peer, ?duration, "ban peer");
+
+        self.ban_list.ban_peer(peer, duration);
+
+        for (_, (_, handle)) in self
+            .connection_handles
+            .iter_mut()
+            .filter(|(_, (connection_peer, _))| connection_peer == &peer)
+        {
+            if let Err(error) = handle.force_close(ConnectionCloseReason::Banned) {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    connection_id = ?handle.connection_id(),
+                    ?error,
+                    "failed to close connection of banned peer",
+                );
+            }
+        }
+    }
+
+    /// Unban `peer`.
+    ///
+    /// Returns `true` if the peer was banned.
+    pub fn unban_peer(&mut self, peer: &PeerId) -> bool {
+        tracing::debug!(target: LOG_TARGET, ?peer, "unban peer");
+
+        self.ban_list.unban_peer(peer)
+    }
+
     /// Get next connection ID.
     fn next_connection_id(&mut self) -> ConnectionId {
         let connection_id = self.next_connection_id.fetch_add(1usize, Ordering::Relaxed);
@@ -330,6 +465,7 @@ impl TransportManager {
             fallback_names.clone(),
             self.next_substream_id.clone(),
             self.transport_manager_handle.clone(),
+            self.metrics.clone(),
         );
 
         self.protocols.insert(
@@ -338,21 +474,88 @@ impl TransportManager {
         );
         self.protocol_names.insert(protocol);
         self.protocol_names.extend(fallback_names);
+        self.protocols_tx.send_replace(self.protocols.clone());
 
         service
     }
 
+    /// Check that neither `protocol` nor any of its `fallback_names` is installed.
+    pub(crate) fn can_register_protocol(
+        &self,
+        protocol: &ProtocolName,
+        fallback_names: &[ProtocolName],
+    ) -> crate::Result<()> {
+        match std::iter::once(protocol)
+            .chain(fallback_names)
+            .find(|name| self.protocol_names.contains(*name))
+        {
+            Some(name) => Err(Error::ProtocolAlreadyExists(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Unregister `protocol`.
+    ///
+    /// Open connections stop accepting substreams for the protocol and the protocol's
+    /// [`TransportService`] is closed once all connections have observed the change.
+    pub(crate) fn unregister_protocol(&mut self, protocol: &ProtocolName) -> crate::Result<()> {
+        let context = self
+            .protocols
+            .remove(protocol)
+            .ok_or_else(|| Error::ProtocolNotSupported(protocol.to_string()))?;
+
+        tracing::debug!(target: LOG_TARGET, ?protocol, "unregister protocol");
+
+        self.protocol_names.remove(protocol);
+        for fallback in &context.fallback_names {
+            self.protocol_names.remove(fallback);
+        }
+        self.protocols_tx.send_replace(self.protocols.clone());
+
+        Ok(())
+    }
+
+    /// Enable or disable inbound substreams for `protocol`.
+    ///
+    /// While disabled, open connections don't negotiate the protocol or its fallback names for
+    /// inbound substreams but the protocol can still open outbound substreams.
+    pub(crate) fn set_protocol_inbound(
+        &mut self,
+        protocol: &ProtocolName,
+        inbound: bool,
+    ) -> crate::Result<()> {
+        let context = self
+            .protocols
+            .get_mut(protocol)
+            .ok_or_else(|| Error::ProtocolNotSupported(protocol.to_string()))?;
+
+        if context.inbound == inbound {
+            return Ok(());
+        }
+
+        tracing::debug!(target: LOG_TARGET, ?protocol, ?inbound, "set inbound substreams");
+
+        context.inbound = inbound;
+        self.protocols_tx.send_replace(self.protocols.clone());
+
+        Ok(())
+    }
+
     /// Acquire `TransportHandle`.
     pub fn transport_handle(&self, executor: Arc<dyn Executor>) -> TransportHandle {
         TransportHandle {
             tx: self.event_tx.clone(),
             executor,
             keypair: self.keypair.clone(),
-            protocols: self.protocols.clone(),
+            protocols: self.protocols_tx.subscribe(),
             bandwidth_sink: self.bandwidth_sink.clone(),
             protocol_names: self.protocol_names.iter().cloned().collect(),
             next_substream_id: self.next_substream_id.clone(),
             next_connection_id: self.next_connection_id.clone(),
+            pending_incoming: self.connection_limits.pending_incoming(),
+            ban_list: self.ban_list.clone(),
+            metrics: self.metrics.clone(),
+            rate_limiter: self.rate_limiter.clone(),
         }
     }
 
@@ -380,6 +583,88 @@ impl TransportManager {
         )));
     }
 
+    /// Get the transport of the established connection `connection_id`.
+    pub(crate) fn connection_transport(
+        &self,
+        connection_id: &ConnectionId,
+    ) -> Option<SupportedTransport> {
+        self.connection_transports.get(connection_id).copied()
+    }
+
+    /// Unregister local listen address.
+    fn unregister_listen_address(&mut self, address: &Multiaddr) {
+        let mut listen_addresses = self.listen_addresses.write();
+
+        listen_addresses.remove(address);
+        listen_addresses.remove(&address.clone().with(Protocol::P2p(
+            Multihash::from_bytes(&self.local_peer_id.to_bytes()).unwrap(),
+        )));
+    }
+
+    /// Get the transport which can listen on `address`.
+    fn listen_transport(address: &Multiaddr) -> crate::Result<SupportedTransport> {
+        let mut protocol_stack = address.iter();
+
+        match protocol_stack.next() {
+            Some(Protocol::Ip4(_) | Protocol::Ip6(_)) => {}
+            Some(Protocol::Memory(_)) => return Ok(SupportedTransport::Memory),
+            _ => return Err(Error::TransportNotSupported(address.clone())),
+        }
+
+        match (protocol_stack.next(), protocol_stack.next()) {
+            (Some(Protocol::Tcp(_)), Some(Protocol::Ws(_) | Protocol::Wss(_))) =>
+                Ok(SupportedTransport::WebSocket),
+            (Some(Protocol::Tcp(_)), Some(Protocol::Tls)) => match protocol_stack.next() {
+                Some(Protocol::Ws(_)) => Ok(SupportedTransport::WebSocket),
+                _ => Err(Error::TransportNotSupported(address.clone())),
+            },
+            (Some(Protocol::Tcp(_)), None | Some(Protocol::P2p(_))) => Ok(SupportedTransport::Tcp),
+            (Some(Protocol::Udp(_)), Some(Protocol::QuicV1)) => Ok(SupportedTransport::Quic),
+            (Some(Protocol::Udp(_)), Some(Protocol::WebRTC)) => Ok(SupportedTransport::WebRtc),
+            _ => Err(Error::TransportNotSupported(address.clone())),
+        }
+    }
+
+    /// Start listening on `address`.
+    ///
+    /// Returns the addresses the new listener is reachable at.
+    pub(crate) fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        let transport = Self::listen_transport(&address)?;
+        let listen_addresses = self
+            .transports
+            .get_mut(&transport)
+            .ok_or_else(|| Error::TransportNotSupported(address.clone()))?
+            .listen_on(address)?;
+
+        for address in &listen_addresses {
+            self.register_listen_address(address.clone());
+        }
+
+        Ok(listen_addresses)
+    }
+
+    /// Stop the listener which is either bound to or reachable at `address`.
+    ///
+    /// Returns the addresses the removed listener was reachable at.
+    pub(crate) fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        let address = address
+            .iter()
+            .filter(|protocol| !std::matches!(protocol, Protocol::P2p(_)))
+            .collect::<Multiaddr>();
+        let transport = Self::listen_transport(&address)?;
+        let listen_addresses = self
+            .transports
+            .get_mut(&transport)
+            .ok_or_else(|| Error::TransportNotSupported(address.clone()))?
+            .remove_listener(&address)?;
+
+        for address in &listen_addresses {
+            self.unregister_listen_address(address);
+        }
+
+        Ok(listen_addresses)
+    }
+
     /// Add one or more known addresses for `peer`.
     pub fn add_known_address(
         &mut self,
@@ -396,6 +681,11 @@ impl TransportManager {
         if peer == self.local_peer_id {
             return Err(Error::TriedToDialSelf);
         }
+
+        if self.ban_list.is_banned(&peer) {
+            return Err(Error::ConnectionRejected(ConnectionRejectReason::Banned));
+        }
+
         let mut peers = self.peers.write();
 
         // if the peer is disconnected, return its context
@@ -460,6 +750,48 @@ impl TransportManager {
             return Err(Error::NoAddressAvailable(peer));
         }
 
+        // verify that the connection limits allow a new dial to be started and that
+        // at least one of the addresses is neither denied nor rejected by the connection gater
+        let denied = records
+            .keys()
+            .filter(|address| self.ban_list.check(&peer, address).is_err())
+            .cloned()
+            .collect::<Vec<_>>();
+        let gated = self.connection_gater.as_ref().map_or(Vec::new(), |gater| {
+            records
+                .keys()
+                .filter(|address| !denied.contains(address) && !gater.allow_dial(&peer, address))
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        let reject_reason = match self.connection_limits.can_dial(self.pending_connections.len()) {
+            Err(error) => Some(ConnectionRejectReason::LimitExceeded(error)),
+            Ok(()) if denied.len() == records.len() => Some(ConnectionRejectReason::AddressDenied),
+            Ok(()) if denied.len() + gated.len() == records.len() =>
+                Some(ConnectionRejectReason::Gated),
+            Ok(()) => None,
+        };
+
+        if let Some(reason) = reject_reason {
+            tracing::debug!(target: LOG_TARGET, ?peer, ?reason, "dial rejected");
+
+            addresses.extend(records.into_values());
+            peers.insert(
+                peer,
+                PeerContext {
+                    state,
+                    secondary_connection,
+                    addresses,
+                },
+            );
+
+            return Err(Error::ConnectionRejected(reason));
+        }
+
+        for address in denied.into_iter().chain(gated) {
+            addresses.insert(records.remove(&address).expect("record to exist"));
+        }
+
         for record in records.values() {
             if self.listen_addresses.read().contains(record.as_ref()) {
                 tracing::warn!(
@@ -489,10 +821,17 @@ impl TransportManager {
         let mut websocket = Vec::new();
         let mut quic = Vec::new();
         let mut tcp = Vec::new();
+        let mut memory = Vec::new();
 
         for (address, record) in &mut records {
             record.set_connection_id(connection_id);
 
+            if std::matches!(address.iter().next(), Some(Protocol::Memory(_))) {
+                memory.push(address.clone());
+                transports.insert(SupportedTransport::Memory);
+                continue;
+            }
+
             let mut iter = address.iter();
             match iter.find(|protocol| std::matches!(protocol, Protocol::QuicV1)) {
                 Some(_) => {
@@ -522,6 +861,7 @@ impl TransportManager {
                     records,
                     connection_id,
                     transports,
+                    errors: Vec::new(),
                 },
                 secondary_connection,
                 addresses,
@@ -549,6 +889,13 @@ impl TransportManager {
                 .open(connection_id, websocket)?;
         }
 
+        if !memory.is_empty() {
+            self.transports
+                .get_mut(&SupportedTransport::Memory)
+                .expect("transport to be supported")
+                .open(connection_id, memory)?;
+        }
+
         self.pending_connections.insert(connection_id, peer);
 
         Ok(())
@@ -568,17 +915,18 @@ impl TransportManager {
         tracing::debug!(target: LOG_TARGET, address = ?record.address(), "dial remote peer over address");
 
         let mut protocol_stack = record.as_ref().iter();
-        match protocol_stack
+        let is_memory = match protocol_stack
             .next()
             .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
         {
-            Protocol::Ip4(_) | Protocol::Ip6(_) => {}
-            Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) => {}
+            Protocol::Ip4(_) | Protocol::Ip6(_) => false,
+            Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) => false,
+            Protocol::Memory(_) => true,
             transport => {
                 tracing::error!(
                     target: LOG_TARGET,
                     ?transport,
-                    "invalid transport, expected `ip4`/`ip6`"
+                    "invalid transport, expected `ip4`/`ip6`/`memory`"
                 );
                 return Err(Error::TransportNotSupported(record.address().clone()));
             }
@@ -588,6 +936,11 @@ impl TransportManager {
             .next()
             .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
         {
+            Protocol::P2p(_) if is_memory => SupportedTransport::Memory,
+            protocol if is_memory => {
+                tracing::debug!(target: LOG_TARGET, ?protocol, "expected `p2p` after `memory`");
+                return Err(Error::TransportNotSupported(record.address().clone()));
+            }
             Protocol::Tcp(_) => match protocol_stack.next() {
                 Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)) => SupportedTransport::WebSocket,
                 Some(Protocol::P2p(_)) => SupportedTransport::Tcp,
@@ -598,8 +951,9 @@ impl TransportManager {
                 .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
             {
                 Protocol::QuicV1 => SupportedTransport::Quic,
+                Protocol::WebRTC => SupportedTransport::WebRtc,
                 _ => {
-                    tracing::debug!(target: LOG_TARGET, address = ?record.address(), "expected `quic-v1`");
+                    tracing::debug!(target: LOG_TARGET, address = ?record.address(), "expected `quic-v1` or `webrtc-direct`");
                     return Err(Error::TransportNotSupported(record.address().clone()));
                 }
             },
@@ -618,6 +972,23 @@ impl TransportManager {
         let remote_peer_id =
             PeerId::try_from_multiaddr(record.address()).expect("`PeerId` to exist");
 
+        if let Err(reason) = self.ban_list.check(&remote_peer_id, record.address()) {
+            tracing::debug!(target: LOG_TARGET, address = ?record.address(), ?reason, "dial denied");
+            return Err(Error::ConnectionRejected(reason));
+        }
+
+        if let Some(gater) = &self.connection_gater {
+            if !gater.allow_dial(&remote_peer_id, record.address()) {
+                tracing::debug!(target: LOG_TARGET, address = ?record.address(), "dial denied by connection gater");
+                return Err(Error::ConnectionRejected(ConnectionRejectReason::Gated));
+            }
+        }
+        self.connection_limits
+            .can_dial(self.pending_connections.len())
+            .map_err(|error| {
+                Error::ConnectionRejected(ConnectionRejectReason::LimitExceeded(error))
+            })?;
+
         // set connection id for the address record and put peer into `Dialing` state
         let connection_id = self.next_connection_id();
         record.set_connection_id(connection_id);
@@ -758,6 +1129,7 @@ impl TransportManager {
         &mut self,
         peer: PeerId,
         connection_id: ConnectionId,
+        reason: ConnectionCloseReason,
     ) -> crate::Result<Option<TransportEvent>> {
         let mut peers = self.peers.write();
         let Some(context) = peers.get_mut(&peer) else {
@@ -801,6 +1173,7 @@ impl TransportManager {
                         Ok(Some(TransportEvent::ConnectionClosed {
                             peer,
                             connection_id,
+                            reason,
                         }))
                     }
                     Some(secondary_connection) => {
@@ -881,6 +1254,7 @@ impl TransportManager {
                     Ok(Some(TransportEvent::ConnectionClosed {
                         peer,
                         connection_id,
+                        reason,
                     }))
                 }
             },
@@ -892,6 +1266,40 @@ impl TransportManager {
         }
     }
 
+    /// Check whether the connection gater and the connection limits allow a negotiated
+    /// connection to be accepted.
+    fn check_connection_allowed(
+        &self,
+        peer: &PeerId,
+        endpoint: &Endpoint,
+    ) -> Result<(), ConnectionRejectReason> {
+        self.ban_list.check(peer, endpoint.address())?;
+
+        if let Some(gater) = &self.connection_gater {
+            if !gater.allow_connection(peer, endpoint) {
+                return Err(ConnectionRejectReason::Gated);
+            }
+        }
+
+        self.connection_limits
+            .can_accept_connection(peer, endpoint)
+            .map_err(ConnectionRejectReason::LimitExceeded)
+    }
+
+    /// Report to all protocols that dialing `peer` failed.
+    async fn report_dial_failure(&mut self, peer: PeerId, address: Multiaddr) {
+        for context in self.protocols.values() {
+            let event = InnerTransportEvent::DialFailure {
+                peer,
+                address: address.clone(),
+            };
+
+            if let Err(TrySendError::Full(event)) = context.tx.try_send(event) {
+                let _ = context.tx.send(event).await;
+            }
+        }
+    }
+
     fn on_connection_established(
         &mut self,
         peer: PeerId,
@@ -1029,6 +1437,7 @@ impl TransportManager {
                     ref mut records,
                     connection_id,
                     ref transports,
+                    ..
                 } => {
                     debug_assert!(std::matches!(endpoint, &Endpoint::Listener { .. }));
 
@@ -1193,6 +1602,7 @@ impl TransportManager {
                 mut records,
                 connection_id,
                 transports,
+                ..
             } => {
                 tracing::trace!(
                     target: LOG_TARGET,
@@ -1284,7 +1694,8 @@ impl TransportManager {
         &mut self,
         transport: SupportedTransport,
         connection_id: ConnectionId,
-    ) -> crate::Result<Option<PeerId>> {
+        open_errors: Vec<(Multiaddr, Error)>,
+    ) -> crate::Result<Option<(PeerId, Vec<(Multiaddr, Error)>)>> {
         let Some(peer) = self.pending_connections.remove(&connection_id) else {
             tracing::warn!(
                 target: LOG_TARGET,
@@ -1317,6 +1728,7 @@ impl TransportManager {
                 records,
                 connection_id,
                 mut transports,
+                mut errors,
             } => {
                 tracing::trace!(
                     target: LOG_TARGET,
@@ -1326,6 +1738,7 @@ impl TransportManager {
                     "open failure for peer",
                 );
                 transports.remove(&transport);
+                errors.extend(open_errors);
 
                 if transports.is_empty() {
                     for (_, mut record) in records {
@@ -1340,7 +1753,7 @@ impl TransportManager {
                         "open failure for last transport",
                     );
 
-                    return Ok(Some(peer));
+                    return Ok(Some((peer, errors)));
                 }
 
                 self.pending_connections.insert(connection_id, peer);
@@ -1348,6 +1761,7 @@ impl TransportManager {
                     records,
                     connection_id,
                     transports,
+                    errors,
                 };
 
                 Ok(None)
@@ -1368,24 +1782,108 @@ impl TransportManager {
         }
     }
 
+    /// Handle event received from a connection.
+    fn on_manager_event(&mut self, event: TransportManagerEvent) -> Option<TransportEvent> {
+        match event {
+            TransportManagerEvent::ConnectionEstablished {
+                peer,
+                connection: connection_id,
+                mut handle,
+            } => {
+                // the peer may have been banned while the connection was being reported to
+                // protocols, before its handle was known
+                if self.ban_list.is_banned(&peer) {
+                    let _ = handle.force_close(ConnectionCloseReason::Banned);
+                }
+
+                self.connection_handles.insert(connection_id, (peer, handle));
+                None
+            }
+            TransportManagerEvent::ConnectionClosed {
+                peer,
+                connection: connection_id,
+                reason,
+            } => {
+                self.connection_limits.on_connection_closed(connection_id);
+                self.connection_handles.remove(&connection_id);
+
+                if let Some(transport) = self.connection_transports.remove(&connection_id) {
+                    self.metrics.on_connection_closed(transport);
+                }
+
+                match self.on_connection_closed(peer, connection_id, reason) {
+                    Ok(event) => event,
+                    Err(error) => {
+                        tracing::error!(
+                            target: LOG_TARGET,
+                            ?error,
+                            "failed to handle closed connection",
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stop all transports, closing their listeners and aborting pending dials.
+    ///
+    /// Established connections are not affected.
+    pub(crate) fn stop_listeners(&mut self) {
+        tracing::debug!(target: LOG_TARGET, "stop listeners");
+
+        self.transports = TransportContext::new();
+    }
+
+    /// Process events of the established connections.
+    ///
+    /// Used during shutdown so the connection bookkeeping stays up to date while protocols are
+    /// finishing their in-flight exchanges. Never returns.
+    pub(crate) async fn process_events(&mut self) {
+        loop {
+            match self.event_rx.recv().await {
+                Some(event) => {
+                    let _ = self.on_manager_event(event);
+                }
+                None => return futures::future::pending().await,
+            }
+        }
+    }
+
+    /// Close all established connections and wait until they have been closed.
+    pub(crate) async fn close_connections(&mut self) {
+        tracing::debug!(
+            target: LOG_TARGET,
+            num_connections = self.connection_handles.len(),
+            "close connections",
+        );
+
+        for (_, handle) in self.connection_handles.values_mut() {
+            let _ = handle.force_close(ConnectionCloseReason::Shutdown);
+        }
+
+        while !self.connection_handles.is_empty() {
+            let Some(mut event) = self.event_rx.recv().await else {
+                return;
+            };
+
+            if let TransportManagerEvent::ConnectionEstablished { handle, .. } = &mut event {
+                let _ = handle.force_close(ConnectionCloseReason::Shutdown);
+            }
+
+            let _ = self.on_manager_event(event);
+        }
+    }
+
     /// Poll next event from [`crate::transport::manager::TransportManager`].
     pub async fn next(&mut self) -> Option<TransportEvent> {
         loop {
             tokio::select! {
-                event = self.event_rx.recv() => match event? {
-                    TransportManagerEvent::ConnectionClosed {
-                        peer,
-                        connection: connection_id,
-                    } => match self.on_connection_closed(peer, connection_id) {
-                        Ok(None) => {}
-                        Ok(Some(event)) => return Some(event),
-                        Err(error) => tracing::error!(
-                            target: LOG_TARGET,
-                            ?error,
-                            "failed to handle closed connection",
-                        ),
+                event = self.event_rx.recv() => {
+                    if let Some(event) = self.on_manager_event(event?) {
+                        return Some(event)
                     }
-                },
+                }
                 command = self.cmd_rx.recv() => match command? {
                     InnerTransportManagerCommand::DialPeer { peer } => {
                         if let Err(error) = self.dial(peer).await {
@@ -1410,6 +1908,7 @@ impl TransportManager {
                                 ?error,
                                 "failed to dial peer",
                             );
+                            self.metrics.on_dial_failure(transport, &error);
 
                             if let Ok(()) = self.on_dial_failure(connection_id) {
                                 match address.iter().last() {
@@ -1491,6 +1990,40 @@ impl TransportManager {
                             }
                         }
                         TransportEvent::ConnectionEstablished { peer, endpoint } => {
+                            if !endpoint.is_listener() {
+                                self.metrics.on_dial_success(transport);
+                            }
+
+                            if let Err(reason) = self.check_connection_allowed(&peer, &endpoint) {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?peer,
+                                    ?endpoint,
+                                    ?reason,
+                                    "connection rejected",
+                                );
+
+                                let _ = self
+                                    .transports
+                                    .get_mut(&transport)
+                                    .expect("transport to exist")
+                                    .reject(endpoint.connection_id());
+
+                                // if the connection was dialed by the local node, the dial has
+                                // failed as far as the peer state and protocols are concerned
+                                if self.pending_connections.contains_key(&endpoint.connection_id()) {
+                                    if let Ok(()) = self.on_dial_failure(endpoint.connection_id()) {
+                                        self.report_dial_failure(peer, endpoint.address().clone()).await;
+                                    }
+                                }
+
+                                return Some(TransportEvent::ConnectionRejected {
+                                    peer,
+                                    endpoint,
+                                    reason,
+                                });
+                            }
+
                             match self.on_connection_established(peer, &endpoint) {
                                 Err(error) => {
                                     tracing::debug!(
@@ -1514,6 +2047,9 @@ impl TransportManager {
                                         ?endpoint,
                                         "accept connection",
                                     );
+                                    self.connection_limits.on_connection_established(peer, &endpoint);
+                                    self.connection_transports.insert(endpoint.connection_id(), transport);
+                                    self.metrics.on_connection_opened(transport);
 
                                     let _ = self
                                         .transports
@@ -1543,24 +2079,25 @@ impl TransportManager {
                             }
                         }
                         TransportEvent::ConnectionOpened { connection_id, address } => {
-                            if let Err(error) = self.on_connection_opened(transport, connection_id, address) {
-                                tracing::debug!(
+                            match self.on_connection_opened(transport, connection_id, address.clone()) {
+                                Err(error) => tracing::debug!(
                                     target: LOG_TARGET,
                                     ?connection_id,
                                     ?error,
                                     "failed to handle opened connection",
-                                );
+                                ),
+                                Ok(()) => return Some(TransportEvent::ConnectionOpened { connection_id, address }),
                             }
                         }
-                        TransportEvent::OpenFailure { connection_id } => {
-                            match self.on_open_failure(transport, connection_id) {
+                        TransportEvent::OpenFailure { connection_id, errors } => {
+                            match self.on_open_failure(transport, connection_id, errors) {
                                 Err(error) => tracing::debug!(
                                     target: LOG_TARGET,
                                     ?connection_id,
                                     ?error,
                                     "failed to handle opened connection",
                                 ),
-                                Ok(Some(peer)) => {
+                                Ok(Some((peer, errors))) => {
                                     tracing::trace!(
                                         target: LOG_TARGET,
                                         ?peer,
@@ -1597,15 +2134,20 @@ impl TransportManager {
                                         };
                                     }
 
-                                    return Some(TransportEvent::DialFailure {
-                                        connection_id,
-                                        address: Multiaddr::empty(),
-                                        error: Error::Unknown,
-                                    })
+                                    return Some(TransportEvent::OpenFailure { connection_id, errors })
                                 }
                                 Ok(None) => {}
                             }
                         }
+                        TransportEvent::ListenerClosed { address } => {
+                            tracing::debug!(target: LOG_TARGET, ?transport, ?address, "listener closed");
+
+                            self.unregister_listen_address(&address);
+                            return Some(TransportEvent::ListenerClosed { address });
+                        }
+                        event @ (TransportEvent::IncomingConnection { .. }
+                        | TransportEvent::IncomingConnectionError { .. }
+                        | TransportEvent::ListenerError { .. }) => return Some(event),
                         event => panic!("event not supported: {event:?}"),
                     }
                 },
@@ -1618,7 +2160,10 @@ impl TransportManager {
 mod tests {
     use super::*;
     use crate::{
-        crypto::ed25519::Keypair, executor::DefaultExecutor, transport::dummy::DummyTransport,
+        crypto::ed25519::Keypair,
+        executor::DefaultExecutor,
+        protocol::ProtocolCommand,
+        transport::{dummy::DummyTransport, manager::limits::ConnectionLimitsError},
     };
     use std::{
         net::{Ipv4Addr, Ipv6Addr},
@@ -2093,7 +2638,13 @@ mod tests {
             .unwrap();
 
         // connection to remote was closed while the dial was still in progress
-        manager.on_connection_closed(peer, ConnectionId::from(1usize)).unwrap();
+        manager
+            .on_connection_closed(
+                peer,
+                ConnectionId::from(1usize),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
 
         // verify that the peer state is `Disconnected`
         {
@@ -2179,7 +2730,13 @@ mod tests {
             .unwrap();
 
         // connection to remote was closed while the dial was still in progress
-        manager.on_connection_closed(peer, ConnectionId::from(1usize)).unwrap();
+        manager
+            .on_connection_closed(
+                peer,
+                ConnectionId::from(1usize),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
 
         // verify that the peer state is `Disconnected`
         {
@@ -2403,7 +2960,13 @@ mod tests {
         drop(peers);
 
         // close the secondary connection and verify that the peer remains connected
-        let emit_event = manager.on_connection_closed(peer, ConnectionId::from(1usize)).unwrap();
+        let emit_event = manager
+            .on_connection_closed(
+                peer,
+                ConnectionId::from(1usize),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
         assert!(emit_event.is_none());
 
         let peers = manager.peers.read();
@@ -2506,7 +3069,13 @@ mod tests {
 
         // close the primary connection and verify that the peer remains connected
         // while the primary connection address is stored in peer addresses
-        let emit_event = manager.on_connection_closed(peer, ConnectionId::from(0usize)).unwrap();
+        let emit_event = manager
+            .on_connection_closed(
+                peer,
+                ConnectionId::from(0usize),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
         assert!(emit_event.is_none());
 
         let peers = manager.peers.read();
@@ -2634,7 +3203,13 @@ mod tests {
         drop(peers);
 
         // close the tertiary connection that was ignored
-        let emit_event = manager.on_connection_closed(peer, ConnectionId::from(2usize)).unwrap();
+        let emit_event = manager
+            .on_connection_closed(
+                peer,
+                ConnectionId::from(2usize),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
         assert!(emit_event.is_none());
 
         // verify that the state remains unchanged
@@ -2706,7 +3281,13 @@ mod tests {
             BandwidthSink::new(),
             8usize,
         );
-        manager.on_connection_closed(PeerId::random(), ConnectionId::random()).unwrap();
+        manager
+            .on_connection_closed(
+                PeerId::random(),
+                ConnectionId::random(),
+                ConnectionCloseReason::LocalClose,
+            )
+            .unwrap();
     }
 
     #[tokio::test]
@@ -2797,7 +3378,7 @@ mod tests {
         );
 
         manager
-            .on_open_failure(SupportedTransport::Tcp, ConnectionId::random())
+            .on_open_failure(SupportedTransport::Tcp, ConnectionId::random(), Vec::new())
             .unwrap();
     }
 
@@ -2819,7 +3400,9 @@ mod tests {
         let peer = PeerId::random();
 
         manager.pending_connections.insert(connection_id, peer);
-        manager.on_open_failure(SupportedTransport::Tcp, connection_id).unwrap();
+        manager
+            .on_open_failure(SupportedTransport::Tcp, connection_id, Vec::new())
+            .unwrap();
     }
 
     #[tokio::test]
@@ -3266,4 +3849,150 @@ mod tests {
             state => panic!("invalid peer state: {state:?}"),
         }
     }
+
+    #[tokio::test]
+    async fn inbound_connection_rejected_by_limits() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (mut manager, _handle) = TransportManager::new(
+            Keypair::generate(),
+            HashSet::new(),
+            BandwidthSink::new(),
+            8usize,
+        );
+        manager.set_connection_limits(
+            ConnectionLimitsConfig::default().with_max_incoming_connections(0usize),
+        );
+
+        let peer = PeerId::random();
+        let address = Multiaddr::empty()
+            .with(Protocol::Ip4(Ipv4Addr::new(127, 0, 0, 1)))
+            .with(Protocol::Tcp(8888))
+            .with(Protocol::P2p(Multihash::from(peer)));
+        let connection_id = ConnectionId::random();
+        let transport = Box::new({
+            let mut transport = DummyTransport::new();
+            transport.inject_event(TransportEvent::ConnectionEstablished {
+                peer,
+                endpoint: Endpoint::listener(address.clone(), connection_id),
+            });
+            transport
+        });
+        manager.register_transport(SupportedTransport::Tcp, transport);
+
+        match manager.next().await.unwrap() {
+            TransportEvent::ConnectionRejected {
+                peer: event_peer,
+                endpoint,
+                reason,
+            } => {
+                assert_eq!(event_peer, peer);
+                assert_eq!(endpoint, Endpoint::listener(address, connection_id));
+                assert_eq!(
+                    reason,
+                    ConnectionRejectReason::LimitExceeded(
+                        ConnectionLimitsError::MaxIncomingConnectionsExceeded
+                    ),
+                );
+            }
+            event => panic!("invalid event: {event:?}"),
+        }
+        assert!(manager.peers.read().get(&peer).is_none());
+    }
+
+    #[tokio::test]
+    async fn dial_rejected_by_connection_gater() {
+        struct DenyAll;
+
+        impl ConnectionGater for DenyAll {
+            fn allow_dial(&self, _peer: &PeerId, _address: &Multiaddr) -> bool {
+                false
+            }
+        }
+
+        let (mut manager, _handle) = TransportManager::new(
+            Keypair::generate(),
+            HashSet::new(),
+            BandwidthSink::new(),
+            8usize,
+        );
+        manager.set_connection_gater(Arc::new(DenyAll));
+        manager.register_transport(SupportedTransport::Tcp, Box::new(DummyTransport::new()));
+
+        let peer = PeerId::random();
+        let address = Multiaddr::empty()
+            .with(Protocol::Ip4(Ipv4Addr::new(127, 0, 0, 1)))
+            .with(Protocol::Tcp(8888))
+            .with(Protocol::P2p(Multihash::from(peer)));
+
+        match manager.dial_address(address.clone()).await {
+            Err(Error::ConnectionRejected(ConnectionRejectReason::Gated)) => {}
+            result => panic!("invalid result: {result:?}"),
+        }
+
+        manager.add_known_address(peer, vec![address].into_iter());
+        match manager.dial(peer).await {
+            Err(Error::ConnectionRejected(ConnectionRejectReason::Gated)) => {}
+            result => panic!("invalid result: {result:?}"),
+        }
+        assert!(manager.pending_connections.is_empty());
+    }
+
+    #[tokio::test]
+    async fn banned_peer_is_disconnected_and_rejected() {
+        let (mut manager, handle) = TransportManager::new(
+            Keypair::generate(),
+            HashSet::new(),
+            BandwidthSink::new(),
+            8usize,
+        );
+        let peer = PeerId::random();
+        let address = Multiaddr::empty()
+            .with(Protocol::Ip4(Ipv4Addr::new(127, 0, 0, 1)))
+            .with(Protocol::Tcp(8888))
+            .with(Protocol::P2p(Multihash::from(peer)));
+        let connection_id = ConnectionId::random();
+
+        let transport = Box::new({
+            let mut transport = DummyTransport::new();
+            transport.inject_event(TransportEvent::ConnectionEstablished {
+                peer,
+                endpoint: Endpoint::listener(address.clone(), connection_id),
+            });
+            transport
+        });
+        manager.register_transport(SupportedTransport::Tcp, transport);
+
+        let (tx, mut rx) = channel(64);
+        let mut connection = ConnectionHandle::new(connection_id, tx);
+        manager.connection_handles.insert(connection_id, (peer, connection.downgrade()));
+
+        manager.ban_peer(peer, Duration::from_secs(60));
+        assert!(std::matches!(
+            rx.try_recv(),
+            Ok(ProtocolCommand::ForceClose {
+                reason: ConnectionCloseReason::Banned
+            })
+        ));
+
+        match manager.next().await.unwrap() {
+            TransportEvent::ConnectionRejected { reason, .. } =>
+                assert_eq!(reason, ConnectionRejectReason::Banned),
+            event => panic!("invalid event: {event:?}"),
+        }
+
+        match manager.dial_address(address.clone()).await {
+            Err(Error::ConnectionRejected(ConnectionRejectReason::Banned)) => {}
+            result => panic!("invalid result: {result:?}"),
+        }
+        match handle.dial(&peer) {
+            Err(Error::ConnectionRejected(ConnectionRejectReason::Banned)) => {}
+            result => panic!("invalid result: {result:?}"),
+        }
+
+        assert!(manager.unban_peer(&peer));
+        assert!(handle.dial_address(address).is_ok());
+    }
 }
@@ -19,8 +19,12 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    transport::manager::address::{AddressRecord, AddressStore},
+    transport::{
+        manager::address::{AddressRecord, AddressStore},
+        Muxer, Security,
+    },
     types::ConnectionId,
+    Error,
 };
 
 use multiaddr::Multiaddr;
@@ -41,6 +45,29 @@ pub enum SupportedTransport {
 
     /// WebSocket
     WebSocket,
+
+    /// In-memory transport.
+    Memory,
+}
+
+impl SupportedTransport {
+    /// Get the security protocol of the connections of the transport.
+    pub fn security(&self) -> Security {
+        match self {
+            Self::Tcp | Self::WebSocket | Self::Memory => Security::Noise,
+            Self::Quic => Security::Tls,
+            Self::WebRtc => Security::DtlsNoise,
+        }
+    }
+
+    /// Get the stream multiplexer of the connections of the transport.
+    pub fn muxer(&self) -> Muxer {
+        match self {
+            Self::Tcp | Self::WebSocket | Self::Memory => Muxer::Yamux,
+            Self::Quic => Muxer::Quic,
+            Self::WebRtc => Muxer::DataChannel,
+        }
+    }
 }
 
 /// Peer state.
@@ -70,6 +97,9 @@ pub enum PeerState {
 
         /// Active transports.
         transports: HashSet<SupportedTransport>,
+
+        /// Addresses that failed to open and their errors.
+        errors: Vec<(Multiaddr, Error)>,
     },
 
     /// Peer is being dialed.
@@ -0,0 +1,81 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory transport configuration.
+
+use crate::{
+    crypto::noise::{MAX_READ_AHEAD_FACTOR, MAX_WRITE_BUFFER_SIZE},
+    transport::{CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT},
+};
+
+/// Memory transport configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Listen addresses for the transport.
+    ///
+    /// `/memory/0` allocates a free port for the listener.
+    ///
+    /// Default listen address is ["/memory/0"].
+    pub listen_addresses: Vec<multiaddr::Multiaddr>,
+
+    /// Yamux configuration.
+    pub yamux_config: crate::yamux::Config,
+
+    /// Noise read-ahead frame count.
+    ///
+    /// Specifies how many Noise frames are read per call to the underlying stream.
+    pub noise_read_ahead_frame_count: usize,
+
+    /// Noise write buffer size.
+    ///
+    /// Specifes how many Noise frames are tried to be coalesced into a single write.
+    pub noise_write_buffer_size: usize,
+
+    /// Size of the in-memory buffer of each direction of a connection.
+    ///
+    /// Defaults to `64 KB`.
+    pub buffer_size: usize,
+
+    /// Connection open timeout.
+    ///
+    /// How long should litep2p wait for a connection to be opened before the host
+    /// is deemed unreachable.
+    pub connection_open_timeout: std::time::Duration,
+
+    /// Substream open timeout.
+    ///
+    /// How long should litep2p wait for a substream to be opened before considering
+    /// the substream rejected.
+    pub substream_open_timeout: std::time::Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addresses: vec!["/memory/0".parse().expect("valid address")],
+            yamux_config: Default::default(),
+            noise_read_ahead_frame_count: MAX_READ_AHEAD_FACTOR,
+            noise_write_buffer_size: MAX_WRITE_BUFFER_SIZE,
+            buffer_size: 64 * 1024,
+            connection_open_timeout: CONNECTION_OPEN_TIMEOUT,
+            substream_open_timeout: SUBSTREAM_OPEN_TIMEOUT,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory listener.
+//!
+//! Listeners register their port in a process-wide registry through which dialers hand them
+//! one half of an in-memory duplex stream.
+
+use crate::error::{AddressError, Error};
+
+use futures::Stream;
+use multiaddr::{Multiaddr, Protocol};
+use parking_lot::Mutex;
+use tokio::{io::DuplexStream, sync::mpsc};
+
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::memory::listener";
+
+/// Maximum number of connections waiting to be accepted by a listener.
+const ACCEPT_QUEUE_SIZE: usize = 64;
+
+/// Active listeners, keyed by port.
+static LISTENERS: Mutex<BTreeMap<u64, mpsc::Sender<(u64, DuplexStream)>>> =
+    parking_lot::const_mutex(BTreeMap::new());
+
+/// Listener bound to a memory port.
+struct Listener {
+    /// Port of the listener.
+    port: u64,
+
+    /// RX channel for inbound connections.
+    rx: mpsc::Receiver<(u64, DuplexStream)>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        LISTENERS.lock().remove(&self.port);
+    }
+}
+
+/// Memory listener.
+#[derive(Default)]
+pub(super) struct MemoryListener {
+    /// Listeners.
+    listeners: Vec<Listener>,
+
+    /// The index in the listeners from which the polling is resumed.
+    poll_index: usize,
+}
+
+impl MemoryListener {
+    /// Start listening on `address`.
+    ///
+    /// Returns the address the new listener is reachable at.
+    pub(super) fn listen_on(&mut self, address: &Multiaddr) -> crate::Result<Multiaddr> {
+        let (port, _) = Self::get_port(address)?;
+        let (tx, rx) = mpsc::channel(ACCEPT_QUEUE_SIZE);
+
+        let port = {
+            let mut listeners = LISTENERS.lock();
+
+            let port = match port {
+                0 => Self::free_port(&listeners),
+                port if listeners.contains_key(&port) =>
+                    return Err(Error::IoError(ErrorKind::AddrInUse)),
+                port => port,
+            };
+            listeners.insert(port, tx);
+
+            port
+        };
+
+        tracing::trace!(target: LOG_TARGET, ?port, "start listening");
+
+        self.listeners.push(Listener { port, rx });
+
+        Ok(Self::port_to_multiaddr(port))
+    }
+
+    /// Stop the listener bound to `address`.
+    ///
+    /// Returns the address the removed listener was reachable at.
+    pub(super) fn remove_listener(&mut self, address: &Multiaddr) -> Option<Multiaddr> {
+        let (port, _) = Self::get_port(address).ok()?;
+        let index = self.listeners.iter().position(|listener| listener.port == port)?;
+
+        self.listeners.remove(index);
+        self.poll_index = 0;
+
+        Some(Self::port_to_multiaddr(port))
+    }
+
+    /// Open connection to the listener bound to `port`.
+    ///
+    /// Returns the port allocated for the dialer and the dialer's half of the stream.
+    pub(super) fn dial(port: u64, buffer_size: usize) -> crate::Result<(u64, DuplexStream)> {
+        let listeners = LISTENERS.lock();
+        let tx = listeners
+            .get(&port)
+            .ok_or(Error::IoError(ErrorKind::ConnectionRefused))?
+            .clone();
+        let dialer_port = Self::free_port(&listeners);
+        drop(listeners);
+
+        let (dialer, listener) = tokio::io::duplex(buffer_size);
+        tx.try_send((dialer_port, listener))
+            .map_err(|_| Error::IoError(ErrorKind::ConnectionRefused))?;
+
+        Ok((dialer_port, dialer))
+    }
+
+    /// Allocate a port which no listener is bound to.
+    fn free_port(listeners: &BTreeMap<u64, mpsc::Sender<(u64, DuplexStream)>>) -> u64 {
+        loop {
+            let port = rand::random::<u64>();
+
+            if port != 0 && !listeners.contains_key(&port) {
+                return port;
+            }
+        }
+    }
+
+    /// Extract port and `PeerId`, if exists, from `address`.
+    pub(super) fn get_port(address: &Multiaddr) -> crate::Result<(u64, Option<crate::PeerId>)> {
+        tracing::trace!(target: LOG_TARGET, ?address, "parse multi address");
+
+        let mut iter = address.iter();
+        let port = match iter.next() {
+            Some(Protocol::Memory(port)) => port,
+            protocol => {
+                tracing::error!(
+                    target: LOG_TARGET,
+                    ?protocol,
+                    "invalid transport protocol, expected `Memory`",
+                );
+                return Err(Error::AddressError(AddressError::InvalidProtocol));
+            }
+        };
+
+        let maybe_peer = match iter.next() {
+            Some(Protocol::P2p(multihash)) => Some(crate::PeerId::from_multihash(multihash)?),
+            None => None,
+            protocol => {
+                tracing::error!(
+                    target: LOG_TARGET,
+                    ?protocol,
+                    "invalid protocol, expected `P2p` or `None`"
+                );
+                return Err(Error::AddressError(AddressError::InvalidProtocol));
+            }
+        };
+
+        Ok((port, maybe_peer))
+    }
+
+    /// Convert `port` into `Multiaddr`.
+    pub(super) fn port_to_multiaddr(port: u64) -> Multiaddr {
+        Multiaddr::empty().with(Protocol::Memory(port))
+    }
+}
+
+impl Stream for MemoryListener {
+    /// Local port, remote port and the listener's half of the stream.
+    type Item = (u64, u64, DuplexStream);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let len = self.listeners.len();
+
+        for i in 0..len {
+            let index = (self.poll_index + i) % len;
+
+            let listener = &mut self.listeners[index];
+
+            if let Poll::Ready(Some((remote_port, stream))) = listener.rx.poll_recv(cx) {
+                let local_port = listener.port;
+                self.poll_index = (index + 1) % len;
+
+                return Poll::Ready(Some((local_port, remote_port, stream)));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn parse_multiaddresses() {
+        let peer = crate::PeerId::random();
+
+        assert_eq!(
+            MemoryListener::get_port(&"/memory/1337".parse().unwrap()).unwrap(),
+            (1337, None)
+        );
+        assert_eq!(
+            MemoryListener::get_port(&format!("/memory/1337/p2p/{peer}").parse().unwrap()).unwrap(),
+            (1337, Some(peer))
+        );
+        assert!(MemoryListener::get_port(&"/ip4/127.0.0.1/tcp/1337".parse().unwrap()).is_err());
+        assert!(MemoryListener::get_port(&"/memory/1337/memory/1".parse().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn dial_and_accept() {
+        let mut listener = MemoryListener::default();
+        let address = listener.listen_on(&"/memory/0".parse().unwrap()).unwrap();
+        let (port, _) = MemoryListener::get_port(&address).unwrap();
+        assert_ne!(port, 0);
+
+        let (dialer_port, _stream) = MemoryListener::dial(port, 1024).unwrap();
+        let (local_port, remote_port, _stream) = listener.next().await.unwrap();
+        assert_eq!(local_port, port);
+        assert_eq!(dialer_port, remote_port);
+    }
+
+    #[tokio::test]
+    async fn port_in_use() {
+        let mut listener = MemoryListener::default();
+        let address = listener.listen_on(&"/memory/0".parse().unwrap()).unwrap();
+
+        assert!(std::matches!(
+            MemoryListener::default().listen_on(&address),
+            Err(Error::IoError(ErrorKind::AddrInUse))
+        ));
+    }
+
+    #[tokio::test]
+    async fn removed_listener_refuses_connections() {
+        let mut listener = MemoryListener::default();
+        let address = listener.listen_on(&"/memory/0".parse().unwrap()).unwrap();
+        let (port, _) = MemoryListener::get_port(&address).unwrap();
+
+        assert_eq!(listener.remove_listener(&address), Some(address.clone()));
+        assert!(listener.remove_listener(&address).is_none());
+        assert!(std::matches!(
+            MemoryListener::dial(port, 1024),
+            Err(Error::IoError(ErrorKind::ConnectionRefused))
+        ));
+    }
+}
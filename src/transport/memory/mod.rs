@@ -0,0 +1,580 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory transport.
+//!
+//! Connections are piped through in-process buffers which allows [`Litep2p`](crate::Litep2p)
+//! instances of the same process to connect to each other without opening sockets. The
+//! connections are still secured with Noise and multiplexed with Yamux.
+
+use crate::{
+    config::Role,
+    error::{AddressError, Error},
+    transport::{
+        manager::TransportHandle,
+        memory::{
+            config::Config,
+            connection::{MemoryConnection, NegotiatedConnection},
+            listener::MemoryListener,
+        },
+        Transport, TransportBuilder, TransportEvent,
+    },
+    types::ConnectionId,
+};
+
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, Stream, StreamExt},
+};
+use multiaddr::Multiaddr;
+use tokio::io::DuplexStream;
+
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub(crate) use substream::Substream;
+
+mod connection;
+mod listener;
+mod substream;
+
+pub mod config;
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::memory";
+
+/// Memory transport.
+pub(crate) struct MemoryTransport {
+    /// Transport context.
+    context: TransportHandle,
+
+    /// Transport configuration.
+    config: Config,
+
+    /// Memory listener.
+    listener: MemoryListener,
+
+    /// Pending dials.
+    pending_dials: HashMap<ConnectionId, Multiaddr>,
+
+    /// Pending opening connections.
+    pending_connections:
+        FuturesUnordered<BoxFuture<'static, Result<NegotiatedConnection, (ConnectionId, Error)>>>,
+
+    /// Pending raw, unnegotiated connections.
+    pending_raw_connections: FuturesUnordered<
+        BoxFuture<
+            'static,
+            Result<
+                (ConnectionId, Multiaddr, DuplexStream),
+                (ConnectionId, Vec<(Multiaddr, Error)>),
+            >,
+        >,
+    >,
+
+    /// Opened raw connection, waiting for approval/rejection from `TransportManager`.
+    opened_raw: HashMap<ConnectionId, (DuplexStream, Multiaddr)>,
+
+    /// Canceled raw connections.
+    canceled: HashSet<ConnectionId>,
+
+    /// Connections which have been opened and negotiated but are being validated by the
+    /// `TransportManager`.
+    pending_open: HashMap<ConnectionId, NegotiatedConnection>,
+}
+
+impl MemoryTransport {
+    /// Handle inbound connection.
+    ///
+    /// Returns [`TransportEvent::IncomingConnection`] if the connection is negotiated.
+    fn on_inbound_connection(
+        &mut self,
+        stream: DuplexStream,
+        local_port: u64,
+        remote_port: u64,
+    ) -> Option<TransportEvent> {
+        let permit = match self.context.pending_incoming.try_acquire() {
+            Ok(permit) => permit,
+            Err(error) => {
+                tracing::debug!(target: LOG_TARGET, ?remote_port, ?error, "rejecting inbound connection");
+                return None;
+            }
+        };
+        let connection_id = self.context.next_connection_id();
+        let local_address = MemoryListener::port_to_multiaddr(local_port);
+        let remote_address = MemoryListener::port_to_multiaddr(remote_port);
+        let address = remote_address.clone();
+        let yamux_config = self.config.yamux_config.clone();
+        let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
+        let max_write_buffer_size = self.config.noise_write_buffer_size;
+        let connection_open_timeout = self.config.connection_open_timeout;
+        let keypair = self.context.keypair.clone();
+
+        self.pending_connections.push(Box::pin(async move {
+            let _permit = permit;
+
+            match tokio::time::timeout(
+                connection_open_timeout,
+                MemoryConnection::negotiate_connection(
+                    stream,
+                    None,
+                    Role::Listener,
+                    address,
+                    connection_id,
+                    keypair,
+                    yamux_config,
+                    max_read_ahead_factor,
+                    max_write_buffer_size,
+                ),
+            )
+            .await
+            {
+                Err(_) => Err((connection_id, Error::Timeout)),
+                Ok(Err(error)) => Err((connection_id, error)),
+                Ok(Ok(connection)) => Ok(connection),
+            }
+        }));
+
+        Some(TransportEvent::IncomingConnection {
+            connection_id,
+            local_address,
+            remote_address,
+        })
+    }
+}
+
+impl TransportBuilder for MemoryTransport {
+    type Config = Config;
+    type Transport = MemoryTransport;
+
+    /// Create new [`MemoryTransport`].
+    fn new(
+        context: TransportHandle,
+        mut config: Self::Config,
+    ) -> crate::Result<(Self, Vec<Multiaddr>)> {
+        tracing::debug!(
+            target: LOG_TARGET,
+            listen_addresses = ?config.listen_addresses,
+            "start memory transport",
+        );
+
+        let mut listener = MemoryListener::default();
+        let listen_addresses = std::mem::take(&mut config.listen_addresses)
+            .iter()
+            .map(|address| listener.listen_on(address))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok((
+            Self {
+                listener,
+                config,
+                context,
+                canceled: HashSet::new(),
+                opened_raw: HashMap::new(),
+                pending_open: HashMap::new(),
+                pending_dials: HashMap::new(),
+                pending_connections: FuturesUnordered::new(),
+                pending_raw_connections: FuturesUnordered::new(),
+            },
+            listen_addresses,
+        ))
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn dial(&mut self, connection_id: ConnectionId, address: Multiaddr) -> crate::Result<()> {
+        tracing::debug!(target: LOG_TARGET, ?connection_id, ?address, "open connection");
+
+        let (port, peer) = MemoryListener::get_port(&address)?;
+        let yamux_config = self.config.yamux_config.clone();
+        let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
+        let max_write_buffer_size = self.config.noise_write_buffer_size;
+        let connection_open_timeout = self.config.connection_open_timeout;
+        let buffer_size = self.config.buffer_size;
+        let keypair = self.context.keypair.clone();
+
+        self.pending_dials.insert(connection_id, address);
+        self.pending_connections.push(Box::pin(async move {
+            let (_, stream) =
+                MemoryListener::dial(port, buffer_size).map_err(|error| (connection_id, error))?;
+
+            match tokio::time::timeout(
+                connection_open_timeout,
+                MemoryConnection::negotiate_connection(
+                    stream,
+                    peer,
+                    Role::Dialer,
+                    MemoryListener::port_to_multiaddr(port),
+                    connection_id,
+                    keypair,
+                    yamux_config,
+                    max_read_ahead_factor,
+                    max_write_buffer_size,
+                ),
+            )
+            .await
+            {
+                Err(_) => Err((connection_id, Error::Timeout)),
+                Ok(Err(error)) => Err((connection_id, error)),
+                Ok(Ok(connection)) => Ok(connection),
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn accept(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        let context = self
+            .pending_open
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+        let protocol_set = self.context.protocol_set(connection_id);
+        let bandwidth_sink = self.context.bandwidth_sink.clone();
+        let substream_open_timeout = self.config.substream_open_timeout;
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?connection_id,
+            "start connection",
+        );
+
+        self.context.executor.run(Box::pin(async move {
+            if let Err(error) = MemoryConnection::new(
+                context,
+                protocol_set,
+                bandwidth_sink,
+                substream_open_timeout,
+            )
+            .start()
+            .await
+            {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?connection_id,
+                    ?error,
+                    "connection exited with error",
+                );
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn reject(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        self.canceled.insert(connection_id);
+        self.pending_open
+            .remove(&connection_id)
+            .map_or(Err(Error::ConnectionDoesntExist(connection_id)), |_| Ok(()))
+    }
+
+    fn open(
+        &mut self,
+        connection_id: ConnectionId,
+        addresses: Vec<Multiaddr>,
+    ) -> crate::Result<()> {
+        let buffer_size = self.config.buffer_size;
+
+        // connecting to a memory listener completes immediately so only the first reachable
+        // address is dialed
+        let mut errors = Vec::new();
+        let connection =
+            addresses.into_iter().find_map(|address| {
+                match MemoryListener::get_port(&address)
+                    .and_then(|(port, _)| MemoryListener::dial(port, buffer_size))
+                {
+                    Ok((_, stream)) => Some((address, stream)),
+                    Err(error) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?connection_id,
+                            ?address,
+                            ?error,
+                            "failed to open connection",
+                        );
+                        errors.push((address, error));
+                        None
+                    }
+                }
+            });
+
+        self.pending_raw_connections.push(Box::pin(async move {
+            match connection {
+                Some((address, stream)) => Ok((connection_id, address, stream)),
+                None => Err((connection_id, errors)),
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn negotiate(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        let (stream, address) = self
+            .opened_raw
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+
+        let (port, peer) = MemoryListener::get_port(&address)?;
+        let yamux_config = self.config.yamux_config.clone();
+        let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
+        let max_write_buffer_size = self.config.noise_write_buffer_size;
+        let connection_open_timeout = self.config.connection_open_timeout;
+        let keypair = self.context.keypair.clone();
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            ?connection_id,
+            ?address,
+            "negotiate connection",
+        );
+
+        self.pending_dials.insert(connection_id, address);
+        self.pending_connections.push(Box::pin(async move {
+            match tokio::time::timeout(
+                connection_open_timeout,
+                MemoryConnection::negotiate_connection(
+                    stream,
+                    peer,
+                    Role::Dialer,
+                    MemoryListener::port_to_multiaddr(port),
+                    connection_id,
+                    keypair,
+                    yamux_config,
+                    max_read_ahead_factor,
+                    max_write_buffer_size,
+                ),
+            )
+            .await
+            {
+                Err(_) => Err((connection_id, Error::Timeout)),
+                Ok(Err(error)) => Err((connection_id, error)),
+                Ok(Ok(connection)) => Ok(connection),
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, connection_id: ConnectionId) {
+        self.canceled.insert(connection_id);
+    }
+
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "start listening");
+
+        Ok(vec![self.listener.listen_on(&address)?])
+    }
+
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "remove listener");
+
+        self.listener
+            .remove_listener(address)
+            .map(|address| vec![address])
+            .ok_or(Error::AddressError(AddressError::AddressNotAvailable))
+    }
+}
+
+impl Stream for MemoryTransport {
+    type Item = TransportEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Poll::Ready(Some((local_port, remote_port, stream))) =
+            self.listener.poll_next_unpin(cx)
+        {
+            if let Some(event) = self.on_inbound_connection(stream, local_port, remote_port) {
+                return Poll::Ready(Some(event));
+            }
+        }
+
+        while let Poll::Ready(Some(result)) = self.pending_raw_connections.poll_next_unpin(cx) {
+            match result {
+                Ok((connection_id, address, stream)) => {
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?connection_id,
+                        ?address,
+                        canceled = self.canceled.contains(&connection_id),
+                        "connection opened",
+                    );
+
+                    if !self.canceled.remove(&connection_id) {
+                        self.opened_raw.insert(connection_id, (stream, address.clone()));
+
+                        return Poll::Ready(Some(TransportEvent::ConnectionOpened {
+                            connection_id,
+                            address,
+                        }));
+                    }
+                }
+                Err((connection_id, errors)) =>
+                    if !self.canceled.remove(&connection_id) {
+                        return Poll::Ready(Some(TransportEvent::OpenFailure {
+                            connection_id,
+                            errors,
+                        }));
+                    },
+            }
+        }
+
+        if let Poll::Ready(Some(connection)) = self.pending_connections.poll_next_unpin(cx) {
+            match connection {
+                Ok(connection) => {
+                    let peer = connection.peer();
+                    let endpoint = connection.endpoint();
+                    self.context.metrics.on_handshake("noise", connection.handshake_duration());
+                    self.pending_open.insert(connection.connection_id(), connection);
+
+                    return Poll::Ready(Some(TransportEvent::ConnectionEstablished {
+                        peer,
+                        endpoint,
+                    }));
+                }
+                Err((connection_id, error)) => {
+                    return Poll::Ready(Some(match self.pending_dials.remove(&connection_id) {
+                        Some(address) => TransportEvent::DialFailure {
+                            connection_id,
+                            address,
+                            error,
+                        },
+                        None => TransportEvent::IncomingConnectionError {
+                            connection_id,
+                            error,
+                        },
+                    }));
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codec::ProtocolCodec,
+        crypto::ed25519::Keypair,
+        executor::DefaultExecutor,
+        transport::manager::{ProtocolContext, TransportHandle},
+        types::protocol::ProtocolName,
+        BandwidthSink, PeerId,
+    };
+    use multiaddr::Protocol;
+    use multihash::Multihash;
+    use std::sync::Arc;
+    use tokio::sync::{mpsc::channel, watch};
+
+    fn make_transport(keypair: Keypair) -> (MemoryTransport, Multiaddr) {
+        let (tx, _rx) = channel(64);
+        let (event_tx, _event_rx) = channel(64);
+
+        let handle = TransportHandle {
+            executor: Arc::new(DefaultExecutor {}),
+            protocol_names: Vec::new(),
+            next_substream_id: Default::default(),
+            next_connection_id: Default::default(),
+            keypair,
+            tx: event_tx,
+            bandwidth_sink: BandwidthSink::new(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
+
+            protocols: watch::channel(HashMap::from_iter([(
+                ProtocolName::from("/notif/1"),
+                ProtocolContext {
+                    tx,
+                    codec: ProtocolCodec::Identity(32),
+                    fallback_names: Vec::new(),
+                    inbound: true,
+                },
+            )]))
+            .1,
+        };
+
+        let (transport, mut listen_addresses) =
+            MemoryTransport::new(handle, Default::default()).unwrap();
+
+        (transport, listen_addresses.remove(0))
+    }
+
+    #[tokio::test]
+    async fn connect_and_accept_works() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let keypair1 = Keypair::generate();
+        let peer1 = PeerId::from_public_key(&keypair1.public().into());
+        let (mut transport1, listen_address) = make_transport(keypair1);
+        let (mut transport2, _) = make_transport(Keypair::generate());
+
+        transport2
+            .dial(
+                ConnectionId::new(),
+                listen_address.with(Protocol::P2p(Multihash::from(peer1))),
+            )
+            .unwrap();
+
+        let (res1, res2) = tokio::join!(
+            async {
+                assert!(std::matches!(
+                    transport1.next().await,
+                    Some(TransportEvent::IncomingConnection { .. })
+                ));
+                transport1.next().await
+            },
+            transport2.next()
+        );
+
+        assert!(std::matches!(
+            res1,
+            Some(TransportEvent::ConnectionEstablished { .. })
+        ));
+        assert!(std::matches!(
+            res2,
+            Some(TransportEvent::ConnectionEstablished { peer, .. }) if peer == peer1
+        ));
+    }
+
+    #[tokio::test]
+    async fn dial_failure() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (mut transport1, listen_address) = make_transport(Keypair::generate());
+        transport1.remove_listener(&listen_address).unwrap();
+
+        let (mut transport2, _) = make_transport(Keypair::generate());
+        transport2.dial(ConnectionId::new(), listen_address).unwrap();
+
+        assert!(std::matches!(
+            transport2.next().await,
+            Some(TransportEvent::DialFailure { .. })
+        ));
+    }
+}
@@ -28,6 +28,7 @@ use multiaddr::Multiaddr;
 use std::{fmt::Debug, time::Duration};
 
 pub(crate) mod common;
+pub mod memory;
 pub mod quic;
 pub mod tcp;
 pub mod webrtc;
@@ -36,9 +37,20 @@ pub mod websocket;
 pub(crate) mod dummy;
 pub(crate) mod manager;
 
+pub use manager::{
+    ban::{IpNetwork, IpNetworkError},
+    limits::{
+        ConnectionGater, ConnectionLimitsConfig, ConnectionLimitsError, ConnectionRejectReason,
+    },
+    SupportedTransport,
+};
+
 /// Timeout for opening a connection.
 pub(crate) const CONNECTION_OPEN_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// Timeout for closing a connection gracefully.
+pub(crate) const CONNECTION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Timeout for opening a substream.
 pub(crate) const SUBSTREAM_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
 
@@ -106,6 +118,92 @@ impl Endpoint {
     }
 }
 
+/// Security protocol of an established connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    /// Noise handshake.
+    Noise,
+
+    /// TLS 1.3 handshake of QUIC.
+    Tls,
+
+    /// DTLS with a Noise handshake, as used by WebRTC.
+    DtlsNoise,
+}
+
+/// Stream multiplexer of an established connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Muxer {
+    /// Yamux.
+    Yamux,
+
+    /// Native QUIC streams.
+    Quic,
+
+    /// WebRTC data channels.
+    DataChannel,
+}
+
+/// Reason why a connection was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionCloseReason {
+    /// Connection was closed by the local node.
+    LocalClose,
+
+    /// Local node is shutting down.
+    Shutdown,
+
+    /// Remote peer closed the connection, for example by sending a yamux `GoAway` frame.
+    RemoteGoAway,
+
+    /// Remote peer stopped responding to keep-alive messages.
+    KeepAliveTimeout,
+
+    /// Remote peer violated the yamux protocol.
+    YamuxError,
+
+    /// I/O error occurred on the underlying socket.
+    IoError(std::io::ErrorKind),
+
+    /// None of the installed protocols were using the connection.
+    Idle,
+
+    /// Remote peer was banned.
+    Banned,
+
+    /// Connection exceeded a limit, such as the maximum number of streams.
+    LimitExceeded,
+}
+
+impl From<&crate::yamux::ConnectionError> for ConnectionCloseReason {
+    fn from(error: &crate::yamux::ConnectionError) -> Self {
+        match error {
+            crate::yamux::ConnectionError::Io(error)
+            | crate::yamux::ConnectionError::Decode(crate::yamux::FrameDecodeError::Io(error)) =>
+                match error.kind() {
+                    std::io::ErrorKind::TimedOut => Self::KeepAliveTimeout,
+                    kind => Self::IoError(kind),
+                },
+            crate::yamux::ConnectionError::Closed => Self::RemoteGoAway,
+            crate::yamux::ConnectionError::TooManyStreams => Self::LimitExceeded,
+            crate::yamux::ConnectionError::Decode(_)
+            | crate::yamux::ConnectionError::NoMoreStreamIds => Self::YamuxError,
+        }
+    }
+}
+
+impl From<&quinn::ConnectionError> for ConnectionCloseReason {
+    fn from(error: &quinn::ConnectionError) -> Self {
+        match error {
+            quinn::ConnectionError::ApplicationClosed(_)
+            | quinn::ConnectionError::ConnectionClosed(_) => Self::RemoteGoAway,
+            quinn::ConnectionError::TimedOut => Self::KeepAliveTimeout,
+            quinn::ConnectionError::LocallyClosed => Self::LocalClose,
+            error => Self::IoError(std::io::Error::from(error.clone()).kind()),
+        }
+    }
+}
+
 /// Transport event.
 #[derive(Debug)]
 pub(crate) enum TransportEvent {
@@ -135,6 +233,9 @@ pub(crate) enum TransportEvent {
 
         /// Connection ID.
         connection_id: ConnectionId,
+
+        /// Reason why the connection was closed.
+        reason: ConnectionCloseReason,
     },
 
     /// Failed to dial remote peer.
@@ -153,6 +254,59 @@ pub(crate) enum TransportEvent {
     OpenFailure {
         /// Connection ID.
         connection_id: ConnectionId,
+
+        /// Addresses that were tried and the errors they failed with.
+        errors: Vec<(Multiaddr, Error)>,
+    },
+
+    /// Negotiated connection was rejected by the connection gater or the connection limits.
+    ConnectionRejected {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Endpoint.
+        endpoint: Endpoint,
+
+        /// Reason for the rejection.
+        reason: ConnectionRejectReason,
+    },
+
+    /// Inbound connection accepted by a listener but not yet negotiated.
+    IncomingConnection {
+        /// Connection ID.
+        connection_id: ConnectionId,
+
+        /// Local address the connection was accepted on.
+        local_address: Multiaddr,
+
+        /// Remote address.
+        remote_address: Multiaddr,
+    },
+
+    /// Failed to negotiate an inbound connection.
+    IncomingConnectionError {
+        /// Connection ID.
+        connection_id: ConnectionId,
+
+        /// Error.
+        error: Error,
+    },
+
+    /// Listener failed to accept an inbound connection.
+    ///
+    /// The listener keeps accepting connections.
+    ListenerError {
+        /// Address of the listener.
+        address: Multiaddr,
+
+        /// Error.
+        error: Error,
+    },
+
+    /// Listener was closed and no longer accepts inbound connections.
+    ListenerClosed {
+        /// Address of the listener.
+        address: Multiaddr,
     },
 }
 
@@ -191,4 +345,15 @@ pub(crate) trait Transport: Stream + Unpin + Send {
     ///
     /// This is a no-op for connections that have already succeeded/canceled.
     fn cancel(&mut self, connection_id: ConnectionId);
+
+    /// Start listening on `address`.
+    ///
+    /// Returns the addresses the new listener is reachable at.
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>>;
+
+    /// Stop the listener which is either bound to or reachable at `address`.
+    ///
+    /// Connections accepted by the listener are not closed. Returns the addresses the removed
+    /// listener was reachable at.
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>>;
 }
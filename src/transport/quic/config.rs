@@ -20,11 +20,32 @@
 
 //! QUIC transport configuration.
 
-use crate::transport::{CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT};
+use crate::{
+    error::Error,
+    transport::{CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT},
+};
 
 use multiaddr::Multiaddr;
+use quinn::{
+    congestion::{BbrConfig, CubicConfig, NewRenoConfig},
+    IdleTimeout, MtuDiscoveryConfig, TransportConfig, VarInt,
+};
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
+
+/// Congestion control algorithm.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CongestionController {
+    /// CUBIC congestion control.
+    #[default]
+    Cubic,
+
+    /// NewReno congestion control.
+    NewReno,
+
+    /// BBR congestion control.
+    Bbr,
+}
 
 /// QUIC transport configuration.
 #[derive(Debug)]
@@ -34,6 +55,15 @@ pub struct Config {
     /// Default listen addres is `/ip4/127.0.0.1/udp/0/quic-v1`.
     pub listen_addresses: Vec<Multiaddr>,
 
+    /// Whether to dial outbound connections through the listening endpoints.
+    ///
+    /// If enabled, outbound connections are sent from the port of a listener of the same address
+    /// family, which keeps NAT port mappings intact. If no suitable listener exists or this is
+    /// disabled, a new endpoint bound to an ephemeral port is created for each dial.
+    ///
+    /// Defaults to `true`.
+    pub reuse_endpoint: bool,
+
     /// Connection open timeout.
     ///
     /// How long should litep2p wait for a connection to be opend before the host
@@ -45,14 +75,169 @@ pub struct Config {
     /// How long should litep2p wait for a substream to be opened before considering
     /// the substream rejected.
     pub substream_open_timeout: Duration,
+
+    /// Keep-alive interval.
+    ///
+    /// Period of inactivity after which a keep-alive packet is sent to the remote peer. Must be
+    /// lower than [`Config::max_idle_timeout`] of both peers to keep the connection open.
+    ///
+    /// Defaults to `None` which disables keep-alive packets.
+    pub keep_alive_interval: Option<Duration>,
+
+    /// Maximum idle timeout.
+    ///
+    /// Connection is closed if no packets have been received during this time. The effective
+    /// timeout is the smaller of the timeouts of the two peers.
+    ///
+    /// Defaults to `10 seconds`.
+    pub max_idle_timeout: Duration,
+
+    /// Maximum number of concurrent bidirectional streams the remote peer is allowed to open.
+    ///
+    /// Defaults to `100`.
+    pub max_concurrent_bidi_streams: u32,
+
+    /// Maximum number of bytes the remote peer may send on a single stream before receiving
+    /// an acknowledgement.
+    ///
+    /// Defaults to `1.25 MB`.
+    pub stream_receive_window: u32,
+
+    /// Maximum number of bytes the remote peer may send on all streams of the connection before
+    /// receiving an acknowledgement.
+    ///
+    /// Defaults to `2^62 - 1` which is the maximum allowed by QUIC.
+    pub receive_window: u64,
+
+    /// Congestion control algorithm.
+    ///
+    /// Defaults to [`CongestionController::Cubic`].
+    pub congestion_controller: CongestionController,
+
+    /// Whether to probe the path for a maximum packet size larger than the initial 1200 bytes.
+    ///
+    /// If disabled, the packets are kept at the initial size for the lifetime of the connection.
+    ///
+    /// Defaults to `true`.
+    pub mtu_discovery: bool,
+
+    /// Maximum number of bytes of incoming datagrams to buffer.
+    ///
+    /// `None` disables incoming datagrams.
+    ///
+    /// Defaults to `1.25 MB`.
+    pub datagram_receive_buffer_size: Option<usize>,
+
+    /// Maximum number of bytes of outgoing datagrams to buffer.
+    ///
+    /// Defaults to `1 MB`.
+    pub datagram_send_buffer_size: usize,
+}
+
+impl Config {
+    /// Create `quinn` transport configuration shared by all connections of the transport.
+    pub(crate) fn transport_config(&self) -> crate::Result<TransportConfig> {
+        let max_idle_timeout = IdleTimeout::try_from(self.max_idle_timeout)
+            .map_err(|error| Error::Other(error.to_string()))?;
+
+        let mut config = TransportConfig::default();
+        config
+            .keep_alive_interval(self.keep_alive_interval)
+            .max_idle_timeout(Some(max_idle_timeout))
+            .max_concurrent_bidi_streams(VarInt::from_u32(self.max_concurrent_bidi_streams))
+            .stream_receive_window(VarInt::from_u32(self.stream_receive_window))
+            .receive_window(
+                VarInt::from_u64(self.receive_window)
+                    .map_err(|error| Error::Other(error.to_string()))?,
+            )
+            .datagram_receive_buffer_size(self.datagram_receive_buffer_size)
+            .datagram_send_buffer_size(self.datagram_send_buffer_size)
+            .mtu_discovery_config(self.mtu_discovery.then(MtuDiscoveryConfig::default));
+
+        match self.congestion_controller {
+            CongestionController::Cubic =>
+                config.congestion_controller_factory(Arc::new(CubicConfig::default())),
+            CongestionController::NewReno =>
+                config.congestion_controller_factory(Arc::new(NewRenoConfig::default())),
+            CongestionController::Bbr =>
+                config.congestion_controller_factory(Arc::new(BbrConfig::default())),
+        };
+
+        Ok(config)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             listen_addresses: vec!["/ip4/127.0.0.1/udp/0/quic-v1".parse().expect("valid address")],
+            reuse_endpoint: true,
             connection_open_timeout: CONNECTION_OPEN_TIMEOUT,
             substream_open_timeout: SUBSTREAM_OPEN_TIMEOUT,
+            keep_alive_interval: None,
+            max_idle_timeout: Duration::from_secs(10),
+            max_concurrent_bidi_streams: 100,
+            stream_receive_window: 1_250_000,
+            receive_window: VarInt::MAX.into_inner(),
+            congestion_controller: CongestionController::Cubic,
+            mtu_discovery: true,
+            datagram_receive_buffer_size: Some(1_250_000),
+            datagram_send_buffer_size: 1024 * 1024,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_transport_config() {
+        assert!(Config::default().transport_config().is_ok());
+    }
+
+    #[test]
+    fn invalid_transport_config() {
+        let config = Config {
+            receive_window: u64::MAX,
+            ..Default::default()
+        };
+        assert!(std::matches!(
+            config.transport_config(),
+            Err(Error::Other(_))
+        ));
+
+        let config = Config {
+            max_idle_timeout: Duration::from_secs(u64::MAX),
+            ..Default::default()
+        };
+        assert!(std::matches!(
+            config.transport_config(),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn congestion_controllers() {
+        for congestion_controller in [
+            CongestionController::Cubic,
+            CongestionController::NewReno,
+            CongestionController::Bbr,
+        ] {
+            let config = Config {
+                congestion_controller,
+                ..Default::default()
+            };
+            assert!(config.transport_config().is_ok());
+        }
+    }
+
+    #[test]
+    fn mtu_discovery_disabled() {
+        let config = Config {
+            mtu_discovery: false,
+            ..Default::default()
+        };
+        assert!(config.transport_config().is_ok());
+    }
+}
@@ -29,8 +29,9 @@ use crate::{
     protocol::{Direction, Permit, ProtocolCommand, ProtocolSet},
     substream,
     transport::{
+        manager::SupportedTransport,
         quic::substream::{NegotiatingSubstream, Substream},
-        Endpoint,
+        ConnectionCloseReason, Endpoint,
     },
     types::{protocol::ProtocolName, SubstreamId},
     BandwidthSink, PeerId,
@@ -269,7 +270,10 @@ impl QuicConnection {
                     }
                     Err(error) => {
                         tracing::debug!(target: LOG_TARGET, peer = ?self.peer, ?error, "failed to accept substream");
-                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await;
+                        return self
+                            .protocol_set
+                            .report_connection_closed(self.peer, self.endpoint.connection_id(), (&error).into())
+                            .await;
                     }
                 },
                 substream = self.pending_substreams.select_next_some(), if !self.pending_substreams.is_empty() => {
@@ -298,9 +302,17 @@ impl QuicConnection {
                         }
                         Ok(substream) => {
                             let protocol = substream.protocol.clone();
+                            let Ok(codec) = self.protocol_set.protocol_codec(&protocol) else {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?protocol,
+                                    "protocol was unregistered, dropping substream",
+                                );
+                                continue;
+                            };
                             let substream_id = substream.substream_id;
                             let direction = substream.direction;
-                            let bandwidth_sink = self.bandwidth_sink.clone();
+                            let bandwidth_sink = self.bandwidth_sink.substream(SupportedTransport::Quic, self.peer, protocol.clone());
                             let substream = substream::Substream::new_quic(
                                 self.peer,
                                 substream_id,
@@ -310,7 +322,8 @@ impl QuicConnection {
                                     substream.receiver,
                                     bandwidth_sink
                                 ),
-                                self.protocol_set.protocol_codec(&protocol)
+                                codec,
+                                self.protocol_set.rate_limiter(SupportedTransport::Quic, &protocol),
                             );
 
                             self.protocol_set
@@ -327,7 +340,10 @@ impl QuicConnection {
                             connection_id = ?self.endpoint.connection_id(),
                             "protocols have dropped connection"
                         );
-                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await;
+                        return self
+                            .protocol_set
+                            .report_connection_closed(self.peer, self.endpoint.connection_id(), ConnectionCloseReason::Idle)
+                            .await;
                     }
                     Some(ProtocolCommand::OpenSubstream { protocol, fallback_names, substream_id, permit }) => {
                         let connection = self.connection.clone();
@@ -367,15 +383,23 @@ impl QuicConnection {
                             }
                         }));
                     }
-                    Some(ProtocolCommand::ForceClose) => {
+                    Some(ProtocolCommand::ForceClose { reason }) => {
                         tracing::debug!(
                             target: LOG_TARGET,
                             peer = ?self.peer,
                             connection_id = ?self.endpoint.connection_id(),
+                            ?reason,
                             "force closing connection",
                         );
 
-                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await;
+                        // on shutdown, send `CONNECTION_CLOSE` right away as the endpoint is about
+                        // to be dropped, otherwise the connection is closed once the protocols have
+                        // dropped their substreams so the data still queued for sending is not discarded
+                        if reason == ConnectionCloseReason::Shutdown {
+                            self.connection.close(0u32.into(), b"shutdown");
+                        }
+
+                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id(), reason).await;
                     }
                 }
             }
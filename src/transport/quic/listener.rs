@@ -24,12 +24,12 @@ use crate::{
     PeerId,
 };
 
-use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, Stream, StreamExt};
+use futures::{future::BoxFuture, FutureExt, Stream};
 use multiaddr::{Multiaddr, Protocol};
-use quinn::{Connecting, Endpoint, ServerConfig};
+use quinn::{Connecting, Endpoint, ServerConfig, TransportConfig};
 
 use std::{
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
@@ -38,16 +38,95 @@ use std::{
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::quic::listener";
 
+/// Listening QUIC endpoint.
+struct Listener {
+    /// QUIC endpoint.
+    endpoint: Endpoint,
+
+    /// Local address of the endpoint.
+    address: SocketAddr,
+
+    /// Pending accept of the next inbound connection.
+    accept: BoxFuture<'static, Option<Connecting>>,
+}
+
+/// Event emitted by [`QuicListener`].
+#[derive(Debug)]
+pub enum ListenerEvent {
+    /// Inbound connection accepted by the listener reachable at `address`.
+    Connection {
+        /// Connection in the middle of the handshake.
+        connecting: Connecting,
+
+        /// Address of the listener.
+        address: Multiaddr,
+    },
+
+    /// Endpoint of the listener reachable at `address` was closed.
+    Closed {
+        /// Address of the listener.
+        address: Multiaddr,
+    },
+}
+
+/// Endpoints to use for outbound connections.
+#[derive(Clone, Default)]
+pub enum DialEndpoints {
+    /// Dial through the endpoints of the listeners.
+    Reuse {
+        /// Local addresses of the listening endpoints and the endpoints.
+        endpoints: Arc<Vec<(SocketAddr, Endpoint)>>,
+    },
+
+    /// Create a new client endpoint for each dial.
+    #[default]
+    NoReuse,
+}
+
+impl DialEndpoints {
+    /// Get endpoint for an outbound connection to `remote_address`.
+    ///
+    /// A listening endpoint is reused if it's bound to the same address family as
+    /// `remote_address` and is able to reach it. Otherwise a new client endpoint bound to
+    /// an ephemeral port is created.
+    pub fn endpoint(&self, remote_address: &SocketAddr) -> crate::Result<Endpoint> {
+        if let DialEndpoints::Reuse { endpoints } = self {
+            let endpoint = endpoints.iter().find(|(address, _)| {
+                address.is_ipv4() == remote_address.is_ipv4()
+                    && (address.ip().is_unspecified()
+                        || address.ip().is_loopback() == remote_address.ip().is_loopback())
+            });
+
+            if let Some((_, endpoint)) = endpoint {
+                return Ok(endpoint.clone());
+            }
+        }
+
+        let address = match remote_address.is_ipv4() {
+            true => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            false => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+
+        Endpoint::client(address).map_err(|error| Error::Other(error.to_string()))
+    }
+}
+
 /// QUIC listener.
 pub struct QuicListener {
-    /// Listen addresses.
-    _listen_addresses: Vec<SocketAddr>,
+    /// Keypair used to create the TLS configuration of new listeners.
+    keypair: Keypair,
 
     /// Listeners.
-    listeners: Vec<Endpoint>,
+    listeners: Vec<Listener>,
+
+    /// The index in the listeners from which the polling is resumed.
+    poll_index: usize,
 
-    /// Incoming connections.
-    incoming: FuturesUnordered<BoxFuture<'static, Option<(usize, Connecting)>>>,
+    /// Whether outbound connections are dialed through the listening endpoints.
+    reuse_endpoint: bool,
+
+    /// Transport configuration of accepted connections.
+    transport_config: Arc<TransportConfig>,
 }
 
 impl QuicListener {
@@ -55,49 +134,89 @@ impl QuicListener {
     pub fn new(
         keypair: &Keypair,
         addresses: Vec<Multiaddr>,
+        reuse_endpoint: bool,
+        transport_config: Arc<TransportConfig>,
     ) -> crate::Result<(Self, Vec<Multiaddr>)> {
-        let mut listeners: Vec<Endpoint> = Vec::new();
-        let mut listen_addresses = Vec::new();
-
-        for address in addresses.into_iter() {
-            let (listen_address, _) = Self::get_socket_address(&address)?;
-            let crypto_config = Arc::new(make_server_config(keypair).expect("to succeed"));
-            let server_config = ServerConfig::with_crypto(crypto_config);
-            let listener = Endpoint::server(server_config, listen_address).unwrap();
-
-            let listen_address = listener.local_addr()?;
-            listen_addresses.push(listen_address);
-            listeners.push(listener);
-            // );
-        }
+        let mut listener = Self {
+            keypair: keypair.clone(),
+            listeners: Vec::new(),
+            poll_index: 0,
+            reuse_endpoint,
+            transport_config,
+        };
 
-        let listen_multi_addresses = listen_addresses
+        let listen_addresses = addresses
             .iter()
-            .cloned()
-            .map(|address| {
-                Multiaddr::empty()
-                    .with(Protocol::from(address.ip()))
-                    .with(Protocol::Udp(address.port()))
-                    .with(Protocol::QuicV1)
-            })
-            .collect();
-
-        Ok((
-            Self {
-                incoming: listeners
-                    .iter_mut()
-                    .enumerate()
-                    .map(|(i, listener)| {
-                        let inner = listener.clone();
-                        async move { inner.accept().await.map(|connecting| (i, connecting)) }
-                            .boxed()
-                    })
-                    .collect(),
-                listeners,
-                _listen_addresses: listen_addresses,
-            },
-            listen_multi_addresses,
-        ))
+            .map(|address| listener.listen_on(address))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok((listener, listen_addresses))
+    }
+
+    /// Start listening on `address`.
+    ///
+    /// Returns the address the new listener is reachable at.
+    pub fn listen_on(&mut self, address: &Multiaddr) -> crate::Result<Multiaddr> {
+        let (listen_address, _) = Self::get_socket_address(address)?;
+        let crypto_config = Arc::new(make_server_config(&self.keypair).expect("to succeed"));
+        let mut server_config = ServerConfig::with_crypto(crypto_config);
+        server_config.transport_config(Arc::clone(&self.transport_config));
+        let endpoint = Endpoint::server(server_config, listen_address)?;
+        let address = endpoint.local_addr()?;
+
+        self.listeners.push(Listener {
+            accept: Self::accept(endpoint.clone()),
+            endpoint,
+            address,
+        });
+
+        Ok(Self::socket_address_to_multiaddr(&address))
+    }
+
+    /// Stop the listener bound to `address`.
+    ///
+    /// Connections accepted by the listener are not affected. Returns the address of the removed
+    /// listener or `None` if no listener matched `address`.
+    pub fn remove_listener(&mut self, address: &Multiaddr) -> Option<Multiaddr> {
+        let (address, _) = Self::get_socket_address(address).ok()?;
+        let index = self.listeners.iter().position(|listener| listener.address == address)?;
+        let listener = self.listeners.remove(index);
+
+        // refuse new connections, the endpoint is kept alive by its open connections
+        listener.endpoint.set_server_config(None);
+
+        tracing::debug!(target: LOG_TARGET, ?address, "listener removed");
+
+        Some(Self::socket_address_to_multiaddr(&listener.address))
+    }
+
+    /// Get endpoints to use for outbound connections.
+    pub fn dial_endpoints(&self) -> DialEndpoints {
+        if self.reuse_endpoint {
+            DialEndpoints::Reuse {
+                endpoints: Arc::new(
+                    self.listeners
+                        .iter()
+                        .map(|listener| (listener.address, listener.endpoint.clone()))
+                        .collect(),
+                ),
+            }
+        } else {
+            DialEndpoints::NoReuse
+        }
+    }
+
+    /// Accept next inbound connection.
+    fn accept(endpoint: Endpoint) -> BoxFuture<'static, Option<Connecting>> {
+        async move { endpoint.accept().await }.boxed()
+    }
+
+    /// Convert `address` into a QUIC `Multiaddr`.
+    pub(crate) fn socket_address_to_multiaddr(address: &SocketAddr) -> Multiaddr {
+        Multiaddr::empty()
+            .with(Protocol::from(address.ip()))
+            .with(Protocol::Udp(address.port()))
+            .with(Protocol::QuicV1)
     }
 
     /// Extract socket address and `PeerId`, if found, from `address`.
@@ -158,26 +277,43 @@ impl QuicListener {
 }
 
 impl Stream for QuicListener {
-    type Item = Connecting;
+    type Item = ListenerEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.incoming.is_empty() {
-            return Poll::Pending;
-        }
+        let mut index = 0;
 
-        match futures::ready!(self.incoming.poll_next_unpin(cx)) {
-            None => Poll::Ready(None),
-            Some(None) => Poll::Ready(None),
-            Some(Some((listener, future))) => {
-                let inner = self.listeners[listener].clone();
-                self.incoming.push(
-                    async move { inner.accept().await.map(|connecting| (listener, connecting)) }
-                        .boxed(),
-                );
+        while index < self.listeners.len() {
+            let current = (self.poll_index + index) % self.listeners.len();
+            let listener = &mut self.listeners[current];
+
+            match listener.accept.poll_unpin(cx) {
+                Poll::Pending => index += 1,
+                Poll::Ready(None) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        address = ?listener.address,
+                        "endpoint closed",
+                    );
+                    let listener = self.listeners.remove(current);
 
-                Poll::Ready(Some(future))
+                    return Poll::Ready(Some(ListenerEvent::Closed {
+                        address: Self::socket_address_to_multiaddr(&listener.address),
+                    }));
+                }
+                Poll::Ready(Some(connecting)) => {
+                    listener.accept = Self::accept(listener.endpoint.clone());
+                    let address = Self::socket_address_to_multiaddr(&listener.address);
+                    self.poll_index = (current + 1) % self.listeners.len();
+
+                    return Poll::Ready(Some(ListenerEvent::Connection {
+                        connecting,
+                        address,
+                    }));
+                }
             }
         }
+
+        Poll::Pending
     }
 }
 
@@ -186,6 +322,7 @@ mod tests {
     use crate::crypto::tls::make_client_config;
 
     use super::*;
+    use futures::StreamExt;
     use quinn::ClientConfig;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
@@ -243,7 +380,29 @@ mod tests {
 
     #[tokio::test]
     async fn no_listeners() {
-        let (mut listener, _) = QuicListener::new(&Keypair::generate(), Vec::new()).unwrap();
+        let (mut listener, _) =
+            QuicListener::new(&Keypair::generate(), Vec::new(), true, Default::default()).unwrap();
+
+        futures::future::poll_fn(|cx| match listener.poll_next_unpin(cx) {
+            Poll::Pending => Poll::Ready(()),
+            event => panic!("unexpected event: {event:?}"),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_listener() {
+        let (mut listener, _) =
+            QuicListener::new(&Keypair::generate(), Vec::new(), true, Default::default()).unwrap();
+
+        let address = listener.listen_on(&"/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap()).unwrap();
+        assert!(std::matches!(
+            address.iter().nth(1),
+            Some(Protocol::Udp(port)) if port != 0
+        ));
+
+        assert_eq!(listener.remove_listener(&address), Some(address.clone()));
+        assert_eq!(listener.remove_listener(&address), None);
 
         futures::future::poll_fn(|cx| match listener.poll_next_unpin(cx) {
             Poll::Pending => Poll::Ready(()),
@@ -258,7 +417,7 @@ mod tests {
         let keypair = Keypair::generate();
         let peer = PeerId::from_public_key(&keypair.public().into());
         let (mut listener, listen_addresses) =
-            QuicListener::new(&keypair, vec![address.clone()]).unwrap();
+            QuicListener::new(&keypair, vec![address.clone()], true, Default::default()).unwrap();
         let Some(Protocol::Udp(port)) =
             listen_addresses.iter().next().unwrap().clone().iter().skip(1).next()
         else {
@@ -301,7 +460,8 @@ mod tests {
         let peer = PeerId::from_public_key(&keypair.public().into());
 
         let (mut listener, listen_addresses) =
-            QuicListener::new(&keypair, vec![address1, address2]).unwrap();
+            QuicListener::new(&keypair, vec![address1, address2], true, Default::default())
+                .unwrap();
 
         let Some(Protocol::Udp(port1)) =
             listen_addresses.iter().next().unwrap().clone().iter().skip(1).next()
@@ -379,6 +539,8 @@ mod tests {
                 "/ip6/::1/udp/0/quic-v1".parse().unwrap(),
                 "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap(),
             ],
+            true,
+            Default::default(),
         )
         .unwrap();
 
@@ -27,7 +27,11 @@ use crate::{
     error::{AddressError, Error},
     transport::{
         manager::TransportHandle,
-        quic::{config::Config as QuicConfig, connection::QuicConnection, listener::QuicListener},
+        quic::{
+            config::Config as QuicConfig,
+            connection::QuicConnection,
+            listener::{DialEndpoints, ListenerEvent, QuicListener},
+        },
         Endpoint as Litep2pEndpoint, Transport, TransportBuilder, TransportEvent,
     },
     types::ConnectionId,
@@ -36,14 +40,14 @@ use crate::{
 
 use futures::{future::BoxFuture, stream::FuturesUnordered, Stream, StreamExt};
 use multiaddr::{Multiaddr, Protocol};
-use quinn::{ClientConfig, Connection, Endpoint, IdleTimeout};
+use quinn::{ClientConfig, Connection, TransportConfig};
 
 use std::{
     collections::{HashMap, HashSet},
-    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 pub(crate) use substream::Substream;
@@ -64,6 +68,9 @@ struct NegotiatedConnection {
 
     /// QUIC connection.
     connection: Connection,
+
+    /// Duration of the TLS handshake.
+    handshake_duration: Duration,
 }
 
 /// QUIC transport object.
@@ -77,6 +84,12 @@ pub(crate) struct QuicTransport {
     /// QUIC listener.
     listener: QuicListener,
 
+    /// Endpoints used for outbound connections.
+    dial_endpoints: DialEndpoints,
+
+    /// Transport configuration shared by all connections.
+    transport_config: Arc<TransportConfig>,
+
     /// Pending dials.
     pending_dials: HashMap<ConnectionId, Multiaddr>,
 
@@ -89,7 +102,13 @@ pub(crate) struct QuicTransport {
 
     /// Pending raw, unnegotiated connections.
     pending_raw_connections: FuturesUnordered<
-        BoxFuture<'static, Result<(ConnectionId, Multiaddr, NegotiatedConnection), ConnectionId>>,
+        BoxFuture<
+            'static,
+            Result<
+                (ConnectionId, Multiaddr, NegotiatedConnection),
+                (ConnectionId, Vec<(Multiaddr, Error)>),
+            >,
+        >,
     >,
 
     /// Opened raw connection, waiting for approval/rejection from `TransportManager`.
@@ -124,6 +143,7 @@ impl QuicTransport {
 
         match result {
             Ok(connection) => {
+                self.context.metrics.on_handshake("tls", connection.handshake_duration);
                 let peer = connection.peer;
                 let endpoint = maybe_address.map_or(
                     {
@@ -140,24 +160,26 @@ impl QuicTransport {
                 );
                 self.pending_open.insert(connection_id, (connection, endpoint.clone()));
 
-                return Some(TransportEvent::ConnectionEstablished { peer, endpoint });
+                Some(TransportEvent::ConnectionEstablished { peer, endpoint })
             }
             Err(error) => {
                 tracing::debug!(target: LOG_TARGET, ?connection_id, ?error, "failed to establish connection");
 
-                // since the address was found from `pending_dials`,
-                // report the error to protocols and `TransportManager`
-                if let Some(address) = maybe_address {
-                    return Some(TransportEvent::DialFailure {
+                // report dial failures to protocols and `TransportManager`,
+                // failed inbound connections only to `TransportManager`
+                Some(match maybe_address {
+                    Some(address) => TransportEvent::DialFailure {
                         connection_id,
                         address,
                         error,
-                    });
-                }
+                    },
+                    None => TransportEvent::IncomingConnectionError {
+                        connection_id,
+                        error,
+                    },
+                })
             }
         }
-
-        None
     }
 }
 
@@ -179,16 +201,22 @@ impl TransportBuilder for QuicTransport {
             "start quic transport",
         );
 
+        let transport_config = Arc::new(config.transport_config()?);
         let (listener, listen_addresses) = QuicListener::new(
             &context.keypair,
             std::mem::take(&mut config.listen_addresses),
+            config.reuse_endpoint,
+            Arc::clone(&transport_config),
         )?;
+        let dial_endpoints = listener.dial_endpoints();
 
         Ok((
             Self {
                 context,
                 config,
                 listener,
+                dial_endpoints,
+                transport_config,
                 canceled: HashSet::new(),
                 opened_raw: HashMap::new(),
                 pending_open: HashMap::new(),
@@ -209,21 +237,10 @@ impl Transport for QuicTransport {
 
         let crypto_config =
             Arc::new(make_client_config(&self.context.keypair, Some(peer)).expect("to succeed"));
-        let mut transport_config = quinn::TransportConfig::default();
-        let timeout =
-            IdleTimeout::try_from(self.config.connection_open_timeout).expect("to succeed");
-        transport_config.max_idle_timeout(Some(timeout));
         let mut client_config = ClientConfig::new(crypto_config);
-        client_config.transport_config(Arc::new(transport_config));
-
-        let client_listen_address = match address.iter().next() {
-            Some(Protocol::Ip6(_)) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
-            Some(Protocol::Ip4(_)) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
-            _ => return Err(Error::AddressError(AddressError::InvalidProtocol)),
-        };
+        client_config.transport_config(Arc::clone(&self.transport_config));
 
-        let client = Endpoint::client(client_listen_address)
-            .map_err(|error| Error::Other(error.to_string()))?;
+        let client = self.dial_endpoints.endpoint(&socket_address)?;
         let connection = client
             .connect_with(client_config, socket_address, "l")
             .map_err(|error| Error::Other(error.to_string()))?;
@@ -232,22 +249,34 @@ impl Transport for QuicTransport {
             target: LOG_TARGET,
             ?address,
             ?peer,
-            ?client_listen_address,
+            local_address = ?client.local_addr(),
             "dial peer",
         );
 
+        let connection_open_timeout = self.config.connection_open_timeout;
+
         self.pending_dials.insert(connection_id, address);
         self.pending_connections.push(Box::pin(async move {
-            let connection = match connection.await {
-                Ok(connection) => connection,
-                Err(error) => return (connection_id, Err(error.into())),
+            let started = Instant::now();
+            let connection = match tokio::time::timeout(connection_open_timeout, connection).await {
+                Ok(Ok(connection)) => connection,
+                Ok(Err(error)) => return (connection_id, Err(error.into())),
+                Err(_) => return (connection_id, Err(Error::Timeout)),
             };
+            let handshake_duration = started.elapsed();
 
             let Some(peer) = Self::extract_peer_id(&connection) else {
                 return (connection_id, Err(Error::InvalidCertificate));
             };
 
-            (connection_id, Ok(NegotiatedConnection { peer, connection }))
+            (
+                connection_id,
+                Ok(NegotiatedConnection {
+                    peer,
+                    connection,
+                    handshake_duration,
+                }),
+            )
         }));
 
         Ok(())
@@ -301,6 +330,8 @@ impl Transport for QuicTransport {
             .map(|address| {
                 let keypair = self.context.keypair.clone();
                 let connection_open_timeout = self.config.connection_open_timeout;
+                let dial_endpoints = self.dial_endpoints.clone();
+                let transport_config = Arc::clone(&self.transport_config);
 
                 async move {
                     let Ok((socket_address, Some(peer))) =
@@ -308,77 +339,79 @@ impl Transport for QuicTransport {
                     else {
                         return (
                             connection_id,
-                            Err(Error::AddressError(AddressError::PeerIdMissing)),
+                            Err((address, Error::AddressError(AddressError::PeerIdMissing))),
                         );
                     };
 
                     let crypto_config =
                         Arc::new(make_client_config(&keypair, Some(peer)).expect("to succeed"));
-                    let mut transport_config = quinn::TransportConfig::default();
-                    let timeout =
-                        IdleTimeout::try_from(connection_open_timeout).expect("to succeed");
-                    transport_config.max_idle_timeout(Some(timeout));
                     let mut client_config = ClientConfig::new(crypto_config);
-                    client_config.transport_config(Arc::new(transport_config));
-
-                    let client_listen_address = match address.iter().next() {
-                        Some(Protocol::Ip6(_)) =>
-                            SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
-                        Some(Protocol::Ip4(_)) =>
-                            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
-                        _ =>
-                            return (
-                                connection_id,
-                                Err(Error::AddressError(AddressError::InvalidProtocol)),
-                            ),
-                    };
+                    client_config.transport_config(Arc::clone(&transport_config));
 
-                    let client = match Endpoint::client(client_listen_address) {
+                    let client = match dial_endpoints.endpoint(&socket_address) {
                         Ok(client) => client,
-                        Err(error) => {
-                            return (connection_id, Err(Error::Other(error.to_string())));
-                        }
+                        Err(error) => return (connection_id, Err((address, error))),
                     };
                     let connection = match client.connect_with(client_config, socket_address, "l") {
                         Ok(connection) => connection,
                         Err(error) => {
-                            return (connection_id, Err(Error::Other(error.to_string())));
+                            return (
+                                connection_id,
+                                Err((address, Error::Other(error.to_string()))),
+                            );
                         }
                     };
 
-                    let connection = match connection.await {
-                        Ok(connection) => connection,
-                        Err(error) => return (connection_id, Err(error.into())),
-                    };
+                    let started = Instant::now();
+                    let connection =
+                        match tokio::time::timeout(connection_open_timeout, connection).await {
+                            Ok(Ok(connection)) => connection,
+                            Ok(Err(error)) => return (connection_id, Err((address, error.into()))),
+                            Err(_) => return (connection_id, Err((address, Error::Timeout))),
+                        };
+                    let handshake_duration = started.elapsed();
 
                     let Some(peer) = Self::extract_peer_id(&connection) else {
-                        return (connection_id, Err(Error::InvalidCertificate));
+                        return (connection_id, Err((address, Error::InvalidCertificate)));
                     };
 
                     (
                         connection_id,
-                        Ok((address, NegotiatedConnection { peer, connection })),
+                        Ok((
+                            address,
+                            NegotiatedConnection {
+                                peer,
+                                connection,
+                                handshake_duration,
+                            },
+                        )),
                     )
                 }
             })
             .collect();
 
         self.pending_raw_connections.push(Box::pin(async move {
+            let mut errors = Vec::new();
+
             while let Some(result) = futures.next().await {
                 let (connection_id, result) = result;
 
                 match result {
                     Ok((address, connection)) => return Ok((connection_id, address, connection)),
-                    Err(error) => tracing::debug!(
-                        target: LOG_TARGET,
-                        ?connection_id,
-                        ?error,
-                        "failed to open connection",
-                    ),
+                    Err((address, error)) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?connection_id,
+                            ?address,
+                            ?error,
+                            "failed to open connection",
+                        );
+                        errors.push((address, error));
+                    }
                 }
             }
 
-            Err(connection_id)
+            Err((connection_id, errors))
         }));
 
         Ok(())
@@ -400,32 +433,109 @@ impl Transport for QuicTransport {
     fn cancel(&mut self, connection_id: ConnectionId) {
         self.canceled.insert(connection_id);
     }
+
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "start listening");
+
+        let listen_address = self.listener.listen_on(&address)?;
+        self.dial_endpoints = self.listener.dial_endpoints();
+
+        Ok(vec![listen_address])
+    }
+
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "remove listener");
+
+        let listen_address = self
+            .listener
+            .remove_listener(address)
+            .ok_or(Error::AddressError(AddressError::AddressNotAvailable))?;
+        self.dial_endpoints = self.listener.dial_endpoints();
+
+        Ok(vec![listen_address])
+    }
 }
 
 impl Stream for QuicTransport {
     type Item = TransportEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        while let Poll::Ready(Some(connection)) = self.listener.poll_next_unpin(cx) {
+        while let Poll::Ready(Some(event)) = self.listener.poll_next_unpin(cx) {
+            let (connection, local_address) = match event {
+                ListenerEvent::Closed { address } => {
+                    self.dial_endpoints = self.listener.dial_endpoints();
+
+                    return Poll::Ready(Some(TransportEvent::ListenerClosed { address }));
+                }
+                ListenerEvent::Connection {
+                    connecting,
+                    address,
+                } => (connecting, address),
+            };
+
+            if !self.context.ban_list.is_ip_allowed(&connection.remote_address().ip()) {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    address = ?connection.remote_address(),
+                    "rejecting inbound connection from denied address",
+                );
+                continue;
+            }
+
+            let permit = match self.context.pending_incoming.try_acquire() {
+                Ok(permit) => permit,
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        address = ?connection.remote_address(),
+                        ?error,
+                        "rejecting inbound connection",
+                    );
+                    continue;
+                }
+            };
             let connection_id = self.context.next_connection_id();
+            let remote_address = connection.remote_address();
 
             tracing::trace!(
                 target: LOG_TARGET,
                 ?connection_id,
+                ?remote_address,
                 "accept connection",
             );
 
+            let connection_open_timeout = self.config.connection_open_timeout;
+
             self.pending_connections.push(Box::pin(async move {
-                let connection = match connection.await {
-                    Ok(connection) => connection,
-                    Err(error) => return (connection_id, Err(error.into())),
-                };
+                let _permit = permit;
+
+                let started = Instant::now();
+                let connection =
+                    match tokio::time::timeout(connection_open_timeout, connection).await {
+                        Ok(Ok(connection)) => connection,
+                        Ok(Err(error)) => return (connection_id, Err(error.into())),
+                        Err(_) => return (connection_id, Err(Error::Timeout)),
+                    };
+                let handshake_duration = started.elapsed();
 
                 let Some(peer) = Self::extract_peer_id(&connection) else {
                     return (connection_id, Err(Error::InvalidCertificate));
                 };
 
-                (connection_id, Ok(NegotiatedConnection { peer, connection }))
+                (
+                    connection_id,
+                    Ok(NegotiatedConnection {
+                        peer,
+                        connection,
+                        handshake_duration,
+                    }),
+                )
+            }));
+
+            return Poll::Ready(Some(TransportEvent::IncomingConnection {
+                connection_id,
+                local_address,
+                remote_address: QuicListener::socket_address_to_multiaddr(&remote_address),
             }));
         }
 
@@ -449,9 +559,12 @@ impl Stream for QuicTransport {
                         }));
                     }
                 }
-                Err(connection_id) =>
+                Err((connection_id, errors)) =>
                     if !self.canceled.remove(&connection_id) {
-                        return Poll::Ready(Some(TransportEvent::OpenFailure { connection_id }));
+                        return Poll::Ready(Some(TransportEvent::OpenFailure {
+                            connection_id,
+                            errors,
+                        }));
                     },
             }
         }
@@ -481,7 +594,7 @@ mod tests {
         BandwidthSink,
     };
     use multihash::Multihash;
-    use tokio::sync::mpsc::channel;
+    use tokio::sync::{mpsc::channel, watch};
 
     #[tokio::test]
     async fn test_quinn() {
@@ -501,15 +614,21 @@ mod tests {
             keypair: keypair1.clone(),
             tx: event_tx1,
             bandwidth_sink: BandwidthSink::new(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
 
         let (mut transport1, listen_addresses) =
@@ -528,15 +647,21 @@ mod tests {
             keypair: keypair2.clone(),
             tx: event_tx2,
             bandwidth_sink: BandwidthSink::new(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx2,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
 
         let (mut transport2, _) = QuicTransport::new(handle2, Default::default()).unwrap();
@@ -547,7 +672,16 @@ mod tests {
         ));
 
         transport2.dial(ConnectionId::new(), listen_address).unwrap();
-        let (res1, res2) = tokio::join!(transport1.next(), transport2.next());
+        let (res1, res2) = tokio::join!(
+            async {
+                assert!(std::matches!(
+                    transport1.next().await,
+                    Some(TransportEvent::IncomingConnection { .. })
+                ));
+                transport1.next().await
+            },
+            transport2.next()
+        );
 
         assert!(std::matches!(
             res1,
@@ -19,8 +19,8 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
+    bandwidth::SubstreamBandwidthSink,
     error::{Error, SubstreamError},
-    BandwidthSink,
 };
 
 use bytes::Bytes;
@@ -41,7 +41,7 @@ use crate::protocol::Permit;
 #[derive(Debug)]
 pub struct Substream {
     _permit: Permit,
-    bandwidth_sink: BandwidthSink,
+    bandwidth_sink: SubstreamBandwidthSink,
     send_stream: SendStream,
     recv_stream: RecvStream,
 }
@@ -52,7 +52,7 @@ impl Substream {
         _permit: Permit,
         send_stream: SendStream,
         recv_stream: RecvStream,
-        bandwidth_sink: BandwidthSink,
+        bandwidth_sink: SubstreamBandwidthSink,
     ) -> Self {
         Self {
             _permit,
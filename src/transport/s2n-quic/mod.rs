@@ -398,6 +398,7 @@ mod tests {
                     tx: tx1,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
             )]),
         };
@@ -431,6 +432,7 @@ mod tests {
                     tx: tx2,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
             )]),
         };
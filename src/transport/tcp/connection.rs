@@ -30,8 +30,9 @@ use crate::{
     substream,
     transport::{
         common::listener::{AddressType, DnsType},
+        manager::SupportedTransport,
         tcp::substream::Substream,
-        Endpoint,
+        ConnectionCloseReason, Endpoint, CONNECTION_CLOSE_TIMEOUT,
     },
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
     BandwidthSink, PeerId,
@@ -56,7 +57,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 /// Logging target for the file.
@@ -121,6 +122,9 @@ pub struct NegotiatedConnection {
 
     /// Substream open timeout.
     substream_open_timeout: Duration,
+
+    /// Duration of the Noise handshake.
+    handshake_duration: Duration,
 }
 
 impl NegotiatedConnection {
@@ -138,6 +142,11 @@ impl NegotiatedConnection {
     pub fn endpoint(&self) -> Endpoint {
         self.endpoint.clone()
     }
+
+    /// Get the duration of the Noise handshake.
+    pub fn handshake_duration(&self) -> Duration {
+        self.handshake_duration
+    }
 }
 
 /// TCP connection.
@@ -194,6 +203,7 @@ impl TcpConnection {
             peer,
             endpoint,
             substream_open_timeout,
+            ..
         } = context;
 
         Self {
@@ -427,6 +437,7 @@ impl TcpConnection {
         );
 
         // perform noise handshake
+        let started = Instant::now();
         let (stream, peer) = noise::handshake(
             stream.inner(),
             &keypair,
@@ -435,6 +446,7 @@ impl TcpConnection {
             max_write_buffer_size,
         )
         .await?;
+        let handshake_duration = started.elapsed();
 
         if let Some(dialed_peer) = dialed_peer {
             if dialed_peer != peer {
@@ -486,9 +498,26 @@ impl TcpConnection {
             connection,
             endpoint,
             substream_open_timeout,
+            handshake_duration,
         })
     }
 
+    /// Close the connection gracefully by sending yamux `GoAway` to the remote peer.
+    async fn close(&mut self) {
+        let close = futures::future::join(
+            self.control.close(),
+            (&mut self.connection).for_each(|_| async {}),
+        );
+
+        if tokio::time::timeout(CONNECTION_CLOSE_TIMEOUT, close).await.is_err() {
+            tracing::debug!(
+                target: LOG_TARGET,
+                peer = ?self.peer,
+                "timed out while closing connection",
+            );
+        }
+    }
+
     /// Start connection event loop.
     pub(crate) async fn start(mut self) -> crate::Result<()> {
         self.protocol_set
@@ -534,13 +563,17 @@ impl TcpConnection {
                             ?error,
                             "connection closed with error",
                         );
-                        self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await?;
+                        self.protocol_set
+                            .report_connection_closed(self.peer, self.endpoint.connection_id(), (&error).into())
+                            .await?;
 
                         return Ok(())
                     }
                     None => {
                         tracing::debug!(target: LOG_TARGET, peer = ?self.peer, "connection closed");
-                        self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await?;
+                        self.protocol_set
+                            .report_connection_closed(self.peer, self.endpoint.connection_id(), ConnectionCloseReason::RemoteGoAway)
+                            .await?;
 
                         return Ok(())
                     }
@@ -583,16 +616,25 @@ impl TcpConnection {
                         }
                         Ok(substream) => {
                             let protocol = substream.protocol.clone();
+                            let Ok(codec) = self.protocol_set.protocol_codec(&protocol) else {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?protocol,
+                                    "protocol was unregistered, dropping substream",
+                                );
+                                continue;
+                            };
                             let direction = substream.direction;
                             let substream_id = substream.substream_id;
                             let socket = FuturesAsyncReadCompatExt::compat(substream.io);
-                            let bandwidth_sink = self.bandwidth_sink.clone();
+                            let bandwidth_sink = self.bandwidth_sink.substream(SupportedTransport::Tcp, self.peer, protocol.clone());
 
                             let substream = substream::Substream::new_tcp(
                                 self.peer,
                                 substream_id,
                                 Substream::new(socket, bandwidth_sink, substream.permit),
-                                self.protocol_set.protocol_codec(&protocol)
+                                codec,
+                                self.protocol_set.rate_limiter(SupportedTransport::Tcp, &protocol),
                             );
 
                             if let Err(error) = self.protocol_set
@@ -647,19 +689,24 @@ impl TcpConnection {
                             }
                         }));
                     }
-                    Some(ProtocolCommand::ForceClose) => {
+                    Some(ProtocolCommand::ForceClose { reason }) => {
                         tracing::debug!(
                             target: LOG_TARGET,
                             peer = ?self.peer,
                             connection_id = ?self.endpoint.connection_id(),
+                            ?reason,
                             "force closing connection",
                         );
 
-                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await
+                        self.close().await;
+                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id(), reason).await
                     }
                     None => {
                         tracing::debug!(target: LOG_TARGET, "protocols have disconnected, closing connection");
-                        return self.protocol_set.report_connection_closed(self.peer, self.endpoint.connection_id()).await
+                        return self
+                            .protocol_set
+                            .report_connection_closed(self.peer, self.endpoint.connection_id(), ConnectionCloseReason::Idle)
+                            .await
                     }
                 }
             }
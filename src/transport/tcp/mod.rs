@@ -23,7 +23,7 @@
 
 use crate::{
     config::Role,
-    error::Error,
+    error::{AddressError, Error},
     transport::{
         common::listener::{DialAddresses, GetSocketAddr, SocketListener, TcpAddress},
         manager::TransportHandle,
@@ -85,7 +85,10 @@ pub(crate) struct TcpTransport {
 
     /// Pending raw, unnegotiated connections.
     pending_raw_connections: FuturesUnordered<
-        BoxFuture<'static, Result<(ConnectionId, Multiaddr, TcpStream), ConnectionId>>,
+        BoxFuture<
+            'static,
+            Result<(ConnectionId, Multiaddr, TcpStream), (ConnectionId, Vec<(Multiaddr, Error)>)>,
+        >,
     >,
 
     /// Opened raw connection, waiting for approval/rejection from `TransportManager`.
@@ -101,8 +104,30 @@ pub(crate) struct TcpTransport {
 
 impl TcpTransport {
     /// Handle inbound TCP connection.
-    fn on_inbound_connection(&mut self, connection: TcpStream, address: SocketAddr) {
+    ///
+    /// Returns [`TransportEvent::IncomingConnection`] if the connection is negotiated.
+    fn on_inbound_connection(
+        &mut self,
+        connection: TcpStream,
+        address: SocketAddr,
+    ) -> Option<TransportEvent> {
+        if !self.context.ban_list.is_ip_allowed(&address.ip()) {
+            tracing::debug!(target: LOG_TARGET, ?address, "rejecting inbound connection from denied address");
+            return None;
+        }
+
+        let permit = match self.context.pending_incoming.try_acquire() {
+            Ok(permit) => permit,
+            Err(error) => {
+                tracing::debug!(target: LOG_TARGET, ?address, ?error, "rejecting inbound connection");
+                return None;
+            }
+        };
         let connection_id = self.context.next_connection_id();
+        let local_address = connection.local_addr().map_or_else(
+            |_| Multiaddr::empty(),
+            |address| TcpAddress::socket_address_to_multiaddr(&address),
+        );
         let yamux_config = self.config.yamux_config.clone();
         let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
         let max_write_buffer_size = self.config.noise_write_buffer_size;
@@ -111,6 +136,8 @@ impl TcpTransport {
         let keypair = self.context.keypair.clone();
 
         self.pending_connections.push(Box::pin(async move {
+            let _permit = permit;
+
             TcpConnection::accept_connection(
                 connection,
                 connection_id,
@@ -125,6 +152,12 @@ impl TcpTransport {
             .await
             .map_err(|error| (connection_id, error))
         }));
+
+        Some(TransportEvent::IncomingConnection {
+            connection_id,
+            local_address,
+            remote_address: TcpAddress::socket_address_to_multiaddr(&address),
+        })
     }
 
     /// Dial remote peer
@@ -330,30 +363,37 @@ impl Transport for TcpTransport {
 
                 async move {
                     TcpTransport::dial_peer(
-                        address,
+                        address.clone(),
                         dial_addresses,
                         connection_open_timeout,
                         nodelay,
                     )
                     .await
+                    .map_err(|error| (address, error))
                 }
             })
             .collect();
 
         self.pending_raw_connections.push(Box::pin(async move {
+            let mut errors = Vec::new();
+
             while let Some(result) = futures.next().await {
                 match result {
                     Ok((address, stream)) => return Ok((connection_id, address, stream)),
-                    Err(error) => tracing::debug!(
-                        target: LOG_TARGET,
-                        ?connection_id,
-                        ?error,
-                        "failed to open connection",
-                    ),
+                    Err((address, error)) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?connection_id,
+                            ?address,
+                            ?error,
+                            "failed to open connection",
+                        );
+                        errors.push((address, error));
+                    }
                 }
             }
 
-            Err(connection_id)
+            Err((connection_id, errors))
         }));
 
         Ok(())
@@ -413,6 +453,27 @@ impl Transport for TcpTransport {
     fn cancel(&mut self, connection_id: ConnectionId) {
         self.canceled.insert(connection_id);
     }
+
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "start listening");
+
+        let listen_addresses = self.listener.listen_on::<TcpAddress>(&address)?;
+        self.dial_addresses = self.listener.dial_addresses();
+
+        Ok(listen_addresses)
+    }
+
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "remove listener");
+
+        let listen_addresses = self
+            .listener
+            .remove_listener::<TcpAddress>(address)
+            .ok_or(Error::AddressError(AddressError::AddressNotAvailable))?;
+        self.dial_addresses = self.listener.dial_addresses();
+
+        Ok(listen_addresses)
+    }
 }
 
 impl Stream for TcpTransport {
@@ -421,9 +482,19 @@ impl Stream for TcpTransport {
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         while let Poll::Ready(event) = self.listener.poll_next_unpin(cx) {
             match event {
-                None | Some(Err(_)) => return Poll::Ready(None),
+                None => return Poll::Ready(None),
+                Some(Err((address, error))) => {
+                    tracing::debug!(target: LOG_TARGET, ?address, ?error, "failed to accept connection");
+
+                    return Poll::Ready(Some(TransportEvent::ListenerError {
+                        address: TcpAddress::socket_address_to_multiaddr(&address),
+                        error: error.into(),
+                    }));
+                }
                 Some(Ok((connection, address))) => {
-                    self.on_inbound_connection(connection, address);
+                    if let Some(event) = self.on_inbound_connection(connection, address) {
+                        return Poll::Ready(Some(event));
+                    }
                 }
             }
         }
@@ -448,18 +519,22 @@ impl Stream for TcpTransport {
                         }));
                     }
                 }
-                Err(connection_id) =>
+                Err((connection_id, errors)) =>
                     if !self.canceled.remove(&connection_id) {
-                        return Poll::Ready(Some(TransportEvent::OpenFailure { connection_id }));
+                        return Poll::Ready(Some(TransportEvent::OpenFailure {
+                            connection_id,
+                            errors,
+                        }));
                     },
             }
         }
 
-        while let Poll::Ready(Some(connection)) = self.pending_connections.poll_next_unpin(cx) {
+        if let Poll::Ready(Some(connection)) = self.pending_connections.poll_next_unpin(cx) {
             match connection {
                 Ok(connection) => {
                     let peer = connection.peer();
                     let endpoint = connection.endpoint();
+                    self.context.metrics.on_handshake("noise", connection.handshake_duration());
                     self.pending_open.insert(connection.connection_id(), connection);
 
                     return Poll::Ready(Some(TransportEvent::ConnectionEstablished {
@@ -468,13 +543,17 @@ impl Stream for TcpTransport {
                     }));
                 }
                 Err((connection_id, error)) => {
-                    if let Some(address) = self.pending_dials.remove(&connection_id) {
-                        return Poll::Ready(Some(TransportEvent::DialFailure {
+                    return Poll::Ready(Some(match self.pending_dials.remove(&connection_id) {
+                        Some(address) => TransportEvent::DialFailure {
                             connection_id,
                             address,
                             error,
-                        }));
-                    }
+                        },
+                        None => TransportEvent::IncomingConnectionError {
+                            connection_id,
+                            error,
+                        },
+                    }));
                 }
             }
         }
@@ -497,7 +576,7 @@ mod tests {
     use multiaddr::Protocol;
     use multihash::Multihash;
     use std::{collections::HashSet, sync::Arc};
-    use tokio::sync::mpsc::channel;
+    use tokio::sync::{mpsc::channel, watch};
 
     #[tokio::test]
     async fn connect_and_accept_works() {
@@ -518,15 +597,21 @@ mod tests {
             keypair: keypair1.clone(),
             tx: event_tx1,
             bandwidth_sink: bandwidth_sink.clone(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
         let transport_config1 = Config {
             listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
@@ -549,15 +634,21 @@ mod tests {
             keypair: keypair2.clone(),
             tx: event_tx2,
             bandwidth_sink: bandwidth_sink.clone(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx2,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
         let transport_config2 = Config {
             listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
@@ -567,7 +658,16 @@ mod tests {
         let (mut transport2, _) = TcpTransport::new(handle2, transport_config2).unwrap();
         transport2.dial(ConnectionId::new(), listen_address).unwrap();
 
-        let (res1, res2) = tokio::join!(transport1.next(), transport2.next());
+        let (res1, res2) = tokio::join!(
+            async {
+                assert!(std::matches!(
+                    transport1.next().await,
+                    Some(TransportEvent::IncomingConnection { .. })
+                ));
+                transport1.next().await
+            },
+            transport2.next()
+        );
 
         assert!(std::matches!(
             res1,
@@ -598,15 +698,21 @@ mod tests {
             keypair: keypair1.clone(),
             tx: event_tx1,
             bandwidth_sink: bandwidth_sink.clone(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx1,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
         let (mut transport1, _) = TcpTransport::new(handle1, Default::default()).unwrap();
 
@@ -618,6 +724,11 @@ mod tests {
                     TransportEvent::DialFailure { .. } => {}
                     TransportEvent::ConnectionOpened { .. } => {}
                     TransportEvent::OpenFailure { .. } => {}
+                    TransportEvent::ConnectionRejected { .. } => {}
+                    TransportEvent::IncomingConnection { .. } => {}
+                    TransportEvent::IncomingConnectionError { .. } => {}
+                    TransportEvent::ListenerError { .. } => {}
+                    TransportEvent::ListenerClosed { .. } => {}
                 }
             }
         });
@@ -634,15 +745,21 @@ mod tests {
             keypair: keypair2.clone(),
             tx: event_tx2,
             bandwidth_sink: bandwidth_sink.clone(),
+            pending_incoming: Default::default(),
+            ban_list: Default::default(),
+            metrics: Default::default(),
+            rate_limiter: Default::default(),
 
-            protocols: HashMap::from_iter([(
+            protocols: watch::channel(HashMap::from_iter([(
                 ProtocolName::from("/notif/1"),
                 ProtocolContext {
                     tx: tx2,
                     codec: ProtocolCodec::Identity(32),
                     fallback_names: Vec::new(),
+                    inbound: true,
                 },
-            )]),
+            )]))
+            .1,
         };
 
         let (mut transport2, _) = TcpTransport::new(handle2, Default::default()).unwrap();
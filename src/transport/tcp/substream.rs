@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{protocol::Permit, BandwidthSink};
+use crate::{bandwidth::SubstreamBandwidthSink, protocol::Permit};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::compat::Compat;
@@ -39,7 +39,7 @@ pub struct Substream {
     io: Compat<crate::yamux::Stream>,
 
     /// Bandwidth sink.
-    bandwidth_sink: BandwidthSink,
+    bandwidth_sink: SubstreamBandwidthSink,
 
     /// Connection permit.
     _permit: Permit,
@@ -49,7 +49,7 @@ impl Substream {
     /// Create new [`Substream`].
     pub fn new(
         io: Compat<crate::yamux::Stream>,
-        bandwidth_sink: BandwidthSink,
+        bandwidth_sink: SubstreamBandwidthSink,
         _permit: Permit,
     ) -> Self {
         Self {
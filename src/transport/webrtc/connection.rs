@@ -24,14 +24,15 @@ use crate::{
     protocol::{Direction, Permit, ProtocolCommand, ProtocolSet},
     substream::Substream,
     transport::{
+        manager::SupportedTransport,
         webrtc::{
             substream::{Event as SubstreamEvent, Substream as WebRtcSubstream, SubstreamHandle},
             util::WebRtcMessage,
         },
-        Endpoint,
+        ConnectionCloseReason, Endpoint,
     },
     types::{protocol::ProtocolName, SubstreamId},
-    PeerId,
+    BandwidthSink, PeerId,
 };
 
 use futures::{Stream, StreamExt};
@@ -197,6 +198,9 @@ pub struct WebRtcConnection {
 
     /// Substream handles.
     handles: SubstreamHandleSet,
+
+    /// Bandwidth sink.
+    bandwidth_sink: BandwidthSink,
 }
 
 impl WebRtcConnection {
@@ -210,6 +214,7 @@ impl WebRtcConnection {
         protocol_set: ProtocolSet,
         endpoint: Endpoint,
         dgram_rx: Receiver<Vec<u8>>,
+        bandwidth_sink: BandwidthSink,
     ) -> Self {
         Self {
             rtc,
@@ -223,6 +228,7 @@ impl WebRtcConnection {
             pending_outbound: HashMap::new(),
             channels: HashMap::new(),
             handles: SubstreamHandleSet::new(),
+            bandwidth_sink,
         }
     }
 
@@ -331,10 +337,15 @@ impl WebRtcConnection {
 
         let protocol = negotiated.ok_or(Error::SubstreamDoesntExist)?;
         let substream_id = self.protocol_set.next_substream_id();
-        let codec = self.protocol_set.protocol_codec(&protocol);
+        let codec = self.protocol_set.protocol_codec(&protocol)?;
         let permit = self.protocol_set.try_get_permit().ok_or(Error::ConnectionClosed)?;
-        let (substream, handle) = WebRtcSubstream::new();
-        let substream = Substream::new_webrtc(self.peer, substream_id, substream, codec);
+        let bandwidth_sink =
+            self.bandwidth_sink
+                .substream(SupportedTransport::WebRtc, self.peer, protocol.clone());
+        let (substream, handle) = WebRtcSubstream::new(bandwidth_sink);
+        let rate_limiter = self.protocol_set.rate_limiter(SupportedTransport::WebRtc, &protocol);
+        let substream =
+            Substream::new_webrtc(self.peer, substream_id, substream, codec, rate_limiter);
 
         tracing::trace!(
             target: LOG_TARGET,
@@ -405,9 +416,14 @@ impl WebRtcConnection {
             permit,
             ..
         } = context;
-        let codec = self.protocol_set.protocol_codec(&protocol);
-        let (substream, handle) = WebRtcSubstream::new();
-        let substream = Substream::new_webrtc(self.peer, substream_id, substream, codec);
+        let codec = self.protocol_set.protocol_codec(&protocol)?;
+        let bandwidth_sink =
+            self.bandwidth_sink
+                .substream(SupportedTransport::WebRtc, self.peer, protocol.clone());
+        let (substream, handle) = WebRtcSubstream::new(bandwidth_sink);
+        let rate_limiter = self.protocol_set.rate_limiter(SupportedTransport::WebRtc, &protocol);
+        let substream =
+            Substream::new_webrtc(self.peer, substream_id, substream, codec, rate_limiter);
 
         tracing::trace!(
             target: LOG_TARGET,
@@ -642,16 +658,17 @@ impl WebRtcConnection {
     }
 
     /// Connection to peer has been closed.
-    async fn on_connection_closed(&mut self) {
+    async fn on_connection_closed(&mut self, reason: ConnectionCloseReason) {
         tracing::trace!(
             target: LOG_TARGET,
             peer = ?self.peer,
+            ?reason,
             "connection closed",
         );
 
         let _ = self
             .protocol_set
-            .report_connection_closed(self.peer, self.endpoint.connection_id())
+            .report_connection_closed(self.peer, self.endpoint.connection_id(), reason)
             .await;
     }
 
@@ -690,7 +707,9 @@ impl WebRtcConnection {
                             peer = ?self.peer,
                             "ice connection state changed to closed",
                         );
-                        return self.on_connection_closed().await;
+                        return self
+                            .on_connection_closed(ConnectionCloseReason::KeepAliveTimeout)
+                            .await;
                     }
                     Event::ChannelOpen(channel_id, name) => {
                         if let Err(error) = self.on_channel_opened(channel_id, name).await {
@@ -771,7 +790,7 @@ impl WebRtcConnection {
                             peer = ?self.peer,
                             "read `None` from `dgram_rx`",
                         );
-                        return self.on_connection_closed().await;
+                        return self.on_connection_closed(ConnectionCloseReason::LocalClose).await;
                     }
                 },
                 event = self.handles.next() => match event {
@@ -801,14 +820,18 @@ impl WebRtcConnection {
                     Some((_, Some(SubstreamEvent::RecvClosed))) => {}
                 },
                 command = self.protocol_set.next() => match command {
-                    None | Some(ProtocolCommand::ForceClose) => {
+                    None | Some(ProtocolCommand::ForceClose { .. }) => {
                         tracing::trace!(
                             target: LOG_TARGET,
                             peer = ?self.peer,
                             ?command,
                             "`ProtocolSet` instructed to close connection",
                         );
-                        return self.on_connection_closed().await;
+                        let reason = match command {
+                            Some(ProtocolCommand::ForceClose { reason }) => reason,
+                            _ => ConnectionCloseReason::Idle,
+                        };
+                        return self.on_connection_closed(reason).await;
                     }
                     Some(ProtocolCommand::OpenSubstream { protocol, fallback_names, substream_id, permit }) => {
                         self.on_open_substream(protocol, fallback_names, substream_id, permit);
@@ -34,13 +34,14 @@ use crate::{
 use futures::{future::BoxFuture, Future, Stream};
 use futures_timer::Delay;
 use multiaddr::{multihash::Multihash, Multiaddr, Protocol};
+use rand::{distributions::Alphanumeric, Rng};
 use socket2::{Domain, Socket, Type};
 use str0m::{
-    change::DtlsCert,
     channel::{ChannelConfig, ChannelId},
+    config::{CryptoProvider, DtlsCert, DtlsCertOptions, Fingerprint},
     ice::IceCreds,
     net::{DatagramRecv, Protocol as Str0mProtocol, Receive},
-    Candidate, Input, Rtc,
+    Candidate, DtlsCertConfig, Input, Rtc,
 };
 use tokio::{
     io::ReadBuf,
@@ -80,9 +81,19 @@ pub(super) mod schema {
 const LOG_TARGET: &str = "litep2p::webrtc";
 
 /// Hardcoded remote fingerprint.
+///
+/// The certificate of the dialer is not known before the DTLS handshake, so the listener disables
+/// fingerprint verification and authenticates the fingerprint negotiated by DTLS in the Noise
+/// handshake instead. `str0m` still requires some remote fingerprint to be set.
 const REMOTE_FINGERPRINT: &str =
     "sha-256 FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF:FF";
 
+/// Prefix of the ICE username fragment generated by the dialer.
+const UFRAG_PREFIX: &str = "libp2p+webrtc+v1/";
+
+/// Multihash code of SHA-256.
+const MULTIHASH_SHA256_CODE: u64 = 0x12;
+
 /// Connection context.
 struct ConnectionContext {
     /// Remote peer ID.
@@ -131,6 +142,12 @@ pub(crate) struct WebRtcTransport {
     /// Assigned listen addresss.
     listen_address: SocketAddr,
 
+    /// Listen address, including the certificate hash.
+    listen_multi_address: Multiaddr,
+
+    /// Whether the UDP socket has failed and the listener has been closed.
+    listener_closed: bool,
+
     /// Datagram buffer size.
     datagram_buffer_size: usize,
 
@@ -146,13 +163,18 @@ pub(crate) struct WebRtcTransport {
     /// Pending timeouts.
     timeouts: HashMap<SocketAddr, BoxFuture<'static, ()>>,
 
+    /// Pending dials.
+    pending_dials: HashMap<SocketAddr, (ConnectionId, Multiaddr)>,
+
     /// Pending events.
     pending_events: VecDeque<TransportEvent>,
 }
 
 impl WebRtcTransport {
-    /// Extract socket address and `PeerId`, if found, from `address`.
-    fn get_socket_address(address: &Multiaddr) -> crate::Result<(SocketAddr, Option<PeerId>)> {
+    /// Extract socket address, certificate hash and `PeerId`, if found, from `address`.
+    fn get_socket_address(
+        address: &Multiaddr,
+    ) -> crate::Result<(SocketAddr, Option<Multihash>, Option<PeerId>)> {
         tracing::trace!(target: LOG_TARGET, ?address, "parse multi address");
 
         let mut iter = address.iter();
@@ -197,6 +219,16 @@ impl WebRtcTransport {
             }
         }
 
+        let mut iter = iter.peekable();
+        let maybe_certhash = match iter.peek() {
+            Some(Protocol::Certhash(certhash)) => {
+                let certhash = *certhash;
+                iter.next();
+                Some(certhash)
+            }
+            _ => None,
+        };
+
         let maybe_peer = match iter.next() {
             Some(Protocol::P2p(multihash)) => Some(PeerId::from_multihash(multihash)?),
             None => None,
@@ -210,7 +242,48 @@ impl WebRtcTransport {
             }
         };
 
-        Ok((socket_address, maybe_peer))
+        Ok((socket_address, maybe_certhash, maybe_peer))
+    }
+
+    /// Convert the certificate hash of a remote peer into a DTLS fingerprint.
+    fn certhash_to_fingerprint(certhash: &Multihash) -> crate::Result<Fingerprint> {
+        if certhash.code() != MULTIHASH_SHA256_CODE || certhash.digest().len() != 32 {
+            tracing::debug!(
+                target: LOG_TARGET,
+                code = ?certhash.code(),
+                "unsupported certificate hash, expected `sha-256`",
+            );
+            return Err(Error::InvalidCertificate);
+        }
+
+        Ok(Fingerprint {
+            hash_func: "sha-256".to_string(),
+            bytes: certhash.digest().to_vec(),
+        })
+    }
+
+    /// Generate random ICE username fragment for a dialed connection.
+    ///
+    /// The username fragment is also used as the ICE password by both peers.
+    fn random_ufrag() -> String {
+        let suffix = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect::<String>();
+
+        format!("{UFRAG_PREFIX}{suffix}")
+    }
+
+    /// Create data channel for the Noise handshake.
+    fn create_noise_channel(rtc: &mut Rtc) -> ChannelId {
+        rtc.direct_api().create_data_channel(ChannelConfig {
+            label: "noise".to_string(),
+            ordered: false,
+            reliability: Default::default(),
+            negotiated: Some(0),
+            protocol: "".to_string(),
+        })
     }
 
     /// Create RTC client and open channel for Noise handshake.
@@ -223,7 +296,7 @@ impl WebRtcTransport {
     ) -> (Rtc, ChannelId) {
         let mut rtc = Rtc::builder()
             .set_ice_lite(true)
-            .set_dtls_cert(self.dtls_cert.clone())
+            .set_dtls_cert_config(DtlsCertConfig::PregeneratedCert(self.dtls_cert.clone()))
             .set_fingerprint_verification(false)
             .build();
         rtc.add_local_candidate(Candidate::host(destination, Str0mProtocol::Udp).unwrap());
@@ -242,17 +315,84 @@ impl WebRtcTransport {
         rtc.direct_api().start_dtls(false).unwrap();
         rtc.direct_api().start_sctp(false);
 
-        let noise_channel_id = rtc.direct_api().create_data_channel(ChannelConfig {
-            label: "noise".to_string(),
-            ordered: false,
-            reliability: Default::default(),
-            negotiated: Some(0),
-            protocol: "".to_string(),
-        });
+        let noise_channel_id = Self::create_noise_channel(&mut rtc);
 
         (rtc, noise_channel_id)
     }
 
+    /// Create RTC client for dialing `destination` and open channel for Noise handshake.
+    ///
+    /// The dialer is the controlling ICE agent and the DTLS client. The certificate of the remote
+    /// peer is verified against `fingerprint`.
+    fn make_rtc_dialer(
+        &self,
+        ufrag: &str,
+        destination: SocketAddr,
+        fingerprint: Fingerprint,
+    ) -> crate::Result<(Rtc, ChannelId)> {
+        let mut rtc = Rtc::builder()
+            .set_dtls_cert_config(DtlsCertConfig::PregeneratedCert(self.dtls_cert.clone()))
+            .build();
+
+        let local_candidate = Candidate::host(self.listen_address, Str0mProtocol::Udp)
+            .map_err(|error| Error::Other(error.to_string()))?;
+        let remote_candidate = Candidate::host(destination, Str0mProtocol::Udp)
+            .map_err(|error| Error::Other(error.to_string()))?;
+
+        rtc.add_local_candidate(local_candidate);
+        rtc.add_remote_candidate(remote_candidate);
+        rtc.direct_api().set_remote_fingerprint(fingerprint);
+        rtc.direct_api().set_remote_ice_credentials(IceCreds {
+            ufrag: ufrag.to_owned(),
+            pass: ufrag.to_owned(),
+        });
+        rtc.direct_api().set_local_ice_credentials(IceCreds {
+            ufrag: ufrag.to_owned(),
+            pass: ufrag.to_owned(),
+        });
+        rtc.direct_api().set_ice_controlling(true);
+        rtc.direct_api().start_dtls(true).map_err(Error::WebRtc)?;
+        rtc.direct_api().start_sctp(true);
+
+        let noise_channel_id = Self::create_noise_channel(&mut rtc);
+
+        Ok((rtc, noise_channel_id))
+    }
+
+    /// Handle closed opening connection.
+    ///
+    /// If the connection was dialed by the local node, the dial is reported as failed.
+    fn on_connection_closed(&mut self, source: &SocketAddr) -> Option<TransportEvent> {
+        let connection = self.opening.remove(source);
+        self.timeouts.remove(source);
+
+        if let Some((connection_id, address)) = self.pending_dials.remove(source) {
+            tracing::debug!(target: LOG_TARGET, ?connection_id, ?address, "failed to dial peer");
+
+            return Some(TransportEvent::DialFailure {
+                connection_id,
+                address,
+                error: Error::Disconnected,
+            });
+        }
+
+        // inbound connection failed before it was reported established
+        let connection_id = connection?.connection_id();
+        (!self.connections.contains_key(&connection_id)).then(|| {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?connection_id,
+                ?source,
+                "failed to negotiate inbound connection",
+            );
+
+            TransportEvent::IncomingConnectionError {
+                connection_id,
+                error: Error::Disconnected,
+            }
+        })
+    }
+
     /// Poll opening connection.
     fn poll_connection(&mut self, source: &SocketAddr) -> ConnectionEvent {
         let Some(connection) = self.opening.get_mut(source) else {
@@ -300,6 +440,8 @@ impl WebRtcTransport {
                     },
                 opening::WebRtcEvent::ConnectionClosed => return ConnectionEvent::ConnectionClosed,
                 opening::WebRtcEvent::ConnectionOpened { peer, endpoint } => {
+                    self.context.metrics.on_handshake("noise", connection.handshake_duration());
+
                     return ConnectionEvent::ConnectionEstablished { peer, endpoint };
                 }
             }
@@ -349,16 +491,9 @@ impl WebRtcTransport {
         let contents: DatagramRecv =
             buffer.as_slice().try_into().map_err(|_| Error::InvalidData)?;
 
-        // Handle non stun packets.
-        if !is_stun_packet(&buffer) {
-            tracing::debug!(
-                target: LOG_TARGET,
-                ?source,
-                "received non-stun message"
-            );
-
-            if let Err(error) = self.opening.get_mut(&source).expect("to exist").on_input(contents)
-            {
+        // handle messages of opening connections, including the STUN messages of dialed connections
+        if let Some(connection) = self.opening.get_mut(&source) {
+            if let Err(error) = connection.on_input(contents) {
                 tracing::error!(
                     target: LOG_TARGET,
                     ?error,
@@ -369,6 +504,16 @@ impl WebRtcTransport {
             return Ok(true);
         }
 
+        if !is_stun_packet(&buffer) {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?source,
+                "received non-stun message from unknown peer"
+            );
+
+            return Err(Error::InvalidData);
+        }
+
         let stun_message =
             str0m::ice::StunMessage::parse(&buffer).map_err(|_| Error::InvalidData)?;
         let Some((ufrag, pass)) = stun_message.split_username() else {
@@ -413,6 +558,14 @@ impl WebRtcTransport {
             self.listen_address,
         );
         self.opening.insert(source, connection);
+        self.pending_events.push_back(TransportEvent::IncomingConnection {
+            connection_id,
+            local_address: self.listen_multi_address.clone(),
+            remote_address: Multiaddr::empty()
+                .with(Protocol::from(source.ip()))
+                .with(Protocol::Udp(source.port()))
+                .with(Protocol::WebRTC),
+        });
 
         Ok(true)
     }
@@ -433,7 +586,7 @@ impl TransportBuilder for WebRtcTransport {
             "start webrtc transport",
         );
 
-        let (listen_address, _) = Self::get_socket_address(&config.listen_addresses[0])?;
+        let (listen_address, _, _) = Self::get_socket_address(&config.listen_addresses[0])?;
 
         let socket = if listen_address.is_ipv4() {
             let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(socket2::Protocol::UDP))?;
@@ -453,20 +606,19 @@ impl TransportBuilder for WebRtcTransport {
 
         let socket = UdpSocket::from_std(socket.into())?;
         let listen_address = socket.local_addr()?;
-        let dtls_cert = DtlsCert::new_openssl();
+        let dtls_cert = DtlsCert::new(CryptoProvider::OpenSsl, DtlsCertOptions::default());
 
-        let listen_multi_addresses = {
+        let listen_multi_address = {
             let fingerprint = dtls_cert.fingerprint().bytes;
 
-            const MULTIHASH_SHA256_CODE: u64 = 0x12;
             let certificate = Multihash::wrap(MULTIHASH_SHA256_CODE, &fingerprint)
                 .expect("fingerprint's len to be 32 bytes");
 
-            vec![Multiaddr::empty()
+            Multiaddr::empty()
                 .with(Protocol::from(listen_address.ip()))
                 .with(Protocol::Udp(listen_address.port()))
                 .with(Protocol::WebRTC)
-                .with(Protocol::Certhash(certificate))]
+                .with(Protocol::Certhash(certificate))
         };
 
         Ok((
@@ -474,29 +626,65 @@ impl TransportBuilder for WebRtcTransport {
                 context,
                 dtls_cert,
                 listen_address,
+                listen_multi_address: listen_multi_address.clone(),
+                listener_closed: false,
                 open: HashMap::new(),
                 opening: HashMap::new(),
                 connections: HashMap::new(),
                 socket: Arc::new(socket),
                 timeouts: HashMap::new(),
+                pending_dials: HashMap::new(),
                 pending_events: VecDeque::new(),
                 datagram_buffer_size: config.datagram_buffer_size,
             },
-            listen_multi_addresses,
+            vec![listen_multi_address],
         ))
     }
 }
 
 impl Transport for WebRtcTransport {
     fn dial(&mut self, connection_id: ConnectionId, address: Multiaddr) -> crate::Result<()> {
-        tracing::warn!(
-            target: LOG_TARGET,
-            ?connection_id,
-            ?address,
-            "webrtc cannot dial",
+        let (socket_address, Some(certhash), peer) = Self::get_socket_address(&address)? else {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?connection_id,
+                ?address,
+                "certificate hash missing from the address",
+            );
+            return Err(Error::AddressError(AddressError::InvalidProtocol));
+        };
+
+        if socket_address.is_ipv4() != self.listen_address.is_ipv4() {
+            return Err(Error::AddressError(AddressError::AddressNotAvailable));
+        }
+
+        if self.open.contains_key(&socket_address) || self.opening.contains_key(&socket_address) {
+            return Err(Error::AlreadyConnected);
+        }
+
+        tracing::trace!(target: LOG_TARGET, ?connection_id, ?address, "dial peer");
+
+        let fingerprint = Self::certhash_to_fingerprint(&certhash)?;
+        let (rtc, noise_channel_id) =
+            self.make_rtc_dialer(&Self::random_ufrag(), socket_address, fingerprint.clone())?;
+
+        let connection = OpeningWebRtcConnection::new_outbound(
+            rtc,
+            connection_id,
+            noise_channel_id,
+            self.context.keypair.clone(),
+            socket_address,
+            self.listen_address,
+            peer,
+            fingerprint,
         );
+        self.opening.insert(socket_address, connection);
+        self.pending_dials.insert(socket_address, (connection_id, address));
+
+        // poll the connection on the next call to `poll_next()` so it starts the ICE checks
+        self.timeouts.insert(socket_address, Box::pin(futures::future::ready(())));
 
-        Err(Error::NotSupported("webrtc cannot dial peers".to_string()))
+        Ok(())
     }
 
     fn accept(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
@@ -541,6 +729,7 @@ impl Transport for WebRtcTransport {
             protocol_set,
             endpoint,
             rx,
+            self.context.bandwidth_sink.clone(),
         );
         self.open.insert(
             source,
@@ -602,6 +791,18 @@ impl Transport for WebRtcTransport {
     }
 
     fn cancel(&mut self, _connection_id: ConnectionId) {}
+
+    fn listen_on(&mut self, _address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        Err(Error::NotSupported(
+            "webrtc transport doesn't support adding listeners".to_string(),
+        ))
+    }
+
+    fn remove_listener(&mut self, _address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        Err(Error::NotSupported(
+            "webrtc transport doesn't support removing listeners".to_string(),
+        ))
+    }
 }
 
 impl Stream for WebRtcTransport {
@@ -614,6 +815,10 @@ impl Stream for WebRtcTransport {
             return Poll::Ready(Some(event));
         }
 
+        if this.listener_closed {
+            return Poll::Ready(None);
+        }
+
         loop {
             let mut buf = vec![0u8; 16384];
             let mut read_buf = ReadBuf::new(&mut buf);
@@ -627,7 +832,10 @@ impl Stream for WebRtcTransport {
                         "webrtc udp socket closed",
                     );
 
-                    return Poll::Ready(None);
+                    this.listener_closed = true;
+                    return Poll::Ready(Some(TransportEvent::ListenerClosed {
+                        address: this.listen_multi_address.clone(),
+                    }));
                 }
                 Poll::Ready(Ok(source)) => {
                     let nread = read_buf.filled().len();
@@ -638,6 +846,7 @@ impl Stream for WebRtcTransport {
                         Ok(true) => loop {
                             match this.poll_connection(&source) {
                                 ConnectionEvent::ConnectionEstablished { peer, endpoint } => {
+                                    this.pending_dials.remove(&source);
                                     this.connections.insert(
                                         endpoint.connection_id(),
                                         (peer, source, endpoint.clone()),
@@ -649,8 +858,9 @@ impl Stream for WebRtcTransport {
                                     );
                                 }
                                 ConnectionEvent::ConnectionClosed => {
-                                    this.opening.remove(&source);
-                                    this.timeouts.remove(&source);
+                                    if let Some(event) = this.on_connection_closed(&source) {
+                                        this.pending_events.push_back(event);
+                                    }
 
                                     break;
                                 }
@@ -679,50 +889,63 @@ impl Stream for WebRtcTransport {
 
         // go over all pending timeouts to see if any of them have expired
         // and if any of them have, poll the connection until it registers another timeout
-        let pending_events = this
-            .timeouts
-            .iter_mut()
-            .filter_map(|(source, mut delay)| match Pin::new(&mut delay).poll(cx) {
-                Poll::Pending => None,
-                Poll::Ready(_) => Some(*source),
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .filter_map(|source| {
-                let mut pending_event = None;
-
-                loop {
-                    match this.poll_connection(&source) {
-                        ConnectionEvent::ConnectionEstablished { peer, endpoint } => {
-                            this.connections
-                                .insert(endpoint.connection_id(), (peer, source, endpoint.clone()));
-
-                            // keep polling the connection until it registers a timeout
-                            pending_event =
-                                Some(TransportEvent::ConnectionEstablished { peer, endpoint });
-                        }
-                        ConnectionEvent::ConnectionClosed => {
-                            this.opening.remove(&source);
-                            return None;
-                        }
-                        ConnectionEvent::Timeout { duration } => {
-                            this.timeouts.insert(
-                                source,
-                                Box::pin(async move {
-                                    Delay::new(duration);
-                                }),
-                            );
-                            break;
+        //
+        // the new timeouts are polled before returning so that the task is woken up when they
+        // expire
+        loop {
+            let expired = this
+                .timeouts
+                .iter_mut()
+                .filter_map(|(source, mut delay)| match Pin::new(&mut delay).poll(cx) {
+                    Poll::Pending => None,
+                    Poll::Ready(_) => Some(*source),
+                })
+                .collect::<Vec<_>>();
+
+            if expired.is_empty() {
+                break;
+            }
+
+            let pending_events = expired
+                .into_iter()
+                .filter_map(|source| {
+                    this.timeouts.remove(&source);
+                    let mut pending_event = None;
+
+                    loop {
+                        match this.poll_connection(&source) {
+                            ConnectionEvent::ConnectionEstablished { peer, endpoint } => {
+                                this.pending_dials.remove(&source);
+                                this.connections.insert(
+                                    endpoint.connection_id(),
+                                    (peer, source, endpoint.clone()),
+                                );
+
+                                // keep polling the connection until it registers a timeout
+                                pending_event =
+                                    Some(TransportEvent::ConnectionEstablished { peer, endpoint });
+                            }
+                            ConnectionEvent::ConnectionClosed => {
+                                return this.on_connection_closed(&source);
+                            }
+                            ConnectionEvent::Timeout { duration } => {
+                                this.timeouts.insert(
+                                    source,
+                                    Box::pin(async move { Delay::new(duration).await }),
+                                );
+                                break;
+                            }
                         }
                     }
-                }
 
-                pending_event
-            })
-            .collect::<VecDeque<_>>();
+                    pending_event
+                })
+                .collect::<VecDeque<_>>();
+
+            this.timeouts.retain(|source, _| this.opening.contains_key(source));
+            this.pending_events.extend(pending_events);
+        }
 
-        this.timeouts.retain(|source, _| this.opening.contains_key(source));
-        this.pending_events.extend(pending_events);
         this.pending_events
             .pop_front()
             .map_or(Poll::Pending, |event| Poll::Ready(Some(event)))
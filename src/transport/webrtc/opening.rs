@@ -30,25 +30,34 @@ use crate::{
 
 use multiaddr::{multihash::Multihash, Multiaddr, Protocol};
 use str0m::{
-    change::Fingerprint,
     channel::ChannelId,
+    config::Fingerprint,
     net::{DatagramRecv, DatagramSend, Protocol as Str0mProtocol, Receive},
     Event, IceConnectionState, Input, Output, Rtc,
 };
 
-use std::{net::SocketAddr, time::Instant};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::webrtc::connection";
 
+/// Interval at which a connection that is being validated by `TransportManager` is polled.
+///
+/// The connection is not progressed while it's being validated so that the data received after
+/// the Noise handshake is left for `WebRtcConnection` to handle.
+const VALIDATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Create Noise prologue.
-fn noise_prologue(local_fingerprint: Vec<u8>, remote_fingerprint: Vec<u8>) -> Vec<u8> {
+fn noise_prologue(client_fingerprint: Vec<u8>, server_fingerprint: Vec<u8>) -> Vec<u8> {
     const PREFIX: &[u8] = b"libp2p-webrtc-noise:";
     let mut prologue =
-        Vec::with_capacity(PREFIX.len() + local_fingerprint.len() + remote_fingerprint.len());
+        Vec::with_capacity(PREFIX.len() + client_fingerprint.len() + server_fingerprint.len());
     prologue.extend_from_slice(PREFIX);
-    prologue.extend_from_slice(&remote_fingerprint);
-    prologue.extend_from_slice(&local_fingerprint);
+    prologue.extend_from_slice(&client_fingerprint);
+    prologue.extend_from_slice(&server_fingerprint);
 
     prologue
 }
@@ -110,6 +119,20 @@ pub struct OpeningWebRtcConnection {
 
     /// Local address.
     local_address: SocketAddr,
+
+    /// Role of the local node.
+    ///
+    /// The dialer of the connection is the responder of the Noise handshake.
+    role: Role,
+
+    /// Expected remote peer ID of a dialed connection, if known.
+    remote_peer: Option<PeerId>,
+
+    /// Expected DTLS fingerprint of the remote peer of a dialed connection.
+    remote_fingerprint: Option<Fingerprint>,
+
+    /// When the connection started opening.
+    started: Instant,
 }
 
 /// Connection state.
@@ -127,7 +150,7 @@ enum State {
         context: NoiseContext,
     },
 
-    /// Local Noise handshake has been sent to peer and the connection
+    /// Noise handshake message has been sent to peer and the connection
     /// is waiting for an answer.
     HandshakeSent {
         /// Noise context.
@@ -167,23 +190,76 @@ impl OpeningWebRtcConnection {
             id_keypair,
             peer_address,
             local_address,
+            role: Role::Listener,
+            remote_peer: None,
+            remote_fingerprint: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Create new [`OpeningWebRtcConnection`] for a connection dialed by the local node.
+    ///
+    /// If `remote_peer` is given, the connection is closed if the peer ID received during the
+    /// Noise handshake doesn't match it.
+    pub fn new_outbound(
+        rtc: Rtc,
+        connection_id: ConnectionId,
+        noise_channel_id: ChannelId,
+        id_keypair: Keypair,
+        peer_address: SocketAddr,
+        local_address: SocketAddr,
+        remote_peer: Option<PeerId>,
+        remote_fingerprint: Fingerprint,
+    ) -> OpeningWebRtcConnection {
+        Self {
+            role: Role::Dialer,
+            remote_peer,
+            remote_fingerprint: Some(remote_fingerprint),
+            ..Self::new(
+                rtc,
+                connection_id,
+                noise_channel_id,
+                id_keypair,
+                peer_address,
+                local_address,
+            )
+        }
+    }
+
+    /// Get connection ID.
+    pub fn connection_id(&self) -> ConnectionId {
+        self.connection_id
+    }
+
+    /// Get the time elapsed since the connection started opening.
+    pub fn handshake_duration(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Get remote fingerprint.
+    ///
+    /// For dialed connections, the fingerprint was verified by `str0m` during the DTLS handshake.
+    fn remote_dtls_fingerprint(&mut self) -> Fingerprint {
+        match &self.remote_fingerprint {
+            Some(fingerprint) => fingerprint.clone(),
+            None => self
+                .rtc
+                .direct_api()
+                .remote_dtls_fingerprint()
+                .expect("fingerprint to exist")
+                .clone(),
         }
     }
 
     /// Get remote fingerprint to bytes.
     fn remote_fingerprint(&mut self) -> Vec<u8> {
-        let fingerprint = self
-            .rtc
-            .direct_api()
-            .remote_dtls_fingerprint()
-            .clone()
-            .expect("fingerprint to exist");
+        let fingerprint = self.remote_dtls_fingerprint();
         Self::fingerprint_to_bytes(&fingerprint)
     }
 
     /// Get local fingerprint as bytes.
     fn local_fingerprint(&mut self) -> Vec<u8> {
-        Self::fingerprint_to_bytes(&self.rtc.direct_api().local_dtls_fingerprint())
+        Self::fingerprint_to_bytes(self.rtc.direct_api().local_dtls_fingerprint())
     }
 
     /// Convert `Fingerprint` to bytes.
@@ -198,7 +274,14 @@ impl OpeningWebRtcConnection {
     /// the WebRTC server will act as the dialer as per the specification.
     ///
     /// Create the first Noise handshake message and send it to remote peer.
+    ///
+    /// If the local node dialed the connection, it waits for the first message from the remote
+    /// peer.
     fn on_noise_channel_open(&mut self) -> crate::Result<()> {
+        if std::matches!(self.role, Role::Dialer) {
+            return Ok(());
+        }
+
         tracing::trace!(target: LOG_TARGET, "send initial noise handshake");
 
         let State::Opened { mut context } = std::mem::replace(&mut self.state, State::Poisoned)
@@ -244,16 +327,36 @@ impl OpeningWebRtcConnection {
     ///
     /// If the peer is accepted, [`OpeningWebRtcConnection::on_accept()`] is called which creates
     /// the final Noise message and sends it to the remote peer, concluding the handshake.
-    fn on_noise_channel_data(&mut self, data: Vec<u8>) -> crate::Result<WebRtcEvent> {
-        tracing::trace!(target: LOG_TARGET, "handle noise handshake reply");
+    ///
+    /// If the local node dialed the connection, the first message received from the remote peer
+    /// is answered with the local Noise payload and the second message concludes the handshake.
+    fn on_noise_channel_data(&mut self, data: Vec<u8>) -> crate::Result<Option<WebRtcEvent>> {
+        tracing::trace!(target: LOG_TARGET, role = ?self.role, "handle noise handshake message");
 
-        let State::HandshakeSent { mut context } =
-            std::mem::replace(&mut self.state, State::Poisoned)
-        else {
-            return Err(Error::InvalidState);
+        let message = WebRtcMessage::decode(&data)?.payload.ok_or(Error::InvalidData)?;
+
+        let mut context = match (
+            std::mem::replace(&mut self.state, State::Poisoned),
+            self.role,
+        ) {
+            (State::Opened { mut context }, Role::Dialer) => {
+                context.read_first_message(&message)?;
+
+                let payload = WebRtcMessage::encode(context.second_message()?);
+
+                self.rtc
+                    .channel(self.noise_channel_id)
+                    .ok_or(Error::ChannelDoesntExist)?
+                    .write(true, payload.as_slice())
+                    .map_err(Error::WebRtc)?;
+
+                self.state = State::HandshakeSent { context };
+                return Ok(None);
+            }
+            (State::HandshakeSent { context }, _) => context,
+            _ => return Err(Error::InvalidState),
         };
 
-        let message = WebRtcMessage::decode(&data)?.payload.ok_or(Error::InvalidData)?;
         let public_key = context.get_remote_public_key(&message)?;
         let remote_peer_id = PeerId::from_public_key(&public_key);
 
@@ -263,15 +366,15 @@ impl OpeningWebRtcConnection {
             "remote reply parsed successfully",
         );
 
+        if let Some(peer) = self.remote_peer {
+            if peer != remote_peer_id {
+                return Err(Error::PeerIdMismatch(peer, remote_peer_id));
+            }
+        }
+
         self.state = State::Validating { context };
 
-        let remote_fingerprint = self
-            .rtc
-            .direct_api()
-            .remote_dtls_fingerprint()
-            .clone()
-            .expect("fingerprint to exist")
-            .bytes;
+        let remote_fingerprint = self.remote_dtls_fingerprint().bytes;
 
         const MULTIHASH_SHA256_CODE: u64 = 0x12;
         let certificate = Multihash::wrap(MULTIHASH_SHA256_CODE, &remote_fingerprint)
@@ -284,14 +387,21 @@ impl OpeningWebRtcConnection {
             .with(Protocol::Certhash(certificate))
             .with(Protocol::P2p(PeerId::from(public_key).into()));
 
-        Ok(WebRtcEvent::ConnectionOpened {
+        let endpoint = match self.role {
+            Role::Dialer => Endpoint::dialer(address, self.connection_id),
+            Role::Listener => Endpoint::listener(address, self.connection_id),
+        };
+
+        Ok(Some(WebRtcEvent::ConnectionOpened {
             peer: remote_peer_id,
-            endpoint: Endpoint::listener(address, self.connection_id),
-        })
+            endpoint,
+        }))
     }
 
     /// Accept connection by sending the final Noise handshake message
     /// and return the `Rtc` object for further use.
+    ///
+    /// If the local node dialed the connection, the handshake has already been concluded.
     pub fn on_accept(mut self) -> crate::Result<Rtc> {
         tracing::trace!(target: LOG_TARGET, "accept webrtc connection");
 
@@ -300,6 +410,10 @@ impl OpeningWebRtcConnection {
             return Err(Error::InvalidState);
         };
 
+        if std::matches!(self.role, Role::Dialer) {
+            return Ok(self.rtc);
+        }
+
         // create second noise handshake message and send it to remote
         let payload = WebRtcMessage::encode(context.second_message()?);
 
@@ -357,6 +471,12 @@ impl OpeningWebRtcConnection {
             return WebRtcEvent::ConnectionClosed;
         }
 
+        if std::matches!(self.state, State::Validating { .. }) {
+            return WebRtcEvent::Timeout {
+                timeout: Instant::now() + VALIDATION_POLL_INTERVAL,
+            };
+        }
+
         loop {
             let output = match self.rtc.poll_output() {
                 Ok(output) => output,
@@ -410,8 +530,16 @@ impl OpeningWebRtcConnection {
                             continue;
                         }
 
-                        // TODO: no expect
-                        self.on_noise_channel_open().expect("to succeed");
+                        if let Err(error) = self.on_noise_channel_open() {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                connection_id = ?self.connection_id,
+                                ?error,
+                                "failed to send noise handshake",
+                            );
+
+                            return WebRtcEvent::ConnectionClosed;
+                        }
                     }
                     Event::ChannelData(data) => {
                         tracing::trace!(
@@ -429,8 +557,20 @@ impl OpeningWebRtcConnection {
                             continue;
                         }
 
-                        // TODO: no expect
-                        return self.on_noise_channel_data(data.data).expect("to succeed");
+                        match self.on_noise_channel_data(data.data) {
+                            Ok(Some(event)) => return event,
+                            Ok(None) => {}
+                            Err(error) => {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    connection_id = ?self.connection_id,
+                                    ?error,
+                                    "failed to handle noise handshake",
+                                );
+
+                                return WebRtcEvent::ConnectionClosed;
+                            }
+                        }
                     }
                     Event::ChannelClose(channel_id) => {
                         tracing::debug!(target: LOG_TARGET, ?channel_id, "channel closed");
@@ -440,22 +580,33 @@ impl OpeningWebRtcConnection {
                             let remote_fingerprint = self.remote_fingerprint();
                             let local_fingerprint = self.local_fingerprint();
 
-                            let context = match NoiseContext::with_prologue(
-                                &self.id_keypair,
-                                noise_prologue(local_fingerprint, remote_fingerprint),
-                            ) {
-                                Ok(context) => context,
-                                Err(err) => {
-                                    tracing::error!(
-                                        target: LOG_TARGET,
-                                        peer = ?self.peer_address,
-                                        "NoiseContext failed with error {err}",
-                                    );
-
-                                    return WebRtcEvent::ConnectionClosed;
-                                }
+                            // the WebRTC server is the initiator of the Noise handshake
+                            let (prologue, role) = match self.role {
+                                Role::Dialer => (
+                                    noise_prologue(local_fingerprint, remote_fingerprint),
+                                    Role::Listener,
+                                ),
+                                Role::Listener => (
+                                    noise_prologue(remote_fingerprint, local_fingerprint),
+                                    Role::Dialer,
+                                ),
                             };
 
+                            let context =
+                                match NoiseContext::with_prologue(&self.id_keypair, prologue, role)
+                                {
+                                    Ok(context) => context,
+                                    Err(err) => {
+                                        tracing::error!(
+                                            target: LOG_TARGET,
+                                            peer = ?self.peer_address,
+                                            "NoiseContext failed with error {err}",
+                                        );
+
+                                        return WebRtcEvent::ConnectionClosed;
+                                    }
+                                };
+
                             tracing::debug!(
                                 target: LOG_TARGET,
                                 peer = ?self.peer_address,
@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
+    bandwidth::SubstreamBandwidthSink,
     transport::webrtc::{schema::webrtc::message::Flag, util::WebRtcMessage},
     Error,
 };
@@ -60,10 +61,15 @@ enum State {
 }
 
 /// Channel-backedn substream.
+///
+/// `BandwidthSink` is used to meter inbound/outbound bytes.
 pub struct Substream {
     /// Substream state.
     state: Arc<Mutex<State>>,
 
+    /// Bandwidth sink.
+    bandwidth_sink: SubstreamBandwidthSink,
+
     /// Read buffer.
     read_buffer: BytesMut,
 
@@ -76,7 +82,7 @@ pub struct Substream {
 
 impl Substream {
     /// Create new [`Substream`].
-    pub fn new() -> (Self, SubstreamHandle) {
+    pub fn new(bandwidth_sink: SubstreamBandwidthSink) -> (Self, SubstreamHandle) {
         let (outbound_tx, outbound_rx) = channel(256);
         let (inbound_tx, inbound_rx) = channel(256);
         let state = Arc::new(Mutex::new(State::Open));
@@ -89,6 +95,7 @@ impl Substream {
         (
             Self {
                 state,
+                bandwidth_sink,
                 tx: outbound_tx,
                 rx: inbound_rx,
                 read_buffer: BytesMut::new(),
@@ -160,6 +167,7 @@ impl tokio::io::AsyncRead for Substream {
 
             buf.put_slice(&self.read_buffer[..num_bytes]);
             self.read_buffer.advance(num_bytes);
+            self.bandwidth_sink.increase_inbound(num_bytes);
 
             // TODO: optimize by trying to read more data from substream and not exiting early
             return Poll::Ready(Ok(()));
@@ -173,14 +181,19 @@ impl tokio::io::AsyncRead for Substream {
                     return Poll::Ready(Err(std::io::ErrorKind::PermissionDenied.into()));
                 }
 
-                match buf.remaining() >= message.len() {
-                    true => buf.put_slice(&message),
+                let num_bytes = match buf.remaining() >= message.len() {
+                    true => {
+                        buf.put_slice(&message);
+                        message.len()
+                    }
                     false => {
                         let remaining = buf.remaining();
                         buf.put_slice(&message[..remaining]);
                         self.read_buffer.put_slice(&message[remaining..]);
+                        remaining
                     }
-                }
+                };
+                self.bandwidth_sink.increase_inbound(num_bytes);
 
                 Poll::Ready(Ok(()))
             }
@@ -211,6 +224,7 @@ impl tokio::io::AsyncWrite for Substream {
 
         let frame = buf[..num_bytes].to_vec();
         permit.send(Event::Message(frame));
+        self.bandwidth_sink.increase_outbound(num_bytes);
 
         Poll::Ready(Ok(num_bytes))
     }
@@ -239,12 +253,25 @@ impl tokio::io::AsyncWrite for Substream {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        bandwidth::{BandwidthSink, BandwidthSnapshot},
+        transport::manager::SupportedTransport,
+        PeerId, ProtocolName,
+    };
     use futures::StreamExt;
     use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
 
+    fn make_substream() -> (Substream, SubstreamHandle) {
+        Substream::new(BandwidthSink::new().substream(
+            SupportedTransport::WebRtc,
+            PeerId::random(),
+            ProtocolName::from("/proto/1"),
+        ))
+    }
+
     #[tokio::test]
     async fn write_small_frame() {
-        let (mut substream, mut handle) = Substream::new();
+        let (mut substream, mut handle) = make_substream();
 
         substream.write_all(&vec![0u8; 1337]).await.unwrap();
 
@@ -259,7 +286,7 @@ mod tests {
 
     #[tokio::test]
     async fn write_large_frame() {
-        let (mut substream, mut handle) = Substream::new();
+        let (mut substream, mut handle) = make_substream();
 
         substream.write_all(&vec![0u8; (2 * MAX_FRAME_SIZE) + 1]).await.unwrap();
 
@@ -282,7 +309,7 @@ mod tests {
 
     #[tokio::test]
     async fn try_to_write_to_closed_substream() {
-        let (mut substream, handle) = Substream::new();
+        let (mut substream, handle) = make_substream();
         *handle.state.lock() = State::SendClosed;
 
         match substream.write_all(&vec![0u8; 1337]).await {
@@ -293,7 +320,7 @@ mod tests {
 
     #[tokio::test]
     async fn substream_shutdown() {
-        let (mut substream, mut handle) = Substream::new();
+        let (mut substream, mut handle) = make_substream();
 
         substream.write_all(&vec![1u8; 1337]).await.unwrap();
         substream.shutdown().await.unwrap();
@@ -304,7 +331,7 @@ mod tests {
 
     #[tokio::test]
     async fn try_to_read_from_closed_substream() {
-        let (mut substream, handle) = Substream::new();
+        let (mut substream, handle) = make_substream();
         handle
             .on_message(WebRtcMessage {
                 payload: None,
@@ -321,7 +348,7 @@ mod tests {
 
     #[tokio::test]
     async fn read_small_frame() {
-        let (mut substream, handle) = Substream::new();
+        let (mut substream, handle) = make_substream();
         handle.tx.send(Event::Message(vec![1u8; 256])).await.unwrap();
 
         let mut buf = vec![0u8; 2048];
@@ -346,7 +373,7 @@ mod tests {
 
     #[tokio::test]
     async fn read_small_frame_in_two_reads() {
-        let (mut substream, handle) = Substream::new();
+        let (mut substream, handle) = make_substream();
         let mut first = vec![1u8; 256];
         first.extend_from_slice(&vec![2u8; 256]);
 
@@ -382,7 +409,7 @@ mod tests {
 
     #[tokio::test]
     async fn read_frames() {
-        let (mut substream, handle) = Substream::new();
+        let (mut substream, handle) = make_substream();
         let mut first = vec![1u8; 256];
         first.extend_from_slice(&vec![2u8; 256]);
 
@@ -441,7 +468,7 @@ mod tests {
 
     #[tokio::test]
     async fn backpressure_works() {
-        let (mut substream, _handle) = Substream::new();
+        let (mut substream, _handle) = make_substream();
 
         // use all available bandwidth which by default is `256 * MAX_FRAME_SIZE`,
         for _ in 0..128 {
@@ -458,4 +485,30 @@ mod tests {
         })
         .await;
     }
+
+    #[tokio::test]
+    async fn bytes_are_metered() {
+        let sink = BandwidthSink::new();
+        let peer = PeerId::random();
+        let protocol = ProtocolName::from("/proto/1");
+        let (mut substream, mut handle) =
+            Substream::new(sink.substream(SupportedTransport::WebRtc, peer, protocol.clone()));
+
+        substream.write_all(&vec![0u8; 1337]).await.unwrap();
+        assert_eq!(handle.next().await, Some(Event::Message(vec![0u8; 1337])));
+
+        handle.tx.send(Event::Message(vec![1u8; 256])).await.unwrap();
+        let mut buf = vec![0u8; 256];
+        substream.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(sink.outbound(), 1337);
+        assert_eq!(sink.inbound(), 256);
+
+        let expected = BandwidthSnapshot {
+            inbound: 256,
+            outbound: 1337,
+        };
+        assert_eq!(sink.protocols().get(&protocol), Some(&expected));
+        assert_eq!(sink.peers().get(&peer), Some(&expected));
+    }
 }
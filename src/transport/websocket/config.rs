@@ -22,17 +22,63 @@
 
 use crate::{
     crypto::noise::{MAX_READ_AHEAD_FACTOR, MAX_WRITE_BUFFER_SIZE},
-    transport::{CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT},
+    transport::{
+        websocket::tls::CertificateResolver, CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT,
+    },
 };
 
+use std::sync::Arc;
+
+/// TLS configuration of secure WebSocket listeners.
+///
+/// Clones of the configuration share the certificate so the node can keep a clone around and
+/// call [`TlsConfig::reload()`] when the certificate is renewed. Connections accepted after the
+/// reload use the new certificate.
+#[derive(Clone)]
+pub struct TlsConfig {
+    /// Certificate resolver shared with the listeners.
+    pub(super) resolver: Arc<CertificateResolver>,
+}
+
+impl TlsConfig {
+    /// Create new [`TlsConfig`] from PEM-encoded certificate chain and private key.
+    ///
+    /// The private key can be either a PKCS #8, a PKCS #1 (RSA) or a SEC1 (EC) key.
+    pub fn new(certificate_chain: &[u8], private_key: &[u8]) -> crate::Result<Self> {
+        Ok(Self {
+            resolver: Arc::new(CertificateResolver::new(certificate_chain, private_key)?),
+        })
+    }
+
+    /// Replace the certificate chain and private key used by the secure listeners.
+    ///
+    /// If the new certificate or key is invalid, the previous certificate is kept in use.
+    pub fn reload(&self, certificate_chain: &[u8], private_key: &[u8]) -> crate::Result<()> {
+        self.resolver.reload(certificate_chain, private_key)
+    }
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig").finish_non_exhaustive()
+    }
+}
+
 /// WebSocket transport configuration.
 #[derive(Debug)]
 pub struct Config {
     /// Listen address address for the transport.
     ///
     /// Default listen addreses are ["/ip4/0.0.0.0/tcp/0/ws", "/ip6/::/tcp/0/ws"].
+    ///
+    /// Secure WebSocket addresses (`/wss` or `/tls/ws`) require [`Config::tls_config`] to be set.
     pub listen_addresses: Vec<multiaddr::Multiaddr>,
 
+    /// TLS configuration for secure WebSocket listen addresses.
+    ///
+    /// Defaults to `None`.
+    pub tls_config: Option<TlsConfig>,
+
     /// Whether to set `SO_REUSEPORT` and bind a socket to the listen address port for outbound
     /// connections.
     ///
@@ -89,6 +135,7 @@ impl Default for Config {
                 "/ip4/0.0.0.0/tcp/0/ws".parse().expect("valid address"),
                 "/ip6/::/tcp/0/ws".parse().expect("valid address"),
             ],
+            tls_config: None,
             reuse_port: true,
             nodelay: false,
             yamux_config: Default::default(),
@@ -29,8 +29,9 @@ use crate::{
     protocol::{Direction, Permit, ProtocolCommand, ProtocolSet},
     substream,
     transport::{
-        websocket::{stream::BufferedStream, substream::Substream},
-        Endpoint,
+        manager::SupportedTransport,
+        websocket::{stream::BufferedStream, substream::Substream, tls::MaybeServerTlsStream},
+        ConnectionCloseReason, Endpoint, CONNECTION_CLOSE_TIMEOUT,
     },
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
     BandwidthSink, PeerId,
@@ -39,11 +40,12 @@ use crate::{
 use futures::{future::BoxFuture, stream::FuturesUnordered, AsyncRead, AsyncWrite, StreamExt};
 use multiaddr::{multihash::Multihash, Multiaddr, Protocol};
 use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 use url::Url;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 mod schema {
     pub(super) mod noise {
@@ -106,11 +108,15 @@ pub(super) struct NegotiatedConnection {
     endpoint: Endpoint,
 
     /// Yamux connection.
-    connection:
-        crate::yamux::ControlledConnection<NoiseSocket<BufferedStream<MaybeTlsStream<TcpStream>>>>,
+    connection: crate::yamux::ControlledConnection<
+        NoiseSocket<BufferedStream<MaybeTlsStream<MaybeServerTlsStream>>>,
+    >,
 
     /// Yamux control.
     control: crate::yamux::Control,
+
+    /// Duration of the Noise handshake.
+    handshake_duration: Duration,
 }
 
 impl NegotiatedConnection {
@@ -128,6 +134,11 @@ impl NegotiatedConnection {
     pub fn endpoint(&self) -> Endpoint {
         self.endpoint.clone()
     }
+
+    /// Get the duration of the Noise handshake.
+    pub fn handshake_duration(&self) -> Duration {
+        self.handshake_duration
+    }
 }
 
 /// WebSocket connection.
@@ -136,8 +147,9 @@ pub(crate) struct WebSocketConnection {
     protocol_set: ProtocolSet,
 
     /// Yamux connection.
-    connection:
-        crate::yamux::ControlledConnection<NoiseSocket<BufferedStream<MaybeTlsStream<TcpStream>>>>,
+    connection: crate::yamux::ControlledConnection<
+        NoiseSocket<BufferedStream<MaybeTlsStream<MaybeServerTlsStream>>>,
+    >,
 
     /// Yamux control.
     control: crate::yamux::Control,
@@ -175,6 +187,7 @@ impl WebSocketConnection {
             endpoint,
             connection,
             control,
+            ..
         } = connection;
 
         Self {
@@ -212,7 +225,7 @@ impl WebSocketConnection {
     pub(super) async fn open_connection(
         connection_id: ConnectionId,
         keypair: Keypair,
-        stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        stream: WebSocketStream<MaybeTlsStream<MaybeServerTlsStream>>,
         address: Multiaddr,
         dialed_peer: PeerId,
         ws_address: Url,
@@ -243,8 +256,12 @@ impl WebSocketConnection {
     }
 
     /// Accept WebSocket connection.
+    ///
+    /// If `tls_acceptor` is set, the connection was accepted by a secure listener and TLS is
+    /// negotiated before the WebSocket handshake.
     pub(super) async fn accept_connection(
         stream: TcpStream,
+        tls_acceptor: Option<TlsAcceptor>,
         connection_id: ConnectionId,
         keypair: Keypair,
         address: Multiaddr,
@@ -252,6 +269,10 @@ impl WebSocketConnection {
         max_read_ahead_factor: usize,
         max_write_buffer_size: usize,
     ) -> crate::Result<NegotiatedConnection> {
+        let stream = match tls_acceptor {
+            Some(acceptor) => MaybeServerTlsStream::Tls(Box::new(acceptor.accept(stream).await?)),
+            None => MaybeServerTlsStream::Plain(stream),
+        };
         let stream = MaybeTlsStream::Plain(stream);
 
         Self::negotiate_connection(
@@ -270,7 +291,7 @@ impl WebSocketConnection {
 
     /// Negotiate WebSocket connection.
     pub(super) async fn negotiate_connection(
-        stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        stream: WebSocketStream<MaybeTlsStream<MaybeServerTlsStream>>,
         dialed_peer: Option<PeerId>,
         role: Role,
         address: Multiaddr,
@@ -299,6 +320,7 @@ impl WebSocketConnection {
         );
 
         // perform noise handshake
+        let started = Instant::now();
         let (stream, peer) = noise::handshake(
             stream.inner(),
             &keypair,
@@ -307,6 +329,7 @@ impl WebSocketConnection {
             max_write_buffer_size,
         )
         .await?;
+        let handshake_duration = started.elapsed();
 
         if let Some(dialed_peer) = dialed_peer {
             if peer != dialed_peer {
@@ -338,6 +361,7 @@ impl WebSocketConnection {
                 Role::Dialer => Endpoint::dialer(address, connection_id),
                 Role::Listener => Endpoint::listener(address, connection_id),
             },
+            handshake_duration,
         })
     }
 
@@ -415,10 +439,26 @@ impl WebSocketConnection {
         })
     }
 
+    /// Close the connection gracefully by sending yamux `GoAway` to the remote peer.
+    async fn close(&mut self) {
+        let close = futures::future::join(
+            self.control.close(),
+            (&mut self.connection).for_each(|_| async {}),
+        );
+
+        if tokio::time::timeout(CONNECTION_CLOSE_TIMEOUT, close).await.is_err() {
+            tracing::debug!(
+                target: LOG_TARGET,
+                peer = ?self.peer,
+                "timed out while closing connection",
+            );
+        }
+    }
+
     /// Start connection event loop.
     pub(crate) async fn start(mut self) -> crate::Result<()> {
         self.protocol_set
-            .report_connection_established(self.peer, self.endpoint)
+            .report_connection_established(self.peer, self.endpoint.clone())
             .await?;
 
         loop {
@@ -457,13 +497,17 @@ impl WebSocketConnection {
                             ?error,
                             "connection closed with error"
                         );
-                        self.protocol_set.report_connection_closed(self.peer, self.connection_id).await?;
+                        self.protocol_set
+                            .report_connection_closed(self.peer, self.connection_id, (&error).into())
+                            .await?;
 
                         return Ok(())
                     }
                     None => {
                         tracing::debug!(target: LOG_TARGET, peer = ?self.peer, "connection closed");
-                        self.protocol_set.report_connection_closed(self.peer, self.connection_id).await?;
+                        self.protocol_set
+                            .report_connection_closed(self.peer, self.connection_id, ConnectionCloseReason::RemoteGoAway)
+                            .await?;
 
                         return Ok(())
                     }
@@ -496,16 +540,25 @@ impl WebSocketConnection {
                         }
                         Ok(substream) => {
                             let protocol = substream.protocol.clone();
+                            let Ok(codec) = self.protocol_set.protocol_codec(&protocol) else {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?protocol,
+                                    "protocol was unregistered, dropping substream",
+                                );
+                                continue;
+                            };
                             let direction = substream.direction;
                             let substream_id = substream.substream_id;
                             let socket = FuturesAsyncReadCompatExt::compat(substream.io);
-                            let bandwidth_sink = self.bandwidth_sink.clone();
+                            let bandwidth_sink = self.bandwidth_sink.substream(SupportedTransport::WebSocket, self.peer, protocol.clone());
 
                             let substream = substream::Substream::new_websocket(
                                 self.peer,
                                 substream_id,
                                 Substream::new(socket, bandwidth_sink, substream.permit),
-                                self.protocol_set.protocol_codec(&protocol)
+                                codec,
+                                self.protocol_set.rate_limiter(SupportedTransport::WebSocket, &protocol),
                             );
 
                             self.protocol_set
@@ -552,19 +605,24 @@ impl WebSocketConnection {
                             }
                         }));
                     }
-                    Some(ProtocolCommand::ForceClose) => {
+                    Some(ProtocolCommand::ForceClose { reason }) => {
                         tracing::debug!(
                             target: LOG_TARGET,
                             peer = ?self.peer,
                             connection_id = ?self.connection_id,
+                            ?reason,
                             "force closing connection",
                         );
 
-                        return self.protocol_set.report_connection_closed(self.peer, self.connection_id).await
+                        self.close().await;
+                        return self.protocol_set.report_connection_closed(self.peer, self.connection_id, reason).await
                     }
                     None => {
                         tracing::debug!(target: LOG_TARGET, "protocols have exited, shutting down connection");
-                        return self.protocol_set.report_connection_closed(self.peer, self.connection_id).await
+                        return self
+                            .protocol_set
+                            .report_connection_closed(self.peer, self.connection_id, ConnectionCloseReason::Idle)
+                            .await
                     }
                 }
             }
@@ -24,11 +24,14 @@ use crate::{
     config::Role,
     error::{AddressError, Error},
     transport::{
-        common::listener::{DialAddresses, GetSocketAddr, SocketListener, WebSocketAddress},
+        common::listener::{
+            DialAddresses, GetSocketAddr, SecureWebSocketAddress, SocketListener, WebSocketAddress,
+        },
         manager::TransportHandle,
         websocket::{
             config::Config,
             connection::{NegotiatedConnection, WebSocketConnection},
+            tls::MaybeServerTlsStream,
         },
         Transport, TransportBuilder, TransportEvent,
     },
@@ -40,12 +43,14 @@ use futures::{future::BoxFuture, stream::FuturesUnordered, Stream, StreamExt};
 use multiaddr::{Multiaddr, Protocol};
 use socket2::{Domain, Socket, Type};
 use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 use url::Url;
 
 use std::{
     collections::{HashMap, HashSet},
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
     time::Duration,
@@ -56,6 +61,7 @@ pub(crate) use substream::Substream;
 mod connection;
 mod stream;
 mod substream;
+mod tls;
 
 pub mod config;
 
@@ -91,6 +97,12 @@ pub(crate) struct WebSocketTransport {
     /// WebSocket listener.
     listener: SocketListener,
 
+    /// Secure WebSocket listener.
+    tls_listener: SocketListener,
+
+    /// TLS acceptor for the connections of the secure listener.
+    tls_acceptor: Option<TlsAcceptor>,
+
     /// Dial addresses.
     dial_addresses: DialAddresses,
 
@@ -109,15 +121,21 @@ pub(crate) struct WebSocketTransport {
                 (
                     ConnectionId,
                     Multiaddr,
-                    WebSocketStream<MaybeTlsStream<TcpStream>>,
+                    WebSocketStream<MaybeTlsStream<MaybeServerTlsStream>>,
                 ),
-                ConnectionId,
+                (ConnectionId, Vec<(Multiaddr, Error)>),
             >,
         >,
     >,
 
     /// Opened raw connection, waiting for approval/rejection from `TransportManager`.
-    opened_raw: HashMap<ConnectionId, (WebSocketStream<MaybeTlsStream<TcpStream>>, Multiaddr)>,
+    opened_raw: HashMap<
+        ConnectionId,
+        (
+            WebSocketStream<MaybeTlsStream<MaybeServerTlsStream>>,
+            Multiaddr,
+        ),
+    >,
 
     /// Canceled raw connections.
     canceled: HashSet<ConnectionId>,
@@ -127,6 +145,20 @@ pub(crate) struct WebSocketTransport {
 }
 
 impl WebSocketTransport {
+    /// Check if `address` is a secure WebSocket address.
+    fn is_secure(address: &Multiaddr) -> bool {
+        address
+            .iter()
+            .any(|protocol| std::matches!(protocol, Protocol::Wss(_) | Protocol::Tls))
+    }
+
+    /// Update the local addresses used for outbound connections from the addresses of both
+    /// listeners.
+    fn update_dial_addresses(&mut self) {
+        self.dial_addresses =
+            self.listener.dial_addresses().merge(self.tls_listener.dial_addresses());
+    }
+
     /// Convert `Multiaddr` into `url::Url`
     fn multiaddr_into_url(address: Multiaddr) -> crate::Result<(Url, PeerId)> {
         let mut protocol_stack = address.iter();
@@ -178,7 +210,10 @@ impl WebSocketTransport {
         dial_addresses: DialAddresses,
         connection_open_timeout: Duration,
         nodelay: bool,
-    ) -> crate::Result<(Multiaddr, WebSocketStream<MaybeTlsStream<TcpStream>>)> {
+    ) -> crate::Result<(
+        Multiaddr,
+        WebSocketStream<MaybeTlsStream<MaybeServerTlsStream>>,
+    )> {
         let (url, _) = Self::multiaddr_into_url(address.clone())?;
 
         let (socket_address, _) = WebSocketAddress::multiaddr_to_socket_address(&address)?;
@@ -237,7 +272,9 @@ impl WebSocketTransport {
 
             Ok((
                 address,
-                tokio_tungstenite::client_async_tls(url, stream).await?.0,
+                tokio_tungstenite::client_async_tls(url, MaybeServerTlsStream::Plain(stream))
+                    .await?
+                    .0,
             ))
         };
 
@@ -266,18 +303,49 @@ impl TransportBuilder for WebSocketTransport {
             listen_addresses = ?config.listen_addresses,
             "start websocket transport",
         );
-        let (listener, listen_addresses, dial_addresses) = SocketListener::new::<WebSocketAddress>(
-            std::mem::take(&mut config.listen_addresses),
-            config.reuse_port,
-            config.nodelay,
-        );
+        let (tls_listen_addresses, listen_addresses): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut config.listen_addresses)
+                .into_iter()
+                .partition(Self::is_secure);
+
+        let tls_acceptor = config
+            .tls_config
+            .as_ref()
+            .map(|tls_config| tls::acceptor(tls_config.resolver.clone()));
+
+        if !tls_listen_addresses.is_empty() && tls_acceptor.is_none() {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?tls_listen_addresses,
+                "tls configuration missing for secure listen addresses",
+            );
+            return Err(Error::NotSupported(
+                "secure websocket listener requires tls configuration".to_string(),
+            ));
+        }
+
+        let (listener, mut listen_addresses, dial_addresses) =
+            SocketListener::new::<WebSocketAddress>(
+                listen_addresses,
+                config.reuse_port,
+                config.nodelay,
+            );
+        let (tls_listener, tls_listen_addresses, tls_dial_addresses) =
+            SocketListener::new::<SecureWebSocketAddress>(
+                tls_listen_addresses,
+                config.reuse_port,
+                config.nodelay,
+            );
+        listen_addresses.extend(tls_listen_addresses);
 
         Ok((
             Self {
                 listener,
+                tls_listener,
+                tls_acceptor,
                 config,
                 context,
-                dial_addresses,
+                dial_addresses: dial_addresses.merge(tls_dial_addresses),
                 canceled: HashSet::new(),
                 opened_raw: HashMap::new(),
                 pending_open: HashMap::new(),
@@ -399,30 +467,37 @@ impl Transport for WebSocketTransport {
 
                 async move {
                     WebSocketTransport::dial_peer(
-                        address,
+                        address.clone(),
                         dial_addresses,
                         connection_open_timeout,
                         nodelay,
                     )
                     .await
+                    .map_err(|error| (address, error))
                 }
             })
             .collect();
 
         self.pending_raw_connections.push(Box::pin(async move {
+            let mut errors = Vec::new();
+
             while let Some(result) = futures.next().await {
                 match result {
                     Ok((address, stream)) => return Ok((connection_id, address, stream)),
-                    Err(error) => tracing::debug!(
-                        target: LOG_TARGET,
-                        ?connection_id,
-                        ?error,
-                        "failed to open connection",
-                    ),
+                    Err((address, error)) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?connection_id,
+                            ?address,
+                            ?error,
+                            "failed to open connection",
+                        );
+                        errors.push((address, error));
+                    }
                 }
             }
 
-            Err(connection_id)
+            Err((connection_id, errors))
         }));
 
         Ok(())
@@ -483,31 +558,120 @@ impl Transport for WebSocketTransport {
     fn cancel(&mut self, connection_id: ConnectionId) {
         self.canceled.insert(connection_id);
     }
+
+    fn listen_on(&mut self, address: Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "start listening");
+
+        if Self::is_secure(&address) {
+            if self.tls_acceptor.is_none() {
+                return Err(Error::NotSupported(
+                    "secure websocket listener requires tls configuration".to_string(),
+                ));
+            }
+
+            let listen_addresses =
+                self.tls_listener.listen_on::<SecureWebSocketAddress>(&address)?;
+            self.update_dial_addresses();
+
+            return Ok(listen_addresses);
+        }
+
+        let listen_addresses = self.listener.listen_on::<WebSocketAddress>(&address)?;
+        self.update_dial_addresses();
+
+        Ok(listen_addresses)
+    }
+
+    fn remove_listener(&mut self, address: &Multiaddr) -> crate::Result<Vec<Multiaddr>> {
+        tracing::debug!(target: LOG_TARGET, ?address, "remove listener");
+
+        let listen_addresses = match Self::is_secure(address) {
+            true => self.tls_listener.remove_listener::<SecureWebSocketAddress>(address),
+            false => self.listener.remove_listener::<WebSocketAddress>(address),
+        }
+        .ok_or(Error::AddressError(AddressError::AddressNotAvailable))?;
+        self.update_dial_addresses();
+
+        Ok(listen_addresses)
+    }
 }
 
 impl Stream for WebSocketTransport {
     type Item = TransportEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        while let Poll::Ready(Some(connection)) = self.listener.poll_next_unpin(cx) {
+        loop {
+            let (connection, secure) = match self.listener.poll_next_unpin(cx) {
+                Poll::Ready(Some(connection)) => (connection, false),
+                _ => match self.tls_listener.poll_next_unpin(cx) {
+                    Poll::Ready(Some(connection)) => (connection, true),
+                    _ => break,
+                },
+            };
+            let to_multiaddr: fn(&SocketAddr) -> Multiaddr = match secure {
+                true => SecureWebSocketAddress::socket_address_to_multiaddr,
+                false => WebSocketAddress::socket_address_to_multiaddr,
+            };
+
             match connection {
-                Err(_) => return Poll::Ready(None),
+                Err((address, error)) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?address,
+                        ?error,
+                        "failed to accept connection",
+                    );
+
+                    return Poll::Ready(Some(TransportEvent::ListenerError {
+                        address: to_multiaddr(&address),
+                        error: error.into(),
+                    }));
+                }
                 Ok((stream, address)) => {
+                    if !self.context.ban_list.is_ip_allowed(&address.ip()) {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?address,
+                            "rejecting inbound connection from denied address",
+                        );
+                        continue;
+                    }
+
+                    let permit = match self.context.pending_incoming.try_acquire() {
+                        Ok(permit) => permit,
+                        Err(error) => {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?address,
+                                ?error,
+                                "rejecting inbound connection",
+                            );
+                            continue;
+                        }
+                    };
                     let connection_id = self.context.next_connection_id();
                     let keypair = self.context.keypair.clone();
                     let yamux_config = self.config.yamux_config.clone();
                     let connection_open_timeout = self.config.connection_open_timeout;
                     let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
                     let max_write_buffer_size = self.config.noise_write_buffer_size;
-                    let address = Multiaddr::empty()
-                        .with(Protocol::from(address.ip()))
-                        .with(Protocol::Tcp(address.port()))
-                        .with(Protocol::Ws(std::borrow::Cow::Owned("/".to_string())));
+                    let tls_acceptor = match secure {
+                        true => self.tls_acceptor.clone(),
+                        false => None,
+                    };
+                    let local_address = stream
+                        .local_addr()
+                        .map_or_else(|_| Multiaddr::empty(), |address| to_multiaddr(&address));
+                    let address = to_multiaddr(&address);
+                    let remote_address = address.clone();
 
                     self.pending_connections.push(Box::pin(async move {
+                        let _permit = permit;
+
                         match tokio::time::timeout(connection_open_timeout, async move {
                             WebSocketConnection::accept_connection(
                                 stream,
+                                tls_acceptor,
                                 connection_id,
                                 keypair,
                                 address,
@@ -516,15 +680,21 @@ impl Stream for WebSocketTransport {
                                 max_write_buffer_size,
                             )
                             .await
-                            .map_err(|error| WebSocketError::new(error, None))
+                            .map_err(|error| WebSocketError::new(error, Some(connection_id)))
                         })
                         .await
                         {
-                            Err(_) => Err(WebSocketError::new(Error::Timeout, None)),
+                            Err(_) => Err(WebSocketError::new(Error::Timeout, Some(connection_id))),
                             Ok(Err(error)) => Err(error),
                             Ok(Ok(result)) => Ok(result),
                         }
                     }));
+
+                    return Poll::Ready(Some(TransportEvent::IncomingConnection {
+                        connection_id,
+                        local_address,
+                        remote_address,
+                    }));
                 }
             }
         }
@@ -549,9 +719,12 @@ impl Stream for WebSocketTransport {
                         }));
                     }
                 }
-                Err(connection_id) =>
+                Err((connection_id, errors)) =>
                     if !self.canceled.remove(&connection_id) {
-                        return Poll::Ready(Some(TransportEvent::OpenFailure { connection_id }));
+                        return Poll::Ready(Some(TransportEvent::OpenFailure {
+                            connection_id,
+                            errors,
+                        }));
                     },
             }
         }
@@ -561,6 +734,7 @@ impl Stream for WebSocketTransport {
                 Ok(connection) => {
                     let peer = connection.peer();
                     let endpoint = connection.endpoint();
+                    self.context.metrics.on_handshake("noise", connection.handshake_duration());
                     self.pending_open.insert(connection.connection_id(), connection);
 
                     return Poll::Ready(Some(TransportEvent::ConnectionEstablished {
@@ -576,9 +750,11 @@ impl Stream for WebSocketTransport {
                                 address,
                                 error: error.error,
                             })),
-                        None => {
-                            tracing::debug!(target: LOG_TARGET, ?error, "failed to establish connection")
-                        }
+                        None =>
+                            return Poll::Ready(Some(TransportEvent::IncomingConnectionError {
+                                connection_id,
+                                error: error.error,
+                            })),
                     },
                     None => {
                         tracing::debug!(target: LOG_TARGET, ?error, "failed to establish connection")
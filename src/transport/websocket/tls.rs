@@ -0,0 +1,225 @@
+// Copyright 2024 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! TLS termination for secure WebSocket listeners.
+
+use crate::error::Error;
+
+use parking_lot::RwLock;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{
+        server::{ClientHello, ResolvesServerCert},
+        sign::{self, CertifiedKey},
+        Certificate, PrivateKey, ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::websocket::tls";
+
+/// Certificate resolver which allows replacing the certificate while the listener is running.
+pub(super) struct CertificateResolver {
+    /// Certificate chain and signing key used for new TLS handshakes.
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertificateResolver {
+    /// Create new [`CertificateResolver`] from PEM-encoded certificate chain and private key.
+    pub(super) fn new(certificate_chain: &[u8], private_key: &[u8]) -> crate::Result<Self> {
+        Ok(Self {
+            key: RwLock::new(certified_key(certificate_chain, private_key)?),
+        })
+    }
+
+    /// Replace the certificate chain and private key.
+    pub(super) fn reload(&self, certificate_chain: &[u8], private_key: &[u8]) -> crate::Result<()> {
+        *self.key.write() = certified_key(certificate_chain, private_key)?;
+
+        tracing::debug!(target: LOG_TARGET, "tls certificate reloaded");
+
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(Arc::clone(&self.key.read()))
+    }
+}
+
+/// Create [`TlsAcceptor`] which uses the certificate of `resolver` for each TLS handshake.
+pub(super) fn acceptor(resolver: Arc<CertificateResolver>) -> TlsAcceptor {
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Parse PEM-encoded certificate chain and private key into [`CertifiedKey`].
+fn certified_key(certificate_chain: &[u8], private_key: &[u8]) -> crate::Result<Arc<CertifiedKey>> {
+    let certificates = rustls_pemfile::certs(&mut &*certificate_chain)
+        .map_err(|_| Error::InvalidCertificate)?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+
+    if certificates.is_empty() {
+        tracing::debug!(target: LOG_TARGET, "no certificate found in pem");
+        return Err(Error::InvalidCertificate);
+    }
+
+    let key = rustls_pemfile::read_all(&mut &*private_key)
+        .map_err(|_| Error::InvalidCertificate)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            tracing::debug!(target: LOG_TARGET, "no private key found in pem");
+            Error::InvalidCertificate
+        })?;
+
+    let key = sign::any_supported_type(&key).map_err(|error| {
+        tracing::debug!(target: LOG_TARGET, ?error, "unsupported private key");
+        Error::InvalidCertificate
+    })?;
+
+    Ok(Arc::new(CertifiedKey::new(certificates, key)))
+}
+
+/// TCP stream of a WebSocket connection.
+///
+/// Connections accepted by a secure listener are terminated here while outbound `wss`
+/// connections are secured by `tokio-tungstenite`.
+pub(crate) enum MaybeServerTlsStream {
+    /// Plain TCP stream.
+    Plain(TcpStream),
+
+    /// TCP stream of a connection accepted by a secure listener.
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeServerTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeServerTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_pem() -> (String, String) {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("certificate to be generated");
+
+        (
+            certificate.serialize_pem().expect("certificate to serialize"),
+            certificate.serialize_private_key_pem(),
+        )
+    }
+
+    #[test]
+    fn parse_valid_certificate() {
+        let (certificate, key) = generate_pem();
+
+        assert!(CertificateResolver::new(certificate.as_bytes(), key.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn invalid_certificate_is_rejected() {
+        let (certificate, key) = generate_pem();
+
+        assert!(std::matches!(
+            CertificateResolver::new(b"", key.as_bytes()),
+            Err(Error::InvalidCertificate)
+        ));
+        assert!(std::matches!(
+            CertificateResolver::new(certificate.as_bytes(), certificate.as_bytes()),
+            Err(Error::InvalidCertificate)
+        ));
+    }
+
+    #[test]
+    fn failed_reload_keeps_previous_certificate() {
+        let (certificate, key) = generate_pem();
+        let resolver = CertificateResolver::new(certificate.as_bytes(), key.as_bytes()).unwrap();
+        let previous = Arc::clone(&resolver.key.read());
+
+        assert!(resolver.reload(b"invalid", b"invalid").is_err());
+        assert!(Arc::ptr_eq(&previous, &resolver.key.read()));
+
+        let (certificate, key) = generate_pem();
+        resolver.reload(certificate.as_bytes(), key.as_bytes()).unwrap();
+        assert!(!Arc::ptr_eq(&previous, &resolver.key.read()));
+    }
+}
@@ -24,8 +24,10 @@ use litep2p::{
     error::{AddressError, Error},
     protocol::libp2p::ping::{Config as PingConfig, PingEvent},
     transport::{
-        quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
-        websocket::config::Config as WebSocketConfig,
+        memory::config::Config as MemoryConfig,
+        quic::config::Config as QuicConfig,
+        tcp::config::Config as TcpConfig,
+        websocket::config::{Config as WebSocketConfig, TlsConfig},
     },
     Litep2p, Litep2pEvent, PeerId,
 };
@@ -34,7 +36,10 @@ use futures::{Stream, StreamExt};
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
 use network_interface::{NetworkInterface, NetworkInterfaceConfig};
-use tokio::net::{TcpListener, UdpSocket};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio_rustls::rustls::{Certificate, ClientConfig, RootCertStore};
+
+use std::sync::Arc;
 
 #[cfg(test)]
 mod protocol_dial_invalid_address;
@@ -43,6 +48,7 @@ enum Transport {
     Tcp(TcpConfig),
     Quic(QuicConfig),
     WebSocket(WebSocketConfig),
+    Memory(MemoryConfig),
 }
 
 #[tokio::test]
@@ -84,6 +90,15 @@ async fn two_litep2ps_work_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn two_litep2ps_work_memory() {
+    two_litep2ps_work(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 async fn two_litep2ps_work(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -98,6 +113,7 @@ async fn two_litep2ps_work(transport1: Transport, transport2: Transport) {
         Transport::Tcp(config) => config1.with_tcp(config),
         Transport::Quic(config) => config1.with_quic(config),
         Transport::WebSocket(config) => config1.with_websocket(config),
+        Transport::Memory(config) => config1.with_memory(config),
     }
     .build();
 
@@ -110,6 +126,7 @@ async fn two_litep2ps_work(transport1: Transport, transport2: Transport) {
         Transport::Tcp(config) => config2.with_tcp(config),
         Transport::Quic(config) => config2.with_quic(config),
         Transport::WebSocket(config) => config2.with_websocket(config),
+        Transport::Memory(config) => config2.with_memory(config),
     }
     .build();
 
@@ -119,7 +136,14 @@ async fn two_litep2ps_work(transport1: Transport, transport2: Transport) {
     let address = litep2p2.listen_addresses().next().unwrap().clone();
     litep2p1.dial_address(address).await.unwrap();
 
-    let (res1, res2) = tokio::join!(litep2p1.next_event(), litep2p2.next_event());
+    let (res1, res2) = tokio::join!(litep2p1.next_event(), async {
+        // the listener reports the inbound connection before it's negotiated
+        assert!(std::matches!(
+            litep2p2.next_event().await,
+            Some(Litep2pEvent::IncomingConnection { .. })
+        ));
+        litep2p2.next_event().await
+    });
 
     assert!(std::matches!(
         res1,
@@ -187,6 +211,16 @@ async fn dial_failure_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn dial_failure_memory() {
+    dial_failure(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+        Multiaddr::empty().with(Protocol::Memory(1)),
+    )
+    .await;
+}
+
 async fn dial_failure(transport1: Transport, transport2: Transport, dial_address: Multiaddr) {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -201,6 +235,7 @@ async fn dial_failure(transport1: Transport, transport2: Transport, dial_address
         Transport::Tcp(config) => config1.with_tcp(config),
         Transport::Quic(config) => config1.with_quic(config),
         Transport::WebSocket(config) => config1.with_websocket(config),
+        Transport::Memory(config) => config1.with_memory(config),
     }
     .build();
 
@@ -213,6 +248,7 @@ async fn dial_failure(transport1: Transport, transport2: Transport, dial_address
         Transport::Tcp(config) => config2.with_tcp(config),
         Transport::Quic(config) => config2.with_quic(config),
         Transport::WebSocket(config) => config2.with_websocket(config),
+        Transport::Memory(config) => config2.with_memory(config),
     }
     .build();
 
@@ -282,7 +318,14 @@ async fn connect_over_dns() {
     ));
 
     litep2p1.dial_address(new_address).await.unwrap();
-    let (res1, res2) = tokio::join!(litep2p1.next_event(), litep2p2.next_event());
+    let (res1, res2) = tokio::join!(litep2p1.next_event(), async {
+        // the listener reports the inbound connection before it's negotiated
+        assert!(std::matches!(
+            litep2p2.next_event().await,
+            Some(Litep2pEvent::IncomingConnection { .. })
+        ));
+        litep2p2.next_event().await
+    });
 
     assert!(std::matches!(
         res1,
@@ -369,6 +412,7 @@ async fn connection_timeout(transport: Transport, address: Multiaddr) {
         Transport::Tcp(config) => litep2p_config.with_tcp(config),
         Transport::Quic(config) => litep2p_config.with_quic(config),
         Transport::WebSocket(config) => litep2p_config.with_websocket(config),
+        Transport::Memory(config) => litep2p_config.with_memory(config),
     }
     .build();
 
@@ -441,6 +485,11 @@ async fn dial_self_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn dial_self_memory() {
+    dial_self(Transport::Memory(Default::default())).await;
+}
+
 async fn dial_self(transport: Transport) {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -455,6 +504,7 @@ async fn dial_self(transport: Transport) {
         Transport::Tcp(config) => litep2p_config.with_tcp(config),
         Transport::Quic(config) => litep2p_config.with_quic(config),
         Transport::WebSocket(config) => litep2p_config.with_websocket(config),
+        Transport::Memory(config) => litep2p_config.with_memory(config),
     }
     .build();
 
@@ -534,6 +584,15 @@ async fn keep_alive_timeout_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn keep_alive_timeout_memory() {
+    keep_alive_timeout(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 async fn keep_alive_timeout(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -548,6 +607,7 @@ async fn keep_alive_timeout(transport1: Transport, transport2: Transport) {
         Transport::Tcp(config) => config1.with_tcp(config),
         Transport::Quic(config) => config1.with_quic(config),
         Transport::WebSocket(config) => config1.with_websocket(config),
+        Transport::Memory(config) => config1.with_memory(config),
     }
     .build();
     let mut litep2p1 = Litep2p::new(config1).unwrap();
@@ -561,6 +621,7 @@ async fn keep_alive_timeout(transport1: Transport, transport2: Transport) {
         Transport::Tcp(config) => config2.with_tcp(config),
         Transport::Quic(config) => config2.with_quic(config),
         Transport::WebSocket(config) => config2.with_websocket(config),
+        Transport::Memory(config) => config2.with_memory(config),
     }
     .build();
     let mut litep2p2 = Litep2p::new(config2).unwrap();
@@ -706,6 +767,68 @@ async fn simultaneous_dial_quic() {
     }
 }
 
+#[tokio::test]
+async fn quic_dial_reuses_listening_endpoint() {
+    let (listen_port, remote_port) = quic_dial_source_port(true).await;
+    assert_eq!(listen_port, remote_port);
+
+    let (listen_port, remote_port) = quic_dial_source_port(false).await;
+    assert_ne!(listen_port, remote_port);
+}
+
+/// Dial a QUIC listener and return the listen port of the dialer and the port the listener
+/// observed the connection from.
+async fn quic_dial_source_port(reuse_endpoint: bool) -> (u16, u16) {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let mut litep2p1 = Litep2p::new(
+        ConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_quic(QuicConfig {
+                reuse_endpoint,
+                ..Default::default()
+            })
+            .build(),
+    )
+    .unwrap();
+    let mut litep2p2 = Litep2p::new(
+        ConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_quic(Default::default())
+            .build(),
+    )
+    .unwrap();
+
+    let listen_port = match litep2p1.listen_addresses().next().unwrap().iter().nth(1) {
+        Some(Protocol::Udp(port)) => port,
+        _ => panic!("invalid listen address"),
+    };
+    let address = litep2p2.listen_addresses().next().unwrap().clone();
+    litep2p1.dial_address(address).await.unwrap();
+
+    let remote_port = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {}
+                event = litep2p2.next_event() => {
+                    if let Some(Litep2pEvent::ConnectionEstablished { endpoint, .. }) = event {
+                        match endpoint.address().iter().nth(1) {
+                            Some(Protocol::Udp(port)) => break port,
+                            _ => panic!("invalid remote address"),
+                        }
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("connection to be established");
+
+    (listen_port, remote_port)
+}
+
 #[tokio::test]
 async fn simultaneous_dial_ipv6_quic() {
     let _ = tracing_subscriber::fmt()
@@ -810,6 +933,133 @@ async fn websocket_over_ipv6() {
     }
 }
 
+/// Generate self-signed certificate for `localhost`.
+///
+/// Returns the PEM-encoded certificate, PEM-encoded private key and the DER-encoded certificate.
+fn generate_certificate() -> (String, String, Vec<u8>) {
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+    (
+        certificate.serialize_pem().unwrap(),
+        certificate.serialize_private_key_pem(),
+        certificate.serialize_der().unwrap(),
+    )
+}
+
+/// Open secure WebSocket connection to `port` and verify the server with `certificate`.
+async fn connect_secure_websocket(port: u16, certificate: Vec<u8>) -> bool {
+    let mut roots = RootCertStore::empty();
+    roots.add(&Certificate(certificate)).unwrap();
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    tokio_tungstenite::client_async_tls_with_config(
+        format!("wss://localhost:{port}/"),
+        stream,
+        None,
+        Some(tokio_tungstenite::Connector::Rustls(Arc::new(config))),
+    )
+    .await
+    .is_ok()
+}
+
+#[tokio::test]
+async fn secure_websocket_listener_reloads_certificate() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (certificate1, key1, der1) = generate_certificate();
+    let (certificate2, key2, der2) = generate_certificate();
+    let tls_config = TlsConfig::new(certificate1.as_bytes(), key1.as_bytes()).unwrap();
+
+    let mut litep2p = Litep2p::new(
+        ConfigBuilder::new()
+            .with_websocket(WebSocketConfig {
+                listen_addresses: vec!["/ip4/127.0.0.1/tcp/0/wss".parse().unwrap()],
+                tls_config: Some(tls_config.clone()),
+                ..Default::default()
+            })
+            .build(),
+    )
+    .unwrap();
+
+    let address = litep2p.listen_addresses().next().unwrap().clone();
+    let mut iter = address.iter();
+    let port = match (iter.next(), iter.next(), iter.next()) {
+        (Some(Protocol::Ip4(_)), Some(Protocol::Tcp(port)), Some(Protocol::Wss(_))) => port,
+        _ => panic!("invalid listen address: {address}"),
+    };
+
+    tokio::spawn(async move { while litep2p.next_event().await.is_some() {} });
+
+    assert!(connect_secure_websocket(port, der1.clone()).await);
+    assert!(!connect_secure_websocket(port, der2.clone()).await);
+
+    tls_config.reload(certificate2.as_bytes(), key2.as_bytes()).unwrap();
+
+    assert!(connect_secure_websocket(port, der2).await);
+    assert!(!connect_secure_websocket(port, der1).await);
+}
+
+#[tokio::test]
+async fn secure_websocket_listener_added_at_runtime() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (certificate, key, der) = generate_certificate();
+    let tls_config = TlsConfig::new(certificate.as_bytes(), key.as_bytes()).unwrap();
+
+    let mut litep2p = Litep2p::new(
+        ConfigBuilder::new()
+            .with_websocket(WebSocketConfig {
+                listen_addresses: Vec::new(),
+                tls_config: Some(tls_config),
+                ..Default::default()
+            })
+            .build(),
+    )
+    .unwrap();
+
+    let listen_addresses =
+        litep2p.listen_on("/ip4/127.0.0.1/tcp/0/tls/ws".parse().unwrap()).await.unwrap();
+    assert_eq!(listen_addresses.len(), 1);
+
+    let mut iter = listen_addresses[0].iter();
+    let port = match (iter.next(), iter.next(), iter.next()) {
+        (Some(Protocol::Ip4(_)), Some(Protocol::Tcp(port)), Some(Protocol::Wss(_))) => port,
+        _ => panic!("invalid listen address: {}", listen_addresses[0]),
+    };
+
+    tokio::spawn(async move { while litep2p.next_event().await.is_some() {} });
+
+    assert!(connect_secure_websocket(port, der).await);
+}
+
+#[tokio::test]
+async fn secure_websocket_listener_requires_tls_config() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    match Litep2p::new(
+        ConfigBuilder::new()
+            .with_websocket(WebSocketConfig {
+                listen_addresses: vec!["/ip4/127.0.0.1/tcp/0/wss".parse().unwrap()],
+                ..Default::default()
+            })
+            .build(),
+    ) {
+        Err(Error::NotSupported(_)) => {}
+        _ => panic!("secure listener without tls configuration must be rejected"),
+    }
+}
+
 #[tokio::test]
 async fn tcp_dns_resolution() {
     let _ = tracing_subscriber::fmt()
@@ -999,6 +1249,25 @@ async fn multiple_listen_addresses_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn multiple_listen_addresses_memory() {
+    multiple_listen_addresses(
+        Transport::Memory(MemoryConfig {
+            listen_addresses: vec!["/memory/0".parse().unwrap(), "/memory/0".parse().unwrap()],
+            ..Default::default()
+        }),
+        Transport::Memory(MemoryConfig {
+            listen_addresses: vec![],
+            ..Default::default()
+        }),
+        Transport::Memory(MemoryConfig {
+            listen_addresses: vec![],
+            ..Default::default()
+        }),
+    )
+    .await;
+}
+
 async fn make_dummy_litep2p(
     transport: Transport,
 ) -> (Litep2p, Box<dyn Stream<Item = PingEvent> + Send + Unpin>) {
@@ -1011,6 +1280,7 @@ async fn make_dummy_litep2p(
         Transport::Tcp(config) => litep2p_config.with_tcp(config),
         Transport::Quic(config) => litep2p_config.with_quic(config),
         Transport::WebSocket(config) => litep2p_config.with_websocket(config),
+        Transport::Memory(config) => litep2p_config.with_memory(config),
     }
     .build();
 
@@ -1104,6 +1374,36 @@ async fn port_in_use_websocket() {
     .unwrap();
 }
 
+#[tokio::test]
+async fn port_in_use_memory() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let litep2p =
+        Litep2p::new(ConfigBuilder::new().with_memory(Default::default()).build()).unwrap();
+    let address = litep2p
+        .listen_addresses()
+        .next()
+        .unwrap()
+        .iter()
+        .filter(|protocol| !std::matches!(protocol, Protocol::P2p(_)))
+        .collect::<Multiaddr>();
+
+    // unlike sockets, memory ports can't be shared
+    assert!(std::matches!(
+        Litep2p::new(
+            ConfigBuilder::new()
+                .with_memory(MemoryConfig {
+                    listen_addresses: vec![address],
+                    ..Default::default()
+                })
+                .build(),
+        ),
+        Err(Error::IoError(std::io::ErrorKind::AddrInUse))
+    ));
+}
+
 #[tokio::test]
 async fn dial_over_multiple_addresses() {
     let _ = tracing_subscriber::fmt()
@@ -1406,6 +1706,7 @@ async fn simultaneous_dial_then_redial(transport1: Transport, transport2: Transp
         Transport::Tcp(config) => config1.with_tcp(config),
         Transport::Quic(config) => config1.with_quic(config),
         Transport::WebSocket(config) => config1.with_websocket(config),
+        Transport::Memory(config) => config1.with_memory(config),
     }
     .build();
 
@@ -1418,6 +1719,7 @@ async fn simultaneous_dial_then_redial(transport1: Transport, transport2: Transp
         Transport::Tcp(config) => config2.with_tcp(config),
         Transport::Quic(config) => config2.with_quic(config),
         Transport::WebSocket(config) => config2.with_websocket(config),
+        Transport::Memory(config) => config2.with_memory(config),
     }
     .build();
 
@@ -24,9 +24,13 @@ use futures::StreamExt;
 use litep2p::{
     config::ConfigBuilder,
     crypto::ed25519::Keypair,
-    protocol::libp2p::kademlia::{
-        ConfigBuilder as KademliaConfigBuilder, IncomingRecordValidationMode, KademliaEvent,
-        PeerRecord, Quorum, Record, RecordKey, RecordsType,
+    protocol::libp2p::{
+        identify::{Config as IdentifyConfig, IdentifyEvent},
+        kademlia::{
+            ConfigBuilder as KademliaConfigBuilder, ConnectionType, ContentProvider, FileStore,
+            IncomingRecordValidationMode, KademliaEvent, Mode, PeerRecord, QueryConfig, Quorum,
+            Record, RecordKey, RecordsType,
+        },
     },
     transport::tcp::config::Config as TcpConfig,
     Litep2p, PeerId,
@@ -172,7 +176,9 @@ async fn records_are_stored_automatically() {
             event = kad_handle2.next() => {
                 match event {
                     Some(KademliaEvent::IncomingRecord { record: got_record }) => {
-                        assert_eq!(got_record, record);
+                        assert_eq!(got_record.key, record.key);
+                        assert_eq!(got_record.value, record.value);
+                        assert!(got_record.expires.is_some());
                         // Check if the record was stored.
                         let _ = kad_handle2
                             .get_record(RecordKey::from(vec![1, 2, 3]), Quorum::One).await;
@@ -180,7 +186,9 @@ async fn records_are_stored_automatically() {
                     Some(KademliaEvent::GetRecordSuccess { query_id: _, records }) => {
                         match records {
                             RecordsType::LocalStore(got_record) => {
-                                assert_eq!(got_record, record);
+                                assert_eq!(got_record.key, record.key);
+                                assert_eq!(got_record.value, record.value);
+                                assert!(got_record.expires.is_some());
                                 break
                             }
                             RecordsType::Network(_) => {
@@ -234,6 +242,9 @@ async fn records_are_stored_manually() {
     let mut record = Record::new(vec![1, 2, 3], vec![0x01]);
     kad_handle1.put_record(record.clone()).await;
 
+    // the publisher is sent along with the record
+    record.publisher = Some(*litep2p1.local_peer_id());
+
     loop {
         tokio::select! {
             _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
@@ -245,8 +256,9 @@ async fn records_are_stored_manually() {
             event = kad_handle2.next() => {
                 match event {
                     Some(KademliaEvent::IncomingRecord { record: got_record }) => {
-                        assert_eq!(got_record, record);
-                        assert!(got_record.expires.is_none());
+                        assert_eq!(got_record.key, record.key);
+                        assert_eq!(got_record.value, record.value);
+                        assert!(got_record.expires.is_some());
                         kad_handle2.store_record(got_record).await;
 
                         // Check if the record was stored.
@@ -325,7 +337,9 @@ async fn not_validated_records_are_not_stored() {
             event = kad_handle2.next() => {
                 match event {
                     Some(KademliaEvent::IncomingRecord { record: got_record }) => {
-                        assert_eq!(got_record, record);
+                        assert_eq!(got_record.key, record.key);
+                        assert_eq!(got_record.value, record.value);
+                        assert!(got_record.expires.is_some());
                         // Do not call `kad_handle2.store_record(record).await`.
 
                         // Check if the record was stored.
@@ -440,3 +454,945 @@ async fn get_record_retrieves_remote_records() {
         }
     }
 }
+
+#[tokio::test]
+async fn get_providers_retrieves_remote_providers() {
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    // Start providing the key on `litep2p1`.
+    let key = RecordKey::from(vec![1, 2, 3]);
+    let query1 = kad_handle1.start_providing(key.clone()).await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("providers were not retrieved in 10 secs")
+            }
+            event = litep2p1.next_event() => {}
+            event = litep2p2.next_event() => {}
+            event = kad_handle1.next() => {
+                match event {
+                    Some(KademliaEvent::QueryFailed { query_id }) => {
+                        // There are no peers to announce the provider to, but the key is
+                        // provided locally.
+                        assert_eq!(query_id, query1);
+
+                        // Let peer2 know about peer1.
+                        kad_handle2
+                            .add_known_peer(
+                                *litep2p1.local_peer_id(),
+                                litep2p1.listen_addresses().cloned().collect(),
+                            )
+                            .await;
+
+                        // Let peer2 get providers from peer1.
+                        kad_handle2.get_providers(key.clone()).await;
+                    }
+                    _ => {}
+                }
+            }
+            event = kad_handle2.next() => {
+                match event {
+                    Some(KademliaEvent::GetProvidersSuccess { provided_key, providers, .. }) => {
+                        assert_eq!(provided_key, key);
+                        assert_eq!(providers.len(), 1);
+
+                        let ContentProvider { peer, addresses } = providers.first().unwrap();
+                        assert_eq!(peer, litep2p1.local_peer_id());
+                        assert_eq!(
+                            addresses,
+                            &litep2p1.listen_addresses().cloned().collect::<Vec<_>>()
+                        );
+                        break
+                    }
+                    Some(KademliaEvent::QueryFailed { query_id: _ }) => {
+                        panic!("query failed")
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn provider_is_announced_to_remote_peers() {
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    // Let peer1 know about peer2 and start providing the key.
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    let key = RecordKey::from(vec![1, 2, 3]);
+    kad_handle1.start_providing(key.clone()).await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("provider was not announced in 10 secs")
+            }
+            event = litep2p1.next_event() => {}
+            event = litep2p2.next_event() => {}
+            event = kad_handle1.next() => {}
+            event = kad_handle2.next() => {
+                match event {
+                    Some(KademliaEvent::IncomingProvider { provided_key, provider }) => {
+                        assert_eq!(provided_key, key);
+                        assert_eq!(provider.peer, *litep2p1.local_peer_id());
+                        assert_eq!(
+                            provider.addresses,
+                            litep2p1.listen_addresses().cloned().collect::<Vec<_>>()
+                        );
+                        break
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn bootstrap_discovers_peers() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let mut litep2ps = Vec::new();
+    let mut handles = Vec::new();
+
+    for _ in 0..3 {
+        let (kad_config, kad_handle) =
+            KademliaConfigBuilder::new().with_routing_table_refresh_interval(None).build();
+
+        let config = ConfigBuilder::new()
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_kademlia(kad_config)
+            .build();
+
+        litep2ps.push(Litep2p::new(config).unwrap());
+        handles.push(kad_handle);
+    }
+
+    let mut litep2p3 = litep2ps.pop().unwrap();
+    let mut litep2p2 = litep2ps.pop().unwrap();
+    let mut litep2p1 = litep2ps.pop().unwrap();
+    let mut kad_handle3 = handles.pop().unwrap();
+    let mut kad_handle2 = handles.pop().unwrap();
+    let mut kad_handle1 = handles.pop().unwrap();
+
+    // `litep2p1` knows about `litep2p2` which knows about `litep2p3`.
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+    kad_handle2
+        .add_known_peer(
+            *litep2p3.local_peer_id(),
+            litep2p3.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    kad_handle1.bootstrap().await;
+    let peer3 = *litep2p3.local_peer_id();
+    let mut peer3_discovered = false;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("bootstrap didn't complete in 10 secs")
+            }
+            event = litep2p1.next_event() => {}
+            event = litep2p2.next_event() => {}
+            event = litep2p3.next_event() => {}
+            event = kad_handle2.next() => {}
+            event = kad_handle3.next() => {}
+            event = kad_handle1.next() => {
+                match event {
+                    Some(KademliaEvent::RoutingTableUpdate { peers }) => {
+                        peer3_discovered |= peers.contains(&peer3);
+                    }
+                    Some(KademliaEvent::BootstrapCompleted) => {
+                        assert!(peer3_discovered);
+                        break
+                    }
+                    Some(KademliaEvent::FindNodeSuccess { .. })
+                    | Some(KademliaEvent::QueryFailed { .. }) => {
+                        panic!("bootstrap lookups must not be reported")
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn records_are_republished() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new()
+        .with_routing_table_refresh_interval(None)
+        .with_record_republish_interval(Some(std::time::Duration::from_secs(2)))
+        .build();
+    let (kad_config2, mut kad_handle2) =
+        KademliaConfigBuilder::new().with_routing_table_refresh_interval(None).build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    // Publish the record before any peers are known.
+    let record = Record::new(vec![1, 2, 3], vec![0x01]);
+    let query_id = kad_handle1.put_record(record.clone()).await;
+
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("record was not republished in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            event = kad_handle1.next() => {
+                if let Some(KademliaEvent::QueryFailed { query_id: failed }) = event {
+                    assert_eq!(failed, query_id, "republish must not be reported");
+                }
+            }
+            event = kad_handle2.next() => {
+                if let Some(KademliaEvent::IncomingRecord { record: got_record }) = event {
+                    assert_eq!(got_record.key, record.key);
+                    assert_eq!(got_record.value, record.value);
+                    break
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn providers_are_republished() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new()
+        .with_routing_table_refresh_interval(None)
+        .with_provider_republish_interval(Some(std::time::Duration::from_secs(2)))
+        .build();
+    let (kad_config2, mut kad_handle2) =
+        KademliaConfigBuilder::new().with_routing_table_refresh_interval(None).build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    // Start providing the key before any peers are known.
+    let key = RecordKey::from(vec![1, 2, 3]);
+    let query_id = kad_handle1.start_providing(key.clone()).await;
+
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("provider was not republished in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            event = kad_handle1.next() => {
+                if let Some(KademliaEvent::QueryFailed { query_id: failed }) = event {
+                    assert_eq!(failed, query_id, "republish must not be reported");
+                }
+            }
+            event = kad_handle2.next() => {
+                if let Some(KademliaEvent::IncomingProvider { provided_key, provider }) = event {
+                    assert_eq!(provided_key, key);
+                    assert_eq!(provider.peer, *litep2p1.local_peer_id());
+                    break
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn records_are_replicated() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new()
+        .with_routing_table_refresh_interval(None)
+        .with_record_replication_interval(Some(std::time::Duration::from_secs(2)))
+        .build();
+    let (kad_config2, mut kad_handle2) =
+        KademliaConfigBuilder::new().with_routing_table_refresh_interval(None).build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    // Store a record published by another peer to the local store of `litep2p1`.
+    let mut record = Record::new(vec![1, 2, 3], vec![0x01]);
+    record.publisher = Some(PeerId::random());
+    kad_handle1.store_record(record.clone()).await;
+
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("record was not replicated in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            _ = kad_handle1.next() => {}
+            event = kad_handle2.next() => {
+                if let Some(KademliaEvent::IncomingRecord { record: got_record }) = event {
+                    assert_eq!(got_record.key, record.key);
+                    assert_eq!(got_record.value, record.value);
+                    break
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn file_store_keeps_records_across_restarts() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let path = std::env::temp_dir().join(format!("litep2p-kademlia-{}", rand::random::<u64>()));
+    let record = Record::new(vec![1, 2, 3], vec![0x01]);
+
+    {
+        let (kad_config, mut kad_handle) = KademliaConfigBuilder::new()
+            .with_record_store(FileStore::open(&path).unwrap())
+            .build();
+        let config = ConfigBuilder::new()
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_kademlia(kad_config)
+            .build();
+        let mut litep2p = Litep2p::new(config).unwrap();
+
+        // No peers are known so the query fails but the record is stored locally.
+        let query_id = kad_handle.put_record(record.clone()).await;
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                    panic!("query did not finish in 10 secs")
+                }
+                _ = litep2p.next_event() => {}
+                event = kad_handle.next() => {
+                    if let Some(KademliaEvent::QueryFailed { query_id: failed }) = event {
+                        assert_eq!(failed, query_id);
+                        break
+                    }
+                }
+            }
+        }
+
+        // the store is flushed when Kademlia exits
+        litep2p.shutdown(std::time::Duration::from_secs(5)).await;
+    }
+
+    let (kad_config, mut kad_handle) = KademliaConfigBuilder::new()
+        .with_record_store(FileStore::open(&path).unwrap())
+        .build();
+    let config = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config)
+        .build();
+    let mut litep2p = Litep2p::new(config).unwrap();
+
+    let _ = kad_handle.get_record(record.key.clone(), Quorum::One).await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("record was not retrieved in 10 secs")
+            }
+            _ = litep2p.next_event() => {}
+            event = kad_handle.next() => {
+                if let Some(KademliaEvent::GetRecordSuccess { records, .. }) = event {
+                    match records {
+                        RecordsType::LocalStore(got_record) => {
+                            assert_eq!(got_record.key, record.key);
+                            assert_eq!(got_record.value, record.value);
+                            break
+                        }
+                        RecordsType::Network(_) => panic!("record was not stored locally"),
+                    }
+                }
+            }
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn client_mode_rejects_inbound_requests() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new()
+        .with_mode(Mode::Client)
+        .with_routing_table_refresh_interval(None)
+        .build();
+    let (kad_config2, mut kad_handle2) =
+        KademliaConfigBuilder::new().with_routing_table_refresh_interval(None).build();
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    kad_handle1
+        .add_known_peer(
+            *litep2p2.local_peer_id(),
+            litep2p2.listen_addresses().cloned().collect(),
+        )
+        .await;
+    kad_handle2
+        .add_known_peer(
+            *litep2p1.local_peer_id(),
+            litep2p1.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    // The client can query the server but not the other way around. The server queries the client
+    // only after the connection is open so the nodes don't dial each other simultaneously.
+    let query1 = kad_handle1.find_node(PeerId::random()).await;
+    let mut query2 = None;
+    let mut server_done = false;
+
+    while !server_done {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("queries did not finish in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            event = kad_handle1.next() => match event {
+                Some(KademliaEvent::FindNodeSuccess { query_id, .. }) => {
+                    assert_eq!(query_id, query1);
+                    query2 = Some(kad_handle2.find_node(PeerId::random()).await);
+                }
+                Some(KademliaEvent::QueryFailed { .. }) => panic!("client query failed"),
+                _ => {}
+            },
+            event = kad_handle2.next() => match event {
+                Some(KademliaEvent::QueryFailed { query_id }) => {
+                    assert_eq!(Some(query_id), query2);
+                    server_done = true;
+                }
+                Some(KademliaEvent::FindNodeSuccess { .. }) => {
+                    panic!("client answered a request")
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+#[tokio::test]
+async fn auto_mode_switches_to_server_with_external_address() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, _kad_handle1) = KademliaConfigBuilder::new()
+        .with_mode(Mode::Auto)
+        .with_routing_table_refresh_interval(None)
+        .build();
+    let (identify_config1, _identify_event_stream1) =
+        IdentifyConfig::new("/proto/1".to_string(), None, Vec::new());
+    let (identify_config2, mut identify_event_stream2) =
+        IdentifyConfig::new("/proto/2".to_string(), None, Vec::new());
+
+    let config1 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_kademlia(kad_config1)
+        .with_libp2p_identify(identify_config1)
+        .build();
+
+    let config2 = ConfigBuilder::new()
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_libp2p_identify(identify_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let address = litep2p2.listen_addresses().next().unwrap().clone();
+    litep2p1.dial_address(address).await.unwrap();
+
+    let kademlia = litep2p::ProtocolName::from("/ipfs/kad/1.0.0");
+    let external_address: litep2p::types::multiaddr::Multiaddr =
+        "/ip4/1.2.3.4/tcp/30333".parse().unwrap();
+
+    // Kademlia is not advertised until the local node has an external address.
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("peer was not identified in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            event = identify_event_stream2.next() => {
+                let IdentifyEvent::PeerIdentified { supported_protocols, .. } = event.unwrap();
+                assert!(!supported_protocols.contains(&kademlia));
+                break
+            }
+        }
+    }
+
+    assert!(litep2p1.add_external_address(external_address.clone()).await);
+    assert!(!litep2p1.add_external_address(external_address).await);
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("protocols were not pushed in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            event = identify_event_stream2.next() => {
+                let IdentifyEvent::PeerIdentified { supported_protocols, .. } = event.unwrap();
+                assert!(supported_protocols.contains(&kademlia));
+                break
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn disjoint_get_record_retrieves_records_from_all_paths() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+    let (kad_config3, mut kad_handle3) =
+        KademliaConfigBuilder::new().with_disjoint_paths(2).build();
+
+    let make_litep2p = |kad_config| {
+        Litep2p::new(
+            ConfigBuilder::new()
+                .with_tcp(TcpConfig {
+                    listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                    ..Default::default()
+                })
+                .with_libp2p_kademlia(kad_config)
+                .build(),
+        )
+        .unwrap()
+    };
+    let mut litep2p1 = make_litep2p(kad_config1);
+    let mut litep2p2 = make_litep2p(kad_config2);
+    let mut litep2p3 = make_litep2p(kad_config3);
+
+    // the first two peers store the record
+    let record = Record::new(vec![1, 2, 3], vec![0x01]);
+    kad_handle1.store_record(record.clone()).await;
+    kad_handle2.store_record(record.clone()).await;
+
+    for litep2p in [&litep2p1, &litep2p2] {
+        kad_handle3
+            .add_known_peer(
+                *litep2p.local_peer_id(),
+                litep2p.listen_addresses().cloned().collect(),
+            )
+            .await;
+    }
+
+    // each path queries one of the peers and the records found by both paths are combined
+    let query = kad_handle3
+        .get_record(
+            RecordKey::from(vec![1, 2, 3]),
+            Quorum::N(std::num::NonZeroUsize::new(2).unwrap()),
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("record was not retrieved in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            _ = litep2p3.next_event() => {}
+            _ = kad_handle1.next() => {}
+            _ = kad_handle2.next() => {}
+            event = kad_handle3.next() => match event {
+                Some(KademliaEvent::GetRecordSuccess { query_id, records }) => {
+                    assert_eq!(query_id, query);
+
+                    let RecordsType::Network(records) = records else {
+                        panic!("record was unexpectedly found from the local store");
+                    };
+                    let peers = records.iter().map(|record| record.peer).collect::<Vec<_>>();
+
+                    assert_eq!(records.len(), 2);
+                    assert!(peers.contains(litep2p1.local_peer_id()));
+                    assert!(peers.contains(litep2p2.local_peer_id()));
+                    break
+                }
+                Some(KademliaEvent::QueryFailed { .. }) => panic!("query failed"),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn find_node_with_query_config() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let make_litep2p = |kad_config| {
+        Litep2p::new(
+            ConfigBuilder::new()
+                .with_tcp(TcpConfig {
+                    listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                    ..Default::default()
+                })
+                .with_libp2p_kademlia(kad_config)
+                .build(),
+        )
+        .unwrap()
+    };
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+    let (kad_config3, mut kad_handle3) = KademliaConfigBuilder::new().build();
+    let mut litep2p1 = make_litep2p(kad_config1);
+    let mut litep2p2 = make_litep2p(kad_config2);
+    let mut litep2p3 = make_litep2p(kad_config3);
+
+    for litep2p in [&litep2p1, &litep2p2] {
+        kad_handle3
+            .add_known_peer(
+                *litep2p.local_peer_id(),
+                litep2p.listen_addresses().cloned().collect(),
+            )
+            .await;
+    }
+
+    // both peers are found but only the closest one is returned
+    let query = kad_handle3
+        .find_node_with(
+            PeerId::random(),
+            QueryConfig {
+                parallelism: Some(1),
+                num_results: Some(1),
+                timeout: Some(std::time::Duration::from_secs(5)),
+                ..Default::default()
+            },
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("query did not finish in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            _ = litep2p3.next_event() => {}
+            _ = kad_handle1.next() => {}
+            _ = kad_handle2.next() => {}
+            event = kad_handle3.next() => match event {
+                Some(KademliaEvent::FindNodeSuccess { query_id, peers, .. }) => {
+                    assert_eq!(query_id, query);
+                    assert_eq!(peers.len(), 1);
+                    break
+                }
+                Some(KademliaEvent::QueryFailed { .. }) => panic!("query failed"),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn get_record_partial_results_and_stop_query() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+
+    let make_litep2p = |kad_config| {
+        Litep2p::new(
+            ConfigBuilder::new()
+                .with_tcp(TcpConfig {
+                    listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                    ..Default::default()
+                })
+                .with_libp2p_kademlia(kad_config)
+                .build(),
+        )
+        .unwrap()
+    };
+    let mut litep2p1 = make_litep2p(kad_config1);
+    let mut litep2p2 = make_litep2p(kad_config2);
+
+    let record = Record::new(vec![1, 2, 3], vec![0x01]);
+    kad_handle1.store_record(record.clone()).await;
+
+    kad_handle2
+        .add_known_peer(
+            *litep2p1.local_peer_id(),
+            litep2p1.listen_addresses().cloned().collect(),
+        )
+        .await;
+
+    // the quorum can't be reached so the query only finishes once it's stopped
+    let query = kad_handle2
+        .get_record(
+            RecordKey::from(vec![1, 2, 3]),
+            Quorum::N(std::num::NonZeroUsize::new(5).unwrap()),
+        )
+        .await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {
+                panic!("query did not finish in 10 secs")
+            }
+            _ = litep2p1.next_event() => {}
+            _ = litep2p2.next_event() => {}
+            _ = kad_handle1.next() => {}
+            event = kad_handle2.next() => match event {
+                Some(KademliaEvent::GetRecordPartialResult { query_id, record: found, step }) => {
+                    assert_eq!(query_id, query);
+                    assert_eq!(step, 1);
+                    assert_eq!(found.peer, *litep2p1.local_peer_id());
+                    assert_eq!(found.record.value, record.value);
+
+                    kad_handle2.stop_query(query).await;
+                }
+                Some(KademliaEvent::GetRecordSuccess { query_id, records }) => {
+                    assert_eq!(query_id, query);
+
+                    let RecordsType::Network(records) = records else {
+                        panic!("record was unexpectedly found from the local store");
+                    };
+                    assert_eq!(records.len(), 1);
+                    assert_eq!(records[0].peer, *litep2p1.local_peer_id());
+                    break
+                }
+                Some(KademliaEvent::QueryFailed { .. }) => panic!("query failed"),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn inspect_and_remove_routing_table_peers() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config, kad_handle) = KademliaConfigBuilder::new().build();
+    let _litep2p = Litep2p::new(
+        ConfigBuilder::new()
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_kademlia(kad_config)
+            .build(),
+    )
+    .unwrap();
+
+    let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+    for peer in &peers {
+        kad_handle
+            .add_known_peer(*peer, vec!["/ip6/::1/tcp/8888".parse().unwrap()])
+            .await;
+    }
+
+    let snapshot = kad_handle.routing_table_snapshot().await;
+    let mut found = snapshot
+        .iter()
+        .flat_map(|bucket| bucket.peers.iter())
+        .inspect(|peer| {
+            assert_eq!(peer.connection, ConnectionType::NotConnected);
+            assert_eq!(peer.addresses.len(), 1);
+        })
+        .map(|peer| peer.peer)
+        .collect::<Vec<_>>();
+    found.sort();
+    let mut expected = peers.clone();
+    expected.sort();
+    assert_eq!(found, expected);
+
+    // the closest peer to the key of a peer in the routing table is the peer itself
+    let closest = kad_handle.closest_local_peers(RecordKey::from(peers[0].to_bytes()), 2).await;
+    assert_eq!(closest.len(), 2);
+    assert_eq!(closest[0].peer, peers[0]);
+
+    kad_handle.remove_peer(peers[0]).await;
+
+    let closest = kad_handle.closest_local_peers(RecordKey::from(peers[0].to_bytes()), 5).await;
+    assert_eq!(closest.len(), 2);
+    assert!(closest.iter().all(|peer| peer.peer != peers[0]));
+    assert_eq!(
+        kad_handle
+            .routing_table_snapshot()
+            .await
+            .iter()
+            .map(|bucket| bucket.peers.len())
+            .sum::<usize>(),
+        2
+    );
+}
@@ -24,8 +24,8 @@ use litep2p::{
     protocol::{Direction, TransportEvent, TransportService, UserProtocol},
     substream::{Substream, SubstreamSet},
     transport::{
-        quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
-        websocket::config::Config as WebSocketConfig,
+        memory::config::Config as MemoryConfig, quic::config::Config as QuicConfig,
+        tcp::config::Config as TcpConfig, websocket::config::Config as WebSocketConfig,
     },
     types::{protocol::ProtocolName, SubstreamId},
     Error, Litep2p, Litep2pEvent, PeerId,
@@ -52,6 +52,7 @@ enum Transport {
     Tcp(TcpConfig),
     Quic(QuicConfig),
     WebSocket(WebSocketConfig),
+    Memory(MemoryConfig),
 }
 
 enum Command {
@@ -106,7 +107,7 @@ impl UserProtocol for CustomProtocol {
                     TransportEvent::ConnectionEstablished { peer, .. } => {
                         self.peers.insert(peer);
                     }
-                    TransportEvent::ConnectionClosed { peer } => {
+                    TransportEvent::ConnectionClosed { peer, .. } => {
                         self.peers.remove(&peer);
                     }
                     TransportEvent::SubstreamOpened {
@@ -261,6 +262,15 @@ async fn too_big_identity_payload_framed_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn too_big_identity_payload_framed_memory() {
+    too_big_identity_payload_framed(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 // send too big payload using `Substream::send_framed()` and verify it's rejected
 async fn too_big_identity_payload_framed(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
@@ -272,6 +282,7 @@ async fn too_big_identity_payload_framed(transport1: Transport, transport2: Tran
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol1))
     .build();
@@ -281,6 +292,7 @@ async fn too_big_identity_payload_framed(transport1: Transport, transport2: Tran
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol2))
     .build();
@@ -346,6 +358,15 @@ async fn too_big_identity_payload_sink_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn too_big_identity_payload_sink_memory() {
+    too_big_identity_payload_sink(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 // send too big payload using `<Substream as Sink>::send()` and verify it's rejected
 async fn too_big_identity_payload_sink(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
@@ -357,6 +378,7 @@ async fn too_big_identity_payload_sink(transport1: Transport, transport2: Transp
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol1))
     .build();
@@ -366,6 +388,7 @@ async fn too_big_identity_payload_sink(transport1: Transport, transport2: Transp
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol2))
     .build();
@@ -433,6 +456,15 @@ async fn correct_payload_size_sink_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn correct_payload_size_sink_memory() {
+    correct_payload_size_sink(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 // send correctly-sized payload using `<Substream as Sink>::send()`
 async fn correct_payload_size_sink(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
@@ -444,6 +476,7 @@ async fn correct_payload_size_sink(transport1: Transport, transport2: Transport)
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol1))
     .build();
@@ -453,6 +486,7 @@ async fn correct_payload_size_sink(transport1: Transport, transport2: Transport)
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol2))
     .build();
@@ -517,6 +551,15 @@ async fn correct_payload_size_async_write_websocket() {
     .await;
 }
 
+#[tokio::test]
+async fn correct_payload_size_async_write_memory() {
+    correct_payload_size_async_write(
+        Transport::Memory(Default::default()),
+        Transport::Memory(Default::default()),
+    )
+    .await;
+}
+
 // send correctly-sized payload using `<Substream as AsyncRead>::poll_write()`
 async fn correct_payload_size_async_write(transport1: Transport, transport2: Transport) {
     let _ = tracing_subscriber::fmt()
@@ -528,6 +571,7 @@ async fn correct_payload_size_async_write(transport1: Transport, transport2: Tra
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol1))
     .build();
@@ -537,6 +581,7 @@ async fn correct_payload_size_async_write(transport1: Transport, transport2: Tra
         Transport::Tcp(config) => ConfigBuilder::new().with_tcp(config),
         Transport::Quic(config) => ConfigBuilder::new().with_quic(config),
         Transport::WebSocket(config) => ConfigBuilder::new().with_websocket(config),
+        Transport::Memory(config) => ConfigBuilder::new().with_memory(config),
     }
     .with_user_protocol(Box::new(custom_protocol2))
     .build();
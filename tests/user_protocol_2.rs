@@ -74,7 +74,7 @@ impl UserProtocol for CustomProtocol {
                     TransportEvent::ConnectionEstablished { peer, .. } => {
                         self.peers.insert(peer);
                     }
-                    TransportEvent::ConnectionClosed { peer: _ } => {}
+                    TransportEvent::ConnectionClosed { .. } => {}
                     TransportEvent::SubstreamOpened {
                         peer: _,
                         protocol: _,
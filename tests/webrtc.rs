@@ -22,11 +22,17 @@ use futures::StreamExt;
 use litep2p::{
     config::ConfigBuilder as Litep2pConfigBuilder,
     crypto::ed25519::Keypair,
-    protocol::{libp2p::ping, notification::ConfigBuilder},
+    protocol::{
+        libp2p::ping::{self, PingEvent},
+        notification::ConfigBuilder,
+    },
     transport::webrtc::config::Config,
     types::protocol::ProtocolName,
-    Litep2p,
+    Litep2p, Litep2pEvent,
 };
+use multiaddr::Protocol;
+
+use std::time::Duration;
 
 #[tokio::test]
 #[ignore]
@@ -78,3 +84,91 @@ async fn webrtc_test() {
         }
     }
 }
+
+#[tokio::test]
+async fn webrtc_dial_and_ping() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (ping_config1, mut ping_event_stream1) = ping::Config::default();
+    let (ping_config2, mut ping_event_stream2) = ping::Config::default();
+
+    let mut litep2p1 = Litep2p::new(
+        Litep2pConfigBuilder::new()
+            .with_webrtc(Config {
+                listen_addresses: vec!["/ip4/127.0.0.1/udp/0/webrtc-direct".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_ping(ping_config1)
+            .build(),
+    )
+    .unwrap();
+    let mut litep2p2 = Litep2p::new(
+        Litep2pConfigBuilder::new()
+            .with_webrtc(Config {
+                listen_addresses: vec!["/ip4/127.0.0.1/udp/0/webrtc-direct".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_ping(ping_config2)
+            .build(),
+    )
+    .unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+    let address = litep2p1
+        .listen_addresses()
+        .next()
+        .unwrap()
+        .clone()
+        .with(Protocol::P2p(peer1.into()));
+
+    litep2p2.dial_address(address).await.unwrap();
+
+    let mut incoming_connection = None;
+    let mut dialer_connected = false;
+    let mut listener_connected = false;
+    let mut dialer_ping = false;
+    let mut listener_ping = false;
+
+    while !(dialer_connected && listener_connected && dialer_ping && listener_ping) {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(20)) => {
+                panic!("failed to connect and ping over webrtc in 20 seconds")
+            }
+            event = litep2p1.next_event() => match event.unwrap() {
+                Litep2pEvent::IncomingConnection { connection_id, .. } => {
+                    incoming_connection = Some(connection_id);
+                }
+                Litep2pEvent::ConnectionEstablished { peer, endpoint, .. } => {
+                    assert_eq!(peer, peer2);
+                    assert!(endpoint.is_listener());
+                    assert_eq!(incoming_connection, Some(endpoint.connection_id()));
+                    listener_connected = true;
+                }
+                Litep2pEvent::DialFailure { .. } => panic!("unexpected dial failure"),
+                _ => {}
+            },
+            event = litep2p2.next_event() => match event.unwrap() {
+                Litep2pEvent::ConnectionEstablished { peer, endpoint, .. } => {
+                    assert_eq!(peer, peer1);
+                    assert!(!endpoint.is_listener());
+                    dialer_connected = true;
+                }
+                Litep2pEvent::DialFailure { .. } => panic!("failed to dial peer"),
+                _ => {}
+            },
+            event = ping_event_stream1.next() => {
+                let PingEvent::Ping { peer, .. } = event.unwrap();
+                assert_eq!(peer, peer2);
+                listener_ping = true;
+            }
+            event = ping_event_stream2.next() => {
+                let PingEvent::Ping { peer, .. } = event.unwrap();
+                assert_eq!(peer, peer1);
+                dialer_ping = true;
+            }
+        }
+    }
+}